@@ -194,6 +194,10 @@ impl WasmerCmd {
                 Package::Push(cmd) => cmd.run(),
                 Package::Publish(cmd) => cmd.run().map(|_| ()),
                 Package::Unpack(cmd) => cmd.execute(),
+                Package::Repack(cmd) => cmd.execute(),
+                Package::Diff(cmd) => cmd.execute(),
+                Package::Merge(cmd) => cmd.execute(),
+                Package::Verify(cmd) => cmd.execute(),
             },
             Some(Cmd::Container(cmd)) => match cmd {
                 crate::commands::Container::Unpack(cmd) => cmd.execute(),
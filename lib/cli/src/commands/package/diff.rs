@@ -0,0 +1,222 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Context;
+use dialoguer::console::style;
+use sha2::Digest;
+use wasmer_package::{
+    unpack::{container_entries, ContainerEntryKind},
+    utils::from_disk,
+};
+use webc::Container;
+
+use super::unpack::DirDiff;
+
+/// Show the differences between two webc images.
+///
+/// Atoms and volume files are compared by their sha256 hash, and the
+/// manifests are compared field by field.
+#[derive(clap::Parser, Debug)]
+pub struct PackageDiff {
+    /// Print the differences as JSON.
+    #[clap(long)]
+    pub json: bool,
+
+    /// Path to the old package.
+    pub old: PathBuf,
+
+    /// Path to the new package.
+    pub new: PathBuf,
+}
+
+impl PackageDiff {
+    pub(crate) fn execute(&self) -> Result<(), anyhow::Error> {
+        let old = from_disk(&self.old)
+            .with_context(|| format!("could not open package at '{}'", self.old.display()))?;
+        let new = from_disk(&self.new)
+            .with_context(|| format!("could not open package at '{}'", self.new.display()))?;
+
+        let diff = ContainerDiff::compute(&old, &new)?;
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else if diff.is_empty() {
+            println!("The packages are identical");
+        } else {
+            print!("{diff}");
+        }
+
+        Ok(())
+    }
+}
+
+/// Everything that changed between two containers.
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct ContainerDiff {
+    atoms: DirDiff,
+    files: DirDiff,
+    metadata: Vec<MetadataChange>,
+}
+
+/// A single manifest field that was added, removed or changed.
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct MetadataChange {
+    /// A JSON pointer to the field, relative to the manifest's root.
+    path: String,
+    old: Option<serde_json::Value>,
+    new: Option<serde_json::Value>,
+}
+
+impl ContainerDiff {
+    fn compute(old: &Container, new: &Container) -> Result<Self, anyhow::Error> {
+        let (old_atoms, old_files) = hash_entries(old)?;
+        let (new_atoms, new_files) = hash_entries(new)?;
+
+        let mut old_manifest = serde_json::to_value(old.manifest())?;
+        let mut new_manifest = serde_json::to_value(new.manifest())?;
+        // Atom signatures are already covered by comparing the atoms
+        // themselves.
+        for manifest in [&mut old_manifest, &mut new_manifest] {
+            if let Some(manifest) = manifest.as_object_mut() {
+                manifest.remove("atoms");
+            }
+        }
+        let mut metadata = Vec::new();
+        diff_json(
+            &mut String::new(),
+            Some(&old_manifest),
+            Some(&new_manifest),
+            &mut metadata,
+        );
+
+        Ok(ContainerDiff {
+            atoms: DirDiff::between(&new_atoms, &old_atoms),
+            files: DirDiff::between(&new_files, &old_files),
+            metadata,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.atoms.is_empty() && self.files.is_empty() && self.metadata.is_empty()
+    }
+}
+
+impl std::fmt::Display for ContainerDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.atoms.is_empty() {
+            writeln!(f, "{}", style("Atoms:").bold())?;
+            write!(f, "{}", self.atoms)?;
+        }
+        if !self.files.is_empty() {
+            writeln!(f, "{}", style("Files:").bold())?;
+            write!(f, "{}", self.files)?;
+        }
+        if !self.metadata.is_empty() {
+            writeln!(f, "{}", style("Metadata:").bold())?;
+            for change in &self.metadata {
+                match (&change.old, &change.new) {
+                    (None, Some(new)) => {
+                        writeln!(f, "{} {}: {new}", style("+").green(), change.path)?
+                    }
+                    (Some(old), None) => {
+                        writeln!(f, "{} {}: {old}", style("-").red(), change.path)?
+                    }
+                    (Some(old), Some(new)) => {
+                        writeln!(f, "{} {}: {old} -> {new}", style("~").yellow(), change.path)?
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+type Hashes = BTreeMap<PathBuf, Vec<u8>>;
+
+/// Hash every atom and volume entry in a container, returning them
+/// separately.
+fn hash_entries(container: &Container) -> Result<(Hashes, Hashes), anyhow::Error> {
+    let mut atoms = BTreeMap::new();
+    let mut files = BTreeMap::new();
+
+    for entry in container_entries(container) {
+        let entry = entry?;
+        let hash = match entry.kind() {
+            ContainerEntryKind::VolumeDirectory => Vec::new(),
+            _ => sha2::Sha256::digest(entry.contents()).to_vec(),
+        };
+        let path = entry.path().to_path_buf();
+        match entry.kind() {
+            ContainerEntryKind::Metadata => {}
+            ContainerEntryKind::Atom => {
+                atoms.insert(path, hash);
+            }
+            ContainerEntryKind::VolumeDirectory | ContainerEntryKind::VolumeFile => {
+                files.insert(path, hash);
+            }
+        }
+    }
+
+    Ok((atoms, files))
+}
+
+/// Recursively compare two JSON values, recording the leaves which differ.
+fn diff_json(
+    path: &mut String,
+    old: Option<&serde_json::Value>,
+    new: Option<&serde_json::Value>,
+    changes: &mut Vec<MetadataChange>,
+) {
+    match (old, new) {
+        (Some(serde_json::Value::Object(old)), Some(serde_json::Value::Object(new))) => {
+            let mut keys: Vec<_> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let len = path.len();
+                path.push('/');
+                path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                diff_json(path, old.get(key), new.get(key), changes);
+                path.truncate(len);
+            }
+        }
+        (old, new) if old != new => changes.push(MetadataChange {
+            path: if path.is_empty() {
+                "/".to_string()
+            } else {
+                path.clone()
+            },
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::package::unpack::test_utils::{webc_fixture, DASH_WEBC, HELLO_WEBC};
+
+    #[test]
+    fn diff_containers() {
+        let hello = from_disk(webc_fixture(HELLO_WEBC)).unwrap();
+        let dash = from_disk(webc_fixture(DASH_WEBC)).unwrap();
+
+        let same = ContainerDiff::compute(&hello, &hello).unwrap();
+        assert!(same.is_empty());
+
+        let diff = ContainerDiff::compute(&hello, &dash).unwrap();
+        assert_eq!(diff.atoms.added, vec![PathBuf::from("dash")]);
+        assert!(diff.atoms.removed.is_empty());
+        assert!(!diff.files.is_empty());
+        let version = diff
+            .metadata
+            .iter()
+            .find(|change| change.path == "/package/wapm/version")
+            .unwrap();
+        assert_eq!(version.old, Some("0.1.0".into()));
+        assert_eq!(version.new, Some("1.0.18".into()));
+    }
+}
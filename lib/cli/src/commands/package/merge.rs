@@ -0,0 +1,483 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use dialoguer::console::{style, Emoji};
+use indicatif::ProgressBar;
+use wasmer_package::{
+    package::Package,
+    unpack::{extract_with_progress, ExtractOptions, MANIFEST_FILE_NAME},
+    utils::from_disk,
+};
+use webc::metadata::{
+    annotations::{Atom as AtomAnnotation, FileSystemMapping, FileSystemMappings, Wasi},
+    Command, Manifest as WebcManifest,
+};
+
+/// Merge several packages into a single webc image.
+///
+/// The atoms, volumes, mounts and commands of every package are combined. The
+/// merged package takes its name, version, entrypoint and metadata (readme,
+/// license, etc.) from the first package.
+#[derive(clap::Parser, Debug)]
+pub struct PackageMerge {
+    /// Output path for the merged package file.
+    #[clap(short = 'o', long)]
+    pub out: PathBuf,
+
+    /// Overwrite the output file if it already exists.
+    #[clap(long)]
+    pub overwrite: bool,
+
+    /// Run the merge command without any output
+    #[clap(long)]
+    pub quiet: bool,
+
+    /// What to do when two packages have an atom, volume or command with the
+    /// same name, or mount a volume at the same path.
+    #[clap(long, value_enum, default_value_t)]
+    pub rename_strategy: RenameStrategy,
+
+    /// The packages to merge.
+    #[clap(required = true, num_args = 2..)]
+    pub packages: Vec<PathBuf>,
+}
+
+/// How `package merge` resolves name collisions.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenameStrategy {
+    /// Fail the merge.
+    #[default]
+    Error,
+    /// Keep the first package's name and append `-2`, `-3`, etc. to the
+    /// names (and mount paths) from later packages.
+    Suffix,
+}
+
+static LOADING_EMOJI: Emoji<'_, '_> = Emoji("📦 ", "");
+static MERGING_EMOJI: Emoji<'_, '_> = Emoji("🔀 ", "");
+static WRITING_PACKAGE_EMOJI: Emoji<'_, '_> = Emoji("📦 ", "");
+static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
+
+impl PackageMerge {
+    pub(crate) fn execute(&self) -> Result<(), anyhow::Error> {
+        // Setup the progress bar
+        let pb = if self.quiet {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new_spinner()
+        };
+
+        if self.out.exists() && !self.overwrite {
+            anyhow::bail!(
+                "Output path '{}' already exists - use --overwrite to replace it",
+                self.out.display()
+            );
+        }
+
+        pb.println(format!(
+            "{} {}Unpacking {} packages...",
+            style("[1/3]").bold().dim(),
+            LOADING_EMOJI,
+            self.packages.len(),
+        ));
+
+        // Each package is unpacked into its own directory, and the first one
+        // doubles as the directory the merged package is assembled in
+        let staging = tempfile::tempdir().context("could not create a staging directory")?;
+        let mut dirs = Vec::new();
+        for (i, path) in self.packages.iter().enumerate() {
+            let container = from_disk(path)
+                .with_context(|| format!("could not load the package at '{}'", path.display()))?;
+            let dir = staging.path().join(i.to_string());
+            extract_with_progress(&container, &dir, &ExtractOptions::default(), &mut |_| {})
+                .with_context(|| format!("could not unpack '{}'", path.display()))?;
+            dirs.push(dir);
+        }
+
+        pb.println(format!(
+            "{} {}Merging packages...",
+            style("[2/3]").bold().dim(),
+            MERGING_EMOJI
+        ));
+
+        let merged_dir = &dirs[0];
+        let mut merged = Merger::new(&self.packages[0], read_manifest(merged_dir)?)?;
+        for (path, dir) in self.packages.iter().zip(&dirs).skip(1) {
+            merged
+                .merge(path, dir, merged_dir, self.rename_strategy)
+                .with_context(|| format!("could not merge '{}'", path.display()))?;
+        }
+        let manifest_path = merged.finish(merged_dir)?;
+
+        pb.println(format!(
+            "{} {}Writing package...",
+            style("[3/3]").bold().dim(),
+            WRITING_PACKAGE_EMOJI
+        ));
+
+        let pkg = Package::from_json_manifest(manifest_path)
+            .context("could not load the merged package")?;
+        let data = pkg
+            .serialize()
+            .context("could not serialize the merged package")?;
+
+        if let Some(parent) = self.out.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).with_context(|| {
+                    format!("could not create output directory '{}'", parent.display())
+                })?;
+            }
+        }
+
+        std::fs::write(&self.out, &data)
+            .with_context(|| format!("could not write contents to '{}'", self.out.display()))?;
+
+        pb.finish_with_message(format!(
+            "{} Package written to '{}'",
+            SPARKLE,
+            self.out.display()
+        ));
+
+        Ok(())
+    }
+}
+
+/// Builds up the merged manifest, remembering which package each name came
+/// from so collisions can be reported.
+struct Merger {
+    manifest: WebcManifest,
+    mappings: Vec<FileSystemMapping>,
+    /// The package each atom, volume, mount path and command came from, keyed
+    /// by kind and name.
+    owners: HashMap<(&'static str, String), PathBuf>,
+}
+
+impl Merger {
+    fn new(path: &Path, manifest: WebcManifest) -> Result<Self, anyhow::Error> {
+        let mappings = filesystem_mappings(&manifest)?;
+
+        let mut owners = HashMap::new();
+        let names = manifest
+            .atoms
+            .keys()
+            .map(|name| ("atom", name.clone()))
+            .chain(
+                mappings
+                    .iter()
+                    .map(|mapping| ("volume", mapping.volume_name.clone())),
+            )
+            .chain(
+                mappings
+                    .iter()
+                    .map(|mapping| ("mount path", mapping.mount_path.clone())),
+            )
+            .chain(
+                manifest
+                    .commands
+                    .keys()
+                    .map(|name| ("command", name.clone())),
+            );
+        for key in names {
+            owners.insert(key, path.to_path_buf());
+        }
+
+        Ok(Merger {
+            manifest,
+            mappings,
+            owners,
+        })
+    }
+
+    /// Pick the name an item from `package` will have in the merged
+    /// package.
+    fn claim(
+        &mut self,
+        kind: &'static str,
+        name: &str,
+        package: &Path,
+        strategy: RenameStrategy,
+    ) -> Result<String, anyhow::Error> {
+        let mut candidate = name.to_string();
+        let mut suffix = 1;
+
+        while let Some(owner) = self.owners.get(&(kind, candidate.clone())) {
+            if strategy == RenameStrategy::Error {
+                anyhow::bail!(
+                    "the {kind} '{name}' is also in '{}' - use --rename-strategy suffix to rename it",
+                    owner.display()
+                );
+            }
+            suffix += 1;
+            candidate = format!("{name}-{suffix}");
+        }
+
+        self.owners
+            .insert((kind, candidate.clone()), package.to_path_buf());
+        Ok(candidate)
+    }
+
+    /// Merge the package unpacked to `dir` into the one being assembled in
+    /// `merged_dir`.
+    fn merge(
+        &mut self,
+        package: &Path,
+        dir: &Path,
+        merged_dir: &Path,
+        strategy: RenameStrategy,
+    ) -> Result<(), anyhow::Error> {
+        let manifest = read_manifest(dir)?;
+
+        let mut atom_names = HashMap::new();
+        for (name, atom) in &manifest.atoms {
+            let new_name = self.claim("atom", name, package, strategy)?;
+            copy_entry(&dir.join(name), &merged_dir.join(&new_name))?;
+            self.manifest.atoms.insert(new_name.clone(), atom.clone());
+            atom_names.insert(name.clone(), new_name);
+        }
+
+        // Several mappings can share a volume, so it's only copied once
+        let mut volume_names: HashMap<String, String> = HashMap::new();
+        for mut mapping in filesystem_mappings(&manifest)? {
+            let volume_name = match volume_names.get(&mapping.volume_name) {
+                Some(name) => name.clone(),
+                None => {
+                    let new_name = self.claim("volume", &mapping.volume_name, package, strategy)?;
+                    copy_entry(
+                        &dir.join(volume_dir(&mapping.volume_name)),
+                        &merged_dir.join(volume_dir(&new_name)),
+                    )?;
+                    volume_names.insert(mapping.volume_name.clone(), new_name.clone());
+                    new_name
+                }
+            };
+            mapping.volume_name = volume_name;
+            mapping.mount_path =
+                self.claim("mount path", &mapping.mount_path, package, strategy)?;
+            self.mappings.push(mapping);
+        }
+
+        for (name, command) in &manifest.commands {
+            let new_name = self.claim("command", name, package, strategy)?;
+            let command = rename_atom_references(name, command, &atom_names)?;
+            self.manifest.commands.insert(new_name, command);
+        }
+
+        for (alias, dependency) in manifest.use_map {
+            match self.manifest.use_map.get(&alias) {
+                Some(existing)
+                    if serde_json::to_value(existing)? != serde_json::to_value(&dependency)? =>
+                {
+                    anyhow::bail!(
+                        "the dependency '{alias}' refers to a different package in '{}'",
+                        package.display()
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    self.manifest.use_map.insert(alias, dependency);
+                }
+            }
+        }
+
+        self.manifest.bindings.extend(manifest.bindings);
+
+        Ok(())
+    }
+
+    /// Write the merged manifest to `merged_dir`, returning its path.
+    fn finish(mut self, merged_dir: &Path) -> Result<PathBuf, anyhow::Error> {
+        if !self.mappings.is_empty() {
+            let mappings = ciborium::Value::serialized(&FileSystemMappings(self.mappings))
+                .context("could not serialize the filesystem mappings")?;
+            self.manifest
+                .package
+                .insert(FileSystemMappings::KEY.to_string(), mappings);
+        }
+
+        let path = merged_dir.join(MANIFEST_FILE_NAME);
+        let json = serde_json::to_vec_pretty(&self.manifest)?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("could not write '{}'", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+fn read_manifest(dir: &Path) -> Result<WebcManifest, anyhow::Error> {
+    let path = dir.join(MANIFEST_FILE_NAME);
+    let contents =
+        std::fs::read(&path).with_context(|| format!("could not read '{}'", path.display()))?;
+    serde_json::from_slice(&contents)
+        .with_context(|| format!("could not parse '{}'", path.display()))
+}
+
+fn filesystem_mappings(manifest: &WebcManifest) -> Result<Vec<FileSystemMapping>, anyhow::Error> {
+    let mappings = manifest
+        .filesystem()
+        .context("could not read the filesystem mappings from the manifest")?;
+    Ok(mappings.map(|m| m.0).unwrap_or_default())
+}
+
+/// The directory a volume is unpacked to.
+fn volume_dir(volume_name: &str) -> &str {
+    volume_name.strip_prefix('/').unwrap_or(volume_name)
+}
+
+/// Point a command at its atom's new name if the atom was renamed.
+fn rename_atom_references(
+    name: &str,
+    command: &Command,
+    atom_names: &HashMap<String, String>,
+) -> Result<Command, anyhow::Error> {
+    let mut command = command.clone();
+    let renamed = |atom: &str| atom_names.get(atom).filter(|new_name| *new_name != atom);
+
+    let atom = command
+        .annotation::<AtomAnnotation>(AtomAnnotation::KEY)
+        .with_context(|| format!("could not read the atom annotation for the command '{name}'"))?;
+    if let Some(mut atom) = atom.filter(|atom| atom.dependency.is_none()) {
+        if let Some(new_name) = renamed(&atom.name) {
+            atom.name = new_name.clone();
+            command.annotations.insert(
+                AtomAnnotation::KEY.to_string(),
+                ciborium::Value::serialized(&atom)?,
+            );
+        }
+    }
+
+    let wasi = command
+        .annotation::<Wasi>(Wasi::KEY)
+        .with_context(|| format!("could not read the WASI annotation for the command '{name}'"))?;
+    if let Some(mut wasi) = wasi {
+        if let Some(new_name) = renamed(&wasi.atom) {
+            wasi.atom = new_name.clone();
+            command
+                .annotations
+                .insert(Wasi::KEY.to_string(), ciborium::Value::serialized(&wasi)?);
+        }
+    }
+
+    Ok(command)
+}
+
+/// Copy a file or directory tree.
+fn copy_entry(from: &Path, to: &Path) -> Result<(), anyhow::Error> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)
+            .with_context(|| format!("could not create '{}'", to.display()))?;
+        for entry in std::fs::read_dir(from)
+            .with_context(|| format!("could not read '{}'", from.display()))?
+        {
+            let entry = entry?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(from, to).with_context(|| {
+            format!("could not copy '{}' to '{}'", from.display(), to.display())
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::package::unpack::test_utils::{webc_fixture, HELLO_WEBC};
+
+    #[test]
+    fn test_cmd_package_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("merged.webc");
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        let mut cmd = PackageMerge {
+            out: out.clone(),
+            overwrite: false,
+            quiet: true,
+            rename_strategy: RenameStrategy::Error,
+            packages: vec![package_path.clone(), package_path.clone()],
+        };
+
+        // Every name collides when a package is merged with itself
+        let err = cmd.execute().unwrap_err();
+        assert!(format!("{err:#}").contains("--rename-strategy"), "{err:#}");
+
+        cmd.rename_strategy = RenameStrategy::Suffix;
+        cmd.execute().unwrap();
+
+        let original = from_disk(&package_path).unwrap();
+        let merged = from_disk(&out).unwrap();
+        for (name, atom) in original.atoms() {
+            assert_eq!(atom, merged.get_atom(&name).unwrap());
+            assert_eq!(atom, merged.get_atom(&format!("{name}-2")).unwrap());
+        }
+        for name in original.manifest().commands.keys() {
+            let renamed = &merged.manifest().commands[&format!("{name}-2")];
+            let atom = renamed
+                .annotation::<AtomAnnotation>(AtomAnnotation::KEY)
+                .unwrap();
+            if let Some(atom) = atom {
+                assert!(atom.name.ends_with("-2"), "{}", atom.name);
+            }
+        }
+    }
+
+    #[test]
+    fn mount_path_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        // Two packages with differently named volumes, mounted at the same path
+        let package = |name: &str| {
+            let package_dir = dir.path().join(name);
+            std::fs::create_dir_all(package_dir.join(name)).unwrap();
+            let mappings = FileSystemMappings(vec![FileSystemMapping {
+                from: None,
+                volume_name: format!("/{name}"),
+                host_path: None,
+                mount_path: "/public".to_string(),
+            }]);
+            let mut manifest = WebcManifest::default();
+            manifest.package.insert(
+                FileSystemMappings::KEY.to_string(),
+                ciborium::Value::serialized(&mappings).unwrap(),
+            );
+            std::fs::write(
+                package_dir.join(MANIFEST_FILE_NAME),
+                serde_json::to_vec(&manifest).unwrap(),
+            )
+            .unwrap();
+            package_dir
+        };
+        let first = package("first");
+        let second = package("second");
+
+        let mut merger = Merger::new(&first, read_manifest(&first).unwrap()).unwrap();
+        let err = merger
+            .merge(&second, &second, &first, RenameStrategy::Error)
+            .unwrap_err();
+        assert!(err.to_string().contains("mount path '/public'"), "{err}");
+
+        let mut merger = Merger::new(&first, read_manifest(&first).unwrap()).unwrap();
+        merger
+            .merge(&second, &second, &first, RenameStrategy::Suffix)
+            .unwrap();
+        merger.finish(&first).unwrap();
+        let manifest = read_manifest(&first).unwrap();
+        let mounts: Vec<_> = filesystem_mappings(&manifest)
+            .unwrap()
+            .into_iter()
+            .map(|mapping| (mapping.volume_name, mapping.mount_path))
+            .collect();
+        assert_eq!(
+            mounts,
+            [
+                ("/first".to_string(), "/public".to_string()),
+                ("/second".to_string(), "/public-2".to_string()),
+            ]
+        );
+    }
+}
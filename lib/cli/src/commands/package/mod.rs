@@ -1,10 +1,14 @@
 mod build;
 mod common;
+mod diff;
 mod download;
+mod merge;
 pub mod publish;
 mod push;
+mod repack;
 mod tag;
 mod unpack;
+mod verify;
 
 pub use build::PackageBuild;
 pub use common::wait::PublishWait;
@@ -22,4 +26,8 @@ pub enum Package {
     Push(push::PackagePush),
     Publish(publish::PackagePublish),
     Unpack(unpack::PackageUnpack),
+    Repack(repack::PackageRepack),
+    Diff(diff::PackageDiff),
+    Merge(merge::PackageMerge),
+    Verify(verify::PackageVerify),
 }
@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use dialoguer::console::{style, Emoji};
+use indicatif::ProgressBar;
+use wasmer_package::package::Package;
+use webc::metadata::Manifest as WebcManifest;
+
+/// Re-create a webc image from a directory produced by `package unpack`.
+///
+/// The directory must have the layout of `package unpack --format webc`: the
+/// webc manifest in a manifest.json file, atoms in the root directory and
+/// volumes in subdirectories.
+#[derive(clap::Parser, Debug)]
+pub struct PackageRepack {
+    /// Output path for the package file.
+    #[clap(short = 'o', long)]
+    pub out: PathBuf,
+
+    /// Overwrite the output file if it already exists.
+    #[clap(long)]
+    pub overwrite: bool,
+
+    /// Run the repack command without any output
+    #[clap(long)]
+    pub quiet: bool,
+
+    /// Path to the unpacked package directory.
+    pub unpacked_dir: PathBuf,
+}
+
+static VALIDATING_EMOJI: Emoji<'_, '_> = Emoji("🔍 ", "");
+static WRITING_PACKAGE_EMOJI: Emoji<'_, '_> = Emoji("📦 ", "");
+static SPARKLE: Emoji<'_, '_> = Emoji("✨ ", ":-)");
+
+impl PackageRepack {
+    pub(crate) fn execute(&self) -> Result<(), anyhow::Error> {
+        // Setup the progress bar
+        let pb = if self.quiet {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new_spinner()
+        };
+
+        pb.println(format!(
+            "{} {}Validating directory layout...",
+            style("[1/2]").bold().dim(),
+            VALIDATING_EMOJI
+        ));
+
+        let manifest_path = validate_layout(&self.unpacked_dir)?;
+
+        if self.out.exists() && !self.overwrite {
+            anyhow::bail!(
+                "Output path '{}' already exists - use --overwrite to replace it",
+                self.out.display()
+            );
+        }
+
+        let pkg = Package::from_json_manifest(manifest_path).with_context(|| {
+            format!(
+                "could not load the unpacked package at '{}'",
+                self.unpacked_dir.display()
+            )
+        })?;
+        let data = pkg.serialize().context("could not serialize the package")?;
+
+        pb.println(format!(
+            "{} {}Writing package...",
+            style("[2/2]").bold().dim(),
+            WRITING_PACKAGE_EMOJI
+        ));
+
+        if let Some(parent) = self.out.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).with_context(|| {
+                    format!("could not create output directory '{}'", parent.display())
+                })?;
+            }
+        }
+
+        std::fs::write(&self.out, &data)
+            .with_context(|| format!("could not write contents to '{}'", self.out.display()))?;
+
+        pb.finish_with_message(format!(
+            "{} Package written to '{}'",
+            SPARKLE,
+            self.out.display()
+        ));
+
+        Ok(())
+    }
+}
+
+/// Make sure `dir` looks like the output of `package unpack --format webc`,
+/// returning the path to its manifest.json.
+fn validate_layout(dir: &Path) -> Result<PathBuf, anyhow::Error> {
+    if !dir.is_dir() {
+        anyhow::bail!("'{}' is not a directory", dir.display());
+    }
+
+    let manifest_path = dir.join("manifest.json");
+    let contents = std::fs::read(&manifest_path).with_context(|| {
+        format!(
+            "'{}' does not contain a manifest.json - was it created by 'wasmer package unpack --format webc'?",
+            dir.display()
+        )
+    })?;
+    let manifest: WebcManifest = serde_json::from_slice(&contents)
+        .with_context(|| format!("could not parse '{}'", manifest_path.display()))?;
+
+    for atom in manifest.atoms.keys() {
+        let path = dir.join(atom);
+        if !path.is_file() {
+            anyhow::bail!(
+                "the manifest references the atom '{atom}', but '{}' is not a file",
+                path.display()
+            );
+        }
+    }
+
+    let mappings = manifest
+        .filesystem()
+        .context("could not read the filesystem mappings from the manifest")?;
+    for mapping in mappings.iter().flat_map(|m| m.iter()) {
+        let name = mapping.volume_name.strip_prefix('/').with_context(|| {
+            format!("the volume name '{}' is not absolute", mapping.volume_name)
+        })?;
+        let path = dir.join(name);
+        if !path.is_dir() {
+            anyhow::bail!(
+                "the manifest references the volume '{}', but '{}' is not a directory",
+                mapping.volume_name,
+                path.display()
+            );
+        }
+    }
+
+    let metadata = dir.join("metadata");
+    if !metadata.is_dir() {
+        anyhow::bail!(
+            "'{}' does not contain a metadata volume directory",
+            dir.display()
+        );
+    }
+
+    Ok(manifest_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer_package::utils::from_disk;
+
+    use super::*;
+    use crate::commands::package::unpack::test_utils::{webc_fixture, webc_unpack, HELLO_WEBC};
+
+    #[test]
+    fn test_cmd_package_repack_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let unpacked = dir.path().join("unpacked");
+        let out = dir.path().join("repacked.webc");
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        webc_unpack(package_path.clone(), Some(unpacked.clone()))
+            .execute()
+            .unwrap();
+
+        PackageRepack {
+            out: out.clone(),
+            overwrite: false,
+            quiet: true,
+            unpacked_dir: unpacked,
+        }
+        .execute()
+        .unwrap();
+
+        let original = from_disk(&package_path).unwrap();
+        let repacked = from_disk(&out).unwrap();
+        assert_eq!(
+            original.manifest().commands.keys().collect::<Vec<_>>(),
+            repacked.manifest().commands.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            original.atoms().keys().collect::<Vec<_>>(),
+            repacked.atoms().keys().collect::<Vec<_>>()
+        );
+        for (name, atom) in original.atoms() {
+            assert_eq!(atom, repacked.get_atom(&name).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_cmd_package_repack_rejects_foreign_layout() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let err = validate_layout(dir.path()).unwrap_err();
+
+        assert!(err.to_string().contains("manifest.json"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Context;
+use wasmer_package::unpack::ContainerEntryKind;
+use webc::Container;
+
+use super::{runners::is_runner, PackageUnpack};
+
+impl PackageUnpack {
+    /// Write the `--format webc` layout to a zip archive at `path` (or
+    /// stdout, if `path` is `-`).
+    pub(super) fn write_zip(&self, pkg: &Container, path: &Path) -> Result<(), anyhow::Error> {
+        if path == Path::new("-") {
+            let stdout = std::io::BufWriter::new(std::io::stdout().lock());
+            let mut zip = zip::ZipWriter::new_stream(stdout);
+            self.write_zip_entries(pkg, &mut zip)?;
+            zip.finish()?.flush()?;
+        } else {
+            if path.exists() && !self.overwrite {
+                anyhow::bail!(
+                    "'{}' already exists - use --overwrite to replace it",
+                    path.display()
+                );
+            }
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("could not create '{}'", path.display()))?;
+            let mut zip = zip::ZipWriter::new(std::io::BufWriter::new(file));
+            self.write_zip_entries(pkg, &mut zip)?;
+            zip.finish()?.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_zip_entries<W: std::io::Write + std::io::Seek>(
+        &self,
+        pkg: &Container,
+        zip: &mut zip::ZipWriter<W>,
+    ) -> Result<(), anyhow::Error> {
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+
+        self.walk_webc(pkg, |kind, _, relative_path, contents| {
+            // Zip archives always use forward slashes
+            let name = relative_path
+                .iter()
+                .map(|segment| segment.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            match kind {
+                ContainerEntryKind::VolumeDirectory => zip.add_directory(name, options)?,
+                ContainerEntryKind::Atom if self.executable_atoms => {
+                    zip.start_file(name, options.unix_permissions(0o755))?;
+                    zip.write_all(contents)?;
+                }
+                ContainerEntryKind::Metadata if is_runner(relative_path) => {
+                    zip.start_file(name, options.unix_permissions(0o755))?;
+                    zip.write_all(contents)?;
+                }
+                _ => {
+                    zip.start_file(name, options)?;
+                    zip.write_all(contents)?;
+                }
+            }
+
+            Ok(())
+        })
+        .context("could not write the zip archive")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::package::unpack::test_utils::{webc_fixture, webc_unpack, HELLO_WEBC};
+
+    #[test]
+    fn test_cmd_package_extract_to_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("hello.zip");
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        let cmd = PackageUnpack {
+            zip: Some(archive.clone()),
+            ..webc_unpack(package_path, None)
+        };
+
+        cmd.execute().unwrap();
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&archive).unwrap()).unwrap();
+        let names: Vec<_> = zip.file_names().collect();
+        assert!(names.contains(&"manifest.json"));
+        assert!(names.contains(&"atom"));
+        assert!(names.iter().any(|name| name.starts_with("metadata/")));
+        let mut manifest = String::new();
+        std::io::Read::read_to_string(&mut zip.by_name("manifest.json").unwrap(), &mut manifest)
+            .unwrap();
+        assert!(manifest.contains("atoms"));
+        // Only the archive was written
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
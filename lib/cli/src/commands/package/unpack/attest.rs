@@ -0,0 +1,166 @@
+use std::path::Path;
+
+use anyhow::Context;
+use wasmer_package::unpack::ContainerEntryKind;
+use webc::{metadata::annotations::Wapm, Container};
+
+use super::{jsonl::entry_kind_name, to_slash_path, PackageUnpack};
+
+const IN_TOTO_STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+/// The predicate type of `--attest` attestations.
+const UNPACK_PREDICATE_TYPE: &str = "https://wasmer.io/attestations/package-unpack/v1";
+
+impl PackageUnpack {
+    /// Write the `--attest` attestation for the files `--format webc`
+    /// extracts from `pkg`.
+    pub(super) fn write_attestation(
+        &self,
+        pkg: &Container,
+        path: &Path,
+    ) -> Result<(), anyhow::Error> {
+        use sha2::Digest;
+
+        let mut subjects = Vec::new();
+        let mut files = serde_json::Map::new();
+
+        self.walk_webc(pkg, |kind, source, relative_path, contents| {
+            if kind == ContainerEntryKind::VolumeDirectory {
+                return Ok(());
+            }
+
+            let name = to_slash_path(relative_path);
+            subjects.push(serde_json::json!({
+                "name": name,
+                "digest": { "sha256": hex::encode(sha2::Sha256::digest(contents)) },
+            }));
+
+            let mut provenance = serde_json::json!({ "kind": entry_kind_name(kind) });
+            match source {
+                Some(entry) => {
+                    provenance["source"] = to_slash_path(entry.path()).into();
+                    if let Some(hash) = entry.hash() {
+                        provenance["sourceDigest"] =
+                            serde_json::json!({ "sha256": hex::encode(hash) });
+                    }
+                }
+                // Generated by --emit-manifest or --emit-runners
+                None => provenance["generated"] = true.into(),
+            }
+            files.insert(name, provenance);
+
+            Ok(())
+        })?;
+
+        let statement = provenance_statement(pkg, &self.fallback_name(), subjects, files)?;
+
+        let mut json = serde_json::to_vec_pretty(&statement)?;
+        json.push(b'\n');
+        std::fs::write(path, json)
+            .with_context(|| format!("could not write the attestation to '{}'", path.display()))
+    }
+}
+
+/// Wrap the `--attest` subjects and per-file provenance in an in-toto
+/// statement about `pkg`.
+///
+/// `fallback_name` is used for packages which don't have a name.
+fn provenance_statement(
+    pkg: &Container,
+    fallback_name: &str,
+    subjects: Vec<serde_json::Value>,
+    files: serde_json::Map<String, serde_json::Value>,
+) -> Result<serde_json::Value, anyhow::Error> {
+    let wapm = pkg
+        .manifest()
+        .wapm()
+        .context("could not read the package annotations")?
+        .unwrap_or_else(|| Wapm::new(None, None, None));
+
+    let mut package = serde_json::json!({
+        "name": wapm.name.unwrap_or_else(|| fallback_name.to_string()),
+    });
+    if let Some(version) = wapm.version {
+        package["version"] = version.into();
+    }
+    if let Some(hash) = pkg.webc_hash() {
+        package["digest"] = serde_json::json!({ "sha256": hex::encode(hash) });
+    }
+
+    Ok(serde_json::json!({
+        "_type": IN_TOTO_STATEMENT_TYPE,
+        "subject": subjects,
+        "predicateType": UNPACK_PREDICATE_TYPE,
+        "predicate": {
+            "package": package,
+            "unpackedBy": {
+                "name": "wasmer",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "files": files,
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer_package::utils::from_disk;
+
+    use super::*;
+    use crate::commands::package::unpack::{
+        test_utils::{webc_fixture, webc_unpack, DASH_WEBC},
+        WASMER_TOML_FILE_NAME,
+    };
+
+    #[test]
+    fn test_cmd_package_extract_with_attestation() {
+        use sha2::Digest;
+
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = dir.path().join("out");
+        let attestation = dir.path().join("attestation.json");
+
+        let package_path = webc_fixture(DASH_WEBC);
+
+        let cmd = PackageUnpack {
+            atom_name_template: "{name}.wasm".parse().unwrap(),
+            emit_manifest: true,
+            attest: Some(attestation.clone()),
+            ..webc_unpack(package_path.clone(), Some(out_dir.clone()))
+        };
+
+        cmd.execute().unwrap();
+
+        let statement: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&attestation).unwrap()).unwrap();
+        assert_eq!(statement["_type"], IN_TOTO_STATEMENT_TYPE);
+        assert_eq!(statement["predicateType"], UNPACK_PREDICATE_TYPE);
+
+        let predicate = &statement["predicate"];
+        assert_eq!(predicate["package"]["name"], "sharrattj/dash");
+        let pkg = from_disk(&package_path).unwrap();
+        if let Some(hash) = pkg.webc_hash() {
+            assert_eq!(predicate["package"]["digest"]["sha256"], hex::encode(hash));
+        }
+
+        // Every subject is a file on disk with the recorded hash
+        let subjects = statement["subject"].as_array().unwrap();
+        assert!(!subjects.is_empty());
+        for subject in subjects {
+            let name = subject["name"].as_str().unwrap();
+            let contents = std::fs::read(out_dir.join(name)).unwrap();
+            assert_eq!(
+                subject["digest"]["sha256"],
+                hex::encode(sha2::Sha256::digest(&contents)),
+                "{name}"
+            );
+            assert!(predicate["files"][name].is_object(), "{name}");
+        }
+
+        let dash = &predicate["files"]["dash.wasm"];
+        assert_eq!(dash["kind"], "atom");
+        assert_eq!(dash["source"], "dash");
+        let manifest = &predicate["files"][WASMER_TOML_FILE_NAME];
+        assert_eq!(manifest["generated"], true);
+        assert!(manifest.get("source").is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Context;
+
+/// How `package unpack --compress` compresses files.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The extension appended to compressed files.
+    pub(super) fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+
+    /// Compress `contents` into a new file at `path`.
+    pub(super) fn write_file(self, path: &Path, contents: &[u8]) -> Result<(), anyhow::Error> {
+        let error = || format!("could not write '{}'", path.display());
+        let file = std::fs::File::create(path).with_context(error)?;
+        let file = std::io::BufWriter::new(file);
+
+        let file = match self {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(file, flate2::Compression::default());
+                encoder.write_all(contents).with_context(error)?;
+                encoder.finish().with_context(error)?
+            }
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(file, 0).with_context(error)?;
+                encoder.write_all(contents).with_context(error)?;
+                encoder.finish().with_context(error)?
+            }
+        };
+        file.into_inner()
+            .map_err(|e| e.into_error())
+            .with_context(error)?;
+
+        Ok(())
+    }
+}
+
+/// Does `contents` start with the magic bytes of a format which is already
+/// compressed, so compressing it again would be a waste of time?
+pub(super) fn is_compressed(contents: &[u8]) -> bool {
+    const MAGIC: &[&[u8]] = &[
+        b"\x1f\x8b",          // gzip
+        b"\x28\xb5\x2f\xfd",  // zstd
+        b"\xfd7zXZ\x00",      // xz
+        b"BZh",               // bzip2
+        b"PK\x03\x04",        // zip
+        b"\x89PNG\r\n\x1a\n", // PNG
+        b"\xff\xd8\xff",      // JPEG
+        b"GIF8",              // GIF
+        b"wOF2",              // WOFF2
+    ];
+
+    MAGIC.iter().any(|magic| contents.starts_with(magic))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn compress_extracted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let contents = "hello world\n".repeat(100);
+
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let path = dir.path().join(compression.extension());
+            compression.write_file(&path, contents.as_bytes()).unwrap();
+            let compressed = std::fs::read(&path).unwrap();
+            assert!(compressed.len() < contents.len());
+            assert!(is_compressed(&compressed));
+
+            let mut decompressed = String::new();
+            match compression {
+                Compression::Gzip => flate2::read::GzDecoder::new(compressed.as_slice())
+                    .read_to_string(&mut decompressed)
+                    .unwrap(),
+                Compression::Zstd => zstd::Decoder::new(compressed.as_slice())
+                    .unwrap()
+                    .read_to_string(&mut decompressed)
+                    .unwrap(),
+            };
+            assert_eq!(decompressed, contents);
+        }
+
+        assert!(!is_compressed(b"\0asm"));
+    }
+}
@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Keeps track of the files extracted so far for `--dedup-hardlink`, so
+/// duplicates can be hardlinked to the first copy.
+#[derive(Debug, Default)]
+pub(super) struct HardlinkDedup {
+    /// The first file written with each hash. Executable files are kept
+    /// separate because hardlinks share their permissions.
+    first_copies: HashMap<(Vec<u8>, bool), PathBuf>,
+}
+
+impl HardlinkDedup {
+    /// Hardlink `path` to an earlier file with the same contents, returning
+    /// `false` if the file still needs to be written.
+    pub(super) fn link_or_remember(
+        &mut self,
+        path: &Path,
+        contents: &[u8],
+        executable: bool,
+    ) -> bool {
+        use sha2::Digest;
+
+        if contents.is_empty() {
+            return false;
+        }
+
+        let key = (sha2::Sha256::digest(contents).to_vec(), executable);
+        let Some(original) = self.first_copies.get(&key) else {
+            self.first_copies.insert(key, path.to_path_buf());
+            return false;
+        };
+
+        match std::fs::hard_link(original, path) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::debug!(
+                    original = %original.display(),
+                    path = %path.display(),
+                    error = &e as &dyn std::error::Error,
+                    "unable to hardlink a duplicate file, copying it instead",
+                );
+                false
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_identical_files() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.txt");
+        let duplicate = dir.path().join("duplicate.txt");
+        let different = dir.path().join("different.txt");
+        let mut dedup = HardlinkDedup::default();
+
+        assert!(!dedup.link_or_remember(&first, b"hello", false));
+        std::fs::write(&first, b"hello").unwrap();
+        assert!(dedup.link_or_remember(&duplicate, b"hello", false));
+        assert!(!dedup.link_or_remember(&different, b"world", false));
+        assert!(!dedup.link_or_remember(&different, b"hello", true));
+
+        let inode = |path: &Path| std::fs::metadata(path).unwrap().ino();
+        assert_eq!(inode(&first), inode(&duplicate));
+        assert_eq!(std::fs::read(&duplicate).unwrap(), b"hello");
+    }
+}
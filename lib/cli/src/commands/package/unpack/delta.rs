@@ -0,0 +1,175 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use wasmer_package::{
+    unpack::{container_entries, ContainerEntry},
+    utils::from_disk,
+};
+use webc::Container;
+
+/// The package annotation which marks a package as a delta against a base
+/// package.
+///
+/// Each entry in `entries` is identified by its path in the `--format webc`
+/// layout (so atoms are just their name) and stored as a patch against the
+/// entry with the same path in the base package.
+///
+/// The only supported `algorithm` is `"bsdiff-raw"` (see
+/// [`apply_raw_bsdiff()`]). Patches in the BSDIFF40 file format, with its
+/// header and bzip2-compressed blocks, are not accepted.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub(super) struct DeltaAnnotation {
+    algorithm: String,
+    entries: Vec<String>,
+}
+
+impl DeltaAnnotation {
+    pub(super) const KEY: &'static str = "delta";
+    const RAW_BSDIFF: &'static str = "bsdiff-raw";
+}
+
+pub(super) struct DeltaPatcher<'a> {
+    patched_entries: &'a [String],
+    base: BTreeMap<PathBuf, ContainerEntry>,
+}
+
+impl<'a> DeltaPatcher<'a> {
+    pub(super) fn from_base_file(
+        delta: &'a DeltaAnnotation,
+        path: &Path,
+    ) -> Result<Self, anyhow::Error> {
+        let base = from_disk(path)
+            .with_context(|| format!("could not open the base package at '{}'", path.display()))?;
+        DeltaPatcher::new(delta, &base)
+    }
+
+    fn new(delta: &'a DeltaAnnotation, base: &Container) -> Result<Self, anyhow::Error> {
+        if delta.algorithm != DeltaAnnotation::RAW_BSDIFF {
+            anyhow::bail!("unsupported delta algorithm, \"{}\"", delta.algorithm);
+        }
+
+        let mut entries = BTreeMap::new();
+        for entry in container_entries(base) {
+            let entry = entry.context("could not read the base package")?;
+            entries.insert(entry.path().to_path_buf(), entry);
+        }
+
+        Ok(DeltaPatcher {
+            patched_entries: &delta.entries,
+            base: entries,
+        })
+    }
+
+    pub(super) fn is_patched(&self, path: &Path) -> bool {
+        self.patched_entries
+            .iter()
+            .any(|entry| Path::new(entry) == path)
+    }
+
+    pub(super) fn apply(&self, path: &Path, patch: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        let base = self.base.get(path).with_context(|| {
+            format!(
+                "the base package doesn't contain '{}' - is it the right base?",
+                path.display()
+            )
+        })?;
+        apply_raw_bsdiff(base.contents().as_slice(), patch)
+            .with_context(|| format!("could not apply the patch for '{}'", path.display()))
+    }
+}
+
+/// Apply a `"bsdiff-raw"` patch.
+///
+/// This uses bsdiff's add/copy/seek instructions without the BSDIFF40
+/// header or compression. The patch is a sequence of blocks, each starting
+/// with three 8-byte little-endian sign-magnitude integers: the number of
+/// bytes to add to the old data, the number of extra bytes to copy verbatim,
+/// and how far to seek in the old data afterwards. The header is followed by
+/// the add bytes and then the extra bytes.
+fn apply_raw_bsdiff(old: &[u8], mut patch: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    fn read_offset(raw: &[u8]) -> i64 {
+        let raw: [u8; 8] = raw.try_into().unwrap();
+        let magnitude = (u64::from_le_bytes(raw) & !(1 << 63)) as i64;
+        if raw[7] & 0x80 != 0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    let mut new = Vec::new();
+    let mut old_pos: i64 = 0;
+
+    while !patch.is_empty() {
+        if patch.len() < 24 {
+            anyhow::bail!("the patch is truncated");
+        }
+        let add_len = usize::try_from(read_offset(&patch[..8]))?;
+        let copy_len = usize::try_from(read_offset(&patch[8..16]))?;
+        let seek = read_offset(&patch[16..24]);
+        patch = &patch[24..];
+
+        let block_len = add_len
+            .checked_add(copy_len)
+            .filter(|&len| len <= patch.len())
+            .context("the patch is truncated")?;
+        let (add, extra) = patch[..block_len].split_at(add_len);
+        patch = &patch[block_len..];
+
+        let old_chunk = usize::try_from(old_pos)
+            .ok()
+            .and_then(|start| old.get(start..start.checked_add(add_len)?));
+        let old_chunk = match old_chunk {
+            Some(chunk) => chunk,
+            None if add_len == 0 => &[],
+            None => anyhow::bail!("the patch reads past the end of the base"),
+        };
+
+        new.extend(add.iter().zip(old_chunk).map(|(a, o)| a.wrapping_add(*o)));
+        new.extend_from_slice(extra);
+        old_pos = old_pos
+            .checked_add(add_len as i64)
+            .and_then(|pos| pos.checked_add(seek))
+            .context("invalid seek in the patch")?;
+    }
+
+    Ok(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_raw_bsdiff_patch() {
+        fn offset(value: i64) -> [u8; 8] {
+            let magnitude = value.unsigned_abs().to_le_bytes();
+            let mut raw = magnitude;
+            if value < 0 {
+                raw[7] |= 0x80;
+            }
+            raw
+        }
+
+        let old = b"hello world";
+        // Keep "hello w" (adding zeroes), append "asm!!", then skip the
+        // rest of the old data
+        let patch = [
+            offset(7).as_slice(),
+            &offset(5),
+            &offset(4),
+            &[0; 7],
+            b"asm!!",
+        ]
+        .concat();
+
+        assert_eq!(apply_raw_bsdiff(old, &patch).unwrap(), b"hello wasm!!");
+        assert!(apply_raw_bsdiff(old, &patch[..patch.len() - 1]).is_err());
+
+        let too_long = [offset(20).as_slice(), &offset(0), &offset(0), &[0; 20]].concat();
+        assert!(apply_raw_bsdiff(old, &too_long).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use dialoguer::console::style;
+
+/// The differences between two sets of files, e.g. an extracted package and a
+/// reference directory.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub(in crate::commands::package) struct DirDiff {
+    /// Files which only exist in the new (extracted) tree.
+    pub(in crate::commands::package) added: Vec<PathBuf>,
+    /// Files which only exist in the old (reference) tree.
+    pub(in crate::commands::package) removed: Vec<PathBuf>,
+    /// Files whose contents differ.
+    pub(in crate::commands::package) changed: Vec<PathBuf>,
+}
+
+impl DirDiff {
+    pub(super) fn compute(actual: &Path, expected: &Path) -> Result<Self, anyhow::Error> {
+        Ok(DirDiff::between(&hash_tree(actual)?, &hash_tree(expected)?))
+    }
+
+    /// Compare two maps from relative paths to content hashes.
+    pub(in crate::commands::package) fn between(
+        actual: &BTreeMap<PathBuf, Vec<u8>>,
+        expected: &BTreeMap<PathBuf, Vec<u8>>,
+    ) -> Self {
+        let mut diff = DirDiff::default();
+        for (path, hash) in actual {
+            match expected.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(other) if other != hash => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = expected
+            .keys()
+            .filter(|path| !actual.contains_key(*path))
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    pub(in crate::commands::package) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl std::fmt::Display for DirDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for path in &self.added {
+            writeln!(f, "{} {}", style("+").green(), path.display())?;
+        }
+        for path in &self.removed {
+            writeln!(f, "{} {}", style("-").red(), path.display())?;
+        }
+        for path in &self.changed {
+            writeln!(f, "{} {}", style("~").yellow(), path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Hash every file under `root`, keyed by its path relative to `root`.
+///
+/// Directories are included with an empty hash so empty directories are
+/// compared too.
+fn hash_tree(root: &Path) -> Result<BTreeMap<PathBuf, Vec<u8>>, anyhow::Error> {
+    use sha2::Digest;
+
+    if !root.is_dir() {
+        anyhow::bail!("'{}' is not a directory", root.display());
+    }
+
+    let mut hashes = BTreeMap::new();
+    for entry in walkdir::WalkDir::new(root).min_depth(1) {
+        let entry = entry.with_context(|| format!("could not walk '{}'", root.display()))?;
+        let relative = entry.path().strip_prefix(root)?.to_path_buf();
+        let hash = if entry.file_type().is_dir() {
+            Vec::new()
+        } else {
+            let contents = std::fs::read(entry.path())
+                .with_context(|| format!("could not read '{}'", entry.path().display()))?;
+            sha2::Sha256::digest(&contents).to_vec()
+        };
+        hashes.insert(relative, hash);
+    }
+
+    Ok(hashes)
+}
@@ -0,0 +1,154 @@
+use std::{
+    io::Read,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use indicatif::{ProgressBar, ProgressStyle};
+use wasmer_package::utils::{from_bytes, from_disk};
+use webc::Container;
+
+use super::PackageUnpack;
+
+impl PackageUnpack {
+    /// Read the package from disk, or download it if `package_path` is a
+    /// URL.
+    ///
+    /// Downloads show their progress unless `pb` is hidden.
+    pub(super) fn load_package(&self, pb: &ProgressBar) -> Result<Container, anyhow::Error> {
+        let url = self
+            .package_path
+            .to_str()
+            .and_then(|s| url::Url::parse(s).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"));
+        let Some(url) = url else {
+            return Ok(from_disk(&self.package_path)?);
+        };
+
+        let response = reqwest::blocking::get(url)
+            .and_then(|response| response.error_for_status())
+            .context("unable to download the package")?;
+        let download = if pb.is_hidden() {
+            ProgressBar::hidden()
+        } else {
+            download_progress_bar(response.content_length())
+        };
+        let reader: Box<dyn Read> = match self.limit_rate {
+            Some(rate) => Box::new(RateLimitedReader::new(response, rate.as_u64())),
+            None => Box::new(response),
+        };
+        let mut contents = Vec::new();
+        download
+            .wrap_read(reader)
+            .read_to_end(&mut contents)
+            .context("unable to download the package")?;
+        download.finish_and_clear();
+
+        Ok(from_bytes(contents)?)
+    }
+}
+
+/// A progress bar for downloading a package, showing the throughput and,
+/// if the package's size is known, roughly how long is left.
+///
+/// Both are based on indicatif's moving average of the recent download
+/// rate, so they follow changes in the link speed.
+fn download_progress_bar(total_bytes: Option<u64>) -> ProgressBar {
+    let (pb, template) = match total_bytes.filter(|&len| len > 0) {
+        Some(len) => (
+            ProgressBar::new(len),
+            "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta} left)",
+        ),
+        None => (
+            ProgressBar::new_spinner(),
+            "{spinner:.green} [{elapsed_precise}] {bytes} ({bytes_per_sec})",
+        ),
+    };
+    pb.set_style(
+        ProgressStyle::with_template(template)
+            .expect("the template is valid")
+            .progress_chars("#>-"),
+    );
+    pb.enable_steady_tick(Duration::from_millis(100));
+    pb
+}
+
+/// A reader which throttles reads from `inner` to `bytes_per_sec`, using a
+/// token bucket which can hold up to one second's worth of bytes.
+struct RateLimitedReader<R> {
+    inner: R,
+    bytes_per_sec: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl<R> RateLimitedReader<R> {
+    fn new(inner: R, bytes_per_sec: u64) -> Self {
+        RateLimitedReader {
+            inner,
+            bytes_per_sec: bytes_per_sec.max(1) as f64,
+            tokens: 0.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Wait until at least one byte may be read, returning how many bytes
+    /// can be read right now.
+    fn acquire(&mut self) -> usize {
+        loop {
+            let now = Instant::now();
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.bytes_per_sec);
+            self.last_refill = now;
+
+            if self.tokens >= 1.0 {
+                return self.tokens as usize;
+            }
+
+            let wait = (1.0 - self.tokens) / self.bytes_per_sec;
+            std::thread::sleep(Duration::from_secs_f64(wait));
+        }
+    }
+}
+
+impl<R: Read> Read for RateLimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let allowed = self.acquire().min(buf.len());
+        let bytes_read = self.inner.read(&mut buf[..allowed])?;
+        self.tokens -= bytes_read as f64;
+        Ok(bytes_read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_progress_bars() {
+        let known = download_progress_bar(Some(1024));
+        assert_eq!(known.length(), Some(1024));
+        known.finish_and_clear();
+
+        let unknown = download_progress_bar(None);
+        assert_eq!(unknown.length(), None);
+        unknown.finish_and_clear();
+    }
+
+    #[test]
+    fn test_rate_limited_reader_throttles_reads() {
+        let data = vec![42_u8; 300];
+        let mut reader = RateLimitedReader::new(std::io::Cursor::new(data.clone()), 1000);
+
+        let start = Instant::now();
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents).unwrap();
+
+        assert_eq!(contents, data);
+        assert!(start.elapsed() >= Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+/// The package annotation which marks atoms as encrypted.
+///
+/// Each encrypted atom is stored as a 12-byte nonce followed by the
+/// AES-256-GCM ciphertext and tag, with the atom's name used as the
+/// associated data.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub(super) struct EncryptionAnnotation {
+    algorithm: String,
+    atoms: Vec<String>,
+}
+
+impl EncryptionAnnotation {
+    pub(super) const KEY: &'static str = "encryption";
+    const AES_256_GCM: &'static str = "aes-256-gcm";
+}
+
+pub(super) struct AtomDecryptor<'a> {
+    encrypted_atoms: &'a [String],
+    key: ring::aead::LessSafeKey,
+}
+
+impl<'a> AtomDecryptor<'a> {
+    pub(super) fn from_key_file(
+        encryption: &'a EncryptionAnnotation,
+        path: &Path,
+    ) -> Result<Self, anyhow::Error> {
+        let raw = std::fs::read(path)
+            .with_context(|| format!("could not read the key from '{}'", path.display()))?;
+        AtomDecryptor::new(encryption, &raw)
+    }
+
+    fn new(encryption: &'a EncryptionAnnotation, raw_key: &[u8]) -> Result<Self, anyhow::Error> {
+        if encryption.algorithm != EncryptionAnnotation::AES_256_GCM {
+            anyhow::bail!(
+                "unsupported encryption algorithm, \"{}\"",
+                encryption.algorithm
+            );
+        }
+
+        let key = match std::str::from_utf8(raw_key).map(str::trim) {
+            Ok(hex_key) if hex_key.len() == 64 => {
+                hex::decode(hex_key).context("the key is not valid hex")?
+            }
+            _ => raw_key.to_vec(),
+        };
+        let key = ring::aead::UnboundKey::new(&ring::aead::AES_256_GCM, &key)
+            .map_err(|_| anyhow::anyhow!("the key must be exactly 32 bytes long"))?;
+
+        Ok(AtomDecryptor {
+            encrypted_atoms: &encryption.atoms,
+            key: ring::aead::LessSafeKey::new(key),
+        })
+    }
+
+    pub(super) fn is_encrypted(&self, atom: &str) -> bool {
+        self.encrypted_atoms.iter().any(|name| name == atom)
+    }
+
+    pub(super) fn decrypt(&self, atom: &str, data: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+        if data.len() < ring::aead::NONCE_LEN {
+            anyhow::bail!("the encrypted atom \"{atom}\" is truncated");
+        }
+        let (nonce, ciphertext) = data.split_at(ring::aead::NONCE_LEN);
+        let nonce = ring::aead::Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow::anyhow!("invalid nonce for atom \"{atom}\""))?;
+
+        let mut buffer = ciphertext.to_vec();
+        let plaintext_len = self
+            .key
+            .open_in_place(nonce, ring::aead::Aad::from(atom.as_bytes()), &mut buffer)
+            .map_err(|_| {
+                anyhow::anyhow!("could not decrypt the atom \"{atom}\" - is the key correct?")
+            })?
+            .len();
+        buffer.truncate(plaintext_len);
+
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_atoms() {
+        use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+
+        let key = [7_u8; 32];
+        let nonce = [1_u8; 12];
+        let mut encrypted = b"\0asm".to_vec();
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).unwrap())
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from("python"),
+                &mut encrypted,
+            )
+            .unwrap();
+        let encrypted = [nonce.as_slice(), &encrypted].concat();
+        let annotation = EncryptionAnnotation {
+            algorithm: EncryptionAnnotation::AES_256_GCM.to_string(),
+            atoms: vec!["python".to_string()],
+        };
+
+        let decryptor = AtomDecryptor::new(&annotation, hex::encode(key).as_bytes()).unwrap();
+        assert!(decryptor.is_encrypted("python"));
+        assert!(!decryptor.is_encrypted("other"));
+        assert_eq!(decryptor.decrypt("python", &encrypted).unwrap(), b"\0asm");
+        // The atom name is authenticated
+        assert!(decryptor.decrypt("other", &encrypted).is_err());
+
+        let wrong_key = AtomDecryptor::new(&annotation, &[8_u8; 32]).unwrap();
+        let err = wrong_key.decrypt("python", &encrypted).unwrap_err();
+        assert!(err.to_string().contains("is the key correct?"));
+
+        assert!(AtomDecryptor::new(&annotation, b"too short").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use std::{io::Write, path::Path};
+
+use wasmer_package::unpack::ContainerEntryKind;
+
+use super::to_slash_path;
+
+/// How `--output jsonl` and `--attest` refer to each kind of entry.
+pub(super) fn entry_kind_name(kind: ContainerEntryKind) -> &'static str {
+    match kind {
+        ContainerEntryKind::Metadata => "metadata",
+        ContainerEntryKind::Atom => "atom",
+        ContainerEntryKind::VolumeDirectory => "directory",
+        ContainerEntryKind::VolumeFile => "file",
+    }
+}
+
+/// Write a line describing an extracted entry for `--output jsonl`.
+pub(super) fn write_jsonl_entry(
+    out: &mut impl Write,
+    kind: ContainerEntryKind,
+    path: &Path,
+    bytes: usize,
+) -> Result<(), anyhow::Error> {
+    #[derive(serde::Serialize)]
+    struct Entry<'a> {
+        path: &'a str,
+        bytes: usize,
+        kind: &'static str,
+    }
+
+    let path = to_slash_path(path);
+    let kind = entry_kind_name(kind);
+
+    serde_json::to_writer(
+        &mut *out,
+        &Entry {
+            path: &path,
+            bytes,
+            kind,
+        },
+    )?;
+    out.write_all(b"\n")?;
+    // Consumers read these as they happen, so don't let them sit in a buffer
+    out.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jsonl_entries() {
+        let mut out = Vec::new();
+
+        write_jsonl_entry(
+            &mut out,
+            ContainerEntryKind::VolumeFile,
+            &Path::new("public").join("index.html"),
+            5,
+        )
+        .unwrap();
+        write_jsonl_entry(&mut out, ContainerEntryKind::Atom, Path::new("python"), 42).unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                serde_json::json!({"path": "public/index.html", "bytes": 5, "kind": "file"}),
+                serde_json::json!({"path": "python", "bytes": 42, "kind": "atom"}),
+            ]
+        );
+    }
+}
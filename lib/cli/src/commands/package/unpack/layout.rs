@@ -0,0 +1,83 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+
+/// A `--layout` file, mapping atom names and volume files to the paths
+/// they should be extracted to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(super) struct Layout {
+    destinations: BTreeMap<String, PathBuf>,
+}
+
+impl Layout {
+    pub(super) fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read '{}'", path.display()))?;
+        raw.parse()
+            .with_context(|| format!("invalid layout file '{}'", path.display()))
+    }
+
+    pub(super) fn destination(&self, name: &str) -> Option<&Path> {
+        self.destinations.get(name).map(PathBuf::as_path)
+    }
+}
+
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw: BTreeMap<String, PathBuf> = toml::from_str(s)?;
+
+        let mut destinations = BTreeMap::new();
+        for (name, destination) in raw {
+            let is_relative = destination
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)));
+            if destination.as_os_str().is_empty() || !is_relative {
+                anyhow::bail!(
+                    "\"{name}\" must be extracted to a path inside the output directory, not \"{}\"",
+                    destination.display()
+                );
+            }
+            if destinations.values().any(|d| *d == destination) {
+                anyhow::bail!(
+                    "more than one entry is extracted to \"{}\"",
+                    destination.display()
+                );
+            }
+            destinations.insert(name, destination);
+        }
+
+        Ok(Layout { destinations })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_layouts() {
+        let layout: Layout = r#"
+            python = "bin/python3"
+            "public/index.html" = "www/index.html"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(layout.destination("python"), Some(Path::new("bin/python3")));
+        assert_eq!(
+            layout.destination("public/index.html"),
+            Some(Path::new("www/index.html"))
+        );
+        assert_eq!(layout.destination("public"), None);
+
+        assert!("python = '../python'".parse::<Layout>().is_err());
+        assert!("python = '/usr/bin/python'".parse::<Layout>().is_err());
+        assert!("python = ''".parse::<Layout>().is_err());
+        assert!("a = 'bin/a'\nb = 'bin/a'".parse::<Layout>().is_err());
+    }
+}
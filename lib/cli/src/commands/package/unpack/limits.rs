@@ -0,0 +1,71 @@
+use bytesize::ByteSize;
+
+/// Enforces `--max-entries` and `--max-total-bytes` while a package is
+/// being walked.
+#[derive(Debug, Default)]
+pub(super) struct ExtractionLimits {
+    max_entries: Option<u64>,
+    max_total_bytes: Option<u64>,
+    entries: u64,
+    total_bytes: u64,
+}
+
+impl ExtractionLimits {
+    pub(super) fn new(max_entries: Option<u64>, max_total_bytes: Option<u64>) -> Self {
+        ExtractionLimits {
+            max_entries,
+            max_total_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// Count another entry of `len` bytes, failing if that goes over either
+    /// limit.
+    pub(super) fn admit(&mut self, len: usize) -> Result<(), anyhow::Error> {
+        self.entries += 1;
+        self.total_bytes = self.total_bytes.saturating_add(len as u64);
+
+        if let Some(max) = self.max_entries.filter(|&max| self.entries > max) {
+            anyhow::bail!(
+                "the package has more than {max} entries (--max-entries) - stopped at entry {} after {} bytes",
+                self.entries,
+                self.total_bytes,
+            );
+        }
+        if let Some(max) = self.max_total_bytes.filter(|&max| self.total_bytes > max) {
+            anyhow::bail!(
+                "the package is larger than {} (--max-total-bytes) - reached {} at entry {}",
+                ByteSize(max),
+                ByteSize(self.total_bytes),
+                self.entries,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extraction_limits() {
+        let mut limits = ExtractionLimits {
+            max_entries: Some(2),
+            ..Default::default()
+        };
+        limits.admit(10).unwrap();
+        limits.admit(10).unwrap();
+        let err = limits.admit(10).unwrap_err();
+        assert!(err.to_string().contains("more than 2 entries"), "{err}");
+
+        let mut limits = ExtractionLimits {
+            max_total_bytes: Some(100),
+            ..Default::default()
+        };
+        limits.admit(100).unwrap();
+        let err = limits.admit(1).unwrap_err();
+        assert!(err.to_string().contains("at entry 2"), "{err}");
+    }
+}
@@ -0,0 +1,1215 @@
+mod archive;
+mod attest;
+mod compression;
+mod dedup;
+mod delta;
+mod dir_diff;
+mod download;
+mod encryption;
+mod jsonl;
+mod layout;
+mod limits;
+mod oci;
+mod resume;
+mod runners;
+mod sbom;
+mod staging;
+mod template;
+#[cfg(test)]
+pub(super) mod test_utils;
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use bytesize::ByteSize;
+use dialoguer::console::{style, Emoji};
+use indicatif::ProgressBar;
+use wasmer_package::unpack::{
+    container_entries, ContainerEntry, ContainerEntryKind, MANIFEST_FILE_NAME,
+};
+use webc::Container;
+
+pub use self::{compression::Compression, template::AtomNameTemplate};
+
+pub(super) use self::dir_diff::DirDiff;
+
+use self::{
+    compression::is_compressed,
+    dedup::HardlinkDedup,
+    delta::{DeltaAnnotation, DeltaPatcher},
+    encryption::{AtomDecryptor, EncryptionAnnotation},
+    jsonl::write_jsonl_entry,
+    layout::Layout,
+    limits::ExtractionLimits,
+    resume::ResumeIndex,
+    runners::{is_runner, runner_scripts},
+    staging::{create_staging_dir, is_empty_dir, move_into_place},
+};
+
+/// Extract contents of a webc image to a directory.
+///
+/// See --format flag for available output formats.
+#[derive(clap::Parser, Debug)]
+pub struct PackageUnpack {
+    /// The output directory.
+    #[clap(
+        short = 'o',
+        long,
+        required_unless_present_any = ["zip", "oci_layout"],
+        conflicts_with_all = ["zip", "oci_layout"]
+    )]
+    pub out_dir: Option<PathBuf>,
+
+    /// Overwrite existing directories/files.
+    #[clap(long)]
+    pub overwrite: bool,
+
+    /// Run the unpack command without any output
+    #[clap(long)]
+    pub quiet: bool,
+
+    /// Path to the package, or an `http://` or `https://` URL to download
+    /// it from.
+    pub package_path: PathBuf,
+
+    /// Output format.
+    ///
+    /// * package
+    ///   Restore a package directory with a wasmer.toml
+    ///   NOTE: this conversion is lossy, because webcs don't store the original
+    ///   wasmer.toml and the full contents can not be restored.
+    ///
+    /// * webc
+    ///   Directly unpack the webc contents.
+    ///   - Volumes will be placed in subdirectories.
+    ///   - atoms will be placed in the root directory
+    ///   - the full webc manifest will be placed in a manifest.json file
+    #[clap(short, long, default_value = "package")]
+    pub format: Format,
+
+    /// Template used for the file names of extracted atoms.
+    ///
+    /// Only supported with `--format webc`. The placeholders `{name}`,
+    /// `{version}` and `{hash}` are replaced with the atom name, the package
+    /// version and the hex-encoded sha256 hash of the atom respectively.
+    #[clap(long, default_value = AtomNameTemplate::DEFAULT)]
+    pub atom_name_template: AtomNameTemplate,
+
+    /// Mark extracted atoms as executable (mode 0o755).
+    ///
+    /// Only supported with `--format webc`, and ignored on platforms without
+    /// Unix permissions.
+    #[clap(long)]
+    pub executable_atoms: bool,
+
+    /// Compare the extracted files against a reference directory.
+    ///
+    /// Files are matched by relative path and sha256 hash. Any additions,
+    /// removals or changes are printed and the command fails.
+    #[clap(long, conflicts_with_all = ["zip", "oci_layout"])]
+    pub compare_to: Option<PathBuf>,
+
+    /// Also generate a wasmer.toml describing the extracted package, so the
+    /// output directory can be used with `wasmer run`.
+    ///
+    /// Only supported with `--format webc` (the `package` format always
+    /// includes a wasmer.toml).
+    #[clap(long, conflicts_with_all = ["metadata_only", "layout_strict"])]
+    pub emit_manifest: bool,
+
+    /// A file containing the AES-256 key used to decrypt encrypted atoms,
+    /// either as 32 raw bytes or 64 hex characters.
+    ///
+    /// Only supported with `--format webc`. Packages which don't mark any
+    /// atoms as encrypted ignore this flag.
+    #[clap(long)]
+    pub decrypt_key: Option<PathBuf>,
+
+    /// Extract directly into the output directory instead of extracting into
+    /// a temporary directory next to it and renaming that into place.
+    ///
+    /// By default a failed unpack leaves the output directory untouched. Use
+    /// this when the output directory's parent can't be written to, at the
+    /// cost of leaving partially extracted files behind on failure.
+    #[clap(long)]
+    pub no_atomic: bool,
+
+    /// Don't rewrite files which already exist with the right contents.
+    ///
+    /// Only supported with `--format webc`. This unpacks in place (like
+    /// `--no-atomic`) into an output directory which may already contain a
+    /// previous unpack, so repeatedly unpacking the same package is cheap.
+    /// Files with different contents are only replaced with `--overwrite`.
+    #[clap(long, conflicts_with_all = ["zip", "oci_layout"])]
+    pub skip_if_present: bool,
+
+    /// Write the package's contents to a zip archive instead of a directory,
+    /// using `-` for stdout.
+    ///
+    /// Only supported with `--format webc`. Nothing is written to disk when
+    /// streaming to stdout.
+    #[clap(long)]
+    pub zip: Option<PathBuf>,
+
+    /// Only extract the manifest.json and the metadata volume, skipping
+    /// atoms and all other volumes.
+    ///
+    /// Only supported with `--format webc`. This is much faster than a full
+    /// unpack when only the package's metadata needs to be inspected.
+    #[clap(long)]
+    pub metadata_only: bool,
+
+    /// Limit how fast the package is downloaded, in bytes per second (e.g.
+    /// `500KiB` or `2MB`).
+    ///
+    /// Only used when the package is a URL.
+    #[clap(long, value_name = "BYTES_PER_SEC")]
+    pub limit_rate: Option<ByteSize>,
+
+    /// The package a delta package was created against.
+    ///
+    /// Only supported with `--format webc`. Delta packages store some of
+    /// their entries as binary patches against the base package (in the
+    /// "bsdiff-raw" format), which are applied while extracting. Full
+    /// packages ignore this flag.
+    #[clap(long, value_name = "CONTAINER")]
+    pub base: Option<PathBuf>,
+
+    /// How to report progress.
+    ///
+    /// * human
+    ///   Progress messages for people to read.
+    ///
+    /// * jsonl
+    ///   Also print a JSON object to stdout for each entry as it is
+    ///   extracted, with its `path`, size in `bytes` and `kind`. Only
+    ///   supported with `--format webc`.
+    #[clap(long, default_value = "human")]
+    pub output: Output,
+
+    /// Hardlink files with identical contents to the first copy instead of
+    /// writing each of them out.
+    ///
+    /// Only supported with `--format webc` on Unix. Files are copied as
+    /// usual when they can't be hardlinked (e.g. across devices).
+    #[clap(long, conflicts_with_all = ["zip", "oci_layout"])]
+    pub dedup_hardlink: bool,
+
+    /// A TOML file mapping atom names and volume files to where they should
+    /// be extracted, relative to the output directory.
+    ///
+    /// Only supported with `--format webc`. Volume files are named by the
+    /// path they would normally be extracted to (e.g.
+    /// `"public/index.html" = "www/index.html"`). Directories can't be
+    /// remapped. Entries which aren't in the file are extracted as usual.
+    #[clap(long, value_name = "FILE")]
+    pub layout: Option<PathBuf>,
+
+    /// Skip every entry which isn't in the `--layout` file.
+    #[clap(long, requires = "layout")]
+    pub layout_strict: bool,
+
+    /// Check that every WebAssembly atom is valid, printing a warning for
+    /// any that aren't.
+    ///
+    /// Only supported with `--format webc`. Atoms which aren't WebAssembly
+    /// are skipped.
+    #[clap(long)]
+    pub validate_wasm: bool,
+
+    /// Fail if any atom doesn't pass `--validate-wasm`.
+    #[clap(long, requires = "validate_wasm")]
+    pub validate_wasm_strict: bool,
+
+    /// A shell command to run in the output directory once the package has
+    /// been extracted (and matched `--compare-to`, if given).
+    ///
+    /// The command is run by `sh -c` (`cmd /C` on Windows) with
+    /// `WASMER_OUT_DIR` set to the output directory and
+    /// `WASMER_PACKAGE_NAME` set to the package's name, if it has one. If it
+    /// exits unsuccessfully, so does the unpack.
+    ///
+    /// The command runs with your privileges and without any sandboxing, so
+    /// never pass one that comes from, or is built from, anything you don't
+    /// trust - including the package being unpacked.
+    #[clap(long, value_name = "CMD", conflicts_with_all = ["zip", "oci_layout"])]
+    pub post_hook: Option<String>,
+
+    /// Abort if the package has more than this many entries to extract,
+    /// to guard against malicious packages.
+    ///
+    /// Only supported with `--format webc`.
+    #[clap(long, value_name = "N")]
+    pub max_entries: Option<u64>,
+
+    /// Abort if extracting the package would write more than this many
+    /// bytes in total (e.g. `500MiB`), to guard against malicious packages.
+    ///
+    /// Only supported with `--format webc`.
+    #[clap(long, value_name = "BYTES")]
+    pub max_total_bytes: Option<ByteSize>,
+
+    /// Make the unpack resumable, picking up where a previous `--resume`
+    /// unpack into the same directory was interrupted.
+    ///
+    /// Entries are extracted in place and recorded in a `.wasmer-unpack-index`
+    /// file in the output directory as they are written. Entries already in
+    /// the index are skipped as long as their contents haven't changed. The
+    /// index is removed once the unpack finishes. Only supported with
+    /// `--format webc`.
+    #[clap(long, conflicts_with_all = ["zip", "oci_layout"])]
+    pub resume: bool,
+
+    /// Compress each extracted file, appending `.gz` or `.zst` to its name.
+    ///
+    /// Files smaller than `--compress-min-size` and files which are already
+    /// compressed (e.g. PNGs or zip archives) are written as-is. Only
+    /// supported with `--format webc`.
+    #[clap(
+        long,
+        value_name = "ALGORITHM",
+        conflicts_with_all = [
+            "zip",
+            "oci_layout",
+            "skip_if_present",
+            "dedup_hardlink",
+            "resume",
+            "attest",
+        ]
+    )]
+    pub compress: Option<Compression>,
+
+    /// The smallest file `--compress` will compress.
+    #[clap(long, value_name = "BYTES", default_value = "4KiB")]
+    pub compress_min_size: ByteSize,
+
+    /// Write a CycloneDX software bill of materials (SBOM) for the package
+    /// to this file.
+    ///
+    /// The SBOM lists the package and its atoms with their SHA-256 hashes,
+    /// along with the dependencies named in the package's manifest.
+    #[clap(long, value_name = "PATH")]
+    pub sbom: Option<PathBuf>,
+
+    /// Also generate a wrapper script for each of the package's commands,
+    /// which invokes `wasmer run` on the extracted atom with the command's
+    /// default arguments.
+    ///
+    /// Scripts are written to the `bin/` directory as `<command>` shell
+    /// scripts (`<command>.cmd` on Windows). Commands whose atom comes from
+    /// a dependency are skipped. Only supported with `--format webc`.
+    #[clap(long, conflicts_with_all = ["metadata_only", "layout_strict"])]
+    pub emit_runners: bool,
+
+    /// Export the package as an OCI image layout in this directory instead
+    /// of extracting it, so it can be used with OCI tooling like `skopeo`
+    /// or `crane`.
+    ///
+    /// Each atom is stored as its own layer, the package's volumes are
+    /// bundled into a single tar layer, and the webc manifest is used as the
+    /// image config. Only supported with `--format webc`.
+    #[clap(long, value_name = "DIR", conflicts_with = "zip")]
+    pub oci_layout: Option<PathBuf>,
+
+    /// Write an in-toto attestation to this file which records, for every
+    /// extracted file, the atom or volume entry it came from and the hash of
+    /// the package it was extracted from.
+    ///
+    /// Each extracted file is a subject of the attestation, along with its
+    /// SHA-256 hash. Only supported with `--format webc`, and can't be used
+    /// with `--compress`.
+    #[clap(long, value_name = "PATH", conflicts_with = "oci_layout")]
+    pub attest: Option<PathBuf>,
+}
+
+const WASMER_TOML_FILE_NAME: &str = "wasmer.toml";
+const METADATA_VOLUME_NAME: &str = "metadata";
+
+static PACKAGE_EMOJI: Emoji<'_, '_> = Emoji("📦 ", "");
+static EXTRACTED_TO_EMOJI: Emoji<'_, '_> = Emoji("📂 ", "");
+
+/// Webc unpack format.
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub enum Format {
+    /// See [`PackageUnpack::format`] for details.
+    Package,
+    /// See [`PackageUnpack::format`] for details.
+    Webc,
+}
+
+/// How `package unpack` reports progress.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Output {
+    /// See [`PackageUnpack::output`] for details.
+    #[default]
+    Human,
+    /// See [`PackageUnpack::output`] for details.
+    Jsonl,
+}
+
+impl PackageUnpack {
+    pub(crate) fn execute(&self) -> Result<(), anyhow::Error> {
+        if matches!(self.format, Format::Package) {
+            if let Some(flag) = self.webc_only_flag() {
+                anyhow::bail!("{flag} is only supported with --format webc");
+            }
+        }
+        if self.output == Output::Jsonl {
+            if self.zip.is_some() {
+                anyhow::bail!("--output jsonl can't be used with --zip");
+            }
+            if self.oci_layout.is_some() {
+                anyhow::bail!("--output jsonl can't be used with --oci-layout");
+            }
+        }
+
+        // Setup the progress bar
+        let pb = if self.quiet {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new_spinner()
+        };
+
+        pb.println(format!(
+            "{} {}Unpacking...",
+            style("[1/2]").bold().dim(),
+            PACKAGE_EMOJI
+        ));
+
+        let pkg = self.load_package(&pb).with_context(|| {
+            format!(
+                "could not open package at '{}'",
+                self.package_path.display()
+            )
+        })?;
+
+        if let Some(zip) = &self.zip {
+            self.write_zip(&pkg, zip)?;
+            if let Some(sbom) = &self.sbom {
+                self.write_sbom(&pkg, sbom)?;
+            }
+            if let Some(attest) = &self.attest {
+                self.write_attestation(&pkg, attest)?;
+            }
+            pb.finish_and_clear();
+            return Ok(());
+        }
+
+        if let Some(layout) = &self.oci_layout {
+            self.write_oci_layout(&pkg, layout)?;
+            if let Some(sbom) = &self.sbom {
+                self.write_sbom(&pkg, sbom)?;
+            }
+            pb.println(format!(
+                "{} {}Exported package as an OCI image layout to '{}'",
+                style("[2/2]").bold().dim(),
+                EXTRACTED_TO_EMOJI,
+                layout.display()
+            ));
+            pb.finish();
+            return Ok(());
+        }
+
+        let outdir = self
+            .out_dir
+            .as_deref()
+            .context("no output directory was specified")?;
+
+        // The extracted files are merged with whatever is already in the
+        // output directory, which `--format webc` only does with
+        // `--overwrite` (or when picking up a previous unpack).
+        let in_place = self.no_atomic || self.skip_if_present || self.resume;
+        let requires_empty =
+            matches!(self.format, Format::Webc) && !self.skip_if_present && !self.resume;
+        if requires_empty && !self.overwrite && !is_empty_dir(outdir)? {
+            anyhow::bail!(
+                "output directory '{}' is not empty - use --overwrite to unpack anyway",
+                outdir.display()
+            );
+        }
+
+        let stats = if in_place {
+            std::fs::create_dir_all(outdir).with_context(|| {
+                format!("could not create output directory '{}'", outdir.display())
+            })?;
+            self.extract(&pkg, outdir)?
+        } else {
+            let staging = create_staging_dir(outdir)?;
+            let stats = self.extract(&pkg, staging.path())?;
+            move_into_place(staging, outdir)?;
+            stats
+        };
+
+        pb.println(format!(
+            "{} {}Extracted package contents to '{}'",
+            style("[2/2]").bold().dim(),
+            EXTRACTED_TO_EMOJI,
+            outdir.display()
+        ));
+        if self.skip_if_present || self.resume {
+            pb.println(format!(
+                "Wrote {} files, skipped {} unchanged files",
+                stats.written, stats.skipped
+            ));
+        }
+        if self.dedup_hardlink {
+            pb.println(format!("Hardlinked {} duplicate files", stats.linked));
+        }
+
+        if let Some(reference) = &self.compare_to {
+            let diff = DirDiff::compute(outdir, reference)?;
+            if !diff.is_empty() {
+                pb.finish_and_clear();
+                eprint!("{diff}");
+                anyhow::bail!(
+                    "the extracted package does not match '{}'",
+                    reference.display()
+                );
+            }
+        }
+
+        if let Some(sbom) = &self.sbom {
+            self.write_sbom(&pkg, sbom)?;
+        }
+
+        if let Some(attest) = &self.attest {
+            self.write_attestation(&pkg, attest)?;
+        }
+
+        if let Some(hook) = &self.post_hook {
+            self.run_post_hook(hook, &pkg, outdir)?;
+        }
+
+        pb.finish();
+
+        Ok(())
+    }
+
+    /// The name to use for packages which don't have one.
+    fn fallback_name(&self) -> String {
+        self.package_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Run the `--post-hook` command in the output directory.
+    fn run_post_hook(
+        &self,
+        hook: &str,
+        pkg: &Container,
+        outdir: &Path,
+    ) -> Result<(), anyhow::Error> {
+        let outdir = outdir.canonicalize().with_context(|| {
+            format!("could not resolve output directory '{}'", outdir.display())
+        })?;
+        let package_name = pkg
+            .manifest()
+            .wapm()
+            .context("could not read the package annotations")?
+            .and_then(|wapm| wapm.name);
+
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = std::process::Command::new("cmd");
+            cmd.arg("/C").arg(hook);
+            cmd
+        } else {
+            let mut cmd = std::process::Command::new("sh");
+            cmd.arg("-c").arg(hook);
+            cmd
+        };
+        cmd.current_dir(&outdir).env("WASMER_OUT_DIR", &outdir);
+        if let Some(name) = package_name {
+            cmd.env("WASMER_PACKAGE_NAME", name);
+        }
+        if self.output == Output::Jsonl {
+            // Keep stdout machine-readable
+            cmd.stdout(std::io::stderr());
+        }
+
+        let status = cmd
+            .status()
+            .with_context(|| format!("could not run the post-hook, \"{hook}\""))?;
+        if !status.success() {
+            anyhow::bail!("the post-hook, \"{hook}\", failed ({status})");
+        }
+
+        Ok(())
+    }
+
+    /// The first flag which was passed that only makes sense with
+    /// `--format webc`, if any.
+    ///
+    /// Most conflicting flags are rejected by clap, but it can't reject flags
+    /// based on another flag's value.
+    fn webc_only_flag(&self) -> Option<&'static str> {
+        [
+            (
+                "--atom-name-template",
+                !self.atom_name_template.is_default(),
+            ),
+            ("--executable-atoms", self.executable_atoms),
+            ("--emit-manifest", self.emit_manifest),
+            ("--decrypt-key", self.decrypt_key.is_some()),
+            ("--skip-if-present", self.skip_if_present),
+            ("--zip", self.zip.is_some()),
+            ("--metadata-only", self.metadata_only),
+            ("--base", self.base.is_some()),
+            ("--output jsonl", self.output == Output::Jsonl),
+            ("--dedup-hardlink", self.dedup_hardlink),
+            ("--layout", self.layout.is_some()),
+            ("--validate-wasm", self.validate_wasm),
+            ("--max-entries", self.max_entries.is_some()),
+            ("--max-total-bytes", self.max_total_bytes.is_some()),
+            ("--resume", self.resume),
+            ("--compress", self.compress.is_some()),
+            ("--emit-runners", self.emit_runners),
+            ("--oci-layout", self.oci_layout.is_some()),
+            ("--attest", self.attest.is_some()),
+        ]
+        .into_iter()
+        .find_map(|(flag, passed)| passed.then_some(flag))
+    }
+
+    fn extract(&self, pkg: &Container, outdir: &Path) -> Result<UnpackStats, anyhow::Error> {
+        match self.format {
+            Format::Package => {
+                wasmer_package::convert::webc_to_package_dir(pkg, outdir)
+                    .with_context(|| "could not extract package")?;
+                Ok(UnpackStats::default())
+            }
+            Format::Webc => self
+                .unpack_webc(pkg, outdir)
+                .with_context(|| "could not extract package".to_string()),
+        }
+    }
+
+    /// Equivalent to [`Container::unpack()`], except atoms are named using
+    /// the `--atom-name-template`.
+    fn unpack_webc(&self, pkg: &Container, outdir: &Path) -> Result<UnpackStats, anyhow::Error> {
+        if self.dedup_hardlink && cfg!(not(unix)) {
+            anyhow::bail!("--dedup-hardlink is only supported on Unix");
+        }
+
+        let mut stats = UnpackStats::default();
+        let mut stdout = std::io::stdout().lock();
+        let mut dedup = self.dedup_hardlink.then(HardlinkDedup::default);
+        let mut resume = self.resume.then(|| ResumeIndex::open(outdir)).transpose()?;
+
+        self.walk_webc(pkg, |kind, _, relative_path, contents| {
+            let mut path = outdir.join(relative_path);
+            let executable = match kind {
+                ContainerEntryKind::Atom => self.executable_atoms,
+                ContainerEntryKind::Metadata => is_runner(relative_path),
+                _ => false,
+            };
+
+            if kind == ContainerEntryKind::VolumeDirectory {
+                std::fs::create_dir_all(&path)
+                    .with_context(|| format!("could not write '{}'", path.display()))?;
+            } else {
+                if self.layout.is_some() || self.emit_runners {
+                    // Remapped files and runners may be put in a directory
+                    // which doesn't exist in the package
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)
+                            .with_context(|| format!("could not write '{}'", parent.display()))?;
+                    }
+                }
+
+                if resume
+                    .as_ref()
+                    .is_some_and(|index| index.is_complete(relative_path, &path, contents))
+                {
+                    stats.skipped += 1;
+                } else if dedup
+                    .as_mut()
+                    .is_some_and(|dedup| dedup.link_or_remember(&path, contents, executable))
+                {
+                    stats.linked += 1;
+                } else if let Some(compression) = self.compression_for(contents) {
+                    let mut compressed = path.into_os_string();
+                    compressed.push(".");
+                    compressed.push(compression.extension());
+                    path = PathBuf::from(compressed);
+                    compression.write_file(&path, contents)?;
+                    stats.written += 1;
+                } else {
+                    self.write_file(&path, contents, &mut stats)?;
+                }
+
+                if let Some(index) = &mut resume {
+                    index.record(relative_path, contents)?;
+                }
+            }
+
+            if executable {
+                make_executable(&path)?;
+            }
+
+            if self.output == Output::Jsonl {
+                write_jsonl_entry(&mut stdout, kind, relative_path, contents.len())
+                    .context("could not write to stdout")?;
+            }
+
+            Ok(())
+        })?;
+
+        if let Some(index) = resume {
+            index.finish()?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Visit everything in the `--format webc` layout, passing the
+    /// [`ContainerEntry`] each entry came from (or `None` for generated
+    /// files) and its path relative to the output directory.
+    ///
+    /// Atoms are named using the `--atom-name-template` and decrypted, delta
+    /// entries are patched against the `--base` package, and a wasmer.toml is
+    /// generated at the end if `--emit-manifest` was passed.
+    /// With `--metadata-only`, only the manifest and the metadata volume are
+    /// visited. Entries in the `--layout` file are moved to their new paths.
+    /// WebAssembly atoms are checked if `--validate-wasm` was passed, and the
+    /// walk stops with an error once `--max-entries` or `--max-total-bytes`
+    /// is exceeded.
+    fn walk_webc(
+        &self,
+        pkg: &Container,
+        mut visit: impl FnMut(
+            ContainerEntryKind,
+            Option<&ContainerEntry>,
+            &Path,
+            &[u8],
+        ) -> Result<(), anyhow::Error>,
+    ) -> Result<(), anyhow::Error> {
+        let layout = self.layout.as_deref().map(Layout::from_file).transpose()?;
+        let is_skipped = |name: &str| {
+            self.layout_strict
+                && layout
+                    .as_ref()
+                    .is_some_and(|layout| layout.destination(name).is_none())
+        };
+
+        let manifest = pkg.manifest();
+        let version = manifest
+            .wapm()
+            .context("could not read the package annotations")?
+            .and_then(|wapm| wapm.version);
+
+        // Work out all the file names before touching the output directory
+        // so a bad template doesn't leave a half-extracted package behind.
+        let mut atoms = BTreeMap::new();
+        for name in manifest.atoms.keys() {
+            let destination = layout.as_ref().and_then(|layout| layout.destination(name));
+            let file_name = match destination {
+                Some(destination) => destination.to_string_lossy().into_owned(),
+                None => {
+                    let hash = manifest.atom_signature(name)?;
+                    self.atom_name_template.render(
+                        name,
+                        version.as_deref(),
+                        &hex::encode(hash.as_bytes()),
+                    )?
+                }
+            };
+            if file_name == MANIFEST_FILE_NAME
+                || (self.emit_manifest && file_name == WASMER_TOML_FILE_NAME)
+                || atoms.values().any(|f| *f == file_name)
+            {
+                anyhow::bail!(
+                    "the atom name template produced a duplicate file name, \"{file_name}\""
+                );
+            }
+            atoms.insert(name.as_str(), file_name);
+        }
+
+        let encryption: Option<EncryptionAnnotation> = manifest
+            .package_annotation(EncryptionAnnotation::KEY)
+            .context("could not read the encryption annotation")?;
+        let decryptor = match (&encryption, &self.decrypt_key) {
+            (Some(encryption), Some(key)) => Some(AtomDecryptor::from_key_file(encryption, key)?),
+            (Some(_), None) => {
+                tracing::warn!(
+                    "the package contains encrypted atoms, but no --decrypt-key was provided"
+                );
+                None
+            }
+            (None, _) => None,
+        };
+
+        let delta: Option<DeltaAnnotation> = manifest
+            .package_annotation(DeltaAnnotation::KEY)
+            .context("could not read the delta annotation")?;
+        let patcher = match (&delta, &self.base) {
+            (Some(delta), Some(base)) => Some(DeltaPatcher::from_base_file(delta, base)?),
+            (Some(_), None) => {
+                anyhow::bail!(
+                    "the package is a delta against another package - use --base to provide it"
+                );
+            }
+            (None, _) => None,
+        };
+
+        let mut limits = ExtractionLimits::new(
+            self.max_entries,
+            self.max_total_bytes.map(|bytes| bytes.as_u64()),
+        );
+
+        for entry in container_entries(pkg) {
+            let entry = entry?;
+            if self.metadata_only && !is_metadata_entry(entry.kind(), entry.path()) {
+                continue;
+            }
+            let mut contents = std::borrow::Cow::Borrowed(entry.contents().as_slice());
+            let path = match entry.kind() {
+                ContainerEntryKind::Atom => {
+                    let name = entry.path().to_str().context("invalid atom name")?;
+                    if is_skipped(name) {
+                        continue;
+                    }
+                    if let Some(decryptor) = &decryptor {
+                        if decryptor.is_encrypted(name) {
+                            contents = decryptor.decrypt(name, &contents)?.into();
+                        }
+                    }
+                    Path::new(&atoms[name])
+                }
+                ContainerEntryKind::VolumeDirectory if self.layout_strict => continue,
+                _ => {
+                    let name = to_slash_path(entry.path());
+                    if is_skipped(&name) {
+                        continue;
+                    }
+                    match layout.as_ref().and_then(|layout| layout.destination(&name)) {
+                        Some(destination) => destination,
+                        None => entry.path(),
+                    }
+                }
+            };
+            if let Some(patcher) = &patcher {
+                if patcher.is_patched(entry.path()) {
+                    contents = patcher.apply(entry.path(), &contents)?.into();
+                }
+            }
+            if self.validate_wasm
+                && entry.kind() == ContainerEntryKind::Atom
+                && wasmer::is_wasm(&contents)
+            {
+                if let Err(e) = validate_wasm(&contents) {
+                    let name = entry.path().display();
+                    if self.validate_wasm_strict {
+                        anyhow::bail!("the \"{name}\" atom is not valid WebAssembly: {e}");
+                    }
+                    tracing::warn!(
+                        atom = %name,
+                        error = &e as &dyn std::error::Error,
+                        "the atom is not valid WebAssembly",
+                    );
+                }
+            }
+
+            limits.admit(contents.len())?;
+            visit(entry.kind(), Some(&entry), path, &contents)?;
+        }
+
+        if self.emit_manifest {
+            let manifest =
+                wasmer_package::convert::webc_to_manifest(pkg, |name| format!("./{}", atoms[name]))
+                    .context("could not generate a wasmer.toml")?;
+            let manifest = toml::to_string(&manifest)?;
+            limits.admit(manifest.len())?;
+            visit(
+                ContainerEntryKind::Metadata,
+                None,
+                Path::new(WASMER_TOML_FILE_NAME),
+                manifest.as_bytes(),
+            )?;
+        }
+
+        if self.emit_runners {
+            for (path, script) in runner_scripts(pkg, &atoms)? {
+                limits.admit(script.len())?;
+                visit(ContainerEntryKind::Metadata, None, &path, script.as_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// How a file should be compressed for `--compress`, if at all.
+    fn compression_for(&self, contents: &[u8]) -> Option<Compression> {
+        self.compress.filter(|_| {
+            contents.len() as u64 >= self.compress_min_size.as_u64() && !is_compressed(contents)
+        })
+    }
+
+    /// Write an extracted file, unless `--skip-if-present` is set and it
+    /// already has the right contents.
+    fn write_file(
+        &self,
+        path: &Path,
+        contents: &[u8],
+        stats: &mut UnpackStats,
+    ) -> Result<(), anyhow::Error> {
+        if self.skip_if_present && path.exists() {
+            if file_has_contents(path, contents)? {
+                stats.skipped += 1;
+                return Ok(());
+            }
+            if !self.overwrite {
+                anyhow::bail!(
+                    "'{}' already exists with different contents - use --overwrite to replace it",
+                    path.display()
+                );
+            }
+        }
+
+        std::fs::write(path, contents)
+            .with_context(|| format!("could not write '{}'", path.display()))?;
+        stats.written += 1;
+
+        Ok(())
+    }
+}
+
+/// Check that an atom is a valid WebAssembly module or component, allowing
+/// any proposal `wasmparser` knows about so nothing a runtime might support
+/// is rejected.
+fn validate_wasm(contents: &[u8]) -> Result<(), wasmparser::BinaryReaderError> {
+    wasmparser::Validator::new_with_features(wasmparser::WasmFeatures::all())
+        .validate_all(contents)
+        .map(|_| ())
+}
+
+/// Is this entry part of the package's metadata (the manifest and the
+/// metadata volume)?
+fn is_metadata_entry(kind: ContainerEntryKind, path: &Path) -> bool {
+    match kind {
+        ContainerEntryKind::Metadata => true,
+        ContainerEntryKind::Atom => false,
+        ContainerEntryKind::VolumeDirectory | ContainerEntryKind::VolumeFile => {
+            path.starts_with(METADATA_VOLUME_NAME)
+        }
+    }
+}
+
+/// Join a relative path's segments with `/`, regardless of the platform.
+fn to_slash_path(path: &Path) -> String {
+    path.iter()
+        .map(|segment| segment.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// How many files an unpack wrote, skipped because they were unchanged, or
+/// hardlinked to an identical file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct UnpackStats {
+    written: usize,
+    skipped: usize,
+    linked: usize,
+}
+
+fn file_has_contents(path: &Path, contents: &[u8]) -> Result<bool, anyhow::Error> {
+    let metadata =
+        std::fs::metadata(path).with_context(|| format!("could not read '{}'", path.display()))?;
+    if !metadata.is_file() || metadata.len() != contents.len() as u64 {
+        return Ok(false);
+    }
+
+    let existing =
+        std::fs::read(path).with_context(|| format!("could not read '{}'", path.display()))?;
+    Ok(existing == contents)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), anyhow::Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("could not make '{}' executable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), anyhow::Error> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer_package::utils::from_disk;
+
+    use super::test_utils::{webc_fixture, webc_unpack, DASH_WEBC, HELLO_WEBC};
+    use super::*;
+
+    /// Download a package from the dev registry.
+    #[test]
+    fn test_cmd_package_extract() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        assert!(package_path.is_file());
+
+        let cmd = webc_unpack(package_path, Some(dir.path().to_owned()));
+
+        cmd.execute().unwrap();
+
+        let mut items = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|x| {
+                x.unwrap()
+                    .path()
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        items.sort();
+        assert_eq!(
+            items,
+            vec![
+                "atom".to_string(),
+                "manifest.json".to_string(),
+                "metadata".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cmd_package_extract_metadata_only() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        let cmd = PackageUnpack {
+            metadata_only: true,
+            ..webc_unpack(package_path, Some(dir.path().to_owned()))
+        };
+
+        cmd.execute().unwrap();
+
+        let mut items = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        items.sort();
+        assert_eq!(items, ["manifest.json", "metadata"]);
+    }
+
+    #[test]
+    fn test_cmd_package_extract_with_atom_name_template() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let package_path = webc_fixture(DASH_WEBC);
+
+        let cmd = PackageUnpack {
+            atom_name_template: "{name}-{version}.wasm".parse().unwrap(),
+            executable_atoms: true,
+            ..webc_unpack(package_path, Some(dir.path().to_owned()))
+        };
+
+        cmd.execute().unwrap();
+
+        let atom = dir.path().join("dash-1.0.18.wasm");
+        assert!(atom.is_file());
+        assert!(!dir.path().join("dash").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = atom.metadata().unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+
+    #[test]
+    fn test_cmd_package_extract_compare_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let reference = dir.path().join("reference");
+        let out = dir.path().join("out");
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        let mut cmd = webc_unpack(package_path, Some(reference.clone()));
+        cmd.execute().unwrap();
+
+        cmd.out_dir = Some(out.clone());
+        cmd.compare_to = Some(reference.clone());
+        cmd.execute().unwrap();
+
+        std::fs::write(reference.join("manifest.json"), "{}").unwrap();
+        std::fs::write(reference.join("extra.txt"), "").unwrap();
+        cmd.overwrite = true;
+        assert!(cmd.execute().is_err());
+
+        let diff = DirDiff::compute(&out, &reference).unwrap();
+        assert_eq!(
+            diff,
+            DirDiff {
+                added: Vec::new(),
+                removed: vec![PathBuf::from("extra.txt")],
+                changed: vec![PathBuf::from("manifest.json")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_cmd_package_extract_is_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        // A failed unpack doesn't leave anything behind
+        let mut cmd = PackageUnpack {
+            max_entries: Some(1),
+            ..webc_unpack(package_path, Some(out.clone()))
+        };
+        assert!(cmd.execute().is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // --overwrite merges the package with the previous contents
+        std::fs::create_dir(&out).unwrap();
+        std::fs::write(out.join("existing.txt"), "").unwrap();
+        std::fs::write(out.join("manifest.json"), "{}").unwrap();
+        cmd.max_entries = None;
+        assert!(cmd.execute().is_err());
+        assert_eq!(std::fs::read(out.join("manifest.json")).unwrap(), b"{}");
+        cmd.overwrite = true;
+        cmd.execute().unwrap();
+        assert!(out.join("existing.txt").exists());
+        assert_ne!(std::fs::read(out.join("manifest.json")).unwrap(), b"{}");
+        assert!(out.join("metadata").is_dir());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // The package format merges without --overwrite
+        let package_out = dir.path().join("package");
+        std::fs::create_dir(&package_out).unwrap();
+        std::fs::write(package_out.join("existing.txt"), "").unwrap();
+        cmd.out_dir = Some(package_out.clone());
+        cmd.format = Format::Package;
+        cmd.overwrite = false;
+        cmd.execute().unwrap();
+        assert!(package_out.join("existing.txt").exists());
+        assert!(package_out.join("wasmer.toml").exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_cmd_package_extract_skip_if_present() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        let mut cmd = PackageUnpack {
+            skip_if_present: true,
+            ..webc_unpack(package_path, Some(dir.path().to_owned()))
+        };
+        cmd.execute().unwrap();
+
+        // Unchanged files are left alone, so a second unpack is a no-op
+        let pkg = from_disk(&cmd.package_path).unwrap();
+        let stats = cmd.unpack_webc(&pkg, dir.path()).unwrap();
+        assert_eq!(stats.written, 0);
+        assert!(stats.skipped > 0);
+
+        // Modified files are only replaced with --overwrite
+        std::fs::write(dir.path().join("manifest.json"), "{}").unwrap();
+        assert!(cmd.unpack_webc(&pkg, dir.path()).is_err());
+        cmd.overwrite = true;
+        let stats = cmd.unpack_webc(&pkg, dir.path()).unwrap();
+        assert_eq!(stats.written, 1);
+    }
+
+    #[test]
+    fn test_cmd_package_extract_emit_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let package_path = webc_fixture(DASH_WEBC);
+
+        let cmd = PackageUnpack {
+            atom_name_template: "{name}.wasm".parse().unwrap(),
+            emit_manifest: true,
+            ..webc_unpack(package_path, Some(dir.path().to_owned()))
+        };
+
+        cmd.execute().unwrap();
+
+        let raw = std::fs::read_to_string(dir.path().join("wasmer.toml")).unwrap();
+        let manifest: wasmer_config::package::Manifest = toml::from_str(&raw).unwrap();
+        assert_eq!(manifest.modules.len(), 1);
+        assert_eq!(manifest.modules[0].name, "dash");
+        assert_eq!(manifest.modules[0].source, PathBuf::from("./dash.wasm"));
+        assert_eq!(manifest.commands.len(), 1);
+        assert_eq!(
+            manifest.package.unwrap().name.as_deref(),
+            Some("sharrattj/dash")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cmd_package_extract_post_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = dir.path().join("out");
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        let mut cmd = PackageUnpack {
+            post_hook: Some("echo \"$WASMER_OUT_DIR\" > hook.txt".to_string()),
+            ..webc_unpack(package_path, Some(out_dir.clone()))
+        };
+
+        cmd.execute().unwrap();
+
+        let hook_output = std::fs::read_to_string(out_dir.join("hook.txt")).unwrap();
+        assert_eq!(
+            Path::new(hook_output.trim()),
+            out_dir.canonicalize().unwrap()
+        );
+
+        cmd.overwrite = true;
+        cmd.post_hook = Some("exit 3".to_string());
+        let err = cmd.execute().unwrap_err();
+        assert!(err.to_string().contains("post-hook"), "{err}");
+    }
+
+    #[test]
+    fn validate_wasm_atoms() {
+        let empty_module = b"\0asm\x01\0\0\0";
+        assert!(validate_wasm(empty_module).is_ok());
+
+        // A type section which claims to be longer than the module
+        let truncated = b"\0asm\x01\0\0\0\x01\x05\x01";
+        assert!(validate_wasm(truncated).is_err());
+    }
+
+    #[test]
+    fn conflicting_flags_are_rejected() {
+        use clap::Parser;
+
+        let parse = |args: &[&str]| {
+            PackageUnpack::try_parse_from(["unpack"].iter().chain(args).chain(&["pkg.webc"]))
+        };
+
+        assert!(parse(&["-o", "out", "-f", "webc", "--resume"]).is_ok());
+        assert!(parse(&["--zip", "out.zip", "-f", "webc", "--resume"]).is_err());
+        assert!(parse(&["--oci-layout", "oci", "-f", "webc", "--post-hook", "true"]).is_err());
+        assert!(parse(&["-o", "out", "-f", "webc", "--compress", "gzip", "--resume"]).is_err());
+        assert!(parse(&["-o", "out", "--emit-runners", "--metadata-only"]).is_err());
+
+        let cmd = parse(&["-o", "out", "--executable-atoms"]).unwrap();
+        let err = cmd.execute().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--executable-atoms is only supported with --format webc"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use anyhow::Context;
+use wasmer_package::unpack::{ContainerEntryKind, MANIFEST_FILE_NAME};
+use webc::{metadata::annotations::Wapm, Container};
+
+use super::{runners::is_runner, staging::is_empty_dir, to_slash_path, PackageUnpack};
+
+const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const OCI_LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar";
+/// The media type of the config blob, which holds the webc manifest.
+const WEBC_MANIFEST_MEDIA_TYPE: &str = "application/vnd.wasmer.webc.manifest.v1+json";
+
+impl PackageUnpack {
+    /// Write the package to `dir` as an OCI image layout for `--oci-layout`.
+    pub(super) fn write_oci_layout(
+        &self,
+        pkg: &Container,
+        dir: &Path,
+    ) -> Result<(), anyhow::Error> {
+        if !is_empty_dir(dir)? {
+            if !self.overwrite {
+                anyhow::bail!(
+                    "'{}' is not empty - use --overwrite to replace it",
+                    dir.display()
+                );
+            }
+            std::fs::remove_dir_all(dir)
+                .with_context(|| format!("could not remove '{}'", dir.display()))?;
+        }
+        let blobs = dir.join("blobs").join("sha256");
+        std::fs::create_dir_all(&blobs)
+            .with_context(|| format!("could not create '{}'", blobs.display()))?;
+
+        let mut config = None;
+        let mut layers = Vec::new();
+        let mut volumes = tar::Builder::new(Vec::new());
+        let mut has_volumes = false;
+
+        self.walk_webc(pkg, |kind, _, relative_path, contents| {
+            let name = to_slash_path(relative_path);
+
+            match kind {
+                ContainerEntryKind::Metadata if relative_path == Path::new(MANIFEST_FILE_NAME) => {
+                    config = Some(write_oci_blob(&blobs, WEBC_MANIFEST_MEDIA_TYPE, contents)?);
+                }
+                ContainerEntryKind::Atom => {
+                    let media_type = if wasmer::is_wasm(contents) {
+                        "application/wasm"
+                    } else {
+                        "application/octet-stream"
+                    };
+                    let mut layer = write_oci_blob(&blobs, media_type, contents)?;
+                    layer["annotations"] =
+                        serde_json::json!({ "org.opencontainers.image.title": name });
+                    layers.push(layer);
+                }
+                ContainerEntryKind::VolumeDirectory => {
+                    let mut header = tar_header(tar::EntryType::Directory, 0o755, 0);
+                    volumes.append_data(&mut header, format!("{name}/"), std::io::empty())?;
+                    has_volumes = true;
+                }
+                _ => {
+                    let mode = if is_runner(relative_path) {
+                        0o755
+                    } else {
+                        0o644
+                    };
+                    let mut header =
+                        tar_header(tar::EntryType::Regular, mode, contents.len() as u64);
+                    volumes.append_data(&mut header, name, contents)?;
+                    has_volumes = true;
+                }
+            }
+
+            Ok(())
+        })
+        .context("could not write the OCI image layout")?;
+
+        if has_volumes {
+            let volumes = volumes.into_inner()?;
+            layers.push(write_oci_blob(&blobs, OCI_LAYER_MEDIA_TYPE, &volumes)?);
+        }
+        let config = config.context("the package doesn't have a manifest")?;
+
+        let wapm = pkg
+            .manifest()
+            .wapm()
+            .context("could not read the package annotations")?
+            .unwrap_or_else(|| Wapm::new(None, None, None));
+        let mut annotations = serde_json::Map::new();
+        if let Some(name) = &wapm.name {
+            annotations.insert("org.opencontainers.image.title".into(), name.clone().into());
+        }
+        if let Some(version) = &wapm.version {
+            annotations.insert(
+                "org.opencontainers.image.version".into(),
+                version.clone().into(),
+            );
+        }
+        if let Some(description) = &wapm.description {
+            annotations.insert(
+                "org.opencontainers.image.description".into(),
+                description.clone().into(),
+            );
+        }
+
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+            "config": config,
+            "layers": layers,
+            "annotations": annotations,
+        });
+        let mut manifest = write_oci_blob(
+            &blobs,
+            OCI_MANIFEST_MEDIA_TYPE,
+            &serde_json::to_vec(&manifest)?,
+        )?;
+        manifest["annotations"] = serde_json::json!({
+            "org.opencontainers.image.ref.name": wapm.version.as_deref().unwrap_or("latest"),
+        });
+
+        let index = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_INDEX_MEDIA_TYPE,
+            "manifests": [manifest],
+        });
+        let oci_layout = serde_json::json!({ "imageLayoutVersion": "1.0.0" });
+        for (file_name, contents) in [("oci-layout", oci_layout), ("index.json", index)] {
+            let path = dir.join(file_name);
+            std::fs::write(&path, serde_json::to_vec_pretty(&contents)?)
+                .with_context(|| format!("could not write '{}'", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write a blob to an OCI image layout's `blobs/sha256` directory,
+/// returning its descriptor.
+fn write_oci_blob(
+    blobs: &Path,
+    media_type: &str,
+    contents: &[u8],
+) -> Result<serde_json::Value, anyhow::Error> {
+    use sha2::Digest;
+
+    let hash = hex::encode(sha2::Sha256::digest(contents));
+    let path = blobs.join(&hash);
+    std::fs::write(&path, contents)
+        .with_context(|| format!("could not write '{}'", path.display()))?;
+
+    Ok(serde_json::json!({
+        "mediaType": media_type,
+        "digest": format!("sha256:{hash}"),
+        "size": contents.len(),
+    }))
+}
+
+/// A tar header with a fixed owner and timestamp, so the same package always
+/// produces the same layer.
+fn tar_header(entry_type: tar::EntryType, mode: u32, size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_mode(mode);
+    header.set_size(size);
+    header.set_mtime(0);
+    header.set_uid(0);
+    header.set_gid(0);
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::package::unpack::test_utils::{webc_fixture, webc_unpack, HELLO_WEBC};
+
+    #[test]
+    fn test_cmd_package_extract_to_oci_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let layout = dir.path().join("layout");
+
+        let package_path = webc_fixture(HELLO_WEBC);
+
+        let cmd = PackageUnpack {
+            oci_layout: Some(layout.clone()),
+            ..webc_unpack(package_path, None)
+        };
+
+        cmd.execute().unwrap();
+
+        let read_json = |path: PathBuf| -> serde_json::Value {
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+        };
+        let read_blob = |descriptor: &serde_json::Value| {
+            let digest = descriptor["digest"].as_str().unwrap();
+            let hash = digest.strip_prefix("sha256:").unwrap();
+            let blob = std::fs::read(layout.join("blobs/sha256").join(hash)).unwrap();
+            assert_eq!(descriptor["size"], blob.len());
+            blob
+        };
+
+        assert_eq!(
+            read_json(layout.join("oci-layout"))["imageLayoutVersion"],
+            "1.0.0"
+        );
+        let index = read_json(layout.join("index.json"));
+        let descriptor = &index["manifests"][0];
+        assert_eq!(descriptor["mediaType"], OCI_MANIFEST_MEDIA_TYPE);
+        assert_eq!(
+            descriptor["annotations"]["org.opencontainers.image.ref.name"],
+            "0.1.0"
+        );
+
+        let manifest: serde_json::Value = serde_json::from_slice(&read_blob(descriptor)).unwrap();
+        assert_eq!(manifest["config"]["mediaType"], WEBC_MANIFEST_MEDIA_TYPE);
+        let config = String::from_utf8(read_blob(&manifest["config"])).unwrap();
+        assert!(config.contains("atoms"));
+
+        let layers = manifest["layers"].as_array().unwrap();
+        let atom = layers
+            .iter()
+            .find(|layer| layer["annotations"]["org.opencontainers.image.title"] == "atom")
+            .unwrap();
+        assert_eq!(atom["mediaType"], "application/wasm");
+        assert!(wasmer::is_wasm(&read_blob(atom)));
+
+        let volumes = layers
+            .iter()
+            .find(|layer| layer["mediaType"] == OCI_LAYER_MEDIA_TYPE)
+            .unwrap();
+        let mut volumes = tar::Archive::new(std::io::Cursor::new(read_blob(volumes)));
+        let paths: Vec<_> = volumes
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect();
+        assert!(paths.iter().any(|path| path.starts_with("metadata")));
+    }
+}
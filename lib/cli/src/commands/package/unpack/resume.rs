@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use super::to_slash_path;
+
+/// The `--resume` index of entries which have been completely written, in
+/// the same format as `sha256sum` output.
+#[derive(Debug)]
+pub(super) struct ResumeIndex {
+    path: PathBuf,
+    file: std::fs::File,
+    /// The sha256 hash of each completed entry, keyed by its slash-separated
+    /// relative path.
+    completed: HashMap<String, String>,
+}
+
+impl ResumeIndex {
+    const FILE_NAME: &'static str = ".wasmer-unpack-index";
+
+    /// Load the index in `outdir` left by an interrupted unpack, if there is
+    /// one, and open it so more entries can be added.
+    pub(super) fn open(outdir: &Path) -> Result<Self, anyhow::Error> {
+        let path = outdir.join(Self::FILE_NAME);
+        let error = || format!("could not open the resume index at '{}'", path.display());
+
+        let mut completed = HashMap::new();
+        match std::fs::read_to_string(&path) {
+            Ok(index) => {
+                for line in index.lines() {
+                    // An interrupted write can leave a partial last line, which
+                    // is just ignored
+                    if let Some((hash, entry)) = line.split_once("  ") {
+                        completed.insert(entry.to_string(), hash.to_string());
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(error),
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(error)?;
+
+        Ok(ResumeIndex {
+            path,
+            file,
+            completed,
+        })
+    }
+
+    /// Was this entry already written with the same contents by a previous
+    /// unpack?
+    pub(super) fn is_complete(&self, relative_path: &Path, path: &Path, contents: &[u8]) -> bool {
+        use sha2::Digest;
+
+        let expected = self.completed.get(&to_slash_path(relative_path));
+        expected.is_some_and(|hash| *hash == hex::encode(sha2::Sha256::digest(contents)))
+            && std::fs::metadata(path).is_ok_and(|meta| meta.len() == contents.len() as u64)
+    }
+
+    /// Remember that an entry has been completely written.
+    pub(super) fn record(
+        &mut self,
+        relative_path: &Path,
+        contents: &[u8],
+    ) -> Result<(), anyhow::Error> {
+        use sha2::Digest;
+
+        let entry = to_slash_path(relative_path);
+        if self.completed.contains_key(&entry) {
+            return Ok(());
+        }
+
+        let hash = hex::encode(sha2::Sha256::digest(contents));
+        writeln!(self.file, "{hash}  {entry}")
+            .with_context(|| format!("could not update '{}'", self.path.display()))?;
+        self.completed.insert(entry, hash);
+
+        Ok(())
+    }
+
+    /// The unpack finished, so the index isn't needed any more.
+    pub(super) fn finish(self) -> Result<(), anyhow::Error> {
+        drop(self.file);
+        std::fs::remove_file(&self.path)
+            .with_context(|| format!("could not remove '{}'", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = Path::new("atom");
+        let path = dir.path().join(entry);
+
+        let mut index = ResumeIndex::open(dir.path()).unwrap();
+        assert!(!index.is_complete(entry, &path, b"contents"));
+        std::fs::write(&path, b"contents").unwrap();
+        index.record(entry, b"contents").unwrap();
+        drop(index);
+
+        // Pick up where the "interrupted" unpack left off
+        let index = ResumeIndex::open(dir.path()).unwrap();
+        assert!(index.is_complete(entry, &path, b"contents"));
+        assert!(!index.is_complete(entry, &path, b"changed!"));
+        assert!(!index.is_complete(Path::new("other"), &path, b"contents"));
+
+        index.finish().unwrap();
+        assert!(!dir.path().join(ResumeIndex::FILE_NAME).exists());
+    }
+}
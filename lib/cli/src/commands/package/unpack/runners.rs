@@ -0,0 +1,159 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use webc::{
+    metadata::annotations::{Atom as AtomAnnotation, Wasi},
+    Container,
+};
+
+use super::to_slash_path;
+
+/// The directory `--emit-runners` writes its scripts to.
+const RUNNERS_DIR: &str = "bin";
+
+/// Is this one of the scripts generated by `--emit-runners`?
+pub(super) fn is_runner(relative_path: &Path) -> bool {
+    relative_path.parent() == Some(Path::new(RUNNERS_DIR))
+}
+
+/// Generate an `--emit-runners` script for each of the package's commands,
+/// given the file names its atoms were extracted to.
+///
+/// Scripts are returned with their path relative to the output directory.
+pub(super) fn runner_scripts(
+    pkg: &Container,
+    atoms: &BTreeMap<&str, String>,
+) -> Result<Vec<(PathBuf, String)>, anyhow::Error> {
+    let mut scripts = Vec::new();
+
+    for (name, command) in &pkg.manifest().commands {
+        if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+            anyhow::bail!("the command name \"{name}\" can't be used as a file name");
+        }
+
+        let atom = command
+            .annotation::<AtomAnnotation>(AtomAnnotation::KEY)
+            .with_context(|| {
+                format!("could not read the atom annotation for the command '{name}'")
+            })?;
+        let wasi = command.annotation::<Wasi>(Wasi::KEY).with_context(|| {
+            format!("could not read the WASI annotation for the command '{name}'")
+        })?;
+
+        let atom_name = match (&atom, &wasi) {
+            (Some(atom), _) if atom.dependency.is_some() => {
+                tracing::warn!(
+                    command = %name,
+                    "skipping the runner for a command whose atom comes from a dependency"
+                );
+                continue;
+            }
+            (Some(atom), _) => atom.name.clone(),
+            (None, Some(wasi)) => wasi.atom.clone(),
+            (None, None) => {
+                anyhow::bail!("the command '{name}' doesn't say which atom it runs");
+            }
+        };
+        let file_name = atoms.get(atom_name.as_str()).with_context(|| {
+            format!("the command '{name}' runs the atom \"{atom_name}\", which doesn't exist")
+        })?;
+        let atom_path = to_slash_path(Path::new(file_name));
+        let args = wasi.and_then(|wasi| wasi.main_args).unwrap_or_default();
+
+        let (path, script) = if cfg!(windows) {
+            (format!("{name}.cmd"), cmd_runner(&atom_path, &args))
+        } else {
+            (name.clone(), sh_runner(&atom_path, &args))
+        };
+        scripts.push((Path::new(RUNNERS_DIR).join(path), script));
+    }
+
+    Ok(scripts)
+}
+
+/// A shell script which runs the atom at `atom_path` (relative to the output
+/// directory) with `args`, followed by the script's own arguments.
+fn sh_runner(atom_path: &str, args: &[String]) -> String {
+    fn quote(s: &str) -> String {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+
+    let mut command = format!(
+        r#"exec wasmer run "$(dirname "$0")/../"{} --"#,
+        quote(atom_path)
+    );
+    for arg in args {
+        command.push(' ');
+        command.push_str(&quote(arg));
+    }
+    command.push_str(r#" "$@""#);
+
+    format!("#!/bin/sh\n{command}\n")
+}
+
+/// The Windows equivalent of [`sh_runner()`].
+fn cmd_runner(atom_path: &str, args: &[String]) -> String {
+    fn quote(s: &str) -> String {
+        format!("\"{}\"", s.replace('%', "%%").replace('"', r#"\""#))
+    }
+
+    let mut command = format!(
+        "wasmer run \"%~dp0..\\{}\" --",
+        atom_path.replace('/', "\\").replace('%', "%%")
+    );
+    for arg in args {
+        command.push(' ');
+        command.push_str(&quote(arg));
+    }
+    command.push_str(" %*");
+
+    format!("@echo off\r\n{command}\r\n")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::commands::package::unpack::{
+        test_utils::{webc_fixture, webc_unpack, DASH_WEBC},
+        PackageUnpack,
+    };
+
+    #[test]
+    fn test_cmd_package_extract_emit_runners() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let package_path = webc_fixture(DASH_WEBC);
+
+        let cmd = PackageUnpack {
+            atom_name_template: "{name}.wasm".parse().unwrap(),
+            emit_runners: true,
+            ..webc_unpack(package_path, Some(dir.path().to_owned()))
+        };
+
+        cmd.execute().unwrap();
+
+        let runner = dir.path().join("bin").join("dash");
+        let script = std::fs::read_to_string(&runner).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(r#"exec wasmer run "$(dirname "$0")/../"'dash.wasm' --"#));
+        assert!(script.ends_with(" \"$@\"\n"));
+
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&runner).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111);
+
+        let args = ["it's".to_string()];
+        assert_eq!(
+            sh_runner("atoms/dash.wasm", &args),
+            "#!/bin/sh\nexec wasmer run \"$(dirname \"$0\")/../\"'atoms/dash.wasm' -- 'it'\\''s' \"$@\"\n"
+        );
+        let args = ["100%".to_string(), "\"x\"".to_string()];
+        assert_eq!(
+            cmd_runner("atoms/dash.wasm", &args),
+            "@echo off\r\nwasmer run \"%~dp0..\\atoms\\dash.wasm\" -- \"100%%\" \"\\\"x\\\"\" %*\r\n"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use anyhow::Context;
+use webc::{
+    metadata::{annotations::Wapm, UrlOrManifest},
+    Container,
+};
+
+use super::PackageUnpack;
+
+impl PackageUnpack {
+    pub(super) fn write_sbom(&self, pkg: &Container, path: &Path) -> Result<(), anyhow::Error> {
+        let sbom = cyclonedx_sbom(pkg, &self.fallback_name())?;
+
+        let mut json = serde_json::to_vec_pretty(&sbom)?;
+        json.push(b'\n');
+        std::fs::write(path, json)
+            .with_context(|| format!("could not write the SBOM to '{}'", path.display()))
+    }
+}
+
+/// Generate a CycloneDX document describing `pkg`, its atoms and its
+/// dependencies for `--sbom`.
+///
+/// `fallback_name` is used for packages which don't have a name.
+fn cyclonedx_sbom(
+    pkg: &Container,
+    fallback_name: &str,
+) -> Result<serde_json::Value, anyhow::Error> {
+    use sha2::Digest;
+
+    let manifest = pkg.manifest();
+    let wapm = manifest
+        .wapm()
+        .context("could not read the package annotations")?
+        .unwrap_or_else(|| Wapm::new(None, None, None));
+    let name = wapm.name.unwrap_or_else(|| fallback_name.to_string());
+    let bom_ref = match &wapm.version {
+        Some(version) => format!("{name}@{version}"),
+        None => name.clone(),
+    };
+
+    let mut root = serde_json::json!({
+        "type": "application",
+        "bom-ref": bom_ref,
+        "name": name,
+    });
+    if let Some(version) = &wapm.version {
+        root["version"] = version.clone().into();
+    }
+    if let Some(description) = &wapm.description {
+        root["description"] = description.clone().into();
+    }
+    if let Some(license) = &wapm.license {
+        root["licenses"] = serde_json::json!([{ "expression": license }]);
+    }
+    if let Some(hash) = pkg.webc_hash() {
+        root["hashes"] = serde_json::json!([{ "alg": "SHA-256", "content": hex::encode(hash) }]);
+    }
+
+    let mut components = Vec::new();
+    let mut depends_on = Vec::new();
+
+    for (atom_name, atom) in pkg.atoms() {
+        let atom_ref = format!("{bom_ref}#atom/{atom_name}");
+        components.push(serde_json::json!({
+            "type": "file",
+            "bom-ref": atom_ref,
+            "name": atom_name,
+            "hashes": [{
+                "alg": "SHA-256",
+                "content": hex::encode(sha2::Sha256::digest(&*atom)),
+            }],
+        }));
+    }
+
+    for (alias, dependency) in &manifest.use_map {
+        let (dep_name, dep_version) = match dependency {
+            UrlOrManifest::RegistryDependentUrl(specifier) => match specifier.rsplit_once('@') {
+                Some((name, version)) => (name.to_string(), Some(version.to_string())),
+                None => (specifier.clone(), None),
+            },
+            UrlOrManifest::Url(url) => (url.to_string(), None),
+            UrlOrManifest::Manifest(vendored) => {
+                let wapm = vendored
+                    .wapm()
+                    .with_context(|| format!("could not read the \"{alias}\" dependency"))?
+                    .unwrap_or_else(|| Wapm::new(None, None, None));
+                (wapm.name.unwrap_or_else(|| alias.clone()), wapm.version)
+            }
+        };
+        let dep_ref = match &dep_version {
+            Some(version) => format!("{dep_name}@{version}"),
+            None => dep_name.clone(),
+        };
+
+        let mut component = serde_json::json!({
+            "type": "library",
+            "bom-ref": dep_ref,
+            "name": dep_name,
+        });
+        if let Some(version) = dep_version {
+            component["version"] = version.into();
+        }
+        components.push(component);
+        depends_on.push(dep_ref);
+    }
+
+    Ok(serde_json::json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "tools": [{
+                "vendor": "Wasmer",
+                "name": "wasmer",
+                "version": env!("CARGO_PKG_VERSION"),
+            }],
+            "component": root,
+        },
+        "components": components,
+        "dependencies": [{
+            "ref": bom_ref,
+            "dependsOn": depends_on,
+        }],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer_package::utils::from_disk;
+
+    use super::*;
+    use crate::commands::package::unpack::test_utils::{webc_fixture, DASH_WEBC};
+
+    #[test]
+    fn cyclonedx_sboms() {
+        use sha2::Digest;
+
+        let package_path = webc_fixture(DASH_WEBC);
+        let pkg = from_disk(&package_path).unwrap();
+
+        let sbom = cyclonedx_sbom(&pkg, "fallback").unwrap();
+
+        assert_eq!(sbom["bomFormat"], "CycloneDX");
+        let root = &sbom["metadata"]["component"];
+        assert_eq!(root["name"], "sharrattj/dash");
+        assert_eq!(root["version"], "1.0.18");
+        assert_eq!(sbom["dependencies"][0]["ref"], "sharrattj/dash@1.0.18");
+
+        let dash = pkg.get_atom("dash").unwrap();
+        let atom = sbom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|component| component["name"] == "dash")
+            .unwrap();
+        assert_eq!(atom["type"], "file");
+        assert_eq!(
+            atom["hashes"][0]["content"],
+            hex::encode(sha2::Sha256::digest(&*dash))
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+pub(super) fn is_empty_dir(path: &Path) -> Result<bool, anyhow::Error> {
+    match std::fs::read_dir(path) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e).with_context(|| format!("could not read '{}'", path.display())),
+    }
+}
+
+/// Create an empty directory next to `outdir` which the package can be
+/// extracted into before being renamed into place.
+///
+/// The directory is deleted when dropped, so a failed unpack doesn't leave
+/// anything behind.
+pub(super) fn create_staging_dir(outdir: &Path) -> Result<tempfile::TempDir, anyhow::Error> {
+    let name = outdir.file_name().with_context(|| {
+        format!(
+            "can't unpack atomically into '{}' - use --no-atomic instead",
+            outdir.display()
+        )
+    })?;
+    let parent = match outdir.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(parent)
+        .with_context(|| format!("could not create directory '{}'", parent.display()))?;
+
+    tempfile::Builder::new()
+        .prefix(&format!(".{}.", name.to_string_lossy()))
+        .suffix(".partial")
+        .tempdir_in(parent)
+        .with_context(|| {
+            format!(
+                "could not create a temporary directory in '{}' - use --no-atomic to unpack in place",
+                parent.display()
+            )
+        })
+}
+
+/// Move a fully extracted staging directory to `outdir`.
+///
+/// A missing or empty `outdir` is replaced by the staging directory in a
+/// single rename. Otherwise the extracted files are merged into `outdir` one
+/// at a time, replacing files with the same path and leaving everything else
+/// alone.
+pub(super) fn move_into_place(
+    staging: tempfile::TempDir,
+    outdir: &Path,
+) -> Result<(), anyhow::Error> {
+    if !is_empty_dir(outdir)? {
+        return merge_into(staging.path(), outdir);
+    }
+
+    if outdir.exists() {
+        std::fs::remove_dir(outdir)
+            .with_context(|| format!("could not remove '{}'", outdir.display()))?;
+    }
+    std::fs::rename(staging.path(), outdir).with_context(|| {
+        format!(
+            "could not move the extracted package to '{}'",
+            outdir.display()
+        )
+    })?;
+    // The staging directory is now the output directory, so make sure it
+    // isn't deleted
+    let _ = staging.into_path();
+
+    Ok(())
+}
+
+/// Move everything in `src` into `dest`, recursing into directories which
+/// exist in both.
+fn merge_into(src: &Path, dest: &Path) -> Result<(), anyhow::Error> {
+    let entries =
+        std::fs::read_dir(src).with_context(|| format!("could not read '{}'", src.display()))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let from = entry.path();
+        let to = dest.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() && to.is_dir() {
+            merge_into(&from, &to)?;
+        } else {
+            std::fs::rename(&from, &to).with_context(|| {
+                format!("could not move the extracted file to '{}'", to.display())
+            })?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::Context;
+
+/// A parsed `--atom-name-template`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtomNameTemplate {
+    raw: String,
+    segments: Vec<TemplateSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplateSegment {
+    Literal(String),
+    Name,
+    Version,
+    Hash,
+}
+
+impl AtomNameTemplate {
+    /// The template which reproduces the atom names used in the webc.
+    pub const DEFAULT: &'static str = "{name}";
+
+    pub fn is_default(&self) -> bool {
+        self.raw == Self::DEFAULT
+    }
+
+    pub(super) fn render(
+        &self,
+        name: &str,
+        version: Option<&str>,
+        hash: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut file_name = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                TemplateSegment::Literal(s) => file_name.push_str(s),
+                TemplateSegment::Name => file_name.push_str(name),
+                TemplateSegment::Version => {
+                    let version = version.context(
+                        "the atom name template uses {version}, but the package has no version",
+                    )?;
+                    file_name.push_str(version);
+                }
+                TemplateSegment::Hash => file_name.push_str(hash),
+            }
+        }
+
+        // Atom names come from the package, so make sure they can't be used
+        // to write outside the output directory
+        let mut components = Path::new(&file_name).components();
+        let is_plain_file_name = matches!(components.next(), Some(std::path::Component::Normal(_)))
+            && components.next().is_none();
+        if !is_plain_file_name || file_name.contains(['/', '\\', '\0']) {
+            anyhow::bail!("the atom name template produced an invalid file name, \"{file_name}\"");
+        }
+
+        Ok(file_name)
+    }
+}
+
+impl Default for AtomNameTemplate {
+    fn default() -> Self {
+        Self::DEFAULT.parse().unwrap()
+    }
+}
+
+impl FromStr for AtomNameTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut rest = s;
+
+        while !rest.is_empty() {
+            match rest.find(['{', '}']) {
+                Some(ix) if rest[ix..].starts_with('}') => {
+                    anyhow::bail!("unmatched '}}' in atom name template \"{s}\"");
+                }
+                Some(ix) => {
+                    if ix > 0 {
+                        segments.push(TemplateSegment::Literal(rest[..ix].to_string()));
+                    }
+                    let end = rest[ix..]
+                        .find('}')
+                        .with_context(|| format!("unclosed '{{' in atom name template \"{s}\""))?;
+                    let segment = match &rest[ix + 1..ix + end] {
+                        "name" => TemplateSegment::Name,
+                        "version" => TemplateSegment::Version,
+                        "hash" => TemplateSegment::Hash,
+                        other => anyhow::bail!(
+                            "unknown placeholder \"{{{other}}}\" in atom name template (expected one of {{name}}, {{version}} or {{hash}})"
+                        ),
+                    };
+                    segments.push(segment);
+                    rest = &rest[ix + end + 1..];
+                }
+                None => {
+                    segments.push(TemplateSegment::Literal(rest.to_string()));
+                    rest = "";
+                }
+            }
+        }
+
+        let has_separator = segments.iter().any(
+            |segment| matches!(segment, TemplateSegment::Literal(s) if s.contains(['/', '\\'])),
+        );
+        if has_separator {
+            anyhow::bail!("atom name templates can't contain path separators");
+        }
+        if segments.is_empty() {
+            anyhow::bail!("the atom name template can't be empty");
+        }
+
+        Ok(AtomNameTemplate {
+            raw: s.to_string(),
+            segments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_atom_name_templates() {
+        let template: AtomNameTemplate = "{name}_{hash}.wasm".parse().unwrap();
+        assert_eq!(
+            template.render("python", None, "abcd").unwrap(),
+            "python_abcd.wasm"
+        );
+        assert!(AtomNameTemplate::default().is_default());
+
+        assert!("{nme}".parse::<AtomNameTemplate>().is_err());
+        assert!("{name".parse::<AtomNameTemplate>().is_err());
+        assert!("name}".parse::<AtomNameTemplate>().is_err());
+        assert!("".parse::<AtomNameTemplate>().is_err());
+        assert!("out/{name}".parse::<AtomNameTemplate>().is_err());
+    }
+
+    #[test]
+    fn hostile_atom_names_are_rejected() {
+        let template = AtomNameTemplate::default();
+        for name in [
+            "../evil",
+            "../../etc/passwd",
+            "nested/atom",
+            "/absolute",
+            "..\\evil",
+            "..",
+            ".",
+            "",
+        ] {
+            let err = template.render(name, None, "abcd").unwrap_err();
+            assert!(
+                err.to_string().contains("invalid file name"),
+                "{name}: {err}"
+            );
+        }
+
+        let template: AtomNameTemplate = "{name}.wasm".parse().unwrap();
+        assert!(template.render("../evil", None, "abcd").is_err());
+        assert_eq!(
+            template.render("..atom", None, "abcd").unwrap(),
+            "..atom.wasm"
+        );
+    }
+}
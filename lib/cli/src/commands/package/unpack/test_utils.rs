@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use bytesize::ByteSize;
+
+use super::{AtomNameTemplate, Format, Output, PackageUnpack};
+
+pub(crate) const HELLO_WEBC: &str = "hello-0.1.0-665d2ddc-80e6-4845-85d3-4587b1693bb7.webc";
+pub(crate) const DASH_WEBC: &str = "dash-1.0.18-f0d13233-bcda-4cf1-9a23-3460bffaae2a.webc";
+
+/// The path to one of the webc files used by the CLI integration tests.
+pub(crate) fn webc_fixture(file_name: &str) -> PathBuf {
+    std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap()
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("tests/integration/cli/tests/webc")
+        .join(file_name)
+}
+
+/// A quiet `--format webc` unpack with every other option at its
+/// command-line default.
+///
+/// Tests should override the fields they care about with struct update
+/// syntax.
+pub(crate) fn webc_unpack(package_path: PathBuf, out_dir: Option<PathBuf>) -> PackageUnpack {
+    PackageUnpack {
+        out_dir,
+        overwrite: false,
+        package_path,
+        quiet: true,
+        format: Format::Webc,
+        atom_name_template: AtomNameTemplate::default(),
+        executable_atoms: false,
+        compare_to: None,
+        emit_manifest: false,
+        decrypt_key: None,
+        no_atomic: false,
+        skip_if_present: false,
+        zip: None,
+        metadata_only: false,
+        limit_rate: None,
+        base: None,
+        output: Output::default(),
+        dedup_hardlink: false,
+        layout: None,
+        layout_strict: false,
+        validate_wasm: false,
+        validate_wasm_strict: false,
+        post_hook: None,
+        max_entries: None,
+        max_total_bytes: None,
+        resume: false,
+        compress: None,
+        compress_min_size: ByteSize::kib(4),
+        sbom: None,
+        emit_runners: false,
+        oci_layout: None,
+        attest: None,
+    }
+}
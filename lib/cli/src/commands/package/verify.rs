@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use dialoguer::console::style;
+use sha2::Digest;
+use wasmer_package::{
+    unpack::{container_entries, ContainerEntryKind},
+    utils::from_disk,
+};
+use webc::Container;
+
+/// Check that a webc image is intact without extracting it.
+///
+/// Every atom and volume file is hashed and compared against the hash the
+/// package recorded for it. Entries are checked one at a time, so this works
+/// for packages of any size.
+#[derive(clap::Parser, Debug)]
+pub struct PackageVerify {
+    /// Print the result as JSON.
+    #[clap(long)]
+    pub json: bool,
+
+    /// Path to the package.
+    pub package: PathBuf,
+}
+
+impl PackageVerify {
+    pub(crate) fn execute(&self) -> Result<(), anyhow::Error> {
+        let pkg = from_disk(&self.package)
+            .with_context(|| format!("could not open package at '{}'", self.package.display()))?;
+
+        let report = VerifyReport::compute(&pkg);
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print!("{report}");
+        }
+
+        if !report.is_ok() {
+            anyhow::bail!("'{}' failed verification", self.package.display());
+        }
+
+        Ok(())
+    }
+}
+
+/// The outcome of checking every entry in a container.
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
+struct VerifyReport {
+    /// How many entries matched their recorded hash.
+    verified: usize,
+    /// Volume files the container didn't record a hash for.
+    unchecked: Vec<PathBuf>,
+    problems: Vec<Problem>,
+}
+
+/// An entry which couldn't be verified.
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct Problem {
+    path: Option<PathBuf>,
+    message: String,
+}
+
+impl VerifyReport {
+    fn compute(pkg: &Container) -> Self {
+        let mut report = VerifyReport::default();
+
+        for entry in container_entries(pkg) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    report.problems.push(Problem {
+                        path: None,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            match (entry.kind(), entry.hash()) {
+                (ContainerEntryKind::Metadata | ContainerEntryKind::VolumeDirectory, _) => {}
+                (ContainerEntryKind::Atom, None) => report.problem(
+                    entry.path(),
+                    "the manifest doesn't have a valid signature for this atom".to_string(),
+                ),
+                (ContainerEntryKind::VolumeFile, None) => {
+                    report.unchecked.push(entry.path().to_path_buf());
+                }
+                (_, Some(expected)) => {
+                    let actual = sha2::Sha256::digest(entry.contents());
+                    if actual.as_slice() == expected {
+                        report.verified += 1;
+                    } else {
+                        report.problem(
+                            entry.path(),
+                            format!(
+                                "expected sha256 {}, found {}",
+                                hex::encode(expected),
+                                hex::encode(actual)
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+
+        report
+    }
+
+    fn problem(&mut self, path: &Path, message: String) {
+        self.problems.push(Problem {
+            path: Some(path.to_path_buf()),
+            message,
+        });
+    }
+
+    fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl std::fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for problem in &self.problems {
+            match &problem.path {
+                Some(path) => writeln!(
+                    f,
+                    "{} {}: {}",
+                    style("✗").red(),
+                    path.display(),
+                    problem.message
+                )?,
+                None => writeln!(f, "{} {}", style("✗").red(), problem.message)?,
+            }
+        }
+
+        write!(f, "Verified {} entries", self.verified)?;
+        if !self.unchecked.is_empty() {
+            write!(
+                f,
+                " ({} files have no recorded hash and were skipped)",
+                self.unchecked.len()
+            )?;
+        }
+        writeln!(f)?;
+
+        if self.is_ok() {
+            writeln!(f, "{}", style("PASS").green().bold())
+        } else {
+            writeln!(f, "{}", style("FAIL").red().bold())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer_package::{package::Package, utils::from_bytes};
+
+    use super::*;
+
+    #[test]
+    fn verify_intact_and_corrupted_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("public")).unwrap();
+        std::fs::write(dir.path().join("public/index.html"), "INTACT CONTENTS").unwrap();
+        std::fs::write(dir.path().join("mywasm.wasm"), "()").unwrap();
+        std::fs::write(
+            dir.path().join("wasmer.toml"),
+            r#"
+[package]
+name = "testns/testpkg"
+version = "0.0.1"
+
+[fs]
+public = "./public"
+
+[[module]]
+name = "mywasm"
+source = "./mywasm.wasm"
+"#,
+        )
+        .unwrap();
+        let pkg = Package::from_manifest(dir.path().join("wasmer.toml")).unwrap();
+        let mut bytes = pkg.serialize().unwrap().to_vec();
+
+        let report = VerifyReport::compute(&from_bytes(bytes.clone()).unwrap());
+        assert!(report.is_ok(), "{report}");
+        assert!(report.verified >= 2);
+
+        let offset = bytes
+            .windows(b"INTACT".len())
+            .position(|window| window == b"INTACT")
+            .unwrap();
+        bytes[offset..offset + b"BROKEN".len()].copy_from_slice(b"BROKEN");
+
+        let report = VerifyReport::compute(&from_bytes(bytes).unwrap());
+        assert!(!report.is_ok());
+        assert_eq!(report.problems.len(), 1);
+        let path = report.problems[0].path.as_ref().unwrap();
+        assert!(path.ends_with("index.html"), "{}", path.display());
+    }
+}
@@ -12,9 +12,9 @@ use anyhow::Context;
 use colored::Colorize;
 use dialoguer::theme::ColorfulTheme;
 use virtual_net::{
-    DynVirtualNetworking, IpCidr, IpRoute, NetworkError, Result, StreamSecurity,
-    UnsupportedVirtualNetworking, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
-    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+    DynVirtualNetworking, IpCidr, IpRoute, NetworkError, Result, SocketId, SocketStats,
+    StreamSecurity, UnsupportedVirtualNetworking, VirtualIcmpSocket, VirtualNetworking,
+    VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
 };
 
 /// A custom implementation of the [`virtual_net::VirtualNetwork`] that asks users if they want to
@@ -166,6 +166,30 @@ impl AskingNetworking {
 #[async_trait::async_trait]
 #[allow(unused_variables)]
 impl VirtualNetworking for AskingNetworking {
+    /// The backend chosen by the user, or `"asking"` if they haven't been
+    /// asked yet
+    fn backend_name(&self) -> &'static str {
+        match self.enable.get() {
+            Some(Ok(true)) => self.capable.backend_name(),
+            Some(_) => self.unsupported.backend_name(),
+            None => "asking",
+        }
+    }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        match self.enable.get() {
+            Some(Ok(true)) => self.capable.open_sockets(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        match self.enable.get() {
+            Some(Ok(true)) => self.capable.socket_stats(id),
+            _ => None,
+        }
+    }
+
     /// Bridges this local network with a remote network, which is required in
     /// order to make lower level networking calls (such as UDP/TCP)
     async fn bridge(
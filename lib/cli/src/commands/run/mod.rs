@@ -891,7 +891,9 @@ impl<R> MonitoringRuntime<R> {
     }
 }
 
-impl<R: wasmer_wasix::Runtime + Send + Sync> wasmer_wasix::Runtime for MonitoringRuntime<R> {
+impl<R: wasmer_wasix::Runtime + Send + Sync + 'static> wasmer_wasix::Runtime
+    for MonitoringRuntime<R>
+{
     fn networking(&self) -> &virtual_net::DynVirtualNetworking {
         self.runtime.networking()
     }
@@ -939,6 +941,126 @@ impl<R: wasmer_wasix::Runtime + Send + Sync> wasmer_wasix::Runtime for Monitorin
     fn tty(&self) -> Option<&(dyn wasmer_wasix::os::TtyBridge + Send + Sync)> {
         self.runtime.tty()
     }
+
+    fn new_store_async(
+        &self,
+    ) -> futures::future::BoxFuture<
+        '_,
+        Result<wasmer::Store, wasmer_wasix::runtime::StoreCreationError>,
+    > {
+        self.runtime.new_store_async()
+    }
+
+    fn http_client_for(&self, url: &Url) -> Option<&wasmer_wasix::http::DynHttpClient> {
+        self.runtime.http_client_for(url)
+    }
+
+    fn trace_context(&self) -> Option<wasmer_wasix::http::TraceContext> {
+        self.runtime.trace_context()
+    }
+
+    fn log_sink(&self) -> Option<&dyn wasmer_wasix::runtime::LogSink> {
+        self.runtime.log_sink()
+    }
+
+    fn env_filter(&self) -> Option<&dyn wasmer_wasix::runtime::EnvFilter> {
+        self.runtime.env_filter()
+    }
+
+    fn secrets(&self) -> Option<&dyn wasmer_wasix::runtime::SecretStore> {
+        self.runtime.secrets()
+    }
+
+    fn fs_access_policy(&self) -> Option<&dyn wasmer_wasix::runtime::FsAccessPolicy> {
+        self.runtime.fs_access_policy()
+    }
+
+    fn fs_watcher(&self) -> Option<&dyn wasmer_wasix::runtime::FsWatcher> {
+        self.runtime.fs_watcher()
+    }
+
+    fn rng(&self) -> Option<Arc<dyn wasmer_wasix::runtime::VirtualRng>> {
+        self.runtime.rng()
+    }
+
+    fn rng_seed(&self) -> Option<u64> {
+        self.runtime.rng_seed()
+    }
+
+    fn entropy_fallback(&self) -> wasmer_wasix::runtime::EntropyFallback {
+        self.runtime.entropy_fallback()
+    }
+
+    fn clock(&self) -> Option<&dyn wasmer_wasix::runtime::VirtualClock> {
+        self.runtime.clock()
+    }
+
+    fn pid_allocator(&self) -> Option<&dyn wasmer_wasix::runtime::PidAllocator> {
+        self.runtime.pid_allocator()
+    }
+
+    fn shared_region(&self, name: &str) -> Option<Arc<wasmer_wasix::runtime::SharedRegion>> {
+        self.runtime.shared_region(name)
+    }
+
+    fn instance_id(&self) -> Option<uuid::Uuid> {
+        self.runtime.instance_id()
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.runtime.hostname()
+    }
+
+    fn domainname(&self) -> Option<String> {
+        self.runtime.domainname()
+    }
+
+    fn memory_limits(&self) -> Option<wasmer_wasix::runtime::MemoryLimits> {
+        self.runtime.memory_limits()
+    }
+
+    fn syscall_rate_limit(&self) -> Option<wasmer_wasix::runtime::RateLimit> {
+        self.runtime.syscall_rate_limit()
+    }
+
+    fn max_open_fds(&self) -> Option<usize> {
+        self.runtime.max_open_fds()
+    }
+
+    fn default_args(&self) -> Vec<String> {
+        self.runtime.default_args()
+    }
+
+    fn program_name(&self) -> Option<String> {
+        self.runtime.program_name()
+    }
+
+    fn default_cwd(&self) -> Option<PathBuf> {
+        self.runtime.default_cwd()
+    }
+
+    fn execution_deadline(&self) -> Option<std::time::Instant> {
+        self.runtime.execution_deadline()
+    }
+
+    fn feature_flags(&self) -> wasmer_wasix::runtime::FeatureFlags {
+        self.runtime.feature_flags()
+    }
+
+    fn on_shutdown(&self, f: wasmer_wasix::runtime::ShutdownCallback) {
+        self.runtime.on_shutdown(f)
+    }
+
+    fn shutdown(&self) -> futures::future::BoxFuture<'_, ()> {
+        self.runtime.shutdown()
+    }
+
+    fn shallow_clone(&self) -> Arc<wasmer_wasix::runtime::DynRuntime> {
+        Arc::new(MonitoringRuntime {
+            runtime: Arc::clone(&self.runtime),
+            progress: self.progress.clone(),
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -36,7 +36,7 @@ use wasmer_wasix::{
         },
         task_manager::{
             tokio::{RuntimeOrHandle, TokioTaskManager},
-            VirtualTaskManagerExt,
+            VirtualTaskManager, VirtualTaskManagerExt,
         },
     },
     types::__WASI_STDIN_FILENO,
@@ -619,7 +619,7 @@ impl Wasi {
         let client = Arc::new(client);
 
         let package_loader = self
-            .prepare_package_loader(env, client.clone())
+            .prepare_package_loader(env, client.clone(), tokio_task_manager.clone())
             .context("Unable to prepare the package loader")?;
 
         let registry = self.prepare_source(env, client, preferred_webc_version)?;
@@ -668,6 +668,7 @@ impl Wasi {
         &self,
         env: &WasmerEnv,
         client: Arc<dyn HttpClient + Send + Sync>,
+        tasks: Arc<dyn VirtualTaskManager>,
     ) -> Result<impl PackageLoader> {
         let checkout_dir = env.cache_dir().join("checkouts");
         let tokens = tokens_by_authority(env)?;
@@ -675,6 +676,7 @@ impl Wasi {
         let loader = BuiltinPackageLoader::new()
             .with_cache_dir(checkout_dir)
             .with_shared_http_client(client)
+            .with_task_manager(tasks)
             .with_tokens(tokens);
 
         Ok(loader)
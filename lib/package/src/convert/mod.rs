@@ -1,4 +1,7 @@
 mod error;
 mod webc_to_package;
 
-pub use self::{error::ConversionError, webc_to_package::webc_to_package_dir};
+pub use self::{
+    error::ConversionError,
+    webc_to_package::{webc_to_manifest, webc_to_package_dir},
+};
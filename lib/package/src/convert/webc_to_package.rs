@@ -9,6 +9,86 @@ use super::ConversionError;
 /// Convert a webc image into a directory with a wasmer.toml file that can
 /// be used for generating a new pacakge.
 pub fn webc_to_package_dir(webc: &Container, target_dir: &Path) -> Result<(), ConversionError> {
+    let module_dir_name = "modules";
+    let pkg_manifest =
+        webc_to_manifest(webc, |atom_name| format!("./{module_dir_name}/{atom_name}"))?;
+
+    // Extract the mapped volumes to "<target-dir>/<volume-name>".
+
+    let fs_annotation = manifest_filesystem(webc)?;
+    for mapping in fs_annotation.into_iter().flat_map(|ann| ann.0) {
+        if mapping.from.is_some() {
+            // Dependency mounts aren't part of this package.
+            continue;
+        }
+
+        let volume = webc.get_volume(&mapping.volume_name).ok_or_else(|| {
+            ConversionError::msg(format!(
+                "Package annotations specify a volume that does not exist: '{}'",
+                mapping.volume_name
+            ))
+        })?;
+
+        let volume_path = target_dir.join(mapping.volume_name.trim_start_matches('/'));
+
+        std::fs::create_dir_all(&volume_path).map_err(|err| {
+            ConversionError::with_cause(
+                format!(
+                    "could not create volume directory '{}'",
+                    volume_path.display()
+                ),
+                err,
+            )
+        })?;
+
+        volume.unpack("/", &volume_path).map_err(|err| {
+            ConversionError::with_cause("could not unpack volume to filesystemt", err)
+        })?;
+    }
+
+    // Write out the modules.
+
+    let module_dir = target_dir.join(module_dir_name);
+
+    let atoms = webc.atoms();
+    if !atoms.is_empty() {
+        std::fs::create_dir_all(&module_dir).map_err(|err| {
+            ConversionError::with_cause(
+                format!("Could not create directory '{}'", module_dir.display(),),
+                err,
+            )
+        })?;
+        for (atom_name, data) in atoms {
+            let atom_path = module_dir.join(&atom_name);
+
+            std::fs::write(&atom_path, &data).map_err(|err| {
+                ConversionError::with_cause(
+                    format!("Could not write atom to path '{}'", atom_path.display()),
+                    err,
+                )
+            })?;
+        }
+    }
+
+    // Write out the manifest.
+    let manifest_toml = toml::to_string(&pkg_manifest)
+        .map_err(|err| ConversionError::with_cause("could not serialize package manifest", err))?;
+    std::fs::write(target_dir.join("wasmer.toml"), manifest_toml)
+        .map_err(|err| ConversionError::with_cause("could not write wasmer.toml", err))?;
+
+    Ok(())
+}
+
+/// Translate a webc image's manifest into a wasmer.toml manifest, without
+/// touching the filesystem.
+///
+/// Volumes are expected to be extracted to `./<volume-name>`, and
+/// `atom_path` gives the path (relative to the wasmer.toml) each atom will be
+/// extracted to.
+pub fn webc_to_manifest(
+    webc: &Container,
+    atom_path: impl Fn(&str) -> String,
+) -> Result<wasmer_config::package::Manifest, ConversionError> {
     let mut pkg_manifest = wasmer_config::package::Manifest::new_empty();
 
     let manifest = webc.manifest();
@@ -72,39 +152,19 @@ pub fn webc_to_package_dir(webc: &Container, target_dir: &Path) -> Result<(), Co
 
     // Convert filesystem mappings.
 
-    let fs_annotation = manifest
-        .filesystem()
-        .map_err(|err| ConversionError::with_cause("could n ot read fs annotation", err))?;
-    if let Some(ann) = fs_annotation {
+    if let Some(ann) = manifest_filesystem(webc)? {
         for mapping in ann.0 {
             if mapping.from.is_some() {
                 // wasmer.toml does not allow specifying dependency mounts.
                 continue;
             }
 
-            // Extract the volume to "<target-dir>/<volume-name>".
-            let volume = webc.get_volume(&mapping.volume_name).ok_or_else(|| {
-                ConversionError::msg(format!(
+            if webc.get_volume(&mapping.volume_name).is_none() {
+                return Err(ConversionError::msg(format!(
                     "Package annotations specify a volume that does not exist: '{}'",
                     mapping.volume_name
-                ))
-            })?;
-
-            let volume_path = target_dir.join(mapping.volume_name.trim_start_matches('/'));
-
-            std::fs::create_dir_all(&volume_path).map_err(|err| {
-                ConversionError::with_cause(
-                    format!(
-                        "could not create volume directory '{}'",
-                        volume_path.display()
-                    ),
-                    err,
-                )
-            })?;
-
-            volume.unpack("/", &volume_path).map_err(|err| {
-                ConversionError::with_cause("could not unpack volume to filesystemt", err)
-            })?;
+                )));
+            }
 
             let mut source_path = mapping
                 .volume_name
@@ -127,38 +187,15 @@ pub fn webc_to_package_dir(webc: &Container, target_dir: &Path) -> Result<(), Co
 
     // Convert modules.
 
-    let module_dir_name = "modules";
-    let module_dir = target_dir.join(module_dir_name);
-
-    let atoms = webc.atoms();
-    if !atoms.is_empty() {
-        std::fs::create_dir_all(&module_dir).map_err(|err| {
-            ConversionError::with_cause(
-                format!("Could not create directory '{}'", module_dir.display(),),
-                err,
-            )
-        })?;
-        for (atom_name, data) in atoms {
-            let atom_path = module_dir.join(&atom_name);
-
-            std::fs::write(&atom_path, &data).map_err(|err| {
-                ConversionError::with_cause(
-                    format!("Could not write atom to path '{}'", atom_path.display()),
-                    err,
-                )
-            })?;
-
-            let relative_path = format!("./{module_dir_name}/{atom_name}");
-
-            pkg_manifest.modules.push(wasmer_config::package::Module {
-                name: atom_name,
-                source: relative_path.into(),
-                abi: wasmer_config::package::Abi::None,
-                kind: None,
-                interfaces: None,
-                bindings: None,
-            });
-        }
+    for atom_name in webc.atoms().into_keys() {
+        pkg_manifest.modules.push(wasmer_config::package::Module {
+            source: atom_path(&atom_name).into(),
+            name: atom_name,
+            abi: wasmer_config::package::Abi::None,
+            kind: None,
+            interfaces: None,
+            bindings: None,
+        });
     }
 
     // Convert commands.
@@ -211,13 +248,15 @@ pub fn webc_to_package_dir(webc: &Container, target_dir: &Path) -> Result<(), Co
         pkg_manifest.commands.push(cmd);
     }
 
-    // Write out the manifest.
-    let manifest_toml = toml::to_string(&pkg_manifest)
-        .map_err(|err| ConversionError::with_cause("could not serialize package manifest", err))?;
-    std::fs::write(target_dir.join("wasmer.toml"), manifest_toml)
-        .map_err(|err| ConversionError::with_cause("could not write wasmer.toml", err))?;
+    Ok(pkg_manifest)
+}
 
-    Ok(())
+fn manifest_filesystem(
+    webc: &Container,
+) -> Result<Option<webc::metadata::annotations::FileSystemMappings>, ConversionError> {
+    webc.manifest()
+        .filesystem()
+        .map_err(|err| ConversionError::with_cause("could n ot read fs annotation", err))
 }
 
 #[cfg(test)]
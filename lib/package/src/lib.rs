@@ -4,4 +4,5 @@ mod macros;
 
 pub mod convert;
 pub mod package;
+pub mod unpack;
 pub mod utils;
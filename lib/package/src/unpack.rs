@@ -0,0 +1,459 @@
+//! Walk the contents of a [`Container`], optionally extracting them to disk.
+
+use std::{
+    collections::{btree_map, BTreeMap, VecDeque},
+    io::{Cursor, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use shared_buffer::OwnedBuffer;
+use webc::{metadata::AtomSignature, Container, Metadata, PathSegments, Volume};
+
+/// The name of the entry holding the container's manifest.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// What a [`ContainerEntry`] represents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContainerEntryKind {
+    /// The container's manifest, serialized as JSON.
+    Metadata,
+    /// An atom (typically a WebAssembly module).
+    Atom,
+    /// A directory inside one of the container's volumes.
+    VolumeDirectory,
+    /// A file inside one of the container's volumes.
+    VolumeFile,
+}
+
+/// A single item yielded by [`container_entries()`].
+#[derive(Debug, Clone)]
+pub struct ContainerEntry {
+    path: PathBuf,
+    kind: ContainerEntryKind,
+    contents: OwnedBuffer,
+    hash: Option<[u8; 32]>,
+}
+
+impl ContainerEntry {
+    /// The relative path this entry would be unpacked to.
+    ///
+    /// This matches the layout used by [`Container::unpack()`]: the manifest
+    /// and atoms live at the top level and volumes get their own
+    /// subdirectory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn kind(&self) -> ContainerEntryKind {
+        self.kind
+    }
+
+    /// The entry's contents. This is always empty for directories.
+    pub fn contents(&self) -> &OwnedBuffer {
+        &self.contents
+    }
+
+    /// The SHA-256 hash the container recorded for this entry's contents.
+    ///
+    /// Atoms are hashed in the manifest, and volume files only have a hash
+    /// in containers whose format stores one. This is always `None` for
+    /// the manifest and for directories.
+    pub fn hash(&self) -> Option<[u8; 32]> {
+        self.hash
+    }
+
+    /// Get a reader for the entry's contents.
+    pub fn reader(&self) -> impl std::io::Read + '_ {
+        Cursor::new(self.contents.as_slice())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ContainerEntryError {
+    #[error("could not serialize the manifest")]
+    Manifest(#[source] serde_json::Error),
+    #[error("the volume \"{volume}\" lists \"{path}\", but it could not be read")]
+    MissingFile { volume: String, path: String },
+}
+
+/// Lazily walk every entry in a [`Container`].
+///
+/// The manifest is yielded first, followed by each volume in depth-first
+/// order and then the atoms. Entries are produced in the order they need to
+/// be created on disk, so a directory is always yielded before its contents.
+pub fn container_entries(
+    container: &Container,
+) -> impl Iterator<Item = Result<ContainerEntry, ContainerEntryError>> {
+    let manifest = container.manifest();
+    let atom_hashes = manifest
+        .atoms
+        .keys()
+        .filter_map(|name| {
+            let AtomSignature::Sha256(hash) = manifest.atom_signature(name).ok()?;
+            Some((name.clone(), hash))
+        })
+        .collect();
+
+    ContainerEntries {
+        manifest: Some(
+            serde_json::to_vec(container.manifest()).map_err(ContainerEntryError::Manifest),
+        ),
+        volumes: container.volumes().into_iter(),
+        directories: Vec::new(),
+        pending: VecDeque::new(),
+        atoms: container.atoms().into_iter(),
+        atom_hashes,
+    }
+}
+
+#[derive(Debug)]
+struct PendingDirectory {
+    volume_name: String,
+    volume: Volume,
+    path: PathSegments,
+    out_path: PathBuf,
+}
+
+struct ContainerEntries {
+    manifest: Option<Result<Vec<u8>, ContainerEntryError>>,
+    volumes: btree_map::IntoIter<String, Volume>,
+    directories: Vec<PendingDirectory>,
+    pending: VecDeque<Result<ContainerEntry, ContainerEntryError>>,
+    atoms: btree_map::IntoIter<String, OwnedBuffer>,
+    atom_hashes: BTreeMap<String, [u8; 32]>,
+}
+
+impl ContainerEntries {
+    fn expand(&mut self, dir: PendingDirectory) {
+        let PendingDirectory {
+            volume_name,
+            volume,
+            path,
+            out_path,
+        } = dir;
+
+        for (name, _, metadata) in volume.read_dir(&path).unwrap_or_default() {
+            let entry_path = path.join(name.clone());
+            let entry_out_path = out_path.join(name.as_str());
+
+            match metadata {
+                Metadata::Dir { .. } => {
+                    self.pending.push_back(Ok(ContainerEntry {
+                        path: entry_out_path.clone(),
+                        kind: ContainerEntryKind::VolumeDirectory,
+                        contents: OwnedBuffer::default(),
+                        hash: None,
+                    }));
+                    self.directories.push(PendingDirectory {
+                        volume_name: volume_name.clone(),
+                        volume: volume.clone(),
+                        path: entry_path,
+                        out_path: entry_out_path,
+                    });
+                }
+                Metadata::File { .. } => {
+                    let entry = match volume.read_file(&entry_path) {
+                        Some((contents, hash)) => Ok(ContainerEntry {
+                            path: entry_out_path,
+                            kind: ContainerEntryKind::VolumeFile,
+                            contents,
+                            hash,
+                        }),
+                        None => Err(ContainerEntryError::MissingFile {
+                            volume: volume_name.clone(),
+                            path: entry_path.to_string(),
+                        }),
+                    };
+                    self.pending.push_back(entry);
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for ContainerEntries {
+    type Item = Result<ContainerEntry, ContainerEntryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(manifest) = self.manifest.take() {
+            return Some(manifest.map(|contents| ContainerEntry {
+                path: PathBuf::from(MANIFEST_FILE_NAME),
+                kind: ContainerEntryKind::Metadata,
+                contents: contents.into(),
+                hash: None,
+            }));
+        }
+
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                return Some(entry);
+            }
+
+            if let Some(dir) = self.directories.pop() {
+                self.expand(dir);
+                continue;
+            }
+
+            if let Some((name, volume)) = self.volumes.next() {
+                let out_path = PathBuf::from(name.strip_prefix('/').unwrap_or(name.as_str()));
+                self.pending.push_back(Ok(ContainerEntry {
+                    path: out_path.clone(),
+                    kind: ContainerEntryKind::VolumeDirectory,
+                    contents: OwnedBuffer::default(),
+                    hash: None,
+                }));
+                self.directories.push(PendingDirectory {
+                    volume_name: name,
+                    volume,
+                    path: PathSegments::ROOT,
+                    out_path,
+                });
+                continue;
+            }
+
+            break;
+        }
+
+        self.atoms.next().map(|(name, contents)| {
+            Ok(ContainerEntry {
+                hash: self.atom_hashes.get(&name).copied(),
+                path: PathBuf::from(name),
+                kind: ContainerEntryKind::Atom,
+                contents,
+            })
+        })
+    }
+}
+
+/// Options for [`extract_with_progress()`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExtractOptions {
+    /// Replace files which already exist in the output directory instead of
+    /// failing.
+    pub overwrite: bool,
+}
+
+/// Progress reported by [`extract_with_progress()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// Extraction is about to start.
+    Started {
+        total_entries: usize,
+        total_bytes: u64,
+    },
+    /// An entry was written to disk.
+    EntryDone {
+        /// The entry's path relative to the output directory.
+        path: PathBuf,
+        /// The size of this entry.
+        bytes: u64,
+        /// The number of bytes written so far, including this entry.
+        bytes_done: u64,
+    },
+    /// Every entry was written to disk.
+    Finished { entries: usize, bytes: u64 },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    #[error(transparent)]
+    Entry(#[from] ContainerEntryError),
+    #[error("\"{}\" already exists", path.display())]
+    AlreadyExists { path: PathBuf },
+    #[error("unable to write \"{}\"", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+}
+
+/// Extract a [`Container`] into `out_dir` using the same layout as
+/// [`container_entries()`], calling `progress` as each entry is written.
+///
+/// The output directory is created if it doesn't exist.
+pub fn extract_with_progress(
+    container: &Container,
+    out_dir: &Path,
+    opts: &ExtractOptions,
+    progress: &mut dyn FnMut(ProgressEvent),
+) -> Result<(), ExtractError> {
+    // Note: entry contents are reference-counted, so collecting them up front
+    // to get the totals is cheap
+    let entries: Vec<ContainerEntry> = container_entries(container).collect::<Result<_, _>>()?;
+    let total_bytes = entries.iter().map(|e| e.contents.len() as u64).sum();
+
+    progress(ProgressEvent::Started {
+        total_entries: entries.len(),
+        total_bytes,
+    });
+
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |error: std::io::Error| ExtractError::Io { path, error }
+    };
+
+    std::fs::create_dir_all(out_dir).map_err(io_error(out_dir))?;
+
+    let mut bytes_done = 0;
+    for entry in &entries {
+        let path = out_dir.join(&entry.path);
+
+        if entry.kind == ContainerEntryKind::VolumeDirectory {
+            std::fs::create_dir_all(&path).map_err(io_error(&path))?;
+        } else {
+            let mut file = std::fs::OpenOptions::new();
+            file.write(true);
+            if opts.overwrite {
+                file.create(true).truncate(true);
+            } else {
+                file.create_new(true);
+            }
+            let mut file = file.open(&path).map_err(|error| match error.kind() {
+                ErrorKind::AlreadyExists => ExtractError::AlreadyExists { path: path.clone() },
+                _ => io_error(&path)(error),
+            })?;
+            std::io::Write::write_all(&mut file, entry.contents.as_slice())
+                .map_err(io_error(&path))?;
+        }
+
+        let bytes = entry.contents.len() as u64;
+        bytes_done += bytes;
+        progress(ProgressEvent::EntryDone {
+            path: entry.path.clone(),
+            bytes,
+            bytes_done,
+        });
+    }
+
+    progress(ProgressEvent::Finished {
+        entries: entries.len(),
+        bytes: bytes_done,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use sha2::Digest;
+
+    use crate::{package::Package, utils::from_bytes};
+
+    use super::*;
+
+    fn test_container() -> Container {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = tmpdir.path();
+        std::fs::create_dir_all(dir.join("public/nested")).unwrap();
+        std::fs::write(dir.join("public/index.html"), "INDEX").unwrap();
+        std::fs::write(dir.join("public/nested/file.txt"), "NESTED").unwrap();
+        std::fs::write(dir.join("mywasm.wasm"), "()").unwrap();
+        std::fs::write(
+            dir.join("wasmer.toml"),
+            r#"
+[package]
+name = "testns/testpkg"
+version = "0.0.1"
+description = "descr1"
+
+[fs]
+public = "./public"
+
+[[module]]
+name = "mywasm"
+source = "./mywasm.wasm"
+"#,
+        )
+        .unwrap();
+        let pkg = Package::from_manifest(dir.join("wasmer.toml")).unwrap();
+        from_bytes(pkg.serialize().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn walk_container_entries() {
+        let container = test_container();
+
+        let entries: Vec<_> = container_entries(&container)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let first = &entries[0];
+        assert_eq!(first.kind(), ContainerEntryKind::Metadata);
+        assert_eq!(first.path(), Path::new(MANIFEST_FILE_NAME));
+        let last = entries.last().unwrap();
+        assert_eq!(last.kind(), ContainerEntryKind::Atom);
+        assert_eq!(last.path(), Path::new("mywasm"));
+        assert_eq!(last.contents().as_slice(), b"()");
+        let hash: [u8; 32] = sha2::Sha256::digest(b"()").into();
+        assert_eq!(last.hash(), Some(hash));
+
+        let index = entries
+            .iter()
+            .find(|e| e.path().ends_with("index.html"))
+            .unwrap();
+        assert_eq!(index.kind(), ContainerEntryKind::VolumeFile);
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut index.reader(), &mut contents).unwrap();
+        assert_eq!(contents, "INDEX");
+
+        let nested_dir = entries
+            .iter()
+            .position(|e| e.path().ends_with("nested"))
+            .unwrap();
+        let nested_file = entries
+            .iter()
+            .position(|e| e.path().ends_with("nested/file.txt"))
+            .unwrap();
+        assert_eq!(
+            entries[nested_dir].kind(),
+            ContainerEntryKind::VolumeDirectory
+        );
+        assert!(nested_dir < nested_file);
+    }
+
+    #[test]
+    fn extract_reports_progress() {
+        let container = test_container();
+        let out = tempfile::tempdir().unwrap();
+        let mut events = Vec::new();
+
+        extract_with_progress(
+            &container,
+            out.path(),
+            &ExtractOptions::default(),
+            &mut |event| events.push(event),
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(out.path().join("public/nested/file.txt")).unwrap(),
+            "NESTED"
+        );
+        let ProgressEvent::Started {
+            total_entries,
+            total_bytes,
+        } = events[0]
+        else {
+            panic!("expected a started event, got {:?}", events[0]);
+        };
+        assert_eq!(events.len(), total_entries + 2);
+        assert_eq!(
+            events.last().unwrap(),
+            &ProgressEvent::Finished {
+                entries: total_entries,
+                bytes: total_bytes,
+            }
+        );
+
+        let err = extract_with_progress(
+            &container,
+            out.path(),
+            &ExtractOptions::default(),
+            &mut |_| {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, ExtractError::AlreadyExists { .. }));
+    }
+}
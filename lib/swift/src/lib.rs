@@ -38,7 +38,7 @@ pub fn run_package(webc_bytes: Vec<u8>, args: Vec<String>) -> Result<String, Was
     let tasks = Arc::new(tasks);
     let mut rt = PluggableRuntime::new(Arc::clone(&tasks) as Arc<_>);
     rt.set_engine(Some(wasmer::Engine::default()))
-        .set_package_loader(BuiltinPackageLoader::new());
+        .set_package_loader(BuiltinPackageLoader::new().with_task_manager(tasks.clone()));
 
     let pkg = tokio_rt
         .handle()
@@ -0,0 +1,320 @@
+use super::*;
+
+use crate::VirtualFile;
+
+/// Wraps a stdio [`VirtualFile`] (e.g. the host's stdout), coalescing small
+/// writes into a buffer so a guest that writes one byte at a time doesn't
+/// cause a write to the host for every byte.
+///
+/// The buffer is written out whenever a newline is written, when it is
+/// full, when the file is flushed, and when it is dropped.
+///
+/// This is meant for streams, so seeking is passed straight through to the
+/// inner file without writing out the buffer first.
+#[derive(derive_more::Debug)]
+pub struct BufferingStdio {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    #[debug(ignore)]
+    buffer: Vec<u8>,
+    /// How much of `buffer` has already been written to `inner`.
+    written: usize,
+    capacity: usize,
+}
+
+impl BufferingStdio {
+    /// The default buffer size.
+    pub const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+    pub fn new(inner: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
+        Self::with_capacity(inner, Self::DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(
+        inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+        capacity: usize,
+    ) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            inner,
+            buffer: Vec::with_capacity(capacity),
+            written: 0,
+            capacity,
+        }
+    }
+
+    /// The number of bytes waiting to be written to the inner file.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.written
+    }
+
+    /// Write everything in the buffer to the inner file.
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.buffer.len() {
+            match Pin::new(&mut self.inner).poll_write(cx, &self.buffer[self.written..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                Poll::Ready(Ok(amt)) => self.written += amt,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        self.buffer.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for BufferingStdio {
+    fn drop(&mut self) {
+        if self.buffered() == 0 {
+            return;
+        }
+
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        match self.poll_write_buffer(&mut cx) {
+            Poll::Ready(Ok(())) => {
+                let _ = Pin::new(&mut self.inner).poll_flush(&mut cx);
+            }
+            Poll::Ready(Err(e)) => {
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    "unable to flush stdio"
+                );
+            }
+            Poll::Pending => {
+                tracing::warn!(
+                    lost_bytes = self.buffered(),
+                    "stdio was not ready to be flushed when it was dropped"
+                );
+            }
+        }
+    }
+}
+
+impl VirtualFile for BufferingStdio {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.inner.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for BufferingStdio {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.buffer.len() + buf.len() > this.capacity {
+            match this.poll_write_buffer(cx) {
+                Poll::Ready(Ok(())) => {}
+                other => return other.map_ok(|_| 0),
+            }
+        }
+
+        // Large writes wouldn't benefit from being buffered
+        if buf.len() >= this.capacity {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+
+        this.buffer.extend_from_slice(buf);
+        if buf.contains(&b'\n') {
+            // The data has been accepted either way, so if the inner file
+            // isn't ready it gets written out on the next write or flush
+            if let Poll::Ready(Err(e)) = this.poll_write_buffer(cx) {
+                tracing::debug!(
+                    error = &e as &dyn std::error::Error,
+                    "unable to write buffered stdio"
+                );
+            }
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_buffer(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_buffer(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+impl AsyncRead for BufferingStdio {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for BufferingStdio {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    /// Records every write made to it.
+    #[derive(Debug, Default, Clone)]
+    struct RecordingFile {
+        writes: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl VirtualFile for RecordingFile {
+        fn last_accessed(&self) -> u64 {
+            0
+        }
+
+        fn last_modified(&self) -> u64 {
+            0
+        }
+
+        fn created_time(&self) -> u64 {
+            0
+        }
+
+        fn size(&self) -> u64 {
+            0
+        }
+
+        fn set_len(&mut self, _new_size: u64) -> crate::Result<()> {
+            Ok(())
+        }
+
+        fn unlink(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(0))
+        }
+
+        fn poll_write_ready(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(usize::MAX))
+        }
+    }
+
+    impl AsyncWrite for RecordingFile {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.writes.lock().unwrap().push(buf.to_vec());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncRead for RecordingFile {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncSeek for RecordingFile {
+        fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+            Poll::Ready(Ok(0))
+        }
+    }
+
+    #[tokio::test]
+    async fn small_writes_are_coalesced() {
+        let inner = RecordingFile::default();
+        let mut stdio = BufferingStdio::with_capacity(Box::new(inner.clone()), 4);
+
+        for byte in b"abcdef\n" {
+            stdio.write_all(&[*byte]).await.unwrap();
+        }
+        stdio.write_all(b"xy").await.unwrap();
+        assert_eq!(
+            *inner.writes.lock().unwrap(),
+            [b"abcd".to_vec(), b"ef\n".to_vec()]
+        );
+
+        stdio.flush().await.unwrap();
+        assert_eq!(inner.writes.lock().unwrap().last().unwrap(), b"xy");
+
+        stdio.write_all(b"z").await.unwrap();
+        drop(stdio);
+        assert_eq!(inner.writes.lock().unwrap().last().unwrap(), b"z");
+    }
+}
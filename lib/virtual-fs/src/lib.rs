@@ -20,6 +20,7 @@ pub mod arc_box_file;
 pub mod arc_file;
 pub mod arc_fs;
 pub mod buffer_file;
+pub mod buffering_stdio;
 pub mod builder;
 pub mod combine_file;
 pub mod cow_file;
@@ -27,6 +28,7 @@ pub mod dual_write_file;
 pub mod empty_fs;
 #[cfg(feature = "host-fs")]
 pub mod host_fs;
+pub mod line_ending_file;
 pub mod mem_fs;
 pub mod null_file;
 pub mod passthru_fs;
@@ -53,12 +55,14 @@ pub use arc_box_file::*;
 pub use arc_file::*;
 pub use arc_fs::*;
 pub use buffer_file::*;
+pub use buffering_stdio::*;
 pub use builder::*;
 pub use combine_file::*;
 pub use cow_file::*;
 pub use dual_write_file::*;
 pub use empty_fs::*;
 pub use filesystems::FileSystems;
+pub use line_ending_file::*;
 pub use null_file::*;
 pub use overlay_fs::OverlayFileSystem;
 pub use passthru_fs::*;
@@ -69,7 +73,7 @@ pub use tmp_fs::*;
 pub use trace_fs::TraceFileSystem;
 pub use union_fs::*;
 #[cfg(feature = "webc-fs")]
-pub use webc_volume_fs::WebcVolumeFileSystem;
+pub use webc_volume_fs::{extract_to_memfs, WebcVolumeFileSystem};
 pub use zero_file::*;
 
 pub type Result<T> = std::result::Result<T, FsError>;
@@ -0,0 +1,257 @@
+use super::*;
+
+use crate::VirtualFile;
+
+/// How [`LineEndingFile`] translates the line endings written to it.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// Write everything exactly as it was given.
+    #[default]
+    Passthrough,
+    /// Turn bare `\n`s into `\r\n`, the way a terminal with `ONLCR` set
+    /// (or a Windows console) expects.
+    CrLf,
+    /// Turn `\r\n`s into `\n`.
+    Lf,
+}
+
+/// Wraps a stdio [`VirtualFile`] (e.g. the host's stdout), translating the
+/// line endings of everything written to it.
+///
+/// Translated bytes which the inner file isn't ready for yet are held on to
+/// and written out on the next write or flush, so a write is never split
+/// part of the way through a line ending.
+#[derive(derive_more::Debug)]
+pub struct LineEndingFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    line_ending: LineEnding,
+    #[debug(ignore)]
+    pending: Vec<u8>,
+    /// How much of `pending` has already been written to `inner`.
+    written: usize,
+    /// The last byte that was written to this file.
+    last: Option<u8>,
+    /// A `\r` which hasn't been written yet because the next byte decides
+    /// whether it is part of a `\r\n` (only used for [`LineEnding::Lf`]).
+    held_cr: bool,
+}
+
+impl LineEndingFile {
+    pub fn new(
+        inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+        line_ending: LineEnding,
+    ) -> Self {
+        Self {
+            inner,
+            line_ending,
+            pending: Vec::new(),
+            written: 0,
+            last: None,
+            held_cr: false,
+        }
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    fn translate(&mut self, data: &[u8]) {
+        for &byte in data {
+            match self.line_ending {
+                LineEnding::Passthrough => self.pending.push(byte),
+                LineEnding::CrLf => {
+                    if byte == b'\n' && self.last != Some(b'\r') {
+                        self.pending.push(b'\r');
+                    }
+                    self.pending.push(byte);
+                }
+                LineEnding::Lf => {
+                    if std::mem::take(&mut self.held_cr) && byte != b'\n' {
+                        self.pending.push(b'\r');
+                    }
+                    if byte == b'\r' {
+                        self.held_cr = true;
+                    } else {
+                        self.pending.push(byte);
+                    }
+                }
+            }
+            self.last = Some(byte);
+        }
+    }
+
+    /// Write everything that has been translated to the inner file.
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            match Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.written..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                Poll::Ready(Ok(amt)) => self.written += amt,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Write out everything, including a `\r` which is still being held back.
+    fn poll_write_all(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if std::mem::take(&mut self.held_cr) {
+            self.pending.push(b'\r');
+        }
+        self.poll_write_pending(cx)
+    }
+}
+
+impl VirtualFile for LineEndingFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.inner.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for LineEndingFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.line_ending == LineEnding::Passthrough {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+
+        match this.poll_write_pending(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other.map_ok(|_| 0),
+        }
+
+        this.translate(buf);
+
+        // The data has been accepted either way, so if the inner file isn't
+        // ready it gets written out on the next write or flush
+        if let Poll::Ready(Err(e)) = this.poll_write_pending(cx) {
+            tracing::debug!(
+                error = &e as &dyn std::error::Error,
+                "unable to write translated stdio"
+            );
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_all(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_all(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+impl AsyncRead for LineEndingFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for LineEndingFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{ArcFile, BufferFile};
+
+    async fn translate(line_ending: LineEnding, writes: &[&[u8]]) -> Vec<u8> {
+        let mut inner = ArcFile::new(Box::<BufferFile>::default());
+        let mut file = LineEndingFile::new(Box::new(inner.clone()), line_ending);
+        for data in writes {
+            file.write_all(data).await.unwrap();
+        }
+        file.flush().await.unwrap();
+
+        let mut contents = Vec::new();
+        inner.seek(io::SeekFrom::Start(0)).await.unwrap();
+        inner.read_to_end(&mut contents).await.unwrap();
+        contents
+    }
+
+    #[tokio::test]
+    async fn line_endings_are_translated() {
+        let writes: &[&[u8]] = &[b"a\nb\r\n", b"c\r", b"\nd\r", b"e\n\r"];
+
+        assert_eq!(
+            translate(LineEnding::Passthrough, writes).await,
+            b"a\nb\r\nc\r\nd\re\n\r"
+        );
+        assert_eq!(
+            translate(LineEnding::CrLf, writes).await,
+            b"a\r\nb\r\nc\r\nd\re\r\n\r"
+        );
+        assert_eq!(
+            translate(LineEnding::Lf, writes).await,
+            b"a\nb\nc\nd\re\n\r"
+        );
+    }
+}
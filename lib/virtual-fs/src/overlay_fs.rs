@@ -14,7 +14,7 @@ use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
 
 use crate::{
     ops, FileOpener, FileSystem, FileSystems, FsError, Metadata, OpenOptions, OpenOptionsConfig,
-    ReadDir, VirtualFile,
+    ReadDir, TmpFileSystem, VirtualFile,
 };
 
 /// A primary filesystem and chain of secondary filesystems that are overlayed
@@ -107,6 +107,23 @@ where
     }
 }
 
+impl<S> OverlayFileSystem<TmpFileSystem, [S; 1]>
+where
+    S: FileSystem + Send + Sync + 'static,
+{
+    /// Layer a fresh, in-memory scratch filesystem on top of a read-only
+    /// `base`.
+    ///
+    /// Files are copied into the scratch layer the first time they are
+    /// modified and deletions are recorded as whiteouts, so `base` is never
+    /// written to. This means a single `base` (e.g. an
+    /// `Arc<dyn FileSystem + Send + Sync>`) can be shared by many instances,
+    /// each getting their own throwaway changes.
+    pub fn scratch(base: S) -> Self {
+        OverlayFileSystem::new(TmpFileSystem::new(), [base])
+    }
+}
+
 impl<P, S> FileSystem for OverlayFileSystem<P, S>
 where
     P: FileSystem + Send + 'static,
@@ -1411,6 +1428,44 @@ mod tests {
         assert!(ops::is_file(&fs.secondaries[0], "/secondary/file.txt"));
     }
 
+    #[tokio::test]
+    async fn scratch_layers_never_modify_the_base() {
+        let base = Arc::new(MemFS::default());
+        ops::create_dir_all(&base, "/etc").unwrap();
+        ops::write(&base, "/etc/config.txt", b"original")
+            .await
+            .unwrap();
+        ops::write(&base, "/etc/remove-me.txt", b"").await.unwrap();
+
+        let first = OverlayFileSystem::scratch(base.clone());
+        let second = OverlayFileSystem::scratch(base.clone());
+
+        ops::write(&first, "/etc/config.txt", b"modified")
+            .await
+            .unwrap();
+        first.remove_file(Path::new("/etc/remove-me.txt")).unwrap();
+        ops::touch(&first, "/etc/new.txt").unwrap();
+
+        assert_eq!(
+            ops::read_to_string(&first, "/etc/config.txt")
+                .await
+                .unwrap(),
+            "modified"
+        );
+        assert!(!ops::exists(&first, "/etc/remove-me.txt"));
+        assert!(ops::is_file(&first, "/etc/new.txt"));
+
+        // Neither the base nor other instances see the changes
+        for fs in [&second as &dyn FileSystem, &base as &dyn FileSystem] {
+            assert_eq!(
+                ops::read_to_string(fs, "/etc/config.txt").await.unwrap(),
+                "original"
+            );
+            assert!(ops::is_file(fs, "/etc/remove-me.txt"));
+            assert!(!ops::exists(fs, "/etc/new.txt"));
+        }
+    }
+
     #[tokio::test]
     async fn unlink_file_from_secondary_fs() {
         let primary = MemFS::default();
@@ -1,14 +1,17 @@
 use std::{
+    borrow::Cow,
     convert::{TryFrom, TryInto},
     io::Cursor,
     path::{Path, PathBuf},
     pin::Pin,
     result::Result,
+    sync::Arc,
     task::Poll,
 };
 
 use futures::future::BoxFuture;
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
+use wasmer_package::unpack::{container_entries, ContainerEntryKind};
 use webc::{
     compat::SharedBytes, Container, Metadata as WebcMetadata, PathSegmentError, PathSegments,
     ToPathSegments, Volume,
@@ -48,6 +51,46 @@ impl WebcVolumeFileSystem {
     }
 }
 
+/// Copy the contents of every [`Volume`] in a [`Container`] into an
+/// in-memory filesystem, so it can be handed to a WASI instance without
+/// touching disk.
+///
+/// Volumes are mounted to the root directory, the same as
+/// [`WebcVolumeFileSystem::mount_all()`], and files in earlier volumes take
+/// precedence. Unlike the read-only [`WebcVolumeFileSystem`], the result can
+/// be modified.
+pub fn extract_to_memfs(
+    container: &Container,
+) -> Result<Arc<dyn FileSystem + Send + Sync>, anyhow::Error> {
+    let fs = crate::mem_fs::FileSystem::default();
+
+    for entry in container_entries(container) {
+        let entry = entry?;
+
+        // Entries are nested under a directory named after their volume,
+        // which we don't want
+        let mut components = entry.path().components();
+        components.next();
+        let path = Path::new("/").join(components.as_path());
+
+        match entry.kind() {
+            ContainerEntryKind::VolumeDirectory => {
+                if fs.metadata(&path).is_err() {
+                    fs.create_dir(&path)?;
+                }
+            }
+            ContainerEntryKind::VolumeFile => {
+                if fs.metadata(&path).is_err() {
+                    fs.insert_ro_file(&path, Cow::Owned(entry.contents().to_vec()))?;
+                }
+            }
+            ContainerEntryKind::Metadata | ContainerEntryKind::Atom => {}
+        }
+    }
+
+    Ok(Arc::new(fs))
+}
+
 impl FileSystem for WebcVolumeFileSystem {
     fn readlink(&self, _path: &Path) -> crate::Result<PathBuf> {
         Err(FsError::InvalidInput)
@@ -552,6 +595,36 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn extract_volumes_to_memfs() {
+        let container = from_bytes(PYTHON_WEBC).unwrap();
+        let mounted = WebcVolumeFileSystem::mount_all(&container);
+
+        let fs = extract_to_memfs(&container).unwrap();
+
+        assert!(fs.metadata("/lib/python3.6".as_ref()).unwrap().is_dir());
+        let mut f = fs
+            .new_open_options()
+            .read(true)
+            .open("/lib/python.wasm")
+            .unwrap();
+        let mut buffer = Vec::new();
+        f.read_to_end(&mut buffer).await.unwrap();
+        let mut expected = Vec::new();
+        mounted
+            .new_open_options()
+            .read(true)
+            .open("/lib/python.wasm")
+            .unwrap()
+            .read_to_end(&mut expected)
+            .await
+            .unwrap();
+        assert_eq!(buffer, expected);
+
+        // The copy is independent of the container, so it can be changed
+        fs.create_dir("/tmp".as_ref()).unwrap();
+    }
+
     #[tokio::test]
     async fn file_opener() {
         let container = from_bytes(PYTHON_WEBC).unwrap();
@@ -580,6 +580,10 @@ impl RemoteCommon {
 
 #[async_trait::async_trait]
 impl VirtualNetworking for RemoteNetworkingClient {
+    fn backend_name(&self) -> &'static str {
+        "remote"
+    }
+
     async fn bridge(
         &self,
         network: &str,
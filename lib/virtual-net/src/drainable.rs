@@ -0,0 +1,492 @@
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Shutdown, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::{
+    DynVirtualNetworking, InterestHandler, IpCidr, IpRoute, NetworkError, Result, SocketId,
+    SocketStats, SocketStatus, StreamSecurity, VirtualConnectedSocket, VirtualIcmpSocket,
+    VirtualIoSource, VirtualNetworking, VirtualRawSocket, VirtualSocket, VirtualTcpListener,
+    VirtualTcpSocket, VirtualUdpSocket,
+};
+
+#[derive(Debug, Default)]
+struct DrainState {
+    paused: AtomicBool,
+    active: AtomicUsize,
+    /// Listeners which were polled while paused, to be woken on resume.
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// Wraps another networking implementation so that new connections can be
+/// temporarily refused while existing ones are allowed to finish.
+///
+/// While paused, `connect_tcp()`, `listen_tcp()`, `bind_udp()` and accepting
+/// on a listener created by this implementation fail with
+/// [`NetworkError::WouldBlock`], and those listeners don't report themselves
+/// as readable until the implementation is resumed. Connections and UDP
+/// sockets that already exist are unaffected. This is intended for draining
+/// a host before a rolling restart.
+#[derive(Debug, Clone)]
+pub struct DrainableNetworking {
+    inner: DynVirtualNetworking,
+    state: Arc<DrainState>,
+}
+
+impl DrainableNetworking {
+    pub fn new(inner: DynVirtualNetworking) -> Self {
+        Self {
+            inner,
+            state: Arc::new(DrainState::default()),
+        }
+    }
+
+    /// Stop handing out new connections.
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Start handing out new connections again.
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.state.wakers.lock().unwrap());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::SeqCst)
+    }
+
+    /// The number of TCP connections created through this implementation
+    /// which haven't been dropped yet.
+    pub fn active_connections(&self) -> usize {
+        self.state.active.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` once the implementation is paused and every
+    /// connection has finished.
+    pub fn is_drained(&self) -> bool {
+        self.is_paused() && self.active_connections() == 0
+    }
+
+    fn check_paused(&self) -> Result<()> {
+        if self.is_paused() {
+            Err(NetworkError::WouldBlock)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for DrainableNetworking {
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        self.inner.open_sockets()
+    }
+
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        self.inner.socket_stats(id)
+    }
+
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix).await
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip).await
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear().await
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list().await
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac().await
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+            .await
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr).await
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear().await
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list().await
+    }
+
+    async fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        self.check_paused()?;
+        let inner = self
+            .inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await?;
+        Ok(Box::new(DrainableTcpListener {
+            inner,
+            state: self.state.clone(),
+        }))
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        self.check_paused()?;
+        self.inner.bind_udp(addr, reuse_port, reuse_addr).await
+    }
+
+    async fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        self.check_paused()?;
+        let inner = self.inner.connect_tcp(addr, peer).await?;
+        Ok(DrainableTcpSocket::wrap(inner, &self.state))
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+#[derive(Debug)]
+struct DrainableTcpListener {
+    inner: Box<dyn VirtualTcpListener + Sync>,
+    state: Arc<DrainState>,
+}
+
+impl VirtualIoSource for DrainableTcpListener {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        // Accepting would fail anyway, so don't let accept loops spin on
+        // connections waiting in the backlog
+        if self.state.paused.load(Ordering::SeqCst) {
+            let mut wakers = self.state.wakers.lock().unwrap();
+            // Note: check again now the lock is held, otherwise a resume()
+            // in between would never wake us
+            if self.state.paused.load(Ordering::SeqCst) {
+                if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                return Poll::Pending;
+            }
+        }
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualTcpListener for DrainableTcpListener {
+    fn try_accept(&mut self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        // Note: pending connections are left in the backlog so they can
+        // still be picked up once we are resumed.
+        if self.state.paused.load(Ordering::SeqCst) {
+            return Err(NetworkError::WouldBlock);
+        }
+        let (socket, addr) = self.inner.try_accept()?;
+        Ok((DrainableTcpSocket::wrap(socket, &self.state), addr))
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        self.inner.ttl()
+    }
+}
+
+/// A TCP socket which counts towards the active connections of a
+/// [`DrainableNetworking`] for as long as it is alive.
+#[derive(Debug)]
+struct DrainableTcpSocket {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    state: Arc<DrainState>,
+}
+
+impl DrainableTcpSocket {
+    fn wrap(
+        inner: Box<dyn VirtualTcpSocket + Sync>,
+        state: &Arc<DrainState>,
+    ) -> Box<dyn VirtualTcpSocket + Sync> {
+        state.active.fetch_add(1, Ordering::SeqCst);
+        Box::new(Self {
+            inner,
+            state: state.clone(),
+        })
+    }
+}
+
+impl Drop for DrainableTcpSocket {
+    fn drop(&mut self) {
+        self.state.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl VirtualIoSource for DrainableTcpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for DrainableTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualConnectedSocket for DrainableTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        self.inner.try_send(data)
+    }
+
+    fn try_flush(&mut self) -> Result<()> {
+        self.inner.try_flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.inner.close()
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        self.inner.try_recv(buf)
+    }
+}
+
+impl VirtualTcpSocket for DrainableTcpSocket {
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, reuse: bool) -> Result<()> {
+        self.inner.set_nodelay(reuse)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn set_keepalive(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    fn keepalive(&self) -> Result<bool> {
+        self.inner.keepalive()
+    }
+
+    fn set_dontroute(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_dontroute(keepalive)
+    }
+
+    fn dontroute(&self) -> Result<bool> {
+        self.inner.dontroute()
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+    use crate::LoopbackNetworking;
+
+    #[tokio::test]
+    async fn paused_networking_refuses_new_connections() {
+        let loopback = LoopbackNetworking::new();
+        let net = DrainableNetworking::new(Arc::new(loopback.clone()));
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+        let mut listener = net.listen_tcp(addr, false, false, false).await.unwrap();
+
+        let _client = loopback.loopback_connect_to(addr, addr).unwrap();
+        let (server, _) = listener.try_accept().unwrap();
+        assert_eq!(net.active_connections(), 1);
+
+        net.pause();
+        let _pending = loopback.loopback_connect_to(addr, addr).unwrap();
+        assert_eq!(listener.try_accept().unwrap_err(), NetworkError::WouldBlock);
+        assert_eq!(
+            net.listen_tcp(addr, false, false, false).await.unwrap_err(),
+            NetworkError::WouldBlock
+        );
+        assert!(!net.is_drained());
+
+        drop(server);
+        assert!(net.is_drained());
+
+        net.resume();
+        let (_server, _) = listener.try_accept().unwrap();
+        assert_eq!(net.active_connections(), 1);
+    }
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl futures_util::task::ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn paused_listeners_are_not_readable() {
+        let loopback = LoopbackNetworking::new();
+        let net = DrainableNetworking::new(Arc::new(loopback.clone()));
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+        let mut listener = net.listen_tcp(addr, false, false, false).await.unwrap();
+        let woken = Arc::new(CountingWaker::default());
+        let waker = futures_util::task::waker(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        net.pause();
+        let _pending = loopback.loopback_connect_to(addr, addr).unwrap();
+        assert!(listener.poll_read_ready(&mut cx).is_pending());
+        assert_eq!(
+            net.bind_udp(addr, false, false).await.unwrap_err(),
+            NetworkError::WouldBlock
+        );
+        assert_eq!(woken.0.load(Ordering::SeqCst), 0);
+
+        net.resume();
+        assert_eq!(woken.0.load(Ordering::SeqCst), 1);
+        assert!(listener.poll_read_ready(&mut cx).is_ready());
+        listener.try_accept().unwrap();
+    }
+}
@@ -7,8 +7,9 @@ use crate::{
     VirtualConnectionlessSocket, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
     VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
 };
+use crate::{SocketId, SocketStats};
 use bytes::{Buf, BytesMut};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::io::{self, Read, Write};
 use std::mem::MaybeUninit;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
@@ -19,9 +20,10 @@ use std::os::fd::RawFd;
 #[cfg(windows)]
 use std::os::windows::io::AsRawSocket;
 
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use std::task::Poll;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::runtime::Handle;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
@@ -29,11 +31,103 @@ use virtual_mio::{
     state_as_waker_map, HandlerGuardState, InterestGuard, InterestHandler, InterestType, Selector,
 };
 
+/// Which IP versions [`LocalNetworking`] should use when talking to a host.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AddressFamilyPreference {
+    /// Only use IPv4 addresses.
+    V4Only,
+    /// Only use IPv6 addresses.
+    V6Only,
+    /// Use IPv4 addresses when available, falling back to IPv6.
+    PreferV4,
+    /// Use IPv6 addresses when available, falling back to IPv4.
+    PreferV6,
+}
+
+impl AddressFamilyPreference {
+    fn allows(self, ip: IpAddr) -> bool {
+        match self {
+            AddressFamilyPreference::V4Only => ip.is_ipv4(),
+            AddressFamilyPreference::V6Only => ip.is_ipv6(),
+            AddressFamilyPreference::PreferV4 | AddressFamilyPreference::PreferV6 => true,
+        }
+    }
+
+    /// Drop any addresses this preference doesn't allow and move the
+    /// preferred family to the front, keeping the resolver's order otherwise.
+    fn apply(self, addrs: &mut Vec<IpAddr>) {
+        addrs.retain(|ip| self.allows(*ip));
+        match self {
+            AddressFamilyPreference::PreferV4 => addrs.sort_by_key(|ip| !ip.is_ipv4()),
+            AddressFamilyPreference::PreferV6 => addrs.sort_by_key(|ip| !ip.is_ipv6()),
+            AddressFamilyPreference::V4Only | AddressFamilyPreference::V6Only => {}
+        }
+    }
+}
+
+/// Socket options [`LocalNetworking`] applies to the host sockets it creates.
+///
+/// Options which are `None` are left at the operating system's defaults.
+/// Guests can still change most of them on individual sockets.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// Disable Nagle's algorithm (`TCP_NODELAY`) on TCP connections.
+    pub tcp_nodelay: Option<bool>,
+    /// The size of the kernel's send buffer (`SO_SNDBUF`).
+    pub send_buffer_size: Option<usize>,
+    /// The size of the kernel's receive buffer (`SO_RCVBUF`).
+    pub recv_buffer_size: Option<usize>,
+    /// Allow listening sockets to bind to an address which is still in use
+    /// by connections in `TIME_WAIT` (`SO_REUSEADDR`).
+    ///
+    /// When unset, TCP listeners use the same default as the standard
+    /// library (enabled on Unix) and UDP sockets leave it disabled.
+    pub reuse_addr: Option<bool>,
+}
+
+impl SocketOptions {
+    /// Create a socket bound to `addr` with these options applied.
+    fn bind(&self, addr: SocketAddr, ty: socket2::Type) -> io::Result<socket2::Socket> {
+        let socket = socket2::Socket::new(socket2::Domain::for_address(addr), ty, None)?;
+
+        match self.reuse_addr {
+            Some(reuse_addr) => socket.set_reuse_address(reuse_addr)?,
+            None if cfg!(unix) && ty == socket2::Type::STREAM => socket.set_reuse_address(true)?,
+            None => {}
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+
+        socket.bind(&addr.into())?;
+        Ok(socket)
+    }
+
+    fn apply_to_stream(&self, stream: &mut LocalTcpStream) -> Result<()> {
+        if let Some(nodelay) = self.tcp_nodelay {
+            stream.set_nodelay(nodelay)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            stream.set_send_buf_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            stream.set_recv_buf_size(size)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct LocalNetworking {
     selector: Arc<Selector>,
     handle: Handle,
     ruleset: Option<Ruleset>,
+    address_family: Option<AddressFamilyPreference>,
+    socket_options: SocketOptions,
+    tracker: Arc<SocketTracker>,
 }
 
 impl LocalNetworking {
@@ -42,6 +136,9 @@ impl LocalNetworking {
             selector: Selector::new(),
             handle: Handle::current(),
             ruleset: None,
+            address_family: None,
+            socket_options: SocketOptions::default(),
+            tracker: Default::default(),
         }
     }
 
@@ -50,8 +147,28 @@ impl LocalNetworking {
             selector: Selector::new(),
             handle: Handle::current(),
             ruleset: Some(ruleset),
+            address_family: None,
+            socket_options: SocketOptions::default(),
+            tracker: Default::default(),
         }
     }
+
+    /// Restrict or reorder the addresses used for DNS resolution and
+    /// outbound connections.
+    ///
+    /// By default, addresses are used in whatever order the system resolver
+    /// returns them.
+    pub fn with_address_family_preference(mut self, preference: AddressFamilyPreference) -> Self {
+        self.address_family = Some(preference);
+        self
+    }
+
+    /// Apply `options` to every socket this networking implementation
+    /// creates.
+    pub fn with_socket_options(mut self, options: SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
 }
 
 impl Drop for LocalNetworking {
@@ -66,9 +183,136 @@ impl Default for LocalNetworking {
     }
 }
 
+/// Keeps track of the sockets a [`LocalNetworking`] has created and the
+/// traffic that has gone through each of them.
+///
+/// Each socket also gets a `socket` span at the `DEBUG` level, which opening,
+/// shutting down and closing the socket and any errors are logged under.
+/// Every send and receive is logged at the `TRACE` level with the number of
+/// bytes and how long the call took, but the clock is only read when those
+/// events are enabled.
+#[derive(Debug, Default)]
+struct SocketTracker {
+    next_id: AtomicU64,
+    sockets: Mutex<HashMap<SocketId, Arc<SocketCounters>>>,
+}
+
+impl SocketTracker {
+    fn register(self: &Arc<Self>, kind: &'static str, addr: SocketAddr) -> TrackedSocket {
+        let raw_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let id = SocketId::from(raw_id);
+        let counters = Arc::new(SocketCounters::default());
+        self.sockets.lock().unwrap().insert(id, counters.clone());
+
+        let span = tracing::debug_span!("socket", id = raw_id, kind, %addr);
+        tracing::debug!(parent: &span, "opened");
+
+        TrackedSocket {
+            id,
+            counters,
+            tracker: Arc::downgrade(self),
+            span,
+        }
+    }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        let mut ids: Vec<_> = self.sockets.lock().unwrap().keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    fn stats(&self, id: SocketId) -> Option<SocketStats> {
+        let sockets = self.sockets.lock().unwrap();
+        let counters = sockets.get(&id)?;
+        Some(SocketStats {
+            bytes_sent: counters.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: counters.bytes_received.load(Ordering::Relaxed),
+            recv_buffered: counters.recv_buffered.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct SocketCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    recv_buffered: AtomicUsize,
+}
+
+/// A socket's entry in a [`SocketTracker`], which is removed when the socket
+/// is dropped.
+#[derive(Debug)]
+struct TrackedSocket {
+    id: SocketId,
+    counters: Arc<SocketCounters>,
+    tracker: Weak<SocketTracker>,
+    span: tracing::Span,
+}
+
+impl TrackedSocket {
+    /// Start timing a send or receive, if its events will be recorded.
+    fn start(&self) -> Option<Instant> {
+        tracing::enabled!(tracing::Level::TRACE).then(Instant::now)
+    }
+
+    fn sent(&self, amt: usize, started: Option<Instant>) {
+        self.counters
+            .bytes_sent
+            .fetch_add(amt as u64, Ordering::Relaxed);
+        if let Some(started) = started {
+            tracing::trace!(
+                parent: &self.span,
+                bytes = amt,
+                elapsed = ?started.elapsed(),
+                "sent",
+            );
+        }
+    }
+
+    fn received(&self, amt: usize, started: Option<Instant>) {
+        self.counters
+            .bytes_received
+            .fetch_add(amt as u64, Ordering::Relaxed);
+        if let Some(started) = started {
+            tracing::trace!(
+                parent: &self.span,
+                bytes = amt,
+                elapsed = ?started.elapsed(),
+                "received",
+            );
+        }
+    }
+
+    fn failed(&self, operation: &'static str, err: NetworkError) {
+        tracing::debug!(parent: &self.span, operation, %err, "socket operation failed");
+    }
+
+    fn set_recv_buffered(&self, len: usize) {
+        self.counters.recv_buffered.store(len, Ordering::Relaxed);
+    }
+}
+
+impl Drop for TrackedSocket {
+    fn drop(&mut self) {
+        tracing::debug!(
+            parent: &self.span,
+            bytes_sent = self.counters.bytes_sent.load(Ordering::Relaxed),
+            bytes_received = self.counters.bytes_received.load(Ordering::Relaxed),
+            "closed",
+        );
+        if let Some(tracker) = self.tracker.upgrade() {
+            tracker.sockets.lock().unwrap().remove(&self.id);
+        }
+    }
+}
+
 #[async_trait::async_trait]
 #[allow(unused_variables)]
 impl VirtualNetworking for LocalNetworking {
+    fn backend_name(&self) -> &'static str {
+        "host"
+    }
+
     async fn listen_tcp(
         &self,
         addr: SocketAddr,
@@ -83,9 +327,16 @@ impl VirtualNetworking for LocalNetworking {
             }
         }
 
-        let listener = std::net::TcpListener::bind(addr)
+        let listener = self
+            .socket_options
+            .bind(addr, socket2::Type::STREAM)
+            .and_then(|sock| {
+                sock.listen(128)?;
+                Ok(std::net::TcpListener::from(sock))
+            })
             .map(|sock| {
                 sock.set_nonblocking(true).ok();
+                let local = sock.local_addr().unwrap_or(addr);
                 Box::new(LocalTcpListener {
                     stream: mio::net::TcpListener::from_std(sock),
                     selector: self.selector.clone(),
@@ -94,6 +345,9 @@ impl VirtualNetworking for LocalNetworking {
                     keep_alive: None,
                     backlog: Default::default(),
                     ruleset: self.ruleset.clone(),
+                    socket_options: self.socket_options,
+                    tracker: self.tracker.clone(),
+                    tracked: self.tracker.register("tcp-listener", local),
                 })
             })
             .map_err(io_err_into_net_error)?;
@@ -113,7 +367,15 @@ impl VirtualNetworking for LocalNetworking {
             }
         }
 
-        let socket = mio::net::UdpSocket::bind(addr).map_err(io_err_into_net_error)?;
+        let socket = self
+            .socket_options
+            .bind(addr, socket2::Type::DGRAM)
+            .and_then(|sock| {
+                sock.set_nonblocking(true)?;
+                Ok(mio::net::UdpSocket::from_std(sock.into()))
+            })
+            .map_err(io_err_into_net_error)?;
+        let local = socket.local_addr().unwrap_or(addr);
 
         #[allow(unused_mut)]
         let mut ret = LocalUdpSocket {
@@ -123,6 +385,7 @@ impl VirtualNetworking for LocalNetworking {
             handler_guard: HandlerGuardState::None,
             backlog: Default::default(),
             ruleset: self.ruleset.clone(),
+            tracked: self.tracker.register("udp", local),
         };
 
         // In windows we can not poll the socket as it is not supported and hence
@@ -151,13 +414,26 @@ impl VirtualNetworking for LocalNetworking {
             }
         }
 
+        if let Some(preference) = self.address_family {
+            if !preference.allows(peer.ip()) {
+                tracing::debug!(%peer, ?preference, "connect_tcp blocked by address family preference");
+                return Err(NetworkError::AddressNotAvailable);
+            }
+        }
+
         let stream = mio::net::TcpStream::connect(peer).map_err(io_err_into_net_error)?;
 
         if let Ok(p) = stream.peer_addr() {
             peer = p;
         }
-        let socket = Box::new(LocalTcpStream::new(self.selector.clone(), stream, peer));
-        Ok(socket)
+        let mut socket = LocalTcpStream::new(
+            self.selector.clone(),
+            stream,
+            peer,
+            self.tracker.register("tcp", peer),
+        );
+        self.socket_options.apply_to_stream(&mut socket)?;
+        Ok(Box::new(socket))
     }
 
     async fn resolve(
@@ -178,7 +454,7 @@ impl VirtualNetworking for LocalNetworking {
         } else {
             format!("{}:{}", host, port.unwrap_or(0))
         };
-        let addrs = self
+        let mut addrs = self
             .handle
             .spawn(tokio::net::lookup_host(host_to_lookup))
             .await
@@ -194,8 +470,20 @@ impl VirtualNetworking for LocalNetworking {
             }
         }
 
+        if let Some(preference) = self.address_family {
+            preference.apply(&mut addrs);
+        }
+
         Ok(addrs)
     }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        self.tracker.open_sockets()
+    }
+
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        self.tracker.stats(id)
+    }
 }
 
 #[derive(Debug)]
@@ -207,6 +495,10 @@ pub struct LocalTcpListener {
     keep_alive: Option<bool>,
     backlog: VecDeque<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)>,
     ruleset: Option<Ruleset>,
+    socket_options: SocketOptions,
+    tracker: Arc<SocketTracker>,
+    #[allow(dead_code)]
+    tracked: TrackedSocket,
 }
 
 impl LocalTcpListener {
@@ -220,7 +512,13 @@ impl LocalTcpListener {
                     }
                 }
 
-                let mut socket = LocalTcpStream::new(self.selector.clone(), stream, addr);
+                let mut socket = LocalTcpStream::new(
+                    self.selector.clone(),
+                    stream,
+                    addr,
+                    self.tracker.register("tcp", addr),
+                );
+                self.socket_options.apply_to_stream(&mut socket).ok();
                 if let Some(no_delay) = self.no_delay {
                     socket.set_nodelay(no_delay).ok();
                 }
@@ -357,14 +655,21 @@ impl VirtualIoSource for LocalTcpListener {
 pub struct LocalTcpStream {
     stream: mio::net::TcpStream,
     addr: SocketAddr,
+    /// The directions the guest has shut down so far.
     shutdown: Option<Shutdown>,
     selector: Arc<Selector>,
     handler_guard: HandlerGuardState,
     buffer: BytesMut,
+    tracked: TrackedSocket,
 }
 
 impl LocalTcpStream {
-    fn new(selector: Arc<Selector>, stream: mio::net::TcpStream, addr: SocketAddr) -> Self {
+    fn new(
+        selector: Arc<Selector>,
+        stream: mio::net::TcpStream,
+        addr: SocketAddr,
+        tracked: TrackedSocket,
+    ) -> Self {
         #[allow(unused_mut)]
         let mut ret = Self {
             stream,
@@ -373,6 +678,7 @@ impl LocalTcpStream {
             selector,
             handler_guard: HandlerGuardState::None,
             buffer: BytesMut::new(),
+            tracked,
         };
 
         // In windows we can not poll the socket as it is not supported and hence
@@ -505,12 +811,32 @@ impl VirtualTcpSocket for LocalTcpStream {
 
     fn shutdown(&mut self, how: Shutdown) -> Result<()> {
         self.stream.shutdown(how).map_err(io_err_into_net_error)?;
-        self.shutdown = Some(how);
+        tracing::debug!(parent: &self.tracked.span, ?how, "shutdown");
+
+        // Shutting down reads and writes separately closes both directions
+        self.shutdown = Some(match self.shutdown {
+            Some(previous) if previous != how => Shutdown::Both,
+            _ => how,
+        });
+        if !self.can_recv() {
+            self.buffer.clear();
+        }
+
         Ok(())
     }
 
     fn is_closed(&self) -> bool {
-        false
+        self.shutdown == Some(Shutdown::Both)
+    }
+}
+
+impl LocalTcpStream {
+    fn can_send(&self) -> bool {
+        !matches!(self.shutdown, Some(Shutdown::Write | Shutdown::Both))
+    }
+
+    fn can_recv(&self) -> bool {
+        !matches!(self.shutdown, Some(Shutdown::Read | Shutdown::Both))
     }
 }
 
@@ -527,6 +853,13 @@ impl VirtualConnectedSocket for LocalTcpStream {
     }
 
     fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        // Not every platform rejects writes after a half-close, so do it
+        // here to behave the same everywhere
+        if !self.can_send() {
+            return Err(NetworkError::BrokenPipe);
+        }
+
+        let started = self.tracked.start();
         let ret = self.stream.write(data).map_err(io_err_into_net_error);
         match &ret {
             Ok(0) | Err(NetworkError::WouldBlock) => {
@@ -534,7 +867,8 @@ impl VirtualConnectedSocket for LocalTcpStream {
                     map.pop(InterestType::Writable);
                 }
             }
-            _ => {}
+            Ok(amt) => self.tracked.sent(*amt, started),
+            Err(err) => self.tracked.failed("send", *err),
         }
         ret
     }
@@ -548,15 +882,28 @@ impl VirtualConnectedSocket for LocalTcpStream {
     }
 
     fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        if !self.can_recv() {
+            return Ok(0);
+        }
+
         let buf: &mut [u8] = unsafe { std::mem::transmute(buf) };
+        let started = self.tracked.start();
         if !self.buffer.is_empty() {
             let amt = buf.len().min(self.buffer.len());
             buf[..amt].copy_from_slice(&self.buffer[..amt]);
             self.buffer.advance(amt);
+            self.tracked.received(amt, started);
+            self.tracked.set_recv_buffered(self.buffer.len());
             return Ok(amt);
         }
 
-        self.stream.read(buf).map_err(io_err_into_net_error)
+        let ret = self.stream.read(buf).map_err(io_err_into_net_error);
+        match ret {
+            Ok(amt) => self.tracked.received(amt, started),
+            Err(NetworkError::WouldBlock) => {}
+            Err(err) => self.tracked.failed("recv", err),
+        }
+        ret
     }
 }
 
@@ -657,6 +1004,7 @@ impl VirtualIoSource for LocalTcpStream {
                 unsafe {
                     buffer.set_len(buffer.len() + amt);
                 }
+                self.tracked.set_recv_buffered(self.buffer.len());
                 Poll::Ready(Ok(amt))
             }
             Err(err) if err.kind() == io::ErrorKind::ConnectionAborted => Poll::Ready(Ok(0)),
@@ -723,6 +1071,7 @@ pub struct LocalUdpSocket {
     handler_guard: HandlerGuardState,
     backlog: VecDeque<(BytesMut, SocketAddr)>,
     ruleset: Option<Ruleset>,
+    tracked: TrackedSocket,
 }
 
 impl LocalUdpSocket {
@@ -830,6 +1179,7 @@ impl VirtualConnectionlessSocket for LocalUdpSocket {
             }
         }
 
+        let started = self.tracked.start();
         let ret = self
             .socket
             .send_to(data, addr)
@@ -840,14 +1190,22 @@ impl VirtualConnectionlessSocket for LocalUdpSocket {
                     map.pop(InterestType::Writable);
                 }
             }
-            _ => {}
+            Ok(amt) => self.tracked.sent(*amt, started),
+            Err(err) => self.tracked.failed("send", *err),
         }
         ret
     }
 
     fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
         let buf: &mut [u8] = unsafe { std::mem::transmute(buf) };
-        self.socket.recv_from(buf).map_err(io_err_into_net_error)
+        let started = self.tracked.start();
+        let ret = self.socket.recv_from(buf).map_err(io_err_into_net_error);
+        match ret {
+            Ok((amt, _)) => self.tracked.received(amt, started),
+            Err(NetworkError::WouldBlock) => {}
+            Err(err) => self.tracked.failed("recv", err),
+        }
+        ret
     }
 }
 
@@ -947,6 +1305,8 @@ impl VirtualIoSource for LocalUdpSocket {
                     buffer.set_len(amt);
                 }
                 self.backlog.push_back((buffer, peer));
+                self.tracked
+                    .set_recv_buffered(self.backlog.iter().map(|a| a.0.len()).sum());
                 Poll::Ready(Ok(amt))
             }
             Err(err) if err.kind() == io::ErrorKind::ConnectionAborted => Poll::Ready(Ok(0)),
@@ -984,3 +1344,166 @@ impl VirtualIoSource for LocalUdpSocket {
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn socket_options_are_applied() {
+        let options = SocketOptions {
+            tcp_nodelay: Some(true),
+            reuse_addr: Some(true),
+            ..Default::default()
+        };
+        let networking = LocalNetworking::new().with_socket_options(options);
+
+        let listener = networking
+            .listen_tcp("127.0.0.1:0".parse().unwrap(), false, false, false)
+            .await
+            .unwrap();
+        let addr = listener.addr_local().unwrap();
+        let stream = networking
+            .connect_tcp("127.0.0.1:0".parse().unwrap(), addr)
+            .await
+            .unwrap();
+
+        assert!(stream.nodelay().unwrap());
+    }
+
+    /// Keep trying a non-blocking operation until it stops blocking.
+    fn retry<T>(mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        for _ in 0..500 {
+            match op() {
+                Err(NetworkError::WouldBlock) => std::thread::sleep(Duration::from_millis(10)),
+                other => return other,
+            }
+        }
+        panic!("timed out");
+    }
+
+    #[tokio::test]
+    async fn half_close_is_passed_to_the_peer() {
+        let networking = LocalNetworking::new();
+        let mut listener = networking
+            .listen_tcp("127.0.0.1:0".parse().unwrap(), false, false, false)
+            .await
+            .unwrap();
+        let addr = listener.addr_local().unwrap();
+        let mut client = networking
+            .connect_tcp("127.0.0.1:0".parse().unwrap(), addr)
+            .await
+            .unwrap();
+        let (mut server, _) = retry(|| listener.try_accept()).unwrap();
+        let mut buf = [MaybeUninit::uninit(); 16];
+
+        retry(|| client.try_send(b"request")).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        assert_eq!(client.try_send(b"more"), Err(NetworkError::BrokenPipe));
+        assert!(!client.is_closed());
+
+        // The server reads the request, then sees EOF, but can still reply
+        let mut request = Vec::new();
+        loop {
+            let read = retry(|| server.try_recv(&mut buf)).unwrap();
+            if read == 0 {
+                break;
+            }
+            request.extend(buf[..read].iter().map(|b| unsafe { b.assume_init() }));
+        }
+        assert_eq!(request, b"request");
+        retry(|| server.try_send(b"response")).unwrap();
+
+        let read = retry(|| client.try_recv(&mut buf)).unwrap();
+        assert!(read > 0);
+
+        client.shutdown(Shutdown::Read).unwrap();
+        assert_eq!(client.try_recv(&mut buf), Ok(0));
+        assert!(client.is_closed());
+    }
+
+    #[tokio::test]
+    async fn sockets_are_tracked_until_they_close() {
+        let networking = LocalNetworking::new();
+        let mut listener = networking
+            .listen_tcp("127.0.0.1:0".parse().unwrap(), false, false, false)
+            .await
+            .unwrap();
+        let addr = listener.addr_local().unwrap();
+        let mut client = networking
+            .connect_tcp("127.0.0.1:0".parse().unwrap(), addr)
+            .await
+            .unwrap();
+        let (mut server, _) = retry(|| listener.try_accept()).unwrap();
+        let sockets = networking.open_sockets();
+        assert_eq!(sockets.len(), 3);
+        let (client_id, server_id) = (sockets[1], sockets[2]);
+
+        retry(|| client.try_send(b"hello")).unwrap();
+        let mut buf = [MaybeUninit::uninit(); 16];
+        let read = retry(|| server.try_recv(&mut buf)).unwrap();
+
+        let client_stats = networking.socket_stats(client_id).unwrap();
+        assert_eq!(client_stats.bytes_sent, 5);
+        assert_eq!(client_stats.bytes_received, 0);
+        let server_stats = networking.socket_stats(server_id).unwrap();
+        assert_eq!(server_stats.bytes_received, read as u64);
+        assert_eq!(server_stats.recv_buffered, 0);
+
+        drop(client);
+        assert_eq!(networking.socket_stats(client_id), None);
+        assert_eq!(networking.open_sockets().len(), 2);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn socket_traffic_is_traced() {
+        let networking = LocalNetworking::new();
+        let mut listener = networking
+            .listen_tcp("127.0.0.1:0".parse().unwrap(), false, false, false)
+            .await
+            .unwrap();
+        let addr = listener.addr_local().unwrap();
+        let mut client = networking
+            .connect_tcp("127.0.0.1:0".parse().unwrap(), addr)
+            .await
+            .unwrap();
+        let (mut server, _) = retry(|| listener.try_accept()).unwrap();
+
+        retry(|| client.try_send(b"hello")).unwrap();
+        let mut buf = [MaybeUninit::uninit(); 16];
+        retry(|| server.try_recv(&mut buf)).unwrap();
+        client.shutdown(Shutdown::Both).unwrap();
+        drop(client);
+
+        assert!(logs_contain(&format!("addr={addr}")));
+        assert!(logs_contain("opened"));
+        assert!(logs_contain("sent bytes=5"));
+        assert!(logs_contain("received bytes="));
+        assert!(logs_contain("shutdown how=Both"));
+        assert!(logs_contain("closed bytes_sent=5 bytes_received=0"));
+    }
+
+    #[test]
+    fn address_family_preference_filters_and_sorts() {
+        let v4 = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let v4_other = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+        let apply = |preference: AddressFamilyPreference| {
+            let mut addrs = vec![v6, v4, v4_other];
+            preference.apply(&mut addrs);
+            addrs
+        };
+
+        assert_eq!(apply(AddressFamilyPreference::V4Only), vec![v4, v4_other]);
+        assert_eq!(apply(AddressFamilyPreference::V6Only), vec![v6]);
+        assert_eq!(
+            apply(AddressFamilyPreference::PreferV4),
+            vec![v4, v4_other, v6]
+        );
+        assert_eq!(
+            apply(AddressFamilyPreference::PreferV6),
+            vec![v6, v4, v4_other]
+        );
+    }
+}
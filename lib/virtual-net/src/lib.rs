@@ -3,6 +3,7 @@
 #[cfg(feature = "remote")]
 pub mod client;
 pub mod composite;
+pub mod drainable;
 #[cfg(feature = "host-net")]
 pub mod host;
 pub mod loopback;
@@ -20,7 +21,9 @@ mod tests;
 #[cfg(feature = "remote")]
 pub use client::{RemoteNetworkingClient, RemoteNetworkingClientDriver};
 pub use composite::CompositeTcpListener;
+pub use drainable::DrainableNetworking;
 pub use loopback::LoopbackNetworking;
+pub use meta::SocketId;
 use pin_project_lite::pin_project;
 #[cfg(feature = "rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
@@ -87,6 +90,14 @@ pub trait VirtualIoSource: fmt::Debug + Send + Sync + 'static {
 #[async_trait::async_trait]
 #[allow(unused_variables)]
 pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
+    /// A short name for this backend (e.g. `"host"` or `"unsupported"`),
+    /// used when diagnosing networking problems.
+    ///
+    /// Wrappers should report the name of the backend they delegate to.
+    fn backend_name(&self) -> &'static str {
+        "custom"
+    }
+
     /// Bridges this local network with a remote network, which is required in
     /// order to make lower level networking calls (such as UDP/TCP)
     async fn bridge(
@@ -219,6 +230,32 @@ pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
     ) -> Result<Vec<IpAddr>> {
         Err(NetworkError::Unsupported)
     }
+
+    /// The sockets created through this networking implementation which are
+    /// still open, if it keeps track of them.
+    fn open_sockets(&self) -> Vec<SocketId> {
+        Vec::new()
+    }
+
+    /// Traffic and buffering statistics for one of the
+    /// [`open_sockets()`](Self::open_sockets), or `None` if the socket
+    /// is closed or this implementation doesn't keep statistics.
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        None
+    }
+}
+
+/// A snapshot of the traffic that has gone through a socket, as returned by
+/// [`VirtualNetworking::socket_stats()`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketStats {
+    /// Bytes the guest has sent through the socket.
+    pub bytes_sent: u64,
+    /// Bytes the guest has read from the socket.
+    pub bytes_received: u64,
+    /// Bytes which have been received from the network but not read by the
+    /// guest yet.
+    pub recv_buffered: usize,
 }
 
 pub type DynVirtualNetworking = Arc<dyn VirtualNetworking>;
@@ -749,7 +786,11 @@ pub trait VirtualUdpSocket:
 pub struct UnsupportedVirtualNetworking {}
 
 #[async_trait::async_trait]
-impl VirtualNetworking for UnsupportedVirtualNetworking {}
+impl VirtualNetworking for UnsupportedVirtualNetworking {
+    fn backend_name(&self) -> &'static str {
+        "unsupported"
+    }
+}
 
 #[derive(Error, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum NetworkError {
@@ -73,6 +73,10 @@ impl Default for LoopbackNetworking {
 #[allow(unused_variables)]
 #[async_trait::async_trait]
 impl VirtualNetworking for LoopbackNetworking {
+    fn backend_name(&self) -> &'static str {
+        "loopback"
+    }
+
     async fn dhcp_acquire(&self) -> crate::Result<Vec<IpAddr>> {
         let mut state: std::sync::MutexGuard<'_, LoopbackNetworkingState> =
             self.state.lock().unwrap();
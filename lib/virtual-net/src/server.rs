@@ -4,7 +4,7 @@ use crate::{
     meta::{MessageRequest, MessageResponse, RequestType, SocketId},
     VirtualNetworking, VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
 };
-use crate::{IpCidr, IpRoute, NetworkError, StreamSecurity, VirtualIcmpSocket};
+use crate::{IpCidr, IpRoute, NetworkError, SocketStats, StreamSecurity, VirtualIcmpSocket};
 use futures_util::stream::FuturesOrdered;
 #[cfg(any(feature = "hyper", feature = "tokio-tungstenite"))]
 use futures_util::stream::{SplitSink, SplitStream};
@@ -191,6 +191,18 @@ impl RemoteNetworkingServer {
 
 #[async_trait::async_trait]
 impl VirtualNetworking for RemoteNetworkingServer {
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        self.inner.open_sockets()
+    }
+
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        self.inner.socket_stats(id)
+    }
+
     async fn bridge(
         &self,
         network: &str,
@@ -294,7 +294,7 @@ mod tests {
         let mut runtime = PluggableRuntime::new(tasks);
         runtime.set_package_loader(
             BuiltinPackageLoader::new()
-                .with_shared_http_client(runtime.http_client().unwrap().clone()),
+                .with_shared_http_client(runtime.routed_http_client().unwrap()),
         );
 
         let pkg = Package::from_manifest(&manifest).unwrap();
@@ -353,7 +353,7 @@ mod tests {
         let mut runtime = PluggableRuntime::new(tasks);
         runtime.set_package_loader(
             BuiltinPackageLoader::new()
-                .with_shared_http_client(runtime.http_client().unwrap().clone()),
+                .with_shared_http_client(runtime.routed_http_client().unwrap()),
         );
 
         let pkg = BinaryPackage::from_dir(temp.path(), &runtime)
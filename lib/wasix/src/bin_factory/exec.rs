@@ -308,6 +308,7 @@ fn call_module(
                     }
                     return;
                 }
+                Ok(WasiError::DeadlineExceeded) => Err(WasiError::DeadlineExceeded.into()),
                 Ok(WasiError::UnknownWasiVersion) => {
                     debug!("failed as wasi version is unknown",);
                     runtime.on_taint(TaintReason::UnknownWasiVersion);
@@ -11,6 +11,9 @@ use wasmer_wasix_types::wasi::Fd as WasiFd;
 pub struct FdList {
     fds: Vec<Option<Fd>>,
     first_free: Option<usize>,
+    /// The number of FDs currently in use.
+    open: usize,
+    max_open: Option<usize>,
 }
 
 pub struct FdListIterator<'a> {
@@ -35,9 +38,37 @@ impl FdList {
         Self {
             fds: vec![],
             first_free: None,
+            open: 0,
+            max_open: None,
         }
     }
 
+    /// The number of FDs currently in use.
+    pub fn len(&self) -> usize {
+        self.open
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.open == 0
+    }
+
+    /// Limit how many FDs may be in use at once.
+    ///
+    /// The limit isn't enforced by the list itself. Callers allocating new
+    /// FDs should check [`FdList::is_full()`] first.
+    pub fn set_max_open(&mut self, max_open: Option<usize>) {
+        self.max_open = max_open;
+    }
+
+    pub fn max_open(&self) -> Option<usize> {
+        self.max_open
+    }
+
+    /// Has the limit set with [`FdList::set_max_open()`] been reached?
+    pub fn is_full(&self) -> bool {
+        self.max_open.is_some_and(|max| self.open >= max)
+    }
+
     pub fn next_free_fd(&self) -> WasiFd {
         match self.first_free {
             Some(i) => i as WasiFd,
@@ -62,6 +93,8 @@ impl FdList {
     }
 
     pub fn insert_first_free(&mut self, fd: Fd) -> WasiFd {
+        self.open += 1;
+
         match self.first_free {
             Some(free) => {
                 debug_assert!(self.fds[free].is_none());
@@ -106,7 +139,9 @@ impl FdList {
             return false;
         }
 
-        self.fds[idx] = Some(fd);
+        if self.fds[idx].replace(fd).is_none() {
+            self.open += 1;
+        }
         true
     }
 
@@ -116,6 +151,7 @@ impl FdList {
         let result = self.fds.get_mut(idx).and_then(|fd| fd.take());
 
         if result.is_some() {
+            self.open -= 1;
             match self.first_free {
                 None => self.first_free = Some(idx),
                 Some(x) if x > idx => self.first_free = Some(idx),
@@ -129,6 +165,7 @@ impl FdList {
     pub fn clear(&mut self) {
         self.fds.clear();
         self.first_free = None;
+        self.open = 0;
     }
 
     pub fn iter(&self) -> FdListIterator {
@@ -452,4 +489,23 @@ mod tests {
 
         assert_fds_match(&l, &[(0, 2), (1, 1)]);
     }
+
+    #[test]
+    fn open_fds_are_counted() {
+        let mut l = FdList::new();
+        l.set_max_open(Some(2));
+
+        l.insert_first_free(useless_fd(0));
+        assert!(l.insert(false, 5, useless_fd(1)));
+        assert_eq!(l.len(), 2);
+        assert!(l.is_full());
+
+        // Replacing an FD doesn't open a new one
+        assert!(l.insert(false, 5, useless_fd(2)));
+        assert_eq!(l.len(), 2);
+
+        l.remove(0);
+        assert_eq!(l.len(), 1);
+        assert!(!l.is_full());
+    }
 }
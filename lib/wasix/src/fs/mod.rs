@@ -1606,6 +1606,11 @@ impl WasiFs {
 
         let mut guard = self.fd_map.write().unwrap();
 
+        let replaces_existing = idx.is_some_and(|idx| guard.get(idx).is_some());
+        if guard.is_full() && !replaces_existing {
+            return Err(Errno::Mfile);
+        }
+
         match idx {
             Some(idx) => {
                 if guard.insert(exclusive, idx, fd) {
@@ -1620,7 +1625,11 @@ impl WasiFs {
 
     pub fn clone_fd(&self, fd: WasiFd) -> Result<WasiFd, Errno> {
         let fd = self.get_fd(fd)?;
-        Ok(self.fd_map.write().unwrap().insert_first_free(Fd {
+        let mut guard = self.fd_map.write().unwrap();
+        if guard.is_full() {
+            return Err(Errno::Mfile);
+        }
+        Ok(guard.insert_first_free(Fd {
             rights: fd.rights,
             rights_inheriting: fd.rights_inheriting,
             flags: fd.flags,
@@ -0,0 +1,154 @@
+use futures::future::BoxFuture;
+
+use super::{DynHttpClient, HttpClient, HttpRequest, HttpResponse};
+
+/// Errors raised by a [`HttpClient`] itself rather than by the server or the
+/// network.
+///
+/// They are returned inside the [`anyhow::Error`], so use
+/// [`anyhow::Error::downcast_ref()`] to check for them.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum HttpError {
+    /// The request was rejected without being sent.
+    #[error("requests to \"{host}\" are not allowed")]
+    Forbidden { host: String },
+    /// The request was rejected because too many requests have already
+    /// been made (see [`QuotaHttpClient`][super::QuotaHttpClient]).
+    #[error("the quota of {limit} requests has been used up")]
+    QuotaExceeded { limit: u64 },
+}
+
+/// A [`HttpClient`] which only lets requests through to an allowlist of
+/// hosts, rejecting everything else with [`HttpError::Forbidden`] before it
+/// reaches the network.
+///
+/// Hosts are either matched exactly (case-insensitively), or with a pattern
+/// like `*.example.com`, which matches every subdomain of `example.com` but
+/// not `example.com` itself. Install it with
+/// [`PluggableRuntime::set_http_client()`][set].
+///
+/// [set]: crate::runtime::PluggableRuntime::set_http_client
+#[derive(Debug, Clone)]
+pub struct AllowlistHttpClient {
+    inner: DynHttpClient,
+    allowed_hosts: Vec<String>,
+}
+
+impl AllowlistHttpClient {
+    pub fn new(inner: DynHttpClient) -> Self {
+        AllowlistHttpClient {
+            inner,
+            allowed_hosts: Vec::new(),
+        }
+    }
+
+    /// Allow requests to hosts matching `pattern`.
+    pub fn with_allowed_host(mut self, pattern: impl Into<String>) -> Self {
+        self.allow_host(pattern);
+        self
+    }
+
+    /// Allow requests to hosts matching `pattern`.
+    pub fn allow_host(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.allowed_hosts.push(pattern.into());
+        self
+    }
+
+    pub fn allowed_hosts(&self) -> &[String] {
+        &self.allowed_hosts
+    }
+
+    pub fn inner(&self) -> &DynHttpClient {
+        &self.inner
+    }
+
+    pub fn is_allowed(&self, host: &str) -> bool {
+        self.allowed_hosts
+            .iter()
+            .any(|pattern| crate::runtime::matches_host(pattern, host))
+    }
+}
+
+impl HttpClient for AllowlistHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        let host = request.url.host_str().unwrap_or_default();
+        if !self.is_allowed(host) {
+            let error = HttpError::Forbidden {
+                host: host.to_string(),
+            };
+            return Box::pin(async move { Err(error.into()) });
+        }
+
+        self.inner.request(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::{HeaderMap, Method, StatusCode};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct OkClient;
+
+    impl HttpClient for OkClient {
+        fn request(
+            &self,
+            _request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            Box::pin(async {
+                Ok(HttpResponse {
+                    body: None,
+                    redirected: false,
+                    version: None,
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                })
+            })
+        }
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            url: url.parse().unwrap(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+            options: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_allowed_hosts_are_requested() {
+        let client = AllowlistHttpClient::new(Arc::new(OkClient))
+            .with_allowed_host("registry.wasmer.io")
+            .with_allowed_host("*.example.com");
+
+        for url in [
+            "https://registry.wasmer.io/graphql",
+            "https://REGISTRY.wasmer.io/",
+            "http://api.example.com/v1",
+            "http://a.b.example.com/",
+        ] {
+            assert!(client.request(get(url)).await.is_ok(), "{url}");
+        }
+
+        for (url, host) in [
+            ("https://example.com/", "example.com"),
+            ("https://evil-example.com/", "evil-example.com"),
+            ("https://wasmer.io/", "wasmer.io"),
+        ] {
+            let err = client.request(get(url)).await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<HttpError>(),
+                Some(&HttpError::Forbidden {
+                    host: host.to_string()
+                })
+            );
+        }
+    }
+}
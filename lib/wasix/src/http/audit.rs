@@ -0,0 +1,199 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use http::{Method, StatusCode};
+use url::Url;
+
+use super::{DynHttpClient, HttpClient, HttpRequest, HttpResponse};
+
+/// A record of a single request made through an [`AuditingHttpClient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub method: Method,
+    pub url: Url,
+    /// The response's status code, or `None` if the request failed.
+    pub status: Option<StatusCode>,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+    /// The size of the request body.
+    pub request_bytes: usize,
+    /// The size of the response body.
+    pub response_bytes: usize,
+    /// How long it took to get the response.
+    pub duration: Duration,
+    /// The request body, if [`AuditingHttpClient::with_request_bodies()`] is
+    /// enabled.
+    pub request_body: Option<Vec<u8>>,
+}
+
+/// A destination for [`AuditRecord`]s (e.g. a file, a database, or a log
+/// pipeline).
+pub trait AuditSink: fmt::Debug + Send + Sync {
+    /// Called once for every request, after it completes or fails.
+    fn record(&self, record: AuditRecord);
+}
+
+/// A [`HttpClient`] which reports every request passing through it to an
+/// [`AuditSink`], so there is a durable record of all the HTTP requests a
+/// guest made.
+///
+/// Request bodies may contain sensitive data, so they aren't recorded unless
+/// [`AuditingHttpClient::with_request_bodies()`] is used. Headers are never
+/// recorded. Install it with
+/// [`PluggableRuntime::set_http_client()`][set].
+///
+/// [set]: crate::runtime::PluggableRuntime::set_http_client
+#[derive(Debug, Clone)]
+pub struct AuditingHttpClient {
+    inner: DynHttpClient,
+    sink: Arc<dyn AuditSink>,
+    record_request_bodies: bool,
+}
+
+impl AuditingHttpClient {
+    pub fn new(inner: DynHttpClient, sink: Arc<dyn AuditSink>) -> Self {
+        AuditingHttpClient {
+            inner,
+            sink,
+            record_request_bodies: false,
+        }
+    }
+
+    /// Include request bodies in the audit records.
+    pub fn with_request_bodies(mut self, enabled: bool) -> Self {
+        self.record_request_bodies = enabled;
+        self
+    }
+
+    pub fn inner(&self) -> &DynHttpClient {
+        &self.inner
+    }
+
+    pub fn sink(&self) -> &Arc<dyn AuditSink> {
+        &self.sink
+    }
+}
+
+impl HttpClient for AuditingHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        let mut record = AuditRecord {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            status: None,
+            error: None,
+            request_bytes: request.body.as_ref().map_or(0, Vec::len),
+            response_bytes: 0,
+            duration: Duration::ZERO,
+            request_body: None,
+        };
+        if self.record_request_bodies {
+            record.request_body = request.body.clone();
+        }
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = self.inner.request(request).await;
+            record.duration = start.elapsed();
+
+            match &result {
+                Ok(response) => {
+                    record.status = Some(response.status);
+                    record.response_bytes = response.body.as_ref().map_or(0, Vec::len);
+                }
+                Err(e) => record.error = Some(format!("{e:#}")),
+            }
+            self.sink.record(record);
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use http::HeaderMap;
+
+    use super::*;
+
+    /// Responds to `/ok` and fails everything else.
+    #[derive(Debug)]
+    struct EchoClient;
+
+    impl HttpClient for EchoClient {
+        fn request(
+            &self,
+            request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            Box::pin(async move {
+                if request.url.path() != "/ok" {
+                    anyhow::bail!("connection refused");
+                }
+                Ok(HttpResponse {
+                    body: Some(b"hello".to_vec()),
+                    redirected: false,
+                    version: None,
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                })
+            })
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn record(&self, record: AuditRecord) {
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    fn post(url: &str) -> HttpRequest {
+        HttpRequest {
+            url: url.parse().unwrap(),
+            method: Method::POST,
+            headers: HeaderMap::new(),
+            body: Some(b"secret".to_vec()),
+            options: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn every_request_is_audited() {
+        let sink = Arc::new(MemorySink::default());
+        let client = AuditingHttpClient::new(Arc::new(EchoClient), sink.clone());
+
+        client
+            .request(post("https://example.com/ok"))
+            .await
+            .unwrap();
+        client
+            .request(post("https://example.com/fail"))
+            .await
+            .unwrap_err();
+
+        let records = sink.0.lock().unwrap().clone();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].method, Method::POST);
+        assert_eq!(records[0].status, Some(StatusCode::OK));
+        assert_eq!(records[0].request_bytes, 6);
+        assert_eq!(records[0].response_bytes, 5);
+        assert_eq!(records[0].request_body, None);
+        assert_eq!(records[1].status, None);
+        assert_eq!(records[1].error.as_deref(), Some("connection refused"));
+
+        let client = client.with_request_bodies(true);
+        client
+            .request(post("https://example.com/ok"))
+            .await
+            .unwrap();
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records[2].request_body.as_deref(), Some(&b"secret"[..]));
+    }
+}
@@ -1,9 +1,11 @@
 use std::{collections::BTreeSet, ops::Deref, sync::Arc};
 
 use futures::future::BoxFuture;
-use http::{HeaderMap, Method, StatusCode};
+use http::{HeaderMap, Method, StatusCode, Version};
 use url::Url;
 
+use super::sniff::DebugBody;
+
 /// Defines http client permissions.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct HttpClientCapabilityV1 {
@@ -98,7 +100,7 @@ impl std::fmt::Debug for HttpRequest {
             .field("url", &format_args!("{}", url))
             .field("method", method)
             .field("headers", headers)
-            .field("body", &body.as_deref().map(String::from_utf8_lossy))
+            .field("body", &body.as_deref().map(DebugBody))
             .field("options", &options)
             .finish()
     }
@@ -144,6 +146,9 @@ pub struct HttpResponse {
     pub redirected: bool,
     pub status: StatusCode,
     pub headers: HeaderMap,
+    /// The HTTP version the response was received over, if the client
+    /// knows it.
+    pub version: Option<Version>,
 }
 
 impl HttpResponse {
@@ -159,14 +164,16 @@ impl std::fmt::Debug for HttpResponse {
             redirected,
             status,
             headers,
+            version,
         } = self;
 
         f.debug_struct("HttpResponse")
             .field("ok", &self.is_ok())
             .field("redirected", &redirected)
             .field("status", &status)
+            .field("version", &version)
             .field("headers", &headers)
-            .field("body", &body.as_deref().map(String::from_utf8_lossy))
+            .field("body", &body.as_deref().map(DebugBody))
             .finish()
     }
 }
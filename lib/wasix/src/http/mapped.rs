@@ -0,0 +1,367 @@
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use url::Url;
+use virtual_fs::{AsyncReadExt, AsyncSeekExt, FileSystem, FsError};
+
+use super::{DynHttpClient, HttpClient, HttpRequest, HttpResponse};
+
+/// A [`HttpClient`] which serves files from a [`FileSystem`] for URLs under
+/// the prefixes it has been given, passing every other request to another
+/// client.
+///
+/// Only `GET` and `HEAD` requests are supported. `Range` requests for a
+/// single range of bytes get a `206 Partial Content` response, and ranges
+/// which are malformed or can't be satisfied get a
+/// `416 Range Not Satisfiable`.
+#[derive(Debug, Clone)]
+pub struct MappedHttpClient {
+    inner: DynHttpClient,
+    mappings: Vec<Mapping>,
+}
+
+#[derive(Debug, Clone)]
+struct Mapping {
+    prefix: Url,
+    fs: Arc<dyn FileSystem + Send + Sync>,
+    dir: PathBuf,
+}
+
+impl MappedHttpClient {
+    pub fn new(inner: DynHttpClient) -> Self {
+        MappedHttpClient {
+            inner,
+            mappings: Vec::new(),
+        }
+    }
+
+    /// Serve URLs starting with `prefix` from the directory `dir` in `fs`.
+    ///
+    /// If several prefixes match a URL, the one which was added first wins.
+    pub fn with_mapping(
+        mut self,
+        mut prefix: Url,
+        fs: Arc<dyn FileSystem + Send + Sync>,
+        dir: impl Into<PathBuf>,
+    ) -> Self {
+        if !prefix.path().ends_with('/') {
+            let path = format!("{}/", prefix.path());
+            prefix.set_path(&path);
+        }
+
+        self.mappings.push(Mapping {
+            prefix,
+            fs,
+            dir: dir.into(),
+        });
+        self
+    }
+
+    pub fn inner(&self) -> &DynHttpClient {
+        &self.inner
+    }
+
+    /// Find the file a URL maps to, if any.
+    ///
+    /// `None` inside the `Some` means the URL is mapped but doesn't name a
+    /// file which may be served (e.g. it tries to escape the directory).
+    fn resolve(&self, url: &Url) -> Option<(&Mapping, Option<PathBuf>)> {
+        let mapping = self.mappings.iter().find(|mapping| {
+            let prefix = &mapping.prefix;
+            url.scheme() == prefix.scheme()
+                && url.host_str() == prefix.host_str()
+                && url.port_or_known_default() == prefix.port_or_known_default()
+                && url.path().starts_with(prefix.path())
+        })?;
+
+        let relative = &url.path()[mapping.prefix.path().len()..];
+        let mut path = mapping.dir.clone();
+        for segment in relative.split('/') {
+            match percent_decode(segment).as_deref() {
+                Some("" | ".") => {}
+                Some(segment) if segment != ".." && !segment.contains(['/', '\\', '\0']) => {
+                    path.push(segment)
+                }
+                _ => return Some((mapping, None)),
+            }
+        }
+
+        Some((mapping, Some(path)))
+    }
+}
+
+impl HttpClient for MappedHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        let Some((mapping, path)) = self.resolve(&request.url) else {
+            return self.inner.request(request);
+        };
+
+        Box::pin(async move {
+            if request.method != Method::GET && request.method != Method::HEAD {
+                let mut response = status_response(StatusCode::METHOD_NOT_ALLOWED);
+                response
+                    .headers
+                    .insert(header::ALLOW, HeaderValue::from_static("GET, HEAD"));
+                return Ok(response);
+            }
+            let Some(path) = path else {
+                return Ok(status_response(StatusCode::NOT_FOUND));
+            };
+
+            serve_file(mapping.fs.as_ref(), &path, &request).await
+        })
+    }
+}
+
+async fn serve_file(
+    fs: &(dyn FileSystem + Send + Sync),
+    path: &Path,
+    request: &HttpRequest,
+) -> Result<HttpResponse, anyhow::Error> {
+    match fs.metadata(path) {
+        Ok(metadata) if metadata.is_file() => {}
+        Ok(_) | Err(FsError::EntryNotFound | FsError::NotAFile | FsError::BaseNotDirectory) => {
+            return Ok(status_response(StatusCode::NOT_FOUND));
+        }
+        Err(FsError::PermissionDenied) => return Ok(status_response(StatusCode::FORBIDDEN)),
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut file = fs.new_open_options().read(true).open(path)?;
+    let len = file.size();
+
+    let range = match request.headers.get(header::RANGE) {
+        Some(value) => match parse_range(value.as_bytes(), len) {
+            Some(range) => range,
+            None => {
+                let mut response = status_response(StatusCode::RANGE_NOT_SATISFIABLE);
+                response.headers.insert(
+                    header::CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes */{len}"))?,
+                );
+                return Ok(response);
+            }
+        },
+        None => None,
+    };
+
+    let mut response = status_response(StatusCode::OK);
+    let (start, end) = match range {
+        Some((start, end)) => {
+            response.status = StatusCode::PARTIAL_CONTENT;
+            response.headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {start}-{end}/{len}"))?,
+            );
+            (start, end + 1)
+        }
+        None => (0, len),
+    };
+    response
+        .headers
+        .insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(end - start));
+
+    if request.method == Method::GET {
+        let mut body = vec![0; usize::try_from(end - start)?];
+        file.seek(SeekFrom::Start(start)).await?;
+        file.read_exact(&mut body).await?;
+        response.body = Some(body);
+    }
+
+    Ok(response)
+}
+
+/// Parse a `Range` header for a file which is `len` bytes long, returning
+/// the inclusive range of bytes to serve.
+///
+/// `Some(None)` means the header should be ignored and the whole file
+/// served (e.g. because it uses a unit other than bytes or asks for several
+/// ranges), while `None` means it is malformed or can't be satisfied.
+fn parse_range(header: &[u8], len: u64) -> Option<Option<(u64, u64)>> {
+    let header = std::str::from_utf8(header).ok()?.trim();
+    let Some(spec) = header.strip_prefix("bytes=") else {
+        return Some(None);
+    };
+    if spec.contains(',') {
+        return Some(None);
+    }
+
+    let (start, end) = spec.trim().split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 || len == 0 {
+                return None;
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            (start.parse().ok()?, end.min(len.checked_sub(1)?))
+        }
+    };
+
+    if start > end || start >= len {
+        return None;
+    }
+
+    Some(Some((start, end)))
+}
+
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2)?;
+            let hex = std::str::from_utf8(hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+fn status_response(status: StatusCode) -> HttpResponse {
+    HttpResponse {
+        body: None,
+        redirected: false,
+        status,
+        headers: HeaderMap::new(),
+        version: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use virtual_fs::AsyncWriteExt;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct NotFoundClient;
+
+    impl HttpClient for NotFoundClient {
+        fn request(
+            &self,
+            _request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            Box::pin(async { Ok(status_response(StatusCode::IM_A_TEAPOT)) })
+        }
+    }
+
+    async fn client() -> MappedHttpClient {
+        let fs = virtual_fs::mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/www")).unwrap();
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .open("/www/hello world.txt")
+            .unwrap();
+        file.write_all(b"Hello, World!").await.unwrap();
+
+        MappedHttpClient::new(Arc::new(NotFoundClient)).with_mapping(
+            "https://files.example.com/static".parse().unwrap(),
+            Arc::new(fs),
+            "/www",
+        )
+    }
+
+    fn get(url: &str, range: Option<&str>) -> HttpRequest {
+        let mut headers = HeaderMap::new();
+        if let Some(range) = range {
+            headers.insert(header::RANGE, range.parse().unwrap());
+        }
+
+        HttpRequest {
+            url: url.parse().unwrap(),
+            method: Method::GET,
+            headers,
+            body: None,
+            options: Default::default(),
+        }
+    }
+
+    const FILE: &str = "https://files.example.com/static/hello%20world.txt";
+
+    #[tokio::test]
+    async fn files_are_served() {
+        let client = client().await;
+
+        let response = client.request(get(FILE, None)).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body.as_deref(), Some(&b"Hello, World!"[..]));
+        assert_eq!(response.headers[header::ACCEPT_RANGES], "bytes");
+
+        let urls = [
+            "https://files.example.com/static/missing.txt",
+            "https://files.example.com/static/..%2Fwww%2Fhello%20world.txt",
+        ];
+        for url in urls {
+            let response = client.request(get(url, None)).await.unwrap();
+            assert_eq!(response.status, StatusCode::NOT_FOUND, "{url}");
+        }
+
+        // Anything else goes to the inner client
+        let response = client
+            .request(get("https://example.com/static/hello%20world.txt", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::IM_A_TEAPOT);
+    }
+
+    #[tokio::test]
+    async fn range_requests() {
+        let client = client().await;
+
+        let ranges = [
+            ("bytes=0-4", "Hello", "bytes 0-4/13"),
+            ("bytes=7-", "World!", "bytes 7-12/13"),
+            ("bytes=-6", "World!", "bytes 7-12/13"),
+            ("bytes=7-100", "World!", "bytes 7-12/13"),
+        ];
+        for (range, body, content_range) in ranges {
+            let response = client.request(get(FILE, Some(range))).await.unwrap();
+            assert_eq!(response.status, StatusCode::PARTIAL_CONTENT, "{range}");
+            assert_eq!(response.body.as_deref(), Some(body.as_bytes()), "{range}");
+            assert_eq!(response.headers[header::CONTENT_RANGE], content_range);
+            assert_eq!(
+                response.headers[header::CONTENT_LENGTH],
+                body.len().to_string()
+            );
+        }
+
+        for range in ["bytes=5-2", "bytes=13-", "bytes=x-y", "bytes=-0", "bytes=4"] {
+            let response = client.request(get(FILE, Some(range))).await.unwrap();
+            assert_eq!(
+                response.status,
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "{range}"
+            );
+            assert_eq!(response.headers[header::CONTENT_RANGE], "bytes */13");
+        }
+
+        // Multiple ranges aren't supported, so the whole file is served
+        let response = client
+            .request(get(FILE, Some("bytes=0-1,3-4")))
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body.as_deref(), Some(&b"Hello, World!"[..]));
+    }
+}
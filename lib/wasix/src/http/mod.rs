@@ -1,4 +1,14 @@
+mod allowlist;
+mod audit;
 mod client;
+mod mapped;
+mod quota;
+mod retry;
+mod routed;
+mod signing;
+mod singleflight;
+mod sniff;
+mod trace_context;
 
 #[cfg(feature = "host-reqwest")]
 pub mod reqwest;
@@ -9,7 +19,18 @@ mod web_http_client;
 #[cfg(feature = "js")]
 pub use self::web_http_client::WebHttpClient;
 
+pub use self::allowlist::{AllowlistHttpClient, HttpError};
+pub use self::audit::{AuditRecord, AuditSink, AuditingHttpClient};
 pub use self::client::*;
+pub use self::mapped::MappedHttpClient;
+pub use self::quota::QuotaHttpClient;
+pub use self::retry::{Jitter, RetryPolicy};
+pub(crate) use self::routed::find_route;
+pub use self::routed::RoutedHttpClient;
+pub use self::signing::{RequestSigner, SigV4Signer, SigningHttpClient};
+pub use self::singleflight::SingleflightHttpClient;
+pub use self::sniff::is_probably_text;
+pub use self::trace_context::{TraceContext, TraceContextHttpClient};
 
 pub(crate) const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "-", env!("CARGO_PKG_VERSION"));
 
@@ -0,0 +1,182 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+
+use super::{DynHttpClient, HttpClient, HttpError, HttpRequest, HttpResponse};
+
+/// A [`HttpClient`] which only lets a limited number of requests through,
+/// rejecting the rest with [`HttpError::QuotaExceeded`] before they reach the
+/// network.
+///
+/// Without a window the limit is a hard cap for the client's lifetime.
+/// With one, the count starts again once the window is over. Clones share
+/// the same count, so give each instance its own quota by installing a
+/// [`fork()`](Self::fork) with
+/// [`PluggableRuntime::set_http_client()`][set].
+///
+/// [set]: crate::runtime::PluggableRuntime::set_http_client
+#[derive(Debug, Clone)]
+pub struct QuotaHttpClient {
+    inner: DynHttpClient,
+    max_requests: u64,
+    window: Option<Duration>,
+    usage: Arc<Mutex<Usage>>,
+}
+
+#[derive(Debug)]
+struct Usage {
+    requests: u64,
+    window_start: Instant,
+}
+
+impl QuotaHttpClient {
+    pub fn new(inner: DynHttpClient, max_requests: u64) -> Self {
+        QuotaHttpClient {
+            inner,
+            max_requests,
+            window: None,
+            usage: Arc::new(Mutex::new(Usage {
+                requests: 0,
+                window_start: Instant::now(),
+            })),
+        }
+    }
+
+    /// Reset the count every `window`, turning the quota into a rate limit.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// A client with the same limits as this one but its own count.
+    pub fn fork(&self) -> Self {
+        let mut client = QuotaHttpClient::new(self.inner.clone(), self.max_requests);
+        client.window = self.window;
+        client
+    }
+
+    pub fn inner(&self) -> &DynHttpClient {
+        &self.inner
+    }
+
+    pub fn max_requests(&self) -> u64 {
+        self.max_requests
+    }
+
+    pub fn window(&self) -> Option<Duration> {
+        self.window
+    }
+
+    /// How many more requests can be made in the current window.
+    pub fn remaining(&self) -> u64 {
+        let mut usage = self.usage.lock().unwrap();
+        self.roll_window(&mut usage);
+        self.max_requests.saturating_sub(usage.requests)
+    }
+
+    fn roll_window(&self, usage: &mut Usage) {
+        if let Some(window) = self.window {
+            if usage.window_start.elapsed() >= window {
+                usage.requests = 0;
+                usage.window_start = Instant::now();
+            }
+        }
+    }
+
+    fn try_acquire(&self) -> Result<(), HttpError> {
+        let mut usage = self.usage.lock().unwrap();
+        self.roll_window(&mut usage);
+
+        if usage.requests >= self.max_requests {
+            return Err(HttpError::QuotaExceeded {
+                limit: self.max_requests,
+            });
+        }
+
+        usage.requests += 1;
+        Ok(())
+    }
+}
+
+impl HttpClient for QuotaHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        if let Err(error) = self.try_acquire() {
+            tracing::debug!(url = %request.url, %error, "HTTP request rejected");
+            return Box::pin(async move { Err(error.into()) });
+        }
+
+        self.inner.request(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{HeaderMap, Method, StatusCode};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct OkClient;
+
+    impl HttpClient for OkClient {
+        fn request(
+            &self,
+            _request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            Box::pin(async {
+                Ok(HttpResponse {
+                    body: None,
+                    redirected: false,
+                    version: None,
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                })
+            })
+        }
+    }
+
+    fn get() -> HttpRequest {
+        HttpRequest {
+            url: "https://example.com/".parse().unwrap(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+            options: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_are_rejected_once_the_quota_is_used_up() {
+        let client = QuotaHttpClient::new(Arc::new(OkClient), 2);
+        let forked = client.fork();
+
+        client.request(get()).await.unwrap();
+        client.clone().request(get()).await.unwrap();
+        let err = client.request(get()).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<HttpError>(),
+            Some(&HttpError::QuotaExceeded { limit: 2 })
+        );
+        assert_eq!(client.remaining(), 0);
+
+        // A fork has its own count
+        assert_eq!(forked.remaining(), 2);
+        forked.request(get()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_count_resets_every_window() {
+        let client =
+            QuotaHttpClient::new(Arc::new(OkClient), 1).with_window(Duration::from_millis(50));
+
+        client.request(get()).await.unwrap();
+        assert!(client.request(get()).await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(client.remaining(), 1);
+        client.request(get()).await.unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use anyhow::Context;
@@ -5,13 +6,76 @@ use futures::{future::BoxFuture, TryStreamExt};
 use std::convert::TryFrom;
 use tokio::runtime::Handle;
 
-use super::{HttpRequest, HttpResponse};
+use super::{HttpRequest, HttpResponse, USER_AGENT};
 
 #[derive(Clone, Debug)]
 pub struct ReqwestHttpClient {
     handle: Handle,
     connect_timeout: Duration,
     response_body_chunk_timeout: Option<std::time::Duration>,
+    decompress: bool,
+    user_agent: String,
+    max_request_bytes: Option<u64>,
+    max_response_bytes: Option<u64>,
+    pool_max_idle_per_host: usize,
+    pool_idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    http_version_pref: HttpVersionPref,
+    #[cfg(not(feature = "js"))]
+    client_identity: Option<reqwest::Identity>,
+    #[cfg(not(feature = "js"))]
+    root_certificates: Vec<reqwest::Certificate>,
+    #[cfg(feature = "host-reqwest-cookies")]
+    cookie_jar: Option<Arc<reqwest::cookie::Jar>>,
+    /// The underlying client, built on first use and shared between clones
+    /// so connections can be reused across requests.
+    ///
+    /// Every setter that changes how the client is built resets this.
+    client: Arc<OnceLock<reqwest::Client>>,
+}
+
+/// Which HTTP versions a [`ReqwestHttpClient`] may use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HttpVersionPref {
+    /// Negotiate the version with the server.
+    #[default]
+    Auto,
+    /// Only use HTTP/1.x.
+    Http1Only,
+    /// Use HTTP/2 without negotiating it first ("prior knowledge"), for
+    /// servers which only speak HTTP/2.
+    ///
+    /// Requires the `host-reqwest-http2` feature.
+    Http2PriorKnowledge,
+    /// Use HTTP/3 (QUIC).
+    ///
+    /// Requires the `host-reqwest-http3` feature, which in turn requires
+    /// building with `RUSTFLAGS="--cfg reqwest_unstable"`.
+    Http3,
+}
+
+/// The error returned when a request or response body exceeds the limits
+/// configured on a [`ReqwestHttpClient`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("the {direction} body exceeds the limit of {limit} bytes")]
+pub struct BodyTooLarge {
+    pub direction: BodyDirection,
+    pub limit: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyDirection {
+    Request,
+    Response,
+}
+
+impl std::fmt::Display for BodyDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyDirection::Request => write!(f, "request"),
+            BodyDirection::Response => write!(f, "response"),
+        }
+    }
 }
 
 impl Default for ReqwestHttpClient {
@@ -20,15 +84,38 @@ impl Default for ReqwestHttpClient {
             handle: Handle::current(),
             connect_timeout: Self::DEFAULT_CONNECT_TIMEOUT,
             response_body_chunk_timeout: None,
+            decompress: false,
+            user_agent: USER_AGENT.to_string(),
+            max_request_bytes: Some(Self::DEFAULT_MAX_BODY_BYTES),
+            max_response_bytes: Some(Self::DEFAULT_MAX_BODY_BYTES),
+            pool_max_idle_per_host: Self::DEFAULT_POOL_MAX_IDLE_PER_HOST,
+            pool_idle_timeout: Self::DEFAULT_POOL_IDLE_TIMEOUT,
+            tcp_keepalive: None,
+            http_version_pref: HttpVersionPref::default(),
+            #[cfg(not(feature = "js"))]
+            client_identity: None,
+            #[cfg(not(feature = "js"))]
+            root_certificates: Vec::new(),
+            #[cfg(feature = "host-reqwest-cookies")]
+            cookie_jar: None,
+            client: Arc::default(),
         }
     }
 }
 
 impl ReqwestHttpClient {
     const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    /// Large enough for any reasonable package, while still stopping a
+    /// misbehaving server from exhausting the host's memory.
+    const DEFAULT_MAX_BODY_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+    /// The same as reqwest's default.
+    const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = usize::MAX;
+    /// The same as reqwest's default.
+    const DEFAULT_POOL_IDLE_TIMEOUT: Option<Duration> = Some(Duration::from_secs(90));
 
     pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
         self.connect_timeout = timeout;
+        self.client = Arc::default();
         self
     }
 
@@ -37,30 +124,244 @@ impl ReqwestHttpClient {
         self
     }
 
-    #[tracing::instrument(skip_all, fields(method=?request.method, url=%request.url))]
-    async fn request(&self, request: HttpRequest) -> Result<HttpResponse, anyhow::Error> {
-        let method = reqwest::Method::try_from(request.method.as_str())
-            .with_context(|| format!("Invalid http method {}", request.method))?;
+    /// Toggle automatic `gzip`/`brotli`/`deflate` decompression of response
+    /// bodies (disabled by default).
+    ///
+    /// When disabled, the body is passed through untouched and the original
+    /// `Content-Encoding` header is preserved in the [`HttpResponse`].
+    ///
+    /// Enabling it requires the `host-reqwest-decompress` feature.
+    pub fn with_decompress(mut self, decompress: bool) -> Self {
+        self.decompress = decompress;
+        self.client = Arc::default();
+        self
+    }
+
+    /// Set the `User-Agent` sent with requests that don't specify their own.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self.client = Arc::default();
+        self
+    }
+
+    /// Limit how large a request body may be (`None` means unlimited).
+    pub fn with_max_request_bytes(mut self, limit: Option<u64>) -> Self {
+        self.max_request_bytes = limit;
+        self
+    }
+
+    /// Limit how large a response body may be (`None` means unlimited).
+    ///
+    /// The limit is enforced while the body is being downloaded, so an
+    /// oversized response is never fully buffered.
+    pub fn with_max_response_bytes(mut self, limit: Option<u64>) -> Self {
+        self.max_response_bytes = limit;
+        self
+    }
+
+    /// The maximum number of idle connections kept open to each host
+    /// (unlimited by default).
+    ///
+    /// Setting this to `0` disables connection reuse.
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = max;
+        self.client = Arc::default();
+        self
+    }
+
+    /// How long an idle connection is kept open before being closed
+    /// (90 seconds by default, `None` means idle connections never expire).
+    pub fn with_pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = timeout;
+        self.client = Arc::default();
+        self
+    }
+
+    /// Enable `SO_KEEPALIVE` on connections, sending TCP keep-alive probes
+    /// at the given interval (disabled by default).
+    pub fn with_tcp_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.tcp_keepalive = interval;
+        self.client = Arc::default();
+        self
+    }
+
+    /// Choose which HTTP versions may be used (negotiated with the server by
+    /// default).
+    pub fn with_http_version_pref(mut self, pref: HttpVersionPref) -> Self {
+        self.http_version_pref = pref;
+        self.client = Arc::default();
+        self
+    }
+
+    /// Present a TLS client certificate when the server asks for one (e.g.
+    /// for mutual TLS).
+    ///
+    /// Builds using `native-tls` can load a PKCS#12 archive with
+    /// [`reqwest::Identity::from_pkcs12_der()`] and pass it in here.
+    #[cfg(not(feature = "js"))]
+    pub fn with_client_identity(mut self, identity: reqwest::Identity) -> Self {
+        self.client_identity = Some(identity);
+        self.client = Arc::default();
+        self
+    }
+
+    /// Load a TLS client certificate from a PEM file containing both the
+    /// private key and the certificate chain.
+    ///
+    /// This is only available with `rustls`, which every target except
+    /// `riscv64` and `loongarch64` uses.
+    #[cfg(not(any(feature = "js", target_arch = "riscv64", target_arch = "loongarch64")))]
+    pub fn with_client_identity_pem(self, pem: &[u8]) -> Result<Self, reqwest::Error> {
+        let identity = reqwest::Identity::from_pem(pem)?;
+        Ok(self.with_client_identity(identity))
+    }
+
+    /// Trust an additional root certificate, on top of the system's, when
+    /// verifying servers (e.g. for self-signed internal services).
+    #[cfg(not(feature = "js"))]
+    pub fn with_root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self.client = Arc::default();
+        self
+    }
+
+    /// Trust an additional PEM-encoded root certificate.
+    #[cfg(not(feature = "js"))]
+    pub fn with_root_certificate_pem(self, pem: &[u8]) -> Result<Self, reqwest::Error> {
+        let certificate = reqwest::Certificate::from_pem(pem)?;
+        Ok(self.with_root_certificate(certificate))
+    }
+
+    /// Remember cookies set by servers and send them back with later
+    /// requests to the same domain, using a fresh cookie jar.
+    ///
+    /// The jar is shared between clones of this client. Requires the
+    /// `host-reqwest-cookies` feature.
+    #[cfg(feature = "host-reqwest-cookies")]
+    pub fn with_cookies(self) -> Self {
+        self.with_cookie_jar(Arc::new(reqwest::cookie::Jar::default()))
+    }
+
+    /// Store cookies in `jar`, e.g. to share a session between several
+    /// clients or to seed it with cookies up front.
+    #[cfg(feature = "host-reqwest-cookies")]
+    pub fn with_cookie_jar(mut self, jar: Arc<reqwest::cookie::Jar>) -> Self {
+        self.cookie_jar = Some(jar);
+        self.client = Arc::default();
+        self
+    }
+
+    /// The cookie jar, if cookies are enabled.
+    ///
+    /// Use [`reqwest::cookie::CookieStore::cookies()`] to see which cookies
+    /// would be sent to a particular URL.
+    #[cfg(feature = "host-reqwest-cookies")]
+    pub fn cookie_jar(&self) -> Option<&Arc<reqwest::cookie::Jar>> {
+        self.cookie_jar.as_ref()
+    }
+
+    fn check_response_size(&self, size: u64) -> Result<(), BodyTooLarge> {
+        match self.max_response_bytes {
+            Some(limit) if size > limit => Err(BodyTooLarge {
+                direction: BodyDirection::Response,
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the underlying client, building it if this is the first request.
+    fn client(&self) -> Result<reqwest::Client, anyhow::Error> {
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
 
-        // TODO: use persistent client?
         let builder = {
             let _guard = Handle::try_current().map_err(|_| self.handle.enter());
             let mut builder = reqwest::ClientBuilder::new();
             #[cfg(not(feature = "js"))]
             {
-                builder = builder.connect_timeout(self.connect_timeout);
+                builder = builder
+                    .connect_timeout(self.connect_timeout)
+                    .user_agent(&self.user_agent)
+                    .pool_max_idle_per_host(self.pool_max_idle_per_host)
+                    .pool_idle_timeout(self.pool_idle_timeout)
+                    .tcp_keepalive(self.tcp_keepalive);
+                // Other crates in the build may turn on reqwest's
+                // decompression features, so they need to be switched off
+                // explicitly.
+                if !self.decompress {
+                    builder = builder.no_gzip().no_brotli().no_deflate();
+                }
+                #[cfg(feature = "host-reqwest-decompress")]
+                if self.decompress {
+                    builder = builder.gzip(true).brotli(true).deflate(true);
+                }
+                #[cfg(not(feature = "host-reqwest-decompress"))]
+                if self.decompress {
+                    anyhow::bail!(
+                        "decompression isn't supported because wasmer-wasix was compiled without the \"host-reqwest-decompress\" feature"
+                    );
+                }
+                builder = match self.http_version_pref {
+                    HttpVersionPref::Auto => builder,
+                    HttpVersionPref::Http1Only => builder.http1_only(),
+                    #[cfg(feature = "host-reqwest-http2")]
+                    HttpVersionPref::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+                    #[cfg(feature = "host-reqwest-http3")]
+                    HttpVersionPref::Http3 => builder.http3_prior_knowledge(),
+                    #[allow(unreachable_patterns)]
+                    other => anyhow::bail!(
+                        "{other:?} isn't supported because wasmer-wasix was compiled without the required feature"
+                    ),
+                };
+                if let Some(identity) = &self.client_identity {
+                    builder = builder.identity(identity.clone());
+                }
+                for certificate in &self.root_certificates {
+                    builder = builder.add_root_certificate(certificate.clone());
+                }
+                #[cfg(feature = "host-reqwest-cookies")]
+                if let Some(jar) = &self.cookie_jar {
+                    builder = builder.cookie_provider(Arc::clone(jar));
+                }
             }
             builder
         };
         let client = builder.build().context("failed to create reqwest client")?;
 
+        // Another request may have raced us, in which case we use its client
+        Ok(self.client.get_or_init(|| client).clone())
+    }
+
+    #[tracing::instrument(skip_all, fields(method=?request.method, url=%request.url))]
+    async fn request(&self, request: HttpRequest) -> Result<HttpResponse, anyhow::Error> {
+        let method = reqwest::Method::try_from(request.method.as_str())
+            .with_context(|| format!("Invalid http method {}", request.method))?;
+
+        let client = self.client()?;
+
         tracing::debug!("sending http request");
         let mut builder = client.request(method, request.url.as_str());
         for (header, val) in &request.headers {
             builder = builder.header(header, val);
         }
+        #[cfg(not(feature = "js"))]
+        if self.http_version_pref == HttpVersionPref::Http3 {
+            // reqwest only uses its HTTP/3 client for requests which ask for it
+            builder = builder.version(reqwest::Version::HTTP_3);
+        }
 
         if let Some(body) = request.body {
+            if let Some(limit) = self.max_request_bytes {
+                if body.len() as u64 > limit {
+                    return Err(BodyTooLarge {
+                        direction: BodyDirection::Request,
+                        limit,
+                    }
+                    .into());
+                }
+            }
             builder = builder.body(reqwest::Body::from(body));
         }
 
@@ -72,8 +373,16 @@ impl ReqwestHttpClient {
         let headers = std::mem::take(response.headers_mut());
 
         let status = response.status();
+        #[cfg(not(feature = "js"))]
+        let version = Some(response.version());
+        #[cfg(feature = "js")]
+        let version = None;
+
+        tracing::debug!(status=?status, ?version, "received http response");
 
-        tracing::debug!(status=?status, "received http response");
+        if let Some(len) = response.content_length() {
+            self.check_response_size(len)?;
+        }
 
         // Download the body.
         #[cfg(not(feature = "js"))]
@@ -103,6 +412,7 @@ impl ReqwestHttpClient {
                         res = stream.try_next() => {
                             match res {
                                 Ok(Some(chunk)) => {
+                                    self.check_response_size((buf.len() + chunk.len()) as u64)?;
                                     buf.extend_from_slice(&chunk);
                                     chunk_count += 1;
                                 }
@@ -132,10 +442,20 @@ impl ReqwestHttpClient {
 
             buf
         } else {
-            response.bytes().await?.to_vec()
+            let mut stream = response.bytes_stream();
+            let mut buf = Vec::new();
+            while let Some(chunk) = stream.try_next().await? {
+                self.check_response_size((buf.len() + chunk.len()) as u64)?;
+                buf.extend_from_slice(&chunk);
+            }
+            buf
         };
         #[cfg(feature = "js")]
-        let data = response.bytes().await?.to_vec();
+        let data = {
+            let data = response.bytes().await?.to_vec();
+            self.check_response_size(data.len() as u64)?;
+            data
+        };
 
         tracing::debug!(body_size_bytes=%data.len(), "downloaded http response body");
 
@@ -144,6 +464,7 @@ impl ReqwestHttpClient {
             redirected: false,
             body: Some(data),
             headers,
+            version,
         })
     }
 }
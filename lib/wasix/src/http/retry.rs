@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// How much randomness is added to the delay between retries.
+///
+/// Without jitter, clients which failed at the same time will also retry at
+/// the same time, hammering the server in lockstep.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Jitter {
+    /// Always wait for exactly the exponential backoff delay.
+    None,
+    /// Wait for a random duration between zero and the backoff delay.
+    #[default]
+    Full,
+    /// Wait for half the backoff delay plus a random duration of up to the
+    /// other half.
+    Equal,
+}
+
+/// Controls how the runtime retries requests which failed with a transient
+/// error (e.g. a dropped connection or a `503 Service Unavailable`).
+///
+/// Retries use exponential backoff, starting at
+/// [`RetryPolicy::initial_delay`] and doubling each time up to
+/// [`RetryPolicy::max_delay`], with [`Jitter`] applied on top.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the first attempt.
+    pub max_retries: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: Jitter,
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    pub const fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            initial_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            jitter: Jitter::None,
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// How long to wait before the `retry`'th retry (starting from zero).
+    ///
+    /// The random number generator is passed in so callers can use a seeded
+    /// one to get deterministic delays.
+    pub fn delay(&self, retry: u32, rng: &mut impl Rng) -> Duration {
+        let backoff = self
+            .initial_delay
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_delay);
+
+        match self.jitter {
+            Jitter::None => backoff,
+            Jitter::Full => rng.gen_range(Duration::ZERO..=backoff),
+            Jitter::Equal => {
+                let half = backoff / 2;
+                half + rng.gen_range(Duration::ZERO..=backoff - half)
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: Jitter::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn backoff_without_jitter_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: Jitter::None,
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let delays: Vec<_> = (0..5).map(|retry| policy.delay(retry, &mut rng)).collect();
+
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.delay(u32::MAX, &mut rng), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = RetryPolicy::default();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for retry in 0..10 {
+            let backoff = RetryPolicy {
+                jitter: Jitter::None,
+                ..policy
+            }
+            .delay(retry, &mut rng);
+
+            let full = policy.with_jitter(Jitter::Full).delay(retry, &mut rng);
+            assert!(full <= backoff);
+
+            let equal = policy.with_jitter(Jitter::Equal).delay(retry, &mut rng);
+            assert!(equal >= backoff / 2 && equal <= backoff);
+        }
+    }
+
+    #[test]
+    fn seeded_jitter_is_deterministic() {
+        let policy = RetryPolicy::default();
+
+        let first = policy.delay(2, &mut StdRng::seed_from_u64(7));
+        let second = policy.delay(2, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(first, second);
+    }
+}
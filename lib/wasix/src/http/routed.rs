@@ -0,0 +1,139 @@
+use futures::future::BoxFuture;
+use url::Url;
+
+use super::{DynHttpClient, HttpClient, HttpRequest, HttpResponse};
+
+/// A [`HttpClient`] which picks the client each request is sent with based
+/// on the request's host, falling back to a default client.
+///
+/// Routes are checked in the order they were added, and use the same
+/// patterns as [`AllowlistHttpClient`][super::AllowlistHttpClient]. This is
+/// how the routes added with
+/// [`PluggableRuntime::add_http_client_route()`][route] are applied to
+/// components which send requests to arbitrary URLs, like the registry
+/// source and package loader.
+///
+/// [route]: crate::runtime::PluggableRuntime::add_http_client_route
+#[derive(Debug, Clone, Default)]
+pub struct RoutedHttpClient {
+    default: Option<DynHttpClient>,
+    routes: Vec<(String, DynHttpClient)>,
+}
+
+impl RoutedHttpClient {
+    pub fn new(default: Option<DynHttpClient>) -> Self {
+        RoutedHttpClient {
+            default,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Send requests to hosts matching `pattern` with `client`.
+    pub fn with_route(mut self, pattern: impl Into<String>, client: DynHttpClient) -> Self {
+        self.add_route(pattern, client);
+        self
+    }
+
+    /// Send requests to hosts matching `pattern` with `client`.
+    pub fn add_route(&mut self, pattern: impl Into<String>, client: DynHttpClient) -> &mut Self {
+        self.routes.push((pattern.into(), client));
+        self
+    }
+
+    /// The client requests to `url` are sent with, if there is one.
+    pub fn client_for(&self, url: &Url) -> Option<&DynHttpClient> {
+        find_route(&self.routes, url).or(self.default.as_ref())
+    }
+}
+
+impl HttpClient for RoutedHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        match self.client_for(&request.url) {
+            Some(client) => client.request(request),
+            None => Box::pin(async move {
+                Err(anyhow::anyhow!(
+                    "no HTTP client is available for \"{}\"",
+                    request.url
+                ))
+            }),
+        }
+    }
+}
+
+/// The client of the first route whose pattern matches `url`'s host.
+pub(crate) fn find_route<'a>(
+    routes: &'a [(String, DynHttpClient)],
+    url: &Url,
+) -> Option<&'a DynHttpClient> {
+    let host = url.host_str()?;
+    routes
+        .iter()
+        .find(|(pattern, _)| crate::runtime::matches_host(pattern, host))
+        .map(|(_, client)| client)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::{HeaderMap, Method, StatusCode};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct StatusClient(StatusCode);
+
+    impl HttpClient for StatusClient {
+        fn request(
+            &self,
+            _request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            let status = self.0;
+            Box::pin(async move {
+                Ok(HttpResponse {
+                    body: None,
+                    redirected: false,
+                    version: None,
+                    status,
+                    headers: HeaderMap::new(),
+                })
+            })
+        }
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            url: url.parse().unwrap(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+            options: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_are_routed_by_host() {
+        let client = RoutedHttpClient::new(Some(Arc::new(StatusClient(StatusCode::OK))))
+            .with_route(
+                "registry.wasmer.io",
+                Arc::new(StatusClient(StatusCode::CREATED)),
+            )
+            .with_route(
+                "*.Example.com",
+                Arc::new(StatusClient(StatusCode::ACCEPTED)),
+            );
+
+        for (url, status) in [
+            ("https://registry.wasmer.io/graphql", StatusCode::CREATED),
+            ("https://api.example.com/v1", StatusCode::ACCEPTED),
+            ("https://example.com/", StatusCode::OK),
+            ("https://wasmer.io/", StatusCode::OK),
+        ] {
+            let response = client.request(get(url)).await.unwrap();
+            assert_eq!(response.status, status, "{url}");
+        }
+
+        let unrouted = RoutedHttpClient::new(None);
+        assert!(unrouted.request(get("https://wasmer.io/")).await.is_err());
+    }
+}
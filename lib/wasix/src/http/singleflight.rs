@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use http::Method;
+use url::Url;
+
+use super::{DynHttpClient, HttpClient, HttpRequest, HttpResponse};
+
+type SharedResponse = Shared<BoxFuture<'static, Result<Arc<HttpResponse>, Arc<anyhow::Error>>>>;
+
+/// Requests are only considered identical if they go to the same URL with
+/// the same headers.
+type RequestKey = (Url, Vec<(String, Vec<u8>)>);
+
+/// A [`HttpClient`] which coalesces concurrent, identical `GET` requests into
+/// a single request to the inner client.
+///
+/// This avoids hammering the network when lots of guests race to fetch the
+/// same resource (e.g. at cold start). Each caller gets its own copy of the
+/// response. Requests with any other method, or with a body, are passed
+/// straight through.
+#[derive(Clone)]
+pub struct SingleflightHttpClient {
+    inner: DynHttpClient,
+    in_flight: Arc<Mutex<HashMap<RequestKey, SharedResponse>>>,
+}
+
+impl SingleflightHttpClient {
+    pub fn new(inner: DynHttpClient) -> Self {
+        Self {
+            inner,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn inner(&self) -> &DynHttpClient {
+        &self.inner
+    }
+
+    /// The number of distinct requests which are currently in progress.
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    fn key(request: &HttpRequest) -> RequestKey {
+        let mut headers: Vec<_> = request
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str().to_string(), value.as_bytes().to_vec()))
+            .collect();
+        headers.sort();
+        (request.url.clone(), headers)
+    }
+}
+
+impl std::fmt::Debug for SingleflightHttpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SingleflightHttpClient")
+            .field("inner", &self.inner)
+            .field("in_flight", &self.in_flight_count())
+            .finish()
+    }
+}
+
+impl HttpClient for SingleflightHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        if request.method != Method::GET || request.body.is_some() {
+            return self.inner.request(request);
+        }
+
+        let key = Self::key(&request);
+
+        let shared = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    let inner = self.inner.clone();
+                    let map = self.in_flight.clone();
+                    async move {
+                        let result = inner.request(request).await;
+                        map.lock().unwrap().remove(&key);
+                        result.map(Arc::new).map_err(Arc::new)
+                    }
+                    .boxed()
+                    .shared()
+                })
+                .clone()
+        };
+
+        Box::pin(async move {
+            match shared.await {
+                Ok(response) => Ok(HttpResponse {
+                    body: response.body.clone(),
+                    redirected: response.redirected,
+                    version: response.version,
+                    status: response.status,
+                    headers: response.headers.clone(),
+                }),
+                Err(err) => Err(anyhow::anyhow!("{err:#}")),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use http::{HeaderMap, StatusCode};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CountingClient {
+        requests: AtomicUsize,
+    }
+
+    impl HttpClient for CountingClient {
+        fn request(
+            &self,
+            request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                // Make sure the request is still in flight when the other
+                // callers show up.
+                tokio::task::yield_now().await;
+                Ok(HttpResponse {
+                    body: Some(request.url.path().as_bytes().to_vec()),
+                    redirected: false,
+                    version: None,
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                })
+            })
+        }
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            url: url.parse().unwrap(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+            options: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_requests_are_coalesced() {
+        let inner = Arc::new(CountingClient::default());
+        let client = SingleflightHttpClient::new(inner.clone());
+
+        let (first, second, other) = futures::join!(
+            client.request(get("https://example.com/a")),
+            client.request(get("https://example.com/a")),
+            client.request(get("https://example.com/b")),
+        );
+
+        assert_eq!(first.unwrap().body.unwrap(), b"/a");
+        assert_eq!(second.unwrap().body.unwrap(), b"/a");
+        assert_eq!(other.unwrap().body.unwrap(), b"/b");
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(client.in_flight_count(), 0);
+
+        // Once the first request finishes, new ones hit the network again
+        client.request(get("https://example.com/a")).await.unwrap();
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 3);
+    }
+}
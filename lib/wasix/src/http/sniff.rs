@@ -0,0 +1,73 @@
+use std::fmt;
+
+/// How many bytes are inspected by [`is_probably_text()`].
+const SNIFF_LEN: usize = 1024;
+
+/// Guess whether `bytes` contain human-readable text rather than binary data,
+/// based on the first few bytes.
+///
+/// This is only a heuristic. Data is treated as binary if it contains a NUL
+/// byte, isn't valid UTF-8, or has a high proportion of control characters.
+pub fn is_probably_text(bytes: &[u8]) -> bool {
+    let prefix = &bytes[..bytes.len().min(SNIFF_LEN)];
+
+    let text = match std::str::from_utf8(prefix) {
+        Ok(text) => text,
+        // The prefix may have cut a multi-byte character in half
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&prefix[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+
+    let mut control_chars = 0;
+    for c in text.chars() {
+        match c {
+            '\0' => return false,
+            '\t' | '\n' | '\r' | '\x0c' | '\x1b' => {}
+            c if c.is_control() => control_chars += 1,
+            _ => {}
+        }
+    }
+
+    control_chars * 10 <= text.len()
+}
+
+/// Formats a body as text when it looks like text, and as a summary
+/// otherwise, so debug output doesn't get filled with mojibake.
+pub(crate) struct DebugBody<'a>(pub &'a [u8]);
+
+impl fmt::Debug for DebugBody<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if is_probably_text(self.0) {
+            fmt::Debug::fmt(&String::from_utf8_lossy(self.0), f)
+        } else {
+            write!(f, "<{} bytes of binary data>", self.0.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_text_and_binary() {
+        assert!(is_probably_text(b""));
+        assert!(is_probably_text(b"Hello, World!\r\n\tindented"));
+        assert!(is_probably_text("héllo wörld".as_bytes()));
+        assert!(is_probably_text(b"\x1b[1mbold\x1b[0m"));
+
+        assert!(!is_probably_text(b"\0asm\x01\0\0\0"));
+        assert!(!is_probably_text(&[0xff, 0xfe, 0xfd]));
+        assert!(!is_probably_text(b"\x01\x02\x03\x04abc"));
+    }
+
+    #[test]
+    fn sniff_ignores_truncated_characters() {
+        let mut text = "a".repeat(SNIFF_LEN - 1).into_bytes();
+        text.extend_from_slice("é".as_bytes());
+
+        assert!(is_probably_text(&text));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use futures::future::BoxFuture;
+use http::HeaderValue;
+
+use super::{DynHttpClient, HttpClient, HttpRequest, HttpResponse};
+
+/// A [W3C Trace Context](https://www.w3.org/TR/trace-context/) that outgoing
+/// requests are stitched into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub parent_id: [u8; 8],
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// The header used to propagate the context.
+    pub const HEADER: &'static str = "traceparent";
+
+    const VERSION: &'static str = "00";
+
+    pub fn new(trace_id: [u8; 16], parent_id: [u8; 8], sampled: bool) -> Self {
+        Self {
+            trace_id,
+            parent_id,
+            sampled,
+        }
+    }
+
+    /// Parse the value of a `traceparent` header.
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let (version, trace_id, parent_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version != Self::VERSION || parts.next().is_some() {
+            return None;
+        }
+
+        let mut ctx = TraceContext::new([0; 16], [0; 8], false);
+        hex::decode_to_slice(trace_id, &mut ctx.trace_id).ok()?;
+        hex::decode_to_slice(parent_id, &mut ctx.parent_id).ok()?;
+        let mut flags_buf = [0_u8; 1];
+        hex::decode_to_slice(flags, &mut flags_buf).ok()?;
+        ctx.sampled = flags_buf[0] & 0x01 != 0;
+
+        // All-zero identifiers are explicitly invalid according to the spec.
+        if ctx.trace_id == [0; 16] || ctx.parent_id == [0; 8] {
+            return None;
+        }
+
+        Some(ctx)
+    }
+
+    /// Render the context as the value of a `traceparent` header.
+    pub fn to_traceparent(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}-{}-{:02x}",
+            Self::VERSION,
+            hex::encode(self.trace_id),
+            hex::encode(self.parent_id),
+            self.sampled as u8,
+        )
+    }
+}
+
+/// A [`HttpClient`] that injects the `traceparent` header of the current
+/// [`TraceContext`] into every outgoing request.
+///
+/// The context is shared, so embedders can update it per-operation without
+/// having to rebuild the client. Requests which already carry a
+/// `traceparent` header are left untouched.
+#[derive(Debug, Clone)]
+pub struct TraceContextHttpClient {
+    inner: DynHttpClient,
+    context: Arc<RwLock<Option<TraceContext>>>,
+}
+
+impl TraceContextHttpClient {
+    pub fn new(inner: DynHttpClient, context: Arc<RwLock<Option<TraceContext>>>) -> Self {
+        Self { inner, context }
+    }
+
+    pub fn inner(&self) -> &DynHttpClient {
+        &self.inner
+    }
+}
+
+impl HttpClient for TraceContextHttpClient {
+    fn request(
+        &self,
+        mut request: HttpRequest,
+    ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        let context = *self.context.read().unwrap();
+
+        if let Some(context) = context {
+            if !request.headers.contains_key(TraceContext::HEADER) {
+                // Note: the rendered context only ever contains ASCII hex
+                // digits and dashes, so this can't fail.
+                let value = HeaderValue::from_str(&context.to_traceparent())
+                    .expect("traceparent is always a valid header value");
+                request.headers.insert(TraceContext::HEADER, value);
+            }
+        }
+
+        self.inner.request(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traceparent_round_trip() {
+        let raw = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+        let ctx = TraceContext::parse(raw).unwrap();
+
+        assert!(ctx.sampled);
+        assert_eq!(
+            ctx.parent_id,
+            [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]
+        );
+        assert_eq!(ctx.to_traceparent(), raw);
+    }
+
+    #[test]
+    fn reject_invalid_traceparent() {
+        let zero_trace = "00-00000000000000000000000000000000-00f067aa0ba902b7-01";
+        let bad_version = "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let truncated = "00-4bf92f3577b34da6a3ce929d0e0e4736";
+
+        assert_eq!(TraceContext::parse(zero_trace), None);
+        assert_eq!(TraceContext::parse(bad_version), None);
+        assert_eq!(TraceContext::parse(truncated), None);
+    }
+}
@@ -221,6 +221,8 @@ async fn read_response(response: &web_sys::Response) -> Result<HttpResponse, any
     Ok(HttpResponse {
         body: Some(body),
         redirected: response.redirected(),
+        // The fetch API doesn't expose which protocol was used
+        version: None,
         status,
         headers,
     })
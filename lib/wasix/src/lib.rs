@@ -67,8 +67,8 @@ pub use wasmer;
 pub use wasmer_wasix_types;
 
 use wasmer::{
-    imports, namespace, AsStoreMut, Exports, FunctionEnv, Imports, Memory32, MemoryAccessError,
-    MemorySize, RuntimeError,
+    imports, namespace, AsStoreMut, Exports, Extern, Function, FunctionEnv, Imports, Memory32,
+    MemoryAccessError, MemorySize, RuntimeError,
 };
 
 pub use virtual_fs;
@@ -120,6 +120,10 @@ pub enum WasiError {
     DeepSleep(DeepSleepWork),
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
+    /// The guest was still running when the runtime's
+    /// [`Runtime::execution_deadline()`] passed.
+    #[error("WASI execution deadline exceeded")]
+    DeadlineExceeded,
 }
 
 pub type WasiResult<T> = Result<Result<T, Errno>, WasiError>;
@@ -370,7 +374,42 @@ pub fn generate_import_object_from_env(
 
     imports.extend(&imports_wasi_generic);
 
-    imports
+    throttle_syscalls(store, ctx, imports)
+}
+
+/// Make every syscall in `imports` wait for the runtime's
+/// [`Runtime::syscall_rate_limit()`], if it has one.
+///
+/// Syscalls don't share a common entry point, so each of them is wrapped in
+/// a function which calls [`WasiEnv::throttle_syscall()`] first.
+fn throttle_syscalls(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    imports: Imports,
+) -> Imports {
+    if env.as_ref(store).runtime().syscall_rate_limit().is_none() {
+        return imports;
+    }
+
+    let mut throttled = Imports::new();
+    for ((namespace, name), import) in &imports {
+        let import = match import {
+            Extern::Function(syscall) => {
+                let ty = syscall.ty(store);
+                let wrapper = Function::new_with_env(store, env, ty, move |mut ctx, args| {
+                    ctx.data()
+                        .throttle_syscall()
+                        .map_err(|err| RuntimeError::user(Box::new(err)))?;
+                    syscall.call(&mut ctx, args).map(Vec::from)
+                });
+                Extern::Function(wrapper)
+            }
+            other => other,
+        };
+        throttled.define(&namespace, &name, import);
+    }
+
+    throttled
 }
 
 fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
@@ -528,6 +567,7 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
         "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
         "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory32>),
+        "fs_watch" => Function::new_typed_with_env(&mut store, env, fs_watch::<Memory32>),
         "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
         "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory32>),
         "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
@@ -563,6 +603,12 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "thread_signal" => Function::new_typed_with_env(&mut store, env, thread_signal),
         "thread_join" => Function::new_typed_with_env(&mut store, env, thread_join::<Memory32>),
         "thread_parallelism" => Function::new_typed_with_env(&mut store, env, thread_parallelism::<Memory32>),
+        "runtime_features" => Function::new_typed_with_env(&mut store, env, runtime_features::<Memory32>),
+        "log_write" => Function::new_typed_with_env(&mut store, env, log_write::<Memory32>),
+        "memory_limits" => Function::new_typed_with_env(&mut store, env, memory_limits::<Memory32>),
+        "secret_get" => Function::new_typed_with_env(&mut store, env, secret_get::<Memory32>),
+        "gethostname" => Function::new_typed_with_env(&mut store, env, gethostname::<Memory32>),
+        "getdomainname" => Function::new_typed_with_env(&mut store, env, getdomainname::<Memory32>),
         "thread_exit" => Function::new_typed_with_env(&mut store, env, thread_exit),
         "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
         "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory32>),
@@ -650,6 +696,7 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory64>),
         "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory64>),
         "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory64>),
+        "fs_watch" => Function::new_typed_with_env(&mut store, env, fs_watch::<Memory64>),
         "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory64>),
         "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory64>),
         "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory64>),
@@ -685,6 +732,12 @@ fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
         "thread_signal" => Function::new_typed_with_env(&mut store, env, thread_signal),
         "thread_join" => Function::new_typed_with_env(&mut store, env, thread_join::<Memory64>),
         "thread_parallelism" => Function::new_typed_with_env(&mut store, env, thread_parallelism::<Memory64>),
+        "runtime_features" => Function::new_typed_with_env(&mut store, env, runtime_features::<Memory64>),
+        "log_write" => Function::new_typed_with_env(&mut store, env, log_write::<Memory64>),
+        "memory_limits" => Function::new_typed_with_env(&mut store, env, memory_limits::<Memory64>),
+        "secret_get" => Function::new_typed_with_env(&mut store, env, secret_get::<Memory64>),
+        "gethostname" => Function::new_typed_with_env(&mut store, env, gethostname::<Memory64>),
+        "getdomainname" => Function::new_typed_with_env(&mut store, env, getdomainname::<Memory64>),
         "thread_exit" => Function::new_typed_with_env(&mut store, env, thread_exit),
         "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory64>),
         "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory64>),
@@ -772,6 +825,7 @@ fn import_object_for_all_wasi_versions(
         "wasix_64v1" => exports_wasix_64v1,
     };
 
+    let imports = throttle_syscalls(store, env, imports);
     let init = Box::new(stub_initializer) as ModuleInitializer;
 
     (imports, init)
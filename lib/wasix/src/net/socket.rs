@@ -73,6 +73,8 @@ pub enum InodeSocketKind {
     UdpSocket {
         socket: Box<dyn VirtualUdpSocket + Sync>,
         peer: Option<SocketAddr>,
+        write_timeout: Option<Duration>,
+        read_timeout: Option<Duration>,
     },
     RemoteSocket {
         props: SocketProperties,
@@ -245,6 +247,9 @@ impl InodeSocket {
             .flatten()
             .unwrap_or(Duration::from_secs(30));
 
+        // The send and receive timeouts carry over to the bound socket
+        let timeouts;
+
         let socket = {
             let mut inner = self.inner.protected.write().unwrap();
             match &mut inner.kind {
@@ -283,6 +288,7 @@ impl InodeSocket {
                         Socktype::Dgram => {
                             let reuse_port = props.reuse_port;
                             let reuse_addr = props.reuse_addr;
+                            timeouts = (props.write_timeout, props.read_timeout);
                             drop(inner);
 
                             net.bind_udp(addr, reuse_port, reuse_addr)
@@ -329,6 +335,7 @@ impl InodeSocket {
                         Socktype::Dgram => {
                             let reuse_port = props.reuse_port;
                             let reuse_addr = props.reuse_addr;
+                            timeouts = (props.write_timeout, props.read_timeout);
                             drop(inner);
 
                             net.bind_udp(addr, reuse_port, reuse_addr)
@@ -343,7 +350,13 @@ impl InodeSocket {
         tokio::select! {
             socket = socket => {
                 let socket = socket.map_err(net_error_into_wasi_err)?;
-                Ok(Some(InodeSocket::new(InodeSocketKind::UdpSocket { socket, peer: None })))
+                let (write_timeout, read_timeout) = timeouts;
+                Ok(Some(InodeSocket::new(InodeSocketKind::UdpSocket {
+                    socket,
+                    peer: None,
+                    write_timeout,
+                    read_timeout,
+                })))
             },
             _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
         }
@@ -896,6 +909,11 @@ impl InodeSocket {
                 write_timeout,
                 read_timeout,
                 ..
+            }
+            | InodeSocketKind::UdpSocket {
+                write_timeout,
+                read_timeout,
+                ..
             } => {
                 match ty {
                     TimeType::WriteTimeout => *write_timeout = timeout,
@@ -933,6 +951,11 @@ impl InodeSocket {
                 read_timeout,
                 write_timeout,
                 ..
+            }
+            | InodeSocketKind::UdpSocket {
+                read_timeout,
+                write_timeout,
+                ..
             } => Ok(match ty {
                 TimeType::ReadTimeout => *read_timeout,
                 TimeType::WriteTimeout => *write_timeout,
@@ -1097,7 +1120,7 @@ impl InodeSocket {
                     let res = match &mut inner.kind {
                         InodeSocketKind::Raw(socket) => socket.try_send(self.data),
                         InodeSocketKind::TcpStream { socket, .. } => socket.try_send(self.data),
-                        InodeSocketKind::UdpSocket { socket, peer } => {
+                        InodeSocketKind::UdpSocket { socket, peer, .. } => {
                             if let Some(peer) = peer {
                                 socket.try_send_to(self.data, *peer)
                             } else {
@@ -1267,7 +1290,7 @@ impl InodeSocket {
                     let res = match &mut inner.kind {
                         InodeSocketKind::Raw(socket) => socket.try_recv(self.data),
                         InodeSocketKind::TcpStream { socket, .. } => socket.try_recv(self.data),
-                        InodeSocketKind::UdpSocket { socket, peer } => {
+                        InodeSocketKind::UdpSocket { socket, peer, .. } => {
                             if let Some(peer) = peer {
                                 match socket.try_recv_from(self.data) {
                                     Ok((amt, addr)) if addr == *peer => Ok(amt),
@@ -1541,3 +1564,59 @@ pub(crate) fn all_socket_rights() -> Rights {
         .union(Rights::SOCK_RECV_FROM)
         .union(Rights::SOCK_SEND_TO)
 }
+
+#[cfg(all(test, feature = "host-vnet"))]
+mod tests {
+    use crate::runtime::task_manager::tokio::TokioTaskManager;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn udp_sockets_keep_their_timeouts() {
+        let tasks = TokioTaskManager::default();
+        let net = virtual_net::host::LocalNetworking::default();
+        let socket = InodeSocket::new(InodeSocketKind::PreSocket {
+            props: SocketProperties {
+                family: Addressfamily::Inet4,
+                ty: Socktype::Dgram,
+                pt: SockProto::Udp,
+                only_v6: false,
+                reuse_port: false,
+                reuse_addr: false,
+                no_delay: None,
+                keep_alive: None,
+                dont_route: None,
+                send_buf_size: None,
+                recv_buf_size: None,
+                write_timeout: None,
+                read_timeout: None,
+                accept_timeout: None,
+                connect_timeout: None,
+                handler: None,
+            },
+            addr: None,
+        });
+        let timeout = Duration::from_millis(10);
+        socket
+            .set_opt_time(TimeType::ReadTimeout, Some(timeout))
+            .unwrap();
+
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let socket = socket.bind(&tasks, &net, addr).await.unwrap().unwrap();
+        assert_eq!(socket.opt_time(TimeType::ReadTimeout), Ok(Some(timeout)));
+
+        // Nothing is ever sent, so the receive gives up
+        let mut buf = [MaybeUninit::uninit(); 8];
+        let timeout = socket.opt_time(TimeType::ReadTimeout).unwrap();
+        let result = socket.recv_from(&tasks, &mut buf, timeout, false).await;
+        assert_eq!(result, Err(Errno::Timedout));
+
+        socket
+            .set_opt_time(TimeType::WriteTimeout, Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(
+            socket.opt_time(TimeType::WriteTimeout),
+            Ok(Some(Duration::from_secs(1)))
+        );
+    }
+}
@@ -278,7 +278,8 @@ impl Console {
 
         // Build the config
         // Run the binary
-        let store = self.runtime.new_store();
+        let store = InlineWaker::block_on(self.runtime.new_store_async())
+            .map_err(|err| SpawnError::Other(err.into()))?;
         let process = InlineWaker::block_on(spawn_exec(pkg, prog, store, env, &self.runtime))?;
 
         // Return the process
@@ -335,7 +336,7 @@ mod tests {
 
         let tm = TokioTaskManager::new(tokio_rt);
         let mut rt = PluggableRuntime::new(Arc::new(tm));
-        let client = rt.http_client().unwrap().clone();
+        let client = rt.routed_http_client().unwrap();
         rt.set_engine(Some(wasmer::Engine::default()))
             .set_package_loader(BuiltinPackageLoader::new().with_shared_http_client(client));
 
@@ -390,7 +391,7 @@ mod tests {
 
         let tm = TokioTaskManager::new(tokio_rt);
         let mut rt = PluggableRuntime::new(Arc::new(tm));
-        let client = rt.http_client().unwrap().clone();
+        let client = rt.routed_http_client().unwrap();
         rt.set_engine(Some(wasmer::Engine::default()))
             .set_package_loader(BuiltinPackageLoader::new().with_shared_http_client(client));
 
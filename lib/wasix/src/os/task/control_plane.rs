@@ -7,7 +7,7 @@ use std::{
     time::Duration,
 };
 
-use crate::{WasiProcess, WasiProcessId};
+use crate::{runtime::PidAllocator, WasiProcess, WasiProcessId};
 use wasmer_types::ModuleHash;
 
 #[derive(Debug, Clone)]
@@ -132,6 +132,18 @@ impl WasiControlPlane {
     // FIXME: De-register terminated processes!
     // Currently they just accumulate.
     pub fn new_process(&self, module_hash: ModuleHash) -> Result<WasiProcess, ControlPlaneError> {
+        self.new_process_with_pid_allocator(module_hash, None, None)
+    }
+
+    /// Creates a new process, taking its ID from `pids` if one is given.
+    ///
+    /// `parent` is the process which forked or spawned the new one.
+    pub fn new_process_with_pid_allocator(
+        &self,
+        module_hash: ModuleHash,
+        pids: Option<&dyn PidAllocator>,
+        parent: Option<WasiProcessId>,
+    ) -> Result<WasiProcess, ControlPlaneError> {
         if let Some(max) = self.state.config.max_task_count {
             if self.active_task_count() >= max {
                 // NOTE: task count is not incremented here, only when new threads are spawned.
@@ -142,10 +154,25 @@ impl WasiControlPlane {
 
         // Create the process first to do all the allocations before locking.
         let mut proc = WasiProcess::new(WasiProcessId::from(0), module_hash, self.handle());
+        proc.parent = parent;
+        let allocated = match pids {
+            Some(pids) => Some(pids.allocate(parent).ok_or(
+                ControlPlaneError::TaskLimitReached {
+                    max: u32::MAX as usize,
+                },
+            )?),
+            None => None,
+        };
 
         let mut mutable = self.state.mutable.write().unwrap();
 
-        let pid = mutable.next_process_id()?;
+        let pid = match allocated {
+            Some(pid) if mutable.processes.contains_key(&pid) => {
+                return Err(ControlPlaneError::PidInUse { pid: pid.raw() });
+            }
+            Some(pid) => pid,
+            None => mutable.next_process_id()?,
+        };
         proc.set_pid(pid);
         mutable.processes.insert(pid, proc.clone());
         Ok(proc)
@@ -207,6 +234,10 @@ pub enum ControlPlaneError {
         /// The maximum number of tasks.
         max: usize,
     },
+    /// A [`PidAllocator`] handed out the ID of a process which is still
+    /// registered.
+    #[error("Process ID {pid} is already in use")]
+    PidInUse { pid: u32 },
 }
 
 #[cfg(test)]
@@ -240,6 +271,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pids_come_from_the_allocator() {
+        #[derive(Debug)]
+        struct FixedPid;
+
+        impl PidAllocator for FixedPid {
+            fn allocate(&self, _parent: Option<WasiProcessId>) -> Option<WasiProcessId> {
+                Some(WasiProcessId::from(100))
+            }
+        }
+
+        let p = WasiControlPlane::default();
+        let pids = crate::runtime::MonotonicPidAllocator::new(42);
+
+        let parent = p
+            .new_process_with_pid_allocator(xxhash_random(), Some(&pids), None)
+            .unwrap();
+        assert_eq!(parent.pid().raw(), 42);
+        assert_eq!(parent.ppid().raw(), 0);
+
+        let child = p
+            .new_process_with_pid_allocator(xxhash_random(), Some(&pids), Some(parent.pid()))
+            .unwrap();
+        assert_eq!(child.pid().raw(), 43);
+        assert_eq!(child.ppid(), parent.pid());
+        assert_eq!(p.get_process(child.pid()).unwrap().ppid(), parent.pid());
+
+        p.new_process_with_pid_allocator(xxhash_random(), Some(&FixedPid), None)
+            .unwrap();
+        assert_eq!(
+            p.new_process_with_pid_allocator(xxhash_random(), Some(&FixedPid), None)
+                .unwrap_err(),
+            ControlPlaneError::PidInUse { pid: 100 }
+        );
+    }
+
     /// Simple test to ensure task limits are respected and that thread drop guards work.
     #[test]
     fn test_control_plane_task_limits_with_dropped_threads() {
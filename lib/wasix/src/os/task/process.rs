@@ -10,7 +10,7 @@ use std::{
     ops::Range,
     sync::{
         atomic::{AtomicU32, Ordering},
-        Arc, Condvar, Mutex, MutexGuard, RwLock, Weak,
+        Arc, Condvar, Mutex, MutexGuard,
     },
     task::Waker,
     time::Duration,
@@ -94,8 +94,8 @@ pub struct WasiProcess {
     pub(crate) pid: WasiProcessId,
     /// Hash of the module that this process is using
     pub(crate) module_hash: ModuleHash,
-    /// List of all the children spawned from this thread
-    pub(crate) parent: Option<Weak<RwLock<WasiProcessInner>>>,
+    /// The process which forked or spawned this one
+    pub(crate) parent: Option<WasiProcessId>,
     /// The inner protected region of the process with a conditional
     /// variable that is used for coordination such as snapshots.
     pub(crate) inner: LockableWasiProcessInner,
@@ -471,12 +471,7 @@ impl WasiProcess {
 
     /// Gets the process ID of the parent process
     pub fn ppid(&self) -> WasiProcessId {
-        self.parent
-            .iter()
-            .filter_map(|parent| parent.upgrade())
-            .map(|parent| parent.read().unwrap().pid)
-            .next()
-            .unwrap_or(WasiProcessId(0))
+        self.parent.unwrap_or(WasiProcessId(0))
     }
 
     /// Gains access to the process internals
@@ -625,6 +625,9 @@ pub enum WasiThreadError {
     /// This will happen if WASM is running in a thread has not been created by the spawn_wasm call
     #[error("WASM context is invalid")]
     InvalidWasmContext,
+    /// The operation did not complete before its deadline passed
+    #[error("The operation timed out")]
+    TimedOut,
 }
 
 impl From<WasiThreadError> for Errno {
@@ -637,6 +640,7 @@ impl From<WasiThreadError> for Errno {
             WasiThreadError::InstanceCreateFailed(_) => Errno::Noexec,
             WasiThreadError::InitFailed(_) => Errno::Noexec,
             WasiThreadError::InvalidWasmContext => Errno::Noexec,
+            WasiThreadError::TimedOut => Errno::Timedout,
         }
     }
 }
@@ -4,7 +4,7 @@ use std::{
 };
 
 use futures::future::BoxFuture;
-use virtual_fs::{AsyncWriteExt, NullFile, VirtualFile};
+use virtual_fs::{AsyncWriteExt, LineEnding, NullFile, VirtualFile};
 use wasmer_wasix_types::wasi::{Signal, Snapshot0Clockid};
 
 use crate::syscalls::platform_clock_time_get;
@@ -13,6 +13,13 @@ use super::task::signal::SignalHandlerAbi;
 
 const TTY_MOBILE_PAUSE: u128 = std::time::Duration::from_millis(200).as_nanos();
 
+/// Sent before pasted input when bracketed paste mode is on.
+pub const PASTE_START: &str = "\x1b[200~";
+/// Sent after pasted input when bracketed paste mode is on.
+pub const PASTE_END: &str = "\x1b[201~";
+
+#[cfg(all(unix, not(target_os = "ios")))]
+pub mod tty_pty;
 pub mod tty_sys;
 
 #[derive(Debug)]
@@ -20,6 +27,8 @@ pub enum InputEvent {
     Key,
     Data(String),
     Raw(Vec<u8>),
+    /// Text which was pasted rather than typed.
+    Paste(String),
 }
 
 #[derive(Clone, Debug)]
@@ -39,6 +48,7 @@ pub struct TtyOptionsInner {
     echo: bool,
     line_buffering: bool,
     line_feeds: bool,
+    bracketed_paste: bool,
     rect: ConsoleRect,
 }
 
@@ -54,6 +64,7 @@ impl Default for TtyOptions {
                 echo: true,
                 line_buffering: true,
                 line_feeds: true,
+                bracketed_paste: false,
                 rect: ConsoleRect { cols: 80, rows: 25 },
             })),
         }
@@ -110,6 +121,18 @@ impl TtyOptions {
         let mut inner = self.inner.lock().unwrap();
         inner.line_feeds = line_feeds;
     }
+
+    pub fn bracketed_paste(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.bracketed_paste
+    }
+
+    /// Wrap [`InputEvent::Paste`]s in [`PASTE_START`] and [`PASTE_END`] so
+    /// the guest can tell them apart from typed input.
+    pub fn set_bracketed_paste(&self, bracketed_paste: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.bracketed_paste = bracketed_paste;
+    }
 }
 
 #[derive(Debug)]
@@ -196,6 +219,14 @@ impl Tty {
                     self.on_data(data.as_bytes().to_vec().into()).await
                 }
                 InputEvent::Raw(data) => self.on_data(data.into()).await,
+                InputEvent::Paste(data) => {
+                    let data = if self.options.bracketed_paste() {
+                        format!("{PASTE_START}{data}{PASTE_END}")
+                    } else {
+                        data
+                    };
+                    self.on_data(data.into_bytes().into()).await
+                }
             }
         })
     }
@@ -451,4 +482,112 @@ pub trait TtyBridge: std::fmt::Debug {
 
     /// Set the TTY state.
     fn tty_set(&self, _tty_state: WasiTtyState);
+
+    /// Switch raw mode on or off.
+    ///
+    /// In raw mode input is handed to the guest a byte at a time, without
+    /// being echoed or turned into signals, which is what full-screen
+    /// programs like editors expect. Use a [`TtyGuard`] to make sure the
+    /// terminal is put back afterwards.
+    ///
+    /// The default implementation only toggles echo and line buffering.
+    fn set_raw_mode(&self, enabled: bool) {
+        let mut state = self.tty_get();
+        state.echo = !enabled;
+        state.line_buffered = !enabled;
+        self.tty_set(state);
+    }
+
+    /// How newlines in the guest's stdout and stderr should be translated
+    /// before they reach this terminal.
+    ///
+    /// The default leaves them alone, which suits terminals that do their own
+    /// translation (e.g. a Unix terminal with `ONLCR` set).
+    fn line_ending(&self) -> LineEnding {
+        LineEnding::Passthrough
+    }
+
+    /// Switch bracketed paste mode on or off, so pasted input reaches the
+    /// guest between [`PASTE_START`] and [`PASTE_END`] and REPLs don't
+    /// auto-indent or run each pasted line as it arrives.
+    ///
+    /// The default implementation ignores this.
+    fn set_bracketed_paste(&self, _enabled: bool) {}
+
+    /// Whether bracketed paste mode is on.
+    fn bracketed_paste(&self) -> bool {
+        false
+    }
+}
+
+/// Restores a TTY to the state it was in when the guard was created once
+/// the guard is dropped, however the scope is left.
+#[derive(Debug)]
+pub struct TtyGuard<'a> {
+    tty: &'a dyn TtyBridge,
+    state: WasiTtyState,
+    raw: bool,
+    bracketed_paste: bool,
+}
+
+impl<'a> TtyGuard<'a> {
+    /// Remember the TTY's current state.
+    pub fn new(tty: &'a dyn TtyBridge) -> Self {
+        TtyGuard {
+            tty,
+            state: tty.tty_get(),
+            raw: false,
+            bracketed_paste: tty.bracketed_paste(),
+        }
+    }
+
+    /// Remember the TTY's current state, then switch it to raw mode.
+    pub fn raw(tty: &'a dyn TtyBridge) -> Self {
+        let mut guard = TtyGuard::new(tty);
+        tty.set_raw_mode(true);
+        guard.raw = true;
+        guard
+    }
+}
+
+impl Drop for TtyGuard<'_> {
+    fn drop(&mut self) {
+        if self.raw {
+            self.tty.set_raw_mode(false);
+        }
+        self.tty.tty_set(self.state.clone());
+        if self.tty.bracketed_paste() != self.bracketed_paste {
+            self.tty.set_bracketed_paste(self.bracketed_paste);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use virtual_fs::{ArcFile, AsyncReadExt, AsyncSeekExt, BufferFile};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn pastes_are_bracketed() {
+        let mut stdin = ArcFile::new(Box::<BufferFile>::default());
+        let options = TtyOptions::default();
+        options.set_echo(false);
+        options.set_line_buffering(false);
+        let tty = Tty::new(
+            Box::new(stdin.clone()),
+            Box::<NullFile>::default(),
+            false,
+            options.clone(),
+        );
+
+        let tty = tty.on_event(InputEvent::Paste("a\n".to_string())).await;
+        options.set_bracketed_paste(true);
+        tty.on_event(InputEvent::Paste("b\n".to_string())).await;
+
+        let mut received = String::new();
+        stdin.seek(std::io::SeekFrom::Start(0)).await.unwrap();
+        stdin.read_to_string(&mut received).await.unwrap();
+        assert_eq!(received, "a\n\x1b[200~b\n\x1b[201~");
+    }
 }
@@ -0,0 +1,260 @@
+use std::{
+    fs::File,
+    io, mem,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+};
+
+use super::TtyBridge;
+use crate::WasiTtyState;
+
+/// [`TtyBridge`] implementation backed by a freshly allocated pseudo-terminal.
+///
+/// The guest's stdio should be connected to the slave side of the PTY (see
+/// [`PtyTty::try_clone_slave()`]), while the host drives the program through
+/// the master side (see [`PtyTty::master()`]). Terminal modes requested by
+/// the guest are applied to the PTY itself, so the kernel's line discipline
+/// handles echoing and line buffering just like it would for a native
+/// program.
+pub struct PtyTty {
+    master: OwnedFd,
+    slave: OwnedFd,
+    /// The modes the PTY was created with, restored by [`TtyBridge::reset()`].
+    initial: libc::termios,
+}
+
+impl PtyTty {
+    /// Allocate a new PTY with the given window size.
+    pub fn new(cols: u16, rows: u16) -> io::Result<Self> {
+        let mut master = -1;
+        let mut slave = -1;
+        io_result(unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        })?;
+        let master = unsafe { OwnedFd::from_raw_fd(master) };
+        let slave = unsafe { OwnedFd::from_raw_fd(slave) };
+
+        // Don't leak the PTY into processes spawned by the host
+        for fd in [&master, &slave] {
+            io_result(unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) })?;
+        }
+
+        let initial = tcgetattr(slave.as_fd())?;
+        let pty = PtyTty {
+            master,
+            slave,
+            initial,
+        };
+        pty.resize(cols, rows)?;
+
+        Ok(pty)
+    }
+
+    /// The master side of the PTY, which the host reads the guest's output
+    /// from and writes its input to.
+    pub fn master(&self) -> BorrowedFd<'_> {
+        self.master.as_fd()
+    }
+
+    /// Get a [`File`] for the master side of the PTY.
+    pub fn try_clone_master(&self) -> io::Result<File> {
+        Ok(File::from(self.master.try_clone()?))
+    }
+
+    /// The slave side of the PTY, which the guest's stdio should be
+    /// connected to.
+    pub fn slave(&self) -> BorrowedFd<'_> {
+        self.slave.as_fd()
+    }
+
+    /// Get a [`File`] for the slave side of the PTY (e.g. to wrap in a
+    /// `virtual_fs::host_fs::File` and use as the guest's stdin).
+    pub fn try_clone_slave(&self) -> io::Result<File> {
+        Ok(File::from(self.slave.try_clone()?))
+    }
+
+    /// Change the PTY's window size, which the guest will see the next time
+    /// it queries the TTY.
+    pub fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        let size = libc::winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        io_result(unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &size) })
+    }
+
+    fn window_size(&self) -> io::Result<libc::winsize> {
+        let mut size = mem::MaybeUninit::<libc::winsize>::uninit();
+        io_result(unsafe {
+            libc::ioctl(self.master.as_raw_fd(), libc::TIOCGWINSZ, size.as_mut_ptr())
+        })?;
+        Ok(unsafe { size.assume_init() })
+    }
+
+    fn update_termios(&self, update: impl FnOnce(&mut libc::termios)) -> io::Result<()> {
+        let mut termios = tcgetattr(self.slave.as_fd())?;
+        update(&mut termios);
+        tcsetattr(self.slave.as_fd(), &termios)
+    }
+}
+
+impl std::fmt::Debug for PtyTty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PtyTty")
+            .field("master", &self.master)
+            .field("slave", &self.slave)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TtyBridge for PtyTty {
+    fn reset(&self) {
+        tcsetattr(self.slave.as_fd(), &self.initial).ok();
+    }
+
+    fn tty_get(&self) -> WasiTtyState {
+        let mut state = WasiTtyState::default();
+
+        if let Ok(size) = self.window_size() {
+            state.cols = size.ws_col.into();
+            state.rows = size.ws_row.into();
+            if size.ws_xpixel != 0 && size.ws_ypixel != 0 {
+                state.width = size.ws_xpixel.into();
+                state.height = size.ws_ypixel.into();
+            }
+        }
+
+        if let Ok(termios) = tcgetattr(self.slave.as_fd()) {
+            state.echo = (termios.c_lflag & libc::ECHO) != 0;
+            state.line_buffered = (termios.c_lflag & libc::ICANON) != 0;
+            state.line_feeds = (termios.c_oflag & libc::ONLCR) != 0;
+        }
+
+        state
+    }
+
+    fn tty_set(&self, tty_state: WasiTtyState) {
+        self.update_termios(|termios| {
+            let echo = libc::ECHO | libc::ECHOE | libc::ECHOK | libc::ECHOCTL | libc::IEXTEN;
+            if tty_state.echo {
+                termios.c_lflag |= echo;
+            } else {
+                termios.c_lflag &= !echo;
+            }
+            if tty_state.line_buffered {
+                termios.c_lflag |= libc::ICANON;
+            } else {
+                termios.c_lflag &= !libc::ICANON;
+            }
+            if tty_state.line_feeds {
+                termios.c_oflag |= libc::ONLCR;
+            } else {
+                termios.c_oflag &= !libc::ONLCR;
+            }
+        })
+        .ok();
+    }
+
+    fn set_raw_mode(&self, enabled: bool) {
+        const LOCAL_FLAGS: libc::tcflag_t = libc::ICANON
+            | libc::ECHO
+            | libc::ECHOE
+            | libc::ECHOK
+            | libc::ECHONL
+            | libc::ISIG
+            | libc::IEXTEN;
+        const INPUT_FLAGS: libc::tcflag_t = libc::IXON | libc::ICRNL;
+
+        self.update_termios(|termios| {
+            termios.c_lflag &= !LOCAL_FLAGS;
+            termios.c_iflag &= !INPUT_FLAGS;
+            if enabled {
+                termios.c_cc[libc::VMIN] = 1;
+                termios.c_cc[libc::VTIME] = 0;
+            } else {
+                // Put back whatever the PTY was created with
+                termios.c_lflag |= self.initial.c_lflag & LOCAL_FLAGS;
+                termios.c_iflag |= self.initial.c_iflag & INPUT_FLAGS;
+                termios.c_cc[libc::VMIN] = self.initial.c_cc[libc::VMIN];
+                termios.c_cc[libc::VTIME] = self.initial.c_cc[libc::VTIME];
+            }
+        })
+        .ok();
+    }
+}
+
+fn io_result(ret: libc::c_int) -> io::Result<()> {
+    match ret {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+fn tcgetattr(fd: BorrowedFd<'_>) -> io::Result<libc::termios> {
+    let mut termios = mem::MaybeUninit::<libc::termios>::uninit();
+    io_result(unsafe { libc::tcgetattr(fd.as_raw_fd(), termios.as_mut_ptr()) })?;
+    Ok(unsafe { termios.assume_init() })
+}
+
+fn tcsetattr(fd: BorrowedFd<'_>, termios: &libc::termios) -> io::Result<()> {
+    io_result(unsafe { libc::tcsetattr(fd.as_raw_fd(), libc::TCSANOW, termios) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+    use crate::os::TtyGuard;
+
+    #[test]
+    fn guest_modes_are_applied_to_the_pty() {
+        let pty = PtyTty::new(120, 40).unwrap();
+
+        let state = pty.tty_get();
+        assert_eq!((state.cols, state.rows), (120, 40));
+
+        pty.tty_set(WasiTtyState {
+            echo: false,
+            line_buffered: false,
+            ..state
+        });
+        let state = pty.tty_get();
+        assert!(!state.echo);
+        assert!(!state.line_buffered);
+
+        // With canonical mode off, input is readable without a newline
+        let mut master = pty.try_clone_master().unwrap();
+        master.write_all(b"x").unwrap();
+        let mut buf = [0; 1];
+        pty.try_clone_slave().unwrap().read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"x");
+
+        pty.reset();
+        assert!(pty.tty_get().line_buffered);
+    }
+
+    #[test]
+    fn raw_mode_is_reverted_by_the_guard() {
+        let pty = PtyTty::new(80, 25).unwrap();
+        let before = tcgetattr(pty.slave()).unwrap();
+
+        {
+            let _guard = TtyGuard::raw(&pty);
+            let raw = tcgetattr(pty.slave()).unwrap();
+            assert_eq!(raw.c_lflag & (libc::ICANON | libc::ECHO | libc::ISIG), 0);
+            assert_eq!(raw.c_iflag & libc::ICRNL, 0);
+        }
+
+        let after = tcgetattr(pty.slave()).unwrap();
+        assert_eq!(after.c_lflag, before.c_lflag);
+        assert_eq!(after.c_iflag, before.c_iflag);
+    }
+}
@@ -1,13 +1,27 @@
+use std::{
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use virtual_fs::LineEnding;
+
 use super::TtyBridge;
 use crate::WasiTtyState;
 
+/// Whether bracketed paste mode has been switched on for the host's terminal.
+static BRACKETED_PASTE: AtomicBool = AtomicBool::new(false);
+
 /// [`TtyBridge`] implementation for Unix systems.
 #[derive(Debug, Default, Clone)]
 pub struct SysTty;
 
 impl TtyBridge for SysTty {
     fn reset(&self) {
+        sys::set_raw_mode(false).ok();
         sys::reset().ok();
+        if self.bracketed_paste() {
+            self.set_bracketed_paste(false);
+        }
     }
 
     fn tty_get(&self) -> WasiTtyState {
@@ -50,6 +64,39 @@ impl TtyBridge for SysTty {
             sys::set_mode_no_line_feeds().ok();
         }
     }
+
+    fn set_raw_mode(&self, enabled: bool) {
+        sys::set_raw_mode(enabled).ok();
+    }
+
+    fn line_ending(&self) -> LineEnding {
+        // Unix terminals already turn `\n` into `\r\n` when `line_feeds` is
+        // set, but the Windows console needs the guest's output translated
+        if cfg!(windows) && self.tty_get().line_feeds {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Passthrough
+        }
+    }
+
+    fn set_bracketed_paste(&self, enabled: bool) {
+        BRACKETED_PASTE.store(enabled, Ordering::SeqCst);
+        if !sys::is_stdout_tty() {
+            return;
+        }
+
+        let sequence: &[u8] = if enabled {
+            b"\x1b[?2004h"
+        } else {
+            b"\x1b[?2004l"
+        };
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(sequence).and_then(|_| stdout.flush()).ok();
+    }
+
+    fn bracketed_paste(&self) -> bool {
+        BRACKETED_PASTE.load(Ordering::SeqCst)
+    }
 }
 
 mod sys_terminal_size {
@@ -82,8 +129,12 @@ mod sys {
         },
         std::mem,
         std::os::unix::io::AsRawFd,
+        std::sync::Mutex,
     };
 
+    /// The terminal's settings from before raw mode was switched on.
+    static BEFORE_RAW_MODE: Mutex<Option<termios>> = Mutex::new(None);
+
     fn io_result(ret: libc::c_int) -> std::io::Result<()> {
         match ret {
             0 => Ok(()),
@@ -223,6 +274,36 @@ mod sys {
         unsafe { tcsetattr(0, TCSANOW, &termios) };
         Ok(())
     }
+
+    pub fn set_raw_mode(enabled: bool) -> Result<(), anyhow::Error> {
+        let mut before = BEFORE_RAW_MODE.lock().unwrap();
+
+        if !enabled {
+            if let Some(termios) = before.take() {
+                io_result(unsafe { tcsetattr(0, TCSANOW, &termios) })?;
+            }
+            return Ok(());
+        }
+        if before.is_some() {
+            return Ok(());
+        }
+
+        let mut termios = mem::MaybeUninit::<termios>::uninit();
+        io_result(unsafe { ::libc::tcgetattr(0, termios.as_mut_ptr()) })?;
+        let original = unsafe { termios.assume_init() };
+
+        // Output processing is left alone so the guest's line feeds setting
+        // still applies
+        let mut termios = original;
+        termios.c_lflag &= !(ICANON | ECHO | ECHOE | ECHOK | ECHONL | ISIG | IEXTEN);
+        termios.c_iflag &= !(IXON | ICRNL);
+        termios.c_cc[::libc::VMIN] = 1;
+        termios.c_cc[::libc::VTIME] = 0;
+
+        io_result(unsafe { tcsetattr(0, TCSANOW, &termios) })?;
+        *before = Some(original);
+        Ok(())
+    }
 }
 
 #[cfg(any(not(unix), target_os = "ios"))]
@@ -278,4 +359,8 @@ mod sys {
     pub fn set_mode_line_feeds() -> Result<(), anyhow::Error> {
         Ok(())
     }
+
+    pub fn set_raw_mode(_enabled: bool) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
 }
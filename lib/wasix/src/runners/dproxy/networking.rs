@@ -6,9 +6,9 @@ use std::{
 };
 
 use virtual_net::{
-    host::LocalNetworking, loopback::LoopbackNetworking, IpCidr, IpRoute, NetworkError,
-    StreamSecurity, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket, VirtualTcpListener,
-    VirtualTcpSocket, VirtualUdpSocket,
+    host::LocalNetworking, loopback::LoopbackNetworking, IpCidr, IpRoute, NetworkError, SocketId,
+    SocketStats, StreamSecurity, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
+    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
 };
 
 #[derive(Debug, Default)]
@@ -65,6 +65,18 @@ impl LocalWithLoopbackNetworking {
 #[allow(unused_variables)]
 #[async_trait::async_trait]
 impl VirtualNetworking for LocalWithLoopbackNetworking {
+    fn backend_name(&self) -> &'static str {
+        "host-with-loopback"
+    }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        self.inner_networking.open_sockets()
+    }
+
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        self.inner_networking.socket_stats(id)
+    }
+
     /// Bridges this local network with a remote network, which is required in
     /// order to make lower level networking calls (such as UDP/TCP)
     async fn bridge(
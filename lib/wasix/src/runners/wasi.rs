@@ -13,7 +13,7 @@ use crate::{
     capabilities::Capabilities,
     journal::{DynJournal, SnapshotTrigger},
     runners::{wasi_common::CommonWasiOptions, MappedDirectory, MountedDirectory},
-    runtime::task_manager::VirtualTaskManagerExt,
+    runtime::task_manager::{InlineWaker, VirtualTaskManagerExt},
     Runtime, WasiEnvBuilder, WasiError, WasiRuntimeError,
 };
 use wasmer_types::ModuleHash;
@@ -299,7 +299,8 @@ impl WasiRunner {
         asyncify: bool,
     ) -> Result<(), Error> {
         let wasi = webc::metadata::annotations::Wasi::new(program_name);
-        let mut store = runtime.new_store();
+        let mut store = InlineWaker::block_on(runtime.new_store_async())
+            .context("Unable to create the store")?;
 
         let mut builder = self.prepare_webc_env(program_name, &wasi, None, runtime, None)?;
 
@@ -345,7 +346,10 @@ impl crate::runners::Runner for WasiRunner {
             .starts_with(webc::metadata::annotations::WASI_RUNNER_URI))
     }
 
-    #[tracing::instrument(skip_all)]
+    #[tracing::instrument(
+        skip_all,
+        fields(instance_id = tracing::field::Empty, networking = runtime.networking_kind())
+    )]
     fn run_command(
         &mut self,
         command_name: &str,
@@ -383,7 +387,9 @@ impl crate::runners::Runner for WasiRunner {
         }
 
         let env = env.build()?;
-        let store = runtime.new_store();
+        tracing::Span::current().record("instance_id", tracing::field::display(env.instance_id()));
+        let store = InlineWaker::block_on(runtime.new_store_async())
+            .context("Unable to create the store")?;
 
         let command_name = command_name.to_string();
         let tasks = runtime.task_manager().clone();
@@ -425,6 +431,9 @@ impl crate::runners::Runner for WasiRunner {
                                     WasiRuntimeError::Wasi(WasiError::UnknownWasiVersion) => {
                                         WasiRuntimeError::Wasi(WasiError::UnknownWasiVersion)
                                     }
+                                    WasiRuntimeError::Wasi(WasiError::DeadlineExceeded) => {
+                                        WasiRuntimeError::Wasi(WasiError::DeadlineExceeded)
+                                    }
                                     WasiRuntimeError::Wasi(WasiError::DeepSleep(_)) => {
                                         WasiRuntimeError::Anyhow(Arc::new(anyhow::format_err!(
                                             "deep-sleep"
@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use virtual_net::{
+    DynVirtualNetworking, IpCidr, IpRoute, Result, SocketId, SocketStats, StreamSecurity,
+    VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket,
+    VirtualUdpSocket,
+};
+
+/// How a [`CachingResolver`] caches lookups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsCacheConfig {
+    /// How long a successful lookup is cached for.
+    ///
+    /// [`VirtualNetworking::resolve()`] doesn't expose the TTLs of the
+    /// records it found, so this is used for every entry.
+    pub ttl: Duration,
+    /// How long a failed lookup is cached for, or zero to not cache
+    /// failures at all.
+    pub negative_ttl: Duration,
+    /// The most lookups to remember. When the cache is full, the entry
+    /// closest to expiring is evicted to make room.
+    pub max_entries: usize,
+}
+
+impl Default for DnsCacheConfig {
+    fn default() -> Self {
+        DnsCacheConfig {
+            ttl: Duration::from_secs(60),
+            negative_ttl: Duration::from_secs(5),
+            max_entries: 1024,
+        }
+    }
+}
+
+/// Wraps another networking implementation, caching the results of DNS
+/// lookups so guests which resolve the same hosts over and over don't pay
+/// for a full lookup every time.
+///
+/// Everything other than [`VirtualNetworking::resolve()`] is passed straight
+/// through. Install it with
+/// [`PluggableRuntime::set_networking_implementation()`][set].
+///
+/// [set]: super::PluggableRuntime::set_networking_implementation
+#[derive(Debug, Clone)]
+pub struct CachingResolver {
+    inner: DynVirtualNetworking,
+    config: DnsCacheConfig,
+    cache: Arc<Mutex<HashMap<CacheKey, CacheEntry>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    /// Host names are case-insensitive, so this is always lowercase.
+    host: String,
+    port: Option<u16>,
+    dns_server: Option<IpAddr>,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    result: Result<Vec<IpAddr>>,
+    expires: Instant,
+}
+
+impl CachingResolver {
+    pub fn new(inner: DynVirtualNetworking, config: DnsCacheConfig) -> Self {
+        CachingResolver {
+            inner,
+            config,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn config(&self) -> &DnsCacheConfig {
+        &self.config
+    }
+
+    /// Forget every cached lookup.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// The number of lookups currently cached, including any which have
+    /// expired but not been evicted yet.
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Option<Result<Vec<IpAddr>>> {
+        let mut cache = self.cache.lock().unwrap();
+        match cache.get(key) {
+            Some(entry) if entry.expires > Instant::now() => Some(entry.result.clone()),
+            Some(_) => {
+                cache.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn remember(&self, key: CacheKey, result: Result<Vec<IpAddr>>) {
+        let ttl = match result {
+            Ok(_) => self.config.ttl,
+            Err(_) => self.config.negative_ttl,
+        };
+        if ttl.is_zero() || self.config.max_entries == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+
+        if !cache.contains_key(&key) && cache.len() >= self.config.max_entries {
+            cache.retain(|_, entry| entry.expires > now);
+
+            if cache.len() >= self.config.max_entries {
+                let closest_to_expiring = cache
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires)
+                    .map(|(key, _)| key.clone());
+                if let Some(evicted) = closest_to_expiring {
+                    cache.remove(&evicted);
+                }
+            }
+        }
+
+        cache.insert(
+            key,
+            CacheEntry {
+                result,
+                expires: now + ttl,
+            },
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for CachingResolver {
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        self.inner.open_sockets()
+    }
+
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        self.inner.socket_stats(id)
+    }
+
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix).await
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip).await
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear().await
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list().await
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac().await
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+            .await
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr).await
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear().await
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list().await
+    }
+
+    async fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        self.inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        self.inner.bind_udp(addr, reuse_port, reuse_addr).await
+    }
+
+    async fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        self.inner.connect_tcp(addr, peer).await
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        let key = CacheKey {
+            host: host.to_ascii_lowercase(),
+            port,
+            dns_server,
+        };
+        if let Some(result) = self.lookup(&key) {
+            return result;
+        }
+
+        let result = self.inner.resolve(host, port, dns_server).await;
+        self.remember(key, result.clone());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::Ipv4Addr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use virtual_net::NetworkError;
+
+    use super::*;
+
+    /// Resolves `localhost` and fails for everything else, counting how many
+    /// lookups it has done.
+    #[derive(Debug, Default)]
+    struct CountingResolver(AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl VirtualNetworking for CountingResolver {
+        async fn resolve(
+            &self,
+            host: &str,
+            _port: Option<u16>,
+            _dns_server: Option<IpAddr>,
+        ) -> Result<Vec<IpAddr>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            match host {
+                "localhost" => Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]),
+                _ => Err(NetworkError::AddressNotAvailable),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn lookups_are_cached_until_they_expire() {
+        let inner = Arc::new(CountingResolver::default());
+        let resolver = CachingResolver::new(
+            inner.clone(),
+            DnsCacheConfig {
+                ttl: Duration::from_millis(50),
+                negative_ttl: Duration::from_secs(60),
+                max_entries: 2,
+            },
+        );
+        let lookups = || inner.0.load(Ordering::SeqCst);
+
+        let addrs = resolver.resolve("localhost", None, None).await.unwrap();
+        assert_eq!(addrs, [IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+        resolver.resolve("LocalHost", None, None).await.unwrap();
+        assert_eq!(lookups(), 1);
+
+        // Failures are cached too
+        for _ in 0..2 {
+            assert_eq!(
+                resolver.resolve("example.com", None, None).await,
+                Err(NetworkError::AddressNotAvailable)
+            );
+        }
+        assert_eq!(lookups(), 2);
+
+        // The successful lookup expires first, so it's evicted to make room
+        resolver.resolve("example.org", None, None).await.ok();
+        assert_eq!(resolver.len(), 2);
+        resolver.resolve("localhost", None, None).await.unwrap();
+        assert_eq!(lookups(), 4);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        resolver.resolve("localhost", None, None).await.unwrap();
+        assert_eq!(lookups(), 5);
+
+        resolver.clear();
+        assert!(resolver.is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use wasmer_wasix_types::wasi::{Errno, Snapshot0Clockid, Timestamp};
+
+use crate::syscalls::platform_clock_time_get;
+
+/// The clocks guests read the time from.
+pub trait VirtualClock: fmt::Debug + Send + Sync {
+    /// Read `clock_id`, in nanoseconds.
+    fn time(&self, clock_id: Snapshot0Clockid, precision: Timestamp) -> Result<i64, Errno>;
+}
+
+/// A [`VirtualClock`] which reads the host's clocks.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct HostClock;
+
+impl VirtualClock for HostClock {
+    fn time(&self, clock_id: Snapshot0Clockid, precision: Timestamp) -> Result<i64, Errno> {
+        platform_clock_time_get(clock_id, precision)
+    }
+}
+
+/// A [`VirtualClock`] which drifts away from another clock, for testing how
+/// guests cope with imperfect time.
+///
+/// The realtime and monotonic clocks run at the drift rate (`1.0` is the
+/// same speed as the inner clock, `1.001` runs 0.1% fast), starting from the
+/// first time each clock is read. [`SkewingClock::jump()`] and
+/// [`SkewingClock::jump_back()`] step the realtime clock the way an NTP
+/// correction would, while the monotonic clock carries on undisturbed. The
+/// CPU time clocks are passed through unchanged.
+#[derive(Debug)]
+pub struct SkewingClock {
+    inner: Arc<dyn VirtualClock>,
+    state: Mutex<SkewState>,
+}
+
+#[derive(Debug)]
+struct SkewState {
+    drift_rate: f64,
+    /// How far the realtime clock has been stepped, in nanoseconds.
+    realtime_offset: i64,
+    /// The readings each clock is drifting from.
+    anchors: HashMap<Snapshot0Clockid, Anchor>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Anchor {
+    inner: i64,
+    skewed: i64,
+}
+
+impl SkewingClock {
+    pub fn new(inner: Arc<dyn VirtualClock>) -> Self {
+        SkewingClock {
+            inner,
+            state: Mutex::new(SkewState {
+                drift_rate: 1.0,
+                realtime_offset: 0,
+                anchors: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Set the initial drift rate.
+    pub fn with_drift_rate(self, drift_rate: f64) -> Self {
+        self.state.lock().unwrap().drift_rate = drift_rate.max(0.0);
+        self
+    }
+
+    /// Change the drift rate from now on.
+    ///
+    /// Negative rates are treated as `0.0` (a stopped clock), because the
+    /// monotonic clock must never go backwards.
+    pub fn set_drift_rate(&self, drift_rate: f64) -> Result<(), Errno> {
+        let mut state = self.state.lock().unwrap();
+
+        // Re-anchor every clock at its current reading so the new rate
+        // doesn't apply retroactively
+        let clocks: Vec<_> = state.anchors.keys().copied().collect();
+        for clock_id in clocks {
+            let inner = self.inner.time(clock_id, 1)?;
+            let skewed = state.drift(clock_id, inner);
+            state.anchors.insert(clock_id, Anchor { inner, skewed });
+        }
+        state.drift_rate = drift_rate.max(0.0);
+
+        Ok(())
+    }
+
+    /// Step the realtime clock forwards.
+    pub fn jump(&self, by: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.realtime_offset = state.realtime_offset.saturating_add(nanos(by));
+    }
+
+    /// Step the realtime clock backwards.
+    pub fn jump_back(&self, by: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.realtime_offset = state.realtime_offset.saturating_sub(nanos(by));
+    }
+}
+
+impl SkewState {
+    fn drift(&mut self, clock_id: Snapshot0Clockid, inner: i64) -> i64 {
+        let anchor = *self.anchors.entry(clock_id).or_insert(Anchor {
+            inner,
+            skewed: inner,
+        });
+        let elapsed = inner.saturating_sub(anchor.inner) as f64 * self.drift_rate;
+        anchor.skewed.saturating_add(elapsed as i64)
+    }
+}
+
+impl VirtualClock for SkewingClock {
+    fn time(&self, clock_id: Snapshot0Clockid, precision: Timestamp) -> Result<i64, Errno> {
+        let inner = self.inner.time(clock_id, precision)?;
+        let mut state = self.state.lock().unwrap();
+
+        match clock_id {
+            Snapshot0Clockid::Realtime => {
+                let offset = state.realtime_offset;
+                Ok(state.drift(clock_id, inner).saturating_add(offset))
+            }
+            Snapshot0Clockid::Monotonic => Ok(state.drift(clock_id, inner)),
+            _ => Ok(inner),
+        }
+    }
+}
+
+fn nanos(duration: Duration) -> i64 {
+    i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use super::*;
+
+    /// A clock which only moves when told to.
+    #[derive(Debug, Default)]
+    struct ManualClock(AtomicI64);
+
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            self.0.fetch_add(nanos(by), Ordering::SeqCst);
+        }
+    }
+
+    impl VirtualClock for ManualClock {
+        fn time(&self, _clock_id: Snapshot0Clockid, _precision: Timestamp) -> Result<i64, Errno> {
+            Ok(self.0.load(Ordering::SeqCst))
+        }
+    }
+
+    #[test]
+    fn drift_and_jumps() {
+        let base = Arc::new(ManualClock::default());
+        let clock = SkewingClock::new(base.clone()).with_drift_rate(2.0);
+        let read = |clock_id| clock.time(clock_id, 1).unwrap();
+
+        assert_eq!(read(Snapshot0Clockid::Realtime), 0);
+        assert_eq!(read(Snapshot0Clockid::Monotonic), 0);
+
+        base.advance(Duration::from_secs(1));
+        assert_eq!(read(Snapshot0Clockid::Realtime), 2_000_000_000);
+        assert_eq!(read(Snapshot0Clockid::ProcessCputimeId), 1_000_000_000);
+
+        clock.jump_back(Duration::from_secs(5));
+        assert_eq!(read(Snapshot0Clockid::Realtime), -3_000_000_000);
+        assert_eq!(read(Snapshot0Clockid::Monotonic), 2_000_000_000);
+
+        clock.set_drift_rate(1.0).unwrap();
+        base.advance(Duration::from_secs(1));
+        assert_eq!(read(Snapshot0Clockid::Monotonic), 3_000_000_000);
+    }
+}
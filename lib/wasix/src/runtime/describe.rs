@@ -0,0 +1,108 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use super::{EntropyFallback, MemoryLimits, RateLimit};
+
+/// A snapshot of how a [`PluggableRuntime`][super::PluggableRuntime] has
+/// been configured, for logging or comparing against another runtime's
+/// configuration.
+///
+/// Hooks which are trait objects (e.g. the [`LogSink`][super::LogSink]) are
+/// only listed by name, because there is nothing to say about them beyond
+/// whether they are installed.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RuntimeDescription {
+    /// The [`Runtime::networking_kind()`][super::Runtime::networking_kind].
+    pub networking: String,
+    /// The features the runtime provides, e.g. `threads` or `tty`.
+    pub features: Vec<String>,
+    /// The engine's deterministic ID, if an engine has been set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+    /// The hosts which have their own HTTP client.
+    pub http_client_routes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domainname: Option<String>,
+    /// The optional hooks which have been installed, e.g. `log-sink`.
+    pub hooks: Vec<String>,
+    /// Where guests get their random bytes from: `host`, `seeded` or
+    /// `custom`.
+    pub rng: String,
+    pub entropy_fallback: EntropyFallback,
+    /// The names of the shared regions guests can map.
+    pub shared_regions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_open_fds: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_creation_timeout_ms: Option<u64>,
+    pub default_args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_cwd: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_limits: Option<MemoryLimits>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syscall_rate_limit: Option<RateLimit>,
+}
+
+impl RuntimeDescription {
+    /// Serialize the description in the given format.
+    pub fn to_string_as(&self, format: ConfigFormat) -> Result<String, DescribeError> {
+        match format {
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            ConfigFormat::Yaml => Ok(serde_yaml::to_string(self)?),
+            ConfigFormat::Toml => Ok(toml::to_string(self)?),
+        }
+    }
+}
+
+/// The formats a [`RuntimeDescription`] can be serialized as.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl FromStr for ConfigFormat {
+    type Err = UnknownConfigFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            "toml" => Ok(ConfigFormat::Toml),
+            _ => Err(UnknownConfigFormat(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Json => f.write_str("json"),
+            ConfigFormat::Yaml => f.write_str("yaml"),
+            ConfigFormat::Toml => f.write_str("toml"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown config format \"{0}\", expected \"json\", \"yaml\" or \"toml\"")]
+pub struct UnknownConfigFormat(String);
+
+/// An error from [`RuntimeDescription::to_string_as()`].
+#[derive(Debug, thiserror::Error)]
+pub enum DescribeError {
+    #[error("unable to serialize the description as JSON")]
+    Json(#[from] serde_json::Error),
+    #[error("unable to serialize the description as YAML")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("unable to serialize the description as TOML")]
+    Toml(#[from] toml::ser::Error),
+}
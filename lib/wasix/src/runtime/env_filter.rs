@@ -0,0 +1,14 @@
+use std::fmt;
+
+/// Rewrites the environment variables a guest can see.
+///
+/// The filter is applied to every variable before the guest can read it via
+/// `environ_get`, making it a good place to scrub secrets (e.g. `AWS_*`)
+/// that were inherited from the host.
+pub trait EnvFilter: fmt::Debug + Send + Sync {
+    /// Transform a single variable, returning `None` to hide it from the
+    /// guest entirely.
+    ///
+    /// Values which aren't valid UTF-8 are passed in lossily converted.
+    fn transform(&self, key: &str, value: &str) -> Option<(String, String)>;
+}
@@ -0,0 +1,813 @@
+use std::{
+    mem::MaybeUninit,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use virtual_mio::{InterestHandler, InterestType};
+use virtual_net::{
+    DynVirtualNetworking, IpCidr, IpRoute, NetworkError, Result, SocketId, SocketStats,
+    SocketStatus, StreamSecurity, VirtualConnectedSocket, VirtualConnectionlessSocket,
+    VirtualIcmpSocket, VirtualIoSource, VirtualNetworking, VirtualRawSocket, VirtualSocket,
+    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+};
+
+use super::{Runtime, SeededRng, VirtualRng, VirtualTaskManager};
+
+/// A range of delays, one of which is picked uniformly at random each time.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FaultDelay {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl FaultDelay {
+    /// No delay at all.
+    pub const NONE: FaultDelay = FaultDelay {
+        min: Duration::ZERO,
+        max: Duration::ZERO,
+    };
+
+    /// Always delay by exactly `delay`.
+    pub fn fixed(delay: Duration) -> Self {
+        FaultDelay {
+            min: delay,
+            max: delay,
+        }
+    }
+
+    /// Delay by anything from `min` to `max`.
+    pub fn between(min: Duration, max: Duration) -> Self {
+        FaultDelay {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    fn sample(&self, faults: &Faults) -> Duration {
+        if self.max <= self.min {
+            return self.min;
+        }
+        self.min + (self.max - self.min).mul_f64(faults.unit())
+    }
+}
+
+/// The faults a [`FaultyNetworking`] injects.
+///
+/// Probabilities are between `0.0` (never) and `1.0` (always). The default
+/// doesn't inject any faults.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FaultConfig {
+    /// How long establishing a TCP connection takes.
+    pub connect_delay: FaultDelay,
+    /// The probability that connecting fails with
+    /// [`NetworkError::ConnectionRefused`].
+    pub connect_failure_probability: f64,
+    /// The probability that each read or write on a TCP connection resets
+    /// it, failing with [`NetworkError::ConnectionReset`] from then on.
+    pub reset_probability: f64,
+    /// How long data received over TCP is held back before the guest can
+    /// read it.
+    pub read_delay: FaultDelay,
+    /// How long each TCP write waits before being sent.
+    pub write_delay: FaultDelay,
+    /// The probability that a UDP datagram is silently dropped, in either
+    /// direction.
+    pub packet_loss: f64,
+}
+
+/// Wraps another networking implementation, injecting latency, packet loss
+/// and connection resets so guests can be tested against an unreliable
+/// network.
+///
+/// All randomness comes from a [`VirtualRng`], so using a [`SeededRng`]
+/// makes the faults reproducible, and delays are timed by the
+/// [`VirtualTaskManager`]. Install it with
+/// [`PluggableRuntime::set_networking_implementation()`][set].
+///
+/// [set]: super::PluggableRuntime::set_networking_implementation
+#[derive(Debug, Clone)]
+pub struct FaultyNetworking {
+    inner: DynVirtualNetworking,
+    faults: Arc<Faults>,
+}
+
+#[derive(Debug)]
+struct Faults {
+    config: FaultConfig,
+    rng: Arc<dyn VirtualRng>,
+    tasks: Arc<dyn VirtualTaskManager>,
+}
+
+impl Faults {
+    /// A random number in `[0, 1)`.
+    fn unit(&self) -> f64 {
+        let mut buf = [0_u8; 8];
+        self.rng.fill_bytes(&mut buf);
+        (u64::from_le_bytes(buf) >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    fn happens(&self, probability: f64) -> bool {
+        probability > 0.0 && self.unit() < probability
+    }
+}
+
+impl FaultyNetworking {
+    pub fn new(
+        inner: DynVirtualNetworking,
+        config: FaultConfig,
+        tasks: Arc<dyn VirtualTaskManager>,
+        rng: Arc<dyn VirtualRng>,
+    ) -> Self {
+        FaultyNetworking {
+            inner,
+            faults: Arc::new(Faults { config, rng, tasks }),
+        }
+    }
+
+    /// Wrap the runtime's current networking, using its task manager and
+    /// RNG (or a randomly seeded one if the runtime doesn't have an RNG).
+    pub fn from_runtime(runtime: &(dyn Runtime + Send + Sync), config: FaultConfig) -> Self {
+        let rng = runtime
+            .rng()
+            .unwrap_or_else(|| Arc::new(SeededRng::new(rand::random())));
+        FaultyNetworking::new(
+            runtime.networking().clone(),
+            config,
+            runtime.task_manager().clone(),
+            rng,
+        )
+    }
+
+    pub fn config(&self) -> &FaultConfig {
+        &self.faults.config
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for FaultyNetworking {
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+
+    fn open_sockets(&self) -> Vec<SocketId> {
+        self.inner.open_sockets()
+    }
+
+    fn socket_stats(&self, id: SocketId) -> Option<SocketStats> {
+        self.inner.socket_stats(id)
+    }
+
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix).await
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip).await
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear().await
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list().await
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac().await
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+            .await
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr).await
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear().await
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list().await
+    }
+
+    async fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let inner = self
+            .inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await?;
+        Ok(Box::new(FaultyTcpListener {
+            inner,
+            faults: self.faults.clone(),
+        }))
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        let inner = self.inner.bind_udp(addr, reuse_port, reuse_addr).await?;
+        Ok(Box::new(FaultyUdpSocket {
+            inner,
+            faults: self.faults.clone(),
+        }))
+    }
+
+    async fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let delay = self.faults.config.connect_delay.sample(&self.faults);
+        if !delay.is_zero() {
+            self.faults.tasks.sleep_now(delay).await;
+        }
+        if self
+            .faults
+            .happens(self.faults.config.connect_failure_probability)
+        {
+            return Err(NetworkError::ConnectionRefused);
+        }
+
+        let inner = self.inner.connect_tcp(addr, peer).await?;
+        Ok(FaultyTcpSocket::wrap(inner, &self.faults))
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+#[derive(Debug)]
+struct FaultyTcpListener {
+    inner: Box<dyn VirtualTcpListener + Sync>,
+    faults: Arc<Faults>,
+}
+
+impl VirtualIoSource for FaultyTcpListener {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualTcpListener for FaultyTcpListener {
+    fn try_accept(&mut self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let (socket, addr) = self.inner.try_accept()?;
+        Ok((FaultyTcpSocket::wrap(socket, &self.faults), addr))
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        self.inner.ttl()
+    }
+}
+
+/// The handler registered on a socket, shared with the timers which hold
+/// back its reads and writes so they can wake it up.
+#[derive(Debug, Clone, Default)]
+struct SharedHandler(Arc<Mutex<Option<Box<dyn InterestHandler + Send + Sync>>>>);
+
+impl InterestHandler for SharedHandler {
+    fn push_interest(&mut self, interest: InterestType) {
+        if let Some(handler) = self.0.lock().unwrap().as_mut() {
+            handler.push_interest(interest);
+        }
+    }
+
+    fn pop_interest(&mut self, interest: InterestType) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .as_mut()
+            .is_some_and(|handler| handler.pop_interest(interest))
+    }
+
+    fn has_interest(&self, interest: InterestType) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|handler| handler.has_interest(interest))
+    }
+}
+
+/// A delay which is being waited out.
+#[derive(Debug, Default)]
+struct PendingDelay {
+    elapsed: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+/// Holds back a socket's reads or writes by a random delay.
+#[derive(Debug, Default)]
+struct DelayGate {
+    pending: Option<Arc<PendingDelay>>,
+}
+
+impl DelayGate {
+    /// Check whether the next read or write may go ahead, starting a new
+    /// delay if there isn't one already.
+    ///
+    /// Fails with [`NetworkError::WouldBlock`] until the delay has passed,
+    /// at which point `handler` is told the socket is ready for `interest`.
+    fn check(
+        &mut self,
+        delay: FaultDelay,
+        faults: &Faults,
+        handler: &SharedHandler,
+        interest: InterestType,
+    ) -> Result<()> {
+        if let Some(pending) = &self.pending {
+            if pending.elapsed.load(Ordering::SeqCst) {
+                self.pending = None;
+                return Ok(());
+            }
+            return Err(NetworkError::WouldBlock);
+        }
+
+        let delay = delay.sample(faults);
+        if delay.is_zero() {
+            return Ok(());
+        }
+
+        let pending = Arc::new(PendingDelay::default());
+        let sleep = faults.tasks.sleep_now(delay);
+        let mut handler = handler.clone();
+        let timer = pending.clone();
+        let spawned = faults.tasks.task_shared(Box::new(move || {
+            Box::pin(async move {
+                sleep.await;
+                timer.elapsed.store(true, Ordering::SeqCst);
+                if let Some(waker) = timer.waker.lock().unwrap().take() {
+                    waker.wake();
+                }
+                handler.push_interest(interest);
+            })
+        }));
+        if let Err(e) = spawned {
+            tracing::debug!(
+                error = &e as &dyn std::error::Error,
+                "unable to delay a socket operation"
+            );
+            return Ok(());
+        }
+
+        self.pending = Some(pending);
+        Err(NetworkError::WouldBlock)
+    }
+
+    /// Is a delay still being waited out? If so, `cx` is woken once it has
+    /// passed.
+    fn is_waiting(&self, cx: &mut Context<'_>) -> bool {
+        let Some(pending) = &self.pending else {
+            return false;
+        };
+        *pending.waker.lock().unwrap() = Some(cx.waker().clone());
+        !pending.elapsed.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug)]
+struct FaultyTcpSocket {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    faults: Arc<Faults>,
+    handler: SharedHandler,
+    reads: DelayGate,
+    writes: DelayGate,
+    reset: bool,
+}
+
+impl FaultyTcpSocket {
+    fn wrap(
+        inner: Box<dyn VirtualTcpSocket + Sync>,
+        faults: &Arc<Faults>,
+    ) -> Box<dyn VirtualTcpSocket + Sync> {
+        Box::new(FaultyTcpSocket {
+            inner,
+            faults: faults.clone(),
+            handler: SharedHandler::default(),
+            reads: DelayGate::default(),
+            writes: DelayGate::default(),
+            reset: false,
+        })
+    }
+
+    /// Randomly reset the connection.
+    fn check_reset(&mut self) -> Result<()> {
+        if !self.reset && self.faults.happens(self.faults.config.reset_probability) {
+            tracing::debug!("injecting a connection reset");
+            self.reset = true;
+            let _ = self.inner.close();
+        }
+
+        if self.reset {
+            Err(NetworkError::ConnectionReset)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl VirtualIoSource for FaultyTcpSocket {
+    fn remove_handler(&mut self) {
+        self.handler.0.lock().unwrap().take();
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        if self.reads.is_waiting(cx) {
+            return Poll::Pending;
+        }
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        if self.writes.is_waiting(cx) {
+            return Poll::Pending;
+        }
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for FaultyTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        if self.reset {
+            return Ok(SocketStatus::Failed);
+        }
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        *self.handler.0.lock().unwrap() = Some(handler);
+        self.inner.set_handler(Box::new(self.handler.clone()))
+    }
+}
+
+impl VirtualConnectedSocket for FaultyTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        self.check_reset()?;
+        self.writes.check(
+            self.faults.config.write_delay,
+            &self.faults,
+            &self.handler,
+            InterestType::Writable,
+        )?;
+        self.inner.try_send(data)
+    }
+
+    fn try_flush(&mut self) -> Result<()> {
+        self.inner.try_flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.inner.close()
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        self.check_reset()?;
+        self.reads.check(
+            self.faults.config.read_delay,
+            &self.faults,
+            &self.handler,
+            InterestType::Readable,
+        )?;
+        self.inner.try_recv(buf)
+    }
+}
+
+impl VirtualTcpSocket for FaultyTcpSocket {
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, reuse: bool) -> Result<()> {
+        self.inner.set_nodelay(reuse)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn set_keepalive(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    fn keepalive(&self) -> Result<bool> {
+        self.inner.keepalive()
+    }
+
+    fn set_dontroute(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_dontroute(keepalive)
+    }
+
+    fn dontroute(&self) -> Result<bool> {
+        self.inner.dontroute()
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.reset || self.inner.is_closed()
+    }
+}
+
+#[derive(Debug)]
+struct FaultyUdpSocket {
+    inner: Box<dyn VirtualUdpSocket + Sync>,
+    faults: Arc<Faults>,
+}
+
+impl VirtualIoSource for FaultyUdpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for FaultyUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+}
+
+impl VirtualConnectionlessSocket for FaultyUdpSocket {
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        if self.faults.happens(self.faults.config.packet_loss) {
+            // Lost datagrams still look like they were sent
+            return Ok(data.len());
+        }
+        self.inner.try_send_to(data, addr)
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        loop {
+            let received = self.inner.try_recv_from(buf)?;
+            if !self.faults.happens(self.faults.config.packet_loss) {
+                return Ok(received);
+            }
+        }
+    }
+}
+
+impl VirtualUdpSocket for FaultyUdpSocket {
+    fn set_broadcast(&mut self, broadcast: bool) -> Result<()> {
+        self.inner.set_broadcast(broadcast)
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        self.inner.broadcast()
+    }
+
+    fn set_multicast_loop_v4(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v4(val)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        self.inner.multicast_loop_v4()
+    }
+
+    fn set_multicast_loop_v6(&mut self, val: bool) -> Result<()> {
+        self.inner.set_multicast_loop_v6(val)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        self.inner.multicast_loop_v6()
+    }
+
+    fn set_multicast_ttl_v4(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_multicast_ttl_v4(ttl)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        self.inner.multicast_ttl_v4()
+    }
+
+    fn join_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.join_multicast_v4(multiaddr, iface)
+    }
+
+    fn leave_multicast_v4(&mut self, multiaddr: Ipv4Addr, iface: Ipv4Addr) -> Result<()> {
+        self.inner.leave_multicast_v4(multiaddr, iface)
+    }
+
+    fn join_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.join_multicast_v6(multiaddr, iface)
+    }
+
+    fn leave_multicast_v6(&mut self, multiaddr: Ipv6Addr, iface: u32) -> Result<()> {
+        self.inner.leave_multicast_v6(multiaddr, iface)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        self.inner.addr_peer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use virtual_net::LoopbackNetworking;
+
+    use super::*;
+    use crate::runtime::task_manager::tokio::TokioTaskManager;
+
+    fn faulty(loopback: &LoopbackNetworking, config: FaultConfig) -> FaultyNetworking {
+        FaultyNetworking::new(
+            Arc::new(loopback.clone()),
+            config,
+            Arc::new(TokioTaskManager::default()),
+            Arc::new(SeededRng::new(42)),
+        )
+    }
+
+    #[tokio::test]
+    async fn connections_can_fail_and_reset() {
+        let loopback = LoopbackNetworking::new();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+        let net = faulty(
+            &loopback,
+            FaultConfig {
+                connect_failure_probability: 1.0,
+                reset_probability: 1.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            net.connect_tcp(addr, addr).await.unwrap_err(),
+            NetworkError::ConnectionRefused
+        );
+
+        let mut listener = net.listen_tcp(addr, false, false, false).await.unwrap();
+        let _client = loopback.loopback_connect_to(addr, addr).unwrap();
+        let (mut server, _) = listener.try_accept().unwrap();
+        assert_eq!(
+            server.try_send(b"hello").unwrap_err(),
+            NetworkError::ConnectionReset
+        );
+        assert!(server.is_closed());
+    }
+
+    #[tokio::test]
+    async fn reads_are_delayed() {
+        let loopback = LoopbackNetworking::new();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+        let net = faulty(
+            &loopback,
+            FaultConfig {
+                read_delay: FaultDelay::fixed(Duration::from_millis(20)),
+                ..Default::default()
+            },
+        );
+        let mut listener = net.listen_tcp(addr, false, false, false).await.unwrap();
+        let mut client = loopback.loopback_connect_to(addr, addr).unwrap();
+        let (mut server, _) = listener.try_accept().unwrap();
+
+        client.try_send(b"hello").unwrap();
+        let mut buf = [MaybeUninit::uninit(); 16];
+        assert_eq!(
+            server.try_recv(&mut buf).unwrap_err(),
+            NetworkError::WouldBlock
+        );
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(server.try_recv(&mut buf).unwrap(), 5);
+    }
+}
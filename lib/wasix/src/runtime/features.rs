@@ -0,0 +1,86 @@
+use std::fmt;
+
+use super::Runtime;
+
+bitflags::bitflags! {
+    /// Optional features a [`Runtime`] may or may not provide, so guests can
+    /// check for them up front (via the `runtime_features` syscall) instead
+    /// of finding out when an operation fails.
+    ///
+    /// The bit values are part of the guest ABI and must never change.
+    #[derive(Default)]
+    pub struct FeatureFlags: u64 {
+        /// Guests can spawn threads.
+        const THREADS = 1 << 0;
+        /// Guests can open sockets.
+        const NETWORKING = 1 << 1;
+        /// Guests can make HTTP requests through the host.
+        const HTTP_CLIENT = 1 << 2;
+        /// Guests are attached to a TTY.
+        const TTY = 1 << 3;
+        /// Guests can read secrets from a secret store with `secret_get`.
+        const SECRETS = 1 << 4;
+        /// The guest's execution is being journaled.
+        const JOURNALING = 1 << 5;
+        /// Guests can watch the filesystem for changes with `fs_watch`.
+        const FS_WATCH = 1 << 6;
+        /// Guests can send structured logs to the host with `log_write`.
+        const LOGGING = 1 << 7;
+    }
+}
+
+impl FeatureFlags {
+    const NAMES: &'static [(FeatureFlags, &'static str)] = &[
+        (FeatureFlags::THREADS, "threads"),
+        (FeatureFlags::NETWORKING, "networking"),
+        (FeatureFlags::HTTP_CLIENT, "http-client"),
+        (FeatureFlags::TTY, "tty"),
+        (FeatureFlags::SECRETS, "secrets"),
+        (FeatureFlags::JOURNALING, "journaling"),
+        (FeatureFlags::FS_WATCH, "fs-watch"),
+        (FeatureFlags::LOGGING, "logging"),
+    ];
+
+    /// Work out which features a runtime provides from the features wasix
+    /// was compiled with and the hooks the runtime has populated.
+    pub fn detect(runtime: &(impl Runtime + ?Sized)) -> Self {
+        let mut flags = FeatureFlags::empty();
+
+        flags.set(
+            FeatureFlags::THREADS,
+            cfg!(any(feature = "sys-thread", feature = "js")),
+        );
+        flags.set(
+            FeatureFlags::NETWORKING,
+            runtime.networking_kind() != "unsupported",
+        );
+        flags.set(FeatureFlags::HTTP_CLIENT, runtime.http_client().is_some());
+        flags.set(FeatureFlags::TTY, runtime.tty().is_some());
+        flags.set(FeatureFlags::SECRETS, runtime.secrets().is_some());
+        flags.set(FeatureFlags::FS_WATCH, runtime.fs_watcher().is_some());
+        flags.set(FeatureFlags::LOGGING, runtime.log_sink().is_some());
+        #[cfg(feature = "journal")]
+        flags.set(FeatureFlags::JOURNALING, !runtime.journals().is_empty());
+
+        flags
+    }
+}
+
+impl fmt::Display for FeatureFlags {
+    /// A comma-separated list of the features, e.g. `threads,tty`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = FeatureFlags::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name);
+
+        for (i, name) in names.enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str(name)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+/// Decides which paths a guest may open.
+///
+/// The policy is consulted every time a guest opens a path (i.e. on each
+/// `path_open` syscall), after the usual WASI rights checks, and denied
+/// opens fail with `EACCES`. This makes it a good place to audit file
+/// access or enforce rules which are more dynamic than the preopened
+/// directories.
+pub trait FsAccessPolicy: fmt::Debug + Send + Sync {
+    /// Check whether `path` may be opened.
+    ///
+    /// `path` is absolute and has been lexically normalized (`.` and `..`
+    /// are resolved), but symlinks are not followed.
+    fn check(&self, path: &Path, mode: OpenMode) -> Decision;
+}
+
+/// What a guest asked for when opening a path.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct OpenMode {
+    pub read: bool,
+    pub write: bool,
+    pub create: bool,
+    pub truncate: bool,
+    pub append: bool,
+    /// The path must be a directory.
+    pub directory: bool,
+}
+
+/// The outcome of [`FsAccessPolicy::check()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+/// Resolve `path` relative to the directory `dir`, removing any `.` and `..`
+/// components without touching the filesystem.
+pub(crate) fn normalize_path(dir: &Path, path: &str) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+
+    for component in dir.join(path).components() {
+        match component {
+            Component::Normal(segment) => normalized.push(segment),
+            // Going above the root stays at the root
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_normalized() {
+        let inputs = [
+            ("/", "etc/passwd", "/etc/passwd"),
+            ("/home/user", "./notes.txt", "/home/user/notes.txt"),
+            ("/home/user", "../../../etc/passwd", "/etc/passwd"),
+            ("/home/user", "/tmp/x", "/tmp/x"),
+            ("", "file", "/file"),
+        ];
+
+        for (dir, path, expected) in inputs {
+            assert_eq!(
+                normalize_path(Path::new(dir), path),
+                Path::new(expected),
+                "{dir} + {path}"
+            );
+        }
+    }
+}
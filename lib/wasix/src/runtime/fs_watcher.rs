@@ -0,0 +1,161 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use futures::{channel::mpsc, stream::BoxStream, StreamExt};
+
+/// Lets guests watch the filesystem for changes (e.g. with `inotify`-style
+/// APIs).
+///
+/// The WASIX syscalls report every change a guest makes through
+/// [`FsWatcher::notify()`]. Watchers for filesystems which can also change
+/// underneath the guest (e.g. a host directory) should report those changes
+/// as well, for example with the `notify` crate.
+///
+/// Guests subscribe with the `fs_watch` syscall, which gives them a file
+/// handle each change can be read from as a line of text.
+pub trait FsWatcher: fmt::Debug + Send + Sync {
+    /// Get a stream of the changes to `path` and, if it is a directory,
+    /// everything inside it.
+    fn subscribe(&self, path: &Path) -> BoxStream<'static, FsEvent>;
+
+    /// Called whenever a guest changes the filesystem.
+    ///
+    /// `event.path` is absolute and has been lexically normalized.
+    fn notify(&self, event: FsEvent) {
+        let _ = event;
+    }
+}
+
+/// A change to the filesystem.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FsEvent {
+    pub path: PathBuf,
+    pub kind: FsEventKind,
+}
+
+impl FsEvent {
+    pub fn new(path: impl Into<PathBuf>, kind: FsEventKind) -> Self {
+        FsEvent {
+            path: path.into(),
+            kind,
+        }
+    }
+}
+
+/// Formats the event the way guests read it, e.g. `created /home/notes.txt`.
+impl fmt::Display for FsEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            FsEventKind::Created => "created",
+            FsEventKind::Modified => "modified",
+            FsEventKind::Removed => "removed",
+        };
+        write!(f, "{kind} {}", self.path.display())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FsEventKind {
+    /// A file or directory was created.
+    Created,
+    /// A file's contents were changed.
+    Modified,
+    /// A file or directory was removed.
+    Removed,
+}
+
+/// An [`FsWatcher`] which only reports the changes guests make themselves,
+/// suitable for in-memory filesystems.
+#[derive(Debug, Default)]
+pub struct InMemoryFsWatcher {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    path: PathBuf,
+    sender: mpsc::UnboundedSender<FsEvent>,
+}
+
+impl InMemoryFsWatcher {
+    pub fn new() -> Self {
+        InMemoryFsWatcher::default()
+    }
+
+    /// The number of streams which are still subscribed to changes.
+    pub fn subscriber_count(&self) -> usize {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|s| !s.sender.is_closed());
+        subscribers.len()
+    }
+}
+
+impl FsWatcher for InMemoryFsWatcher {
+    fn subscribe(&self, path: &Path) -> BoxStream<'static, FsEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers.lock().unwrap().push(Subscriber {
+            path: path.to_path_buf(),
+            sender,
+        });
+        receiver.boxed()
+    }
+
+    fn notify(&self, event: FsEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        // Streams which have been dropped are cleaned up as we go
+        subscribers.retain(|subscriber| {
+            if !event.path.starts_with(&subscriber.path) {
+                return !subscriber.sender.is_closed();
+            }
+            subscriber.sender.unbounded_send(event.clone()).is_ok()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn subscribers_only_see_changes_under_their_path() {
+        let watcher = InMemoryFsWatcher::new();
+        let mut home = watcher.subscribe(Path::new("/home"));
+        let mut file = watcher.subscribe(Path::new("/home/notes.txt"));
+
+        watcher.notify(FsEvent::new("/home/notes.txt", FsEventKind::Modified));
+        watcher.notify(FsEvent::new("/home/todo.txt", FsEventKind::Created));
+        watcher.notify(FsEvent::new("/tmp/x", FsEventKind::Removed));
+
+        assert_eq!(
+            home.next().now_or_never().flatten(),
+            Some(FsEvent::new("/home/notes.txt", FsEventKind::Modified))
+        );
+        assert_eq!(
+            home.next().now_or_never().flatten(),
+            Some(FsEvent::new("/home/todo.txt", FsEventKind::Created))
+        );
+        assert_eq!(home.next().now_or_never(), None);
+        assert_eq!(
+            file.next().now_or_never().flatten(),
+            Some(FsEvent::new("/home/notes.txt", FsEventKind::Modified))
+        );
+        assert_eq!(file.next().now_or_never(), None);
+
+        drop(home);
+        assert_eq!(watcher.subscriber_count(), 1);
+    }
+
+    #[test]
+    fn events_are_formatted_as_lines_for_guests() {
+        let event = FsEvent::new("/home/notes.txt", FsEventKind::Created);
+        assert_eq!(event.to_string(), "created /home/notes.txt");
+
+        let event = FsEvent::new("/tmp", FsEventKind::Removed);
+        assert_eq!(event.to_string(), "removed /tmp");
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+
+/// The severity of a guest log record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// The level a guest passed to the `log_write` syscall, where `0` is
+    /// [`LogLevel::Error`] and `4` is [`LogLevel::Trace`].
+    pub(crate) fn from_guest(level: u32) -> Option<Self> {
+        match level {
+            0 => Some(LogLevel::Error),
+            1 => Some(LogLevel::Warn),
+            2 => Some(LogLevel::Info),
+            3 => Some(LogLevel::Debug),
+            4 => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+}
+
+/// A destination for structured log output produced by guests.
+///
+/// Unlike stdout and stderr, records sent to a [`LogSink`] carry a level and
+/// key-value fields, letting the host filter and route them.
+pub trait LogSink: fmt::Debug + Send + Sync {
+    fn record(&self, level: LogLevel, msg: &str, fields: &[(&str, &str)]);
+}
+
+/// A [`LogSink`] which emits every record as a [`tracing`] event under the
+/// `wasmer_wasix::guest` target.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TracingLogSink;
+
+impl LogSink for TracingLogSink {
+    fn record(&self, level: LogLevel, msg: &str, fields: &[(&str, &str)]) {
+        let fields = Fields(fields);
+        match level {
+            LogLevel::Error => tracing::error!(target: "wasmer_wasix::guest", %fields, "{msg}"),
+            LogLevel::Warn => tracing::warn!(target: "wasmer_wasix::guest", %fields, "{msg}"),
+            LogLevel::Info => tracing::info!(target: "wasmer_wasix::guest", %fields, "{msg}"),
+            LogLevel::Debug => tracing::debug!(target: "wasmer_wasix::guest", %fields, "{msg}"),
+            LogLevel::Trace => tracing::trace!(target: "wasmer_wasix::guest", %fields, "{msg}"),
+        }
+    }
+}
+
+/// Renders guest fields as `key=value` pairs, because [`tracing`] only
+/// supports field names known at compile time.
+struct Fields<'a>(&'a [(&'a str, &'a str)]);
+
+impl fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{key}={value:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the fields a guest passed to the `log_write` syscall, which are
+/// `key=value` pairs separated by newlines.
+///
+/// Returns `None` if a field has no `=` or an empty key.
+pub(crate) fn parse_guest_fields(fields: &str) -> Option<Vec<(&str, &str)>> {
+    fields
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Some((key, value)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guest_records_are_parsed() {
+        assert_eq!(LogLevel::from_guest(0), Some(LogLevel::Error));
+        assert_eq!(LogLevel::from_guest(4), Some(LogLevel::Trace));
+        assert_eq!(LogLevel::from_guest(5), None);
+
+        assert_eq!(
+            parse_guest_fields("user=alice\nrequest=a=b\n"),
+            Some(vec![("user", "alice"), ("request", "a=b")])
+        );
+        assert_eq!(parse_guest_fields(""), Some(Vec::new()));
+        assert_eq!(parse_guest_fields("oops"), None);
+        assert_eq!(parse_guest_fields("=value"), None);
+    }
+}
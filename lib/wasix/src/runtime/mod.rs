@@ -1,19 +1,61 @@
+pub mod caching_resolver;
+pub mod clock;
+pub mod describe;
+pub mod env_filter;
+pub mod faulty_networking;
+pub mod features;
+pub mod fs_access;
+pub mod fs_watcher;
+pub mod log_sink;
 pub mod module_cache;
 pub mod package_loader;
+pub mod pid_allocator;
+pub mod recording;
 pub mod resolver;
+pub mod rng;
+pub mod secrets;
+pub mod shared_region;
 pub mod task_manager;
 
-pub use self::task_manager::{SpawnMemoryType, VirtualTaskManager};
+pub use self::{
+    caching_resolver::{CachingResolver, DnsCacheConfig},
+    clock::{HostClock, SkewingClock, VirtualClock},
+    describe::{ConfigFormat, DescribeError, RuntimeDescription, UnknownConfigFormat},
+    env_filter::EnvFilter,
+    faulty_networking::{FaultConfig, FaultDelay, FaultyNetworking},
+    features::FeatureFlags,
+    fs_access::{Decision, FsAccessPolicy, OpenMode},
+    fs_watcher::{FsEvent, FsEventKind, FsWatcher, InMemoryFsWatcher},
+    log_sink::{LogLevel, LogSink, TracingLogSink},
+    pid_allocator::{MonotonicPidAllocator, PidAllocator},
+    recording::{RecordingRuntime, ReplayRuntime, RuntimeTrace},
+    rng::{EntropyFallback, RandomDeviceFile, SeededRng, VirtualRng},
+    secrets::{InMemorySecretStore, SecretBytes, SecretStore},
+    shared_region::{SharedRegion, SharedRegionFile},
+    task_manager::{
+        AffinityKey, PriorityTaskManager, SpawnMemoryType, TaskId, TaskPriority,
+        ThreadAffinityTaskManager, VirtualTaskManager,
+    },
+};
 use self::{module_cache::CacheError, task_manager::InlineWaker};
 use wasmer_types::ModuleHash;
 
 use std::{
+    collections::HashMap,
     fmt,
     ops::Deref,
-    sync::{Arc, Mutex},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
 
 use futures::future::BoxFuture;
+use url::Url;
+use uuid::Uuid;
+use virtual_fs::LineEnding;
 use virtual_net::{DynVirtualNetworking, VirtualNetworking};
 use wasmer::{Module, RuntimeError};
 use wasmer_wasix_types::wasi::ExitCode;
@@ -21,8 +63,8 @@ use wasmer_wasix_types::wasi::ExitCode;
 #[cfg(feature = "journal")]
 use crate::journal::DynJournal;
 use crate::{
-    http::{DynHttpClient, HttpClient},
-    os::TtyBridge,
+    http::{DynHttpClient, HttpClient, RoutedHttpClient, TraceContext, TraceContextHttpClient},
+    os::{task::thread::WasiThreadError, TtyBridge},
     runtime::{
         module_cache::{ModuleCache, ThreadLocalCache},
         package_loader::{PackageLoader, UnsupportedPackageLoader},
@@ -38,6 +80,102 @@ pub enum TaintReason {
     RuntimeError(RuntimeError),
 }
 
+/// How much memory guests may use, as configured by the embedder.
+///
+/// This is purely informational so well-behaved guests can size their heaps
+/// accordingly. The hard cap should be enforced separately, e.g. with
+/// [`wasmer::Tunables`] that limit the maximum memory size. Guests read
+/// their limits with the `memory_limits` syscall, which also takes that cap
+/// into account (see [`MemoryLimits::for_memory()`]).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MemoryLimits {
+    /// The total number of bytes a guest may use.
+    pub total_bytes: u64,
+    /// The number of bytes which are still available to the guest.
+    pub available_bytes: u64,
+}
+
+impl MemoryLimits {
+    /// Limits where all of `total_bytes` is still available.
+    pub fn new(total_bytes: u64) -> Self {
+        MemoryLimits {
+            total_bytes,
+            available_bytes: total_bytes,
+        }
+    }
+
+    /// The limits for a guest which is using `used_bytes` of a memory that
+    /// can grow to at most `maximum_bytes`.
+    ///
+    /// The memory's maximum is where the cap from the engine's
+    /// [`wasmer::Tunables`] shows up, so the total is the smaller of it and
+    /// the `configured` limits. Returns `None` when neither is known.
+    pub fn for_memory(
+        configured: Option<MemoryLimits>,
+        maximum_bytes: Option<u64>,
+        used_bytes: u64,
+    ) -> Option<MemoryLimits> {
+        let total_bytes = match (configured, maximum_bytes) {
+            (Some(configured), Some(maximum)) => configured.total_bytes.min(maximum),
+            (Some(configured), None) => configured.total_bytes,
+            (None, Some(maximum)) => maximum,
+            (None, None) => return None,
+        };
+
+        Some(MemoryLimits {
+            total_bytes,
+            available_bytes: total_bytes.saturating_sub(used_bytes),
+        })
+    }
+}
+
+/// How many syscalls a guest may make, enforced with a token bucket.
+///
+/// Guests which go over the limit aren't failed, their syscalls are just
+/// delayed until enough tokens have been refilled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RateLimit {
+    /// The number of syscalls a guest may make each second.
+    pub per_second: u32,
+    /// The number of syscalls a guest may make in a quick burst before it
+    /// gets throttled.
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// A limit which allows bursts of up to one second's worth of syscalls.
+    pub fn new(per_second: u32) -> Self {
+        RateLimit {
+            per_second,
+            burst: per_second,
+        }
+    }
+
+    pub fn with_burst(self, burst: u32) -> Self {
+        RateLimit { burst, ..self }
+    }
+}
+
+/// An error from [`Runtime::new_store_async()`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum StoreCreationError {
+    /// Creating the store took longer than the runtime allows.
+    #[error("creating the store timed out after {timeout:?}")]
+    TimedOut { timeout: Duration },
+    /// The store couldn't be created on a background thread.
+    #[error("unable to create the store")]
+    Thread(#[source] WasiThreadError),
+}
+
+impl From<StoreCreationError> for WasiThreadError {
+    fn from(err: StoreCreationError) -> Self {
+        match err {
+            StoreCreationError::TimedOut { .. } => WasiThreadError::TimedOut,
+            StoreCreationError::Thread(err) => err,
+        }
+    }
+}
+
 /// Runtime components used when running WebAssembly programs.
 ///
 /// Think of this as the "System" in "WebAssembly Systems Interface".
@@ -49,9 +187,25 @@ where
     /// Provides access to all the networking related functions such as sockets.
     fn networking(&self) -> &DynVirtualNetworking;
 
+    /// The name of the networking backend (e.g. `"host"` or
+    /// `"unsupported"`), for diagnosing why a guest can't connect.
+    ///
+    /// This is included in [`PluggableRuntime::describe()`], the
+    /// `NETWORKING` [`FeatureFlags`], and the tracing spans for running
+    /// guests and their `sock_connect` calls.
+    fn networking_kind(&self) -> &'static str {
+        self.networking().backend_name()
+    }
+
     /// Retrieve the active [`VirtualTaskManager`].
     fn task_manager(&self) -> &Arc<dyn VirtualTaskManager>;
 
+    /// Retrieve an owned handle to the active [`VirtualTaskManager`], which
+    /// is convenient when it needs to be moved into a `'static` future.
+    fn task_manager_owned(&self) -> Arc<dyn VirtualTaskManager> {
+        self.task_manager().clone()
+    }
+
     /// A package loader.
     fn package_loader(&self) -> Arc<dyn PackageLoader + Send + Sync> {
         Arc::new(UnsupportedPackageLoader)
@@ -87,16 +241,202 @@ where
         }
     }
 
+    /// Create a new [`wasmer::Store`] without blocking the caller.
+    ///
+    /// Runtimes may give up with a [`StoreCreationError::TimedOut`] error if
+    /// creating the store takes too long (e.g. because the engine is still
+    /// warming up). This is how the stores for new instances are created by
+    /// the runners and when guests spawn processes or threads.
+    fn new_store_async(&self) -> BoxFuture<'_, Result<wasmer::Store, StoreCreationError>> {
+        let store = self.new_store();
+        Box::pin(async move { Ok(store) })
+    }
+
     /// Get a custom HTTP client
     fn http_client(&self) -> Option<&DynHttpClient> {
         None
     }
 
+    /// Get the HTTP client that should be used when sending requests to a
+    /// particular URL.
+    ///
+    /// This lets credentials be scoped to specific hosts (e.g. only sending
+    /// an auth token to the registry). By default the URL is ignored and
+    /// [`Runtime::http_client()`] is used.
+    fn http_client_for(&self, url: &Url) -> Option<&DynHttpClient> {
+        self.http_client()
+    }
+
     /// Get access to the TTY used by the environment.
     fn tty(&self) -> Option<&(dyn TtyBridge + Send + Sync)> {
         None
     }
 
+    /// The distributed tracing context that outgoing HTTP requests should be
+    /// stitched into, if any.
+    fn trace_context(&self) -> Option<TraceContext> {
+        None
+    }
+
+    /// Where structured log output from guests should be sent.
+    ///
+    /// Guests send records with the `log_write` syscall. When this returns
+    /// `None`, that fails with `Errno::Notsup` and guests only have stdout
+    /// and stderr.
+    fn log_sink(&self) -> Option<&dyn LogSink> {
+        None
+    }
+
+    /// A filter applied to the environment variables before they are exposed
+    /// to guests.
+    fn env_filter(&self) -> Option<&dyn EnvFilter> {
+        None
+    }
+
+    /// Named secrets guests may be given access to, kept out of their
+    /// environment variables.
+    ///
+    /// Guests read them with the `secret_get` syscall.
+    fn secrets(&self) -> Option<&dyn SecretStore> {
+        None
+    }
+
+    /// A policy which is consulted every time a guest opens a path, in
+    /// addition to the usual WASI rights checks.
+    fn fs_access_policy(&self) -> Option<&dyn FsAccessPolicy> {
+        None
+    }
+
+    /// Watches the filesystem for changes, so guests can be notified about
+    /// them.
+    fn fs_watcher(&self) -> Option<&dyn FsWatcher> {
+        None
+    }
+
+    /// The source of randomness for guests, used instead of the host's.
+    ///
+    /// This backs both `random_get` and the `/dev/random` and
+    /// `/dev/urandom` device files.
+    fn rng(&self) -> Option<Arc<dyn VirtualRng>> {
+        None
+    }
+
+    /// A seed shared by every instance created with this runtime. Each
+    /// instance derives its own RNG from it and its instance ID (see
+    /// [`SeededRng::for_instance()`]), which is used instead of
+    /// [`Runtime::rng()`].
+    fn rng_seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// What to do when there is no [`Runtime::rng()`] and the host's
+    /// entropy source fails.
+    fn entropy_fallback(&self) -> EntropyFallback {
+        EntropyFallback::default()
+    }
+
+    /// The clocks guests read, instead of the host's.
+    fn clock(&self) -> Option<&dyn VirtualClock> {
+        None
+    }
+
+    /// Assigns the IDs of new processes, instead of the control plane.
+    fn pid_allocator(&self) -> Option<&dyn PidAllocator> {
+        None
+    }
+
+    /// Look up a block of memory shared between the host and guests.
+    fn shared_region(&self, name: &str) -> Option<Arc<SharedRegion>> {
+        None
+    }
+
+    /// An ID assigned to the instances created with this runtime, used to
+    /// tell guests apart in logs and traces.
+    ///
+    /// When this is `None`, every [`WasiEnv`][crate::WasiEnv] generates its
+    /// own (see [`crate::WasiEnvBuilder::set_instance_id()`]).
+    fn instance_id(&self) -> Option<Uuid> {
+        None
+    }
+
+    /// The hostname presented to guests through the `gethostname` syscall
+    /// and the `HOSTNAME` environment variable.
+    ///
+    /// Guests see `localhost` when this is `None`.
+    fn hostname(&self) -> Option<String> {
+        None
+    }
+
+    /// The domain name presented to guests through the `getdomainname`
+    /// syscall.
+    ///
+    /// Guests see an empty domain name when this is `None`.
+    fn domainname(&self) -> Option<String> {
+        None
+    }
+
+    /// The memory limits guests should respect, if any.
+    ///
+    /// Guests see these, narrowed down to the maximum size of their memory,
+    /// through the `memory_limits` syscall.
+    fn memory_limits(&self) -> Option<MemoryLimits> {
+        None
+    }
+
+    /// How quickly guests may make syscalls, if they are limited at all.
+    ///
+    /// Syscalls over the limit are delayed with
+    /// [`VirtualTaskManager::sleep_now()`], using the runtime's
+    /// [`Runtime::clock()`] to refill the guest's budget.
+    fn syscall_rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// The most file descriptors a guest may have open at once, including
+    /// stdio and preopened directories.
+    ///
+    /// Opening more fails with `EMFILE`.
+    fn max_open_fds(&self) -> Option<usize> {
+        None
+    }
+
+    /// Arguments passed to every guest, before the arguments it was
+    /// explicitly started with.
+    fn default_args(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The name guests see as their `argv[0]` (e.g. via `getprogname()`),
+    /// instead of the one they were started with.
+    fn program_name(&self) -> Option<String> {
+        None
+    }
+
+    /// The directory guests start in, unless the [`crate::WasiEnvBuilder`]
+    /// sets one explicitly.
+    fn default_cwd(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// The point in time after which guests should be aborted.
+    ///
+    /// This is checked whenever a guest processes signals (i.e. on most
+    /// blocking syscalls), giving a hard wall-clock limit for runaway guests.
+    /// Guests which are aborted trap with [`crate::WasiError::DeadlineExceeded`].
+    fn execution_deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    /// The optional features guests can use, reported to them by the
+    /// `runtime_features` syscall.
+    ///
+    /// By default this is worked out from the features wasix was compiled
+    /// with and the hooks this runtime provides (see
+    /// [`FeatureFlags::detect()`]).
+    fn feature_flags(&self) -> FeatureFlags {
+        FeatureFlags::detect(self)
+    }
+
     /// Load a a Webassembly module, trying to use a pre-compiled version if possible.
     fn load_module<'a>(&'a self, wasm: &'a [u8]) -> BoxFuture<'a, Result<Module, SpawnError>> {
         let engine = self.engine();
@@ -119,6 +459,28 @@ where
     /// for multiple reasons however the most common is a panic within the process
     fn on_taint(&self, _reason: TaintReason) {}
 
+    /// Register a callback to be invoked when the runtime is shut down (e.g.
+    /// to flush buffers or close files).
+    ///
+    /// Callbacks are run by [`Runtime::shutdown()`] in the reverse order
+    /// they were registered. Runtimes which don't support shutdown callbacks
+    /// will drop `f` without calling it.
+    fn on_shutdown(&self, f: ShutdownCallback) {}
+
+    /// Tear down the runtime, running any callbacks registered with
+    /// [`Runtime::on_shutdown()`].
+    fn shutdown(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async {})
+    }
+
+    /// Create a new handle to this runtime which shares all of its underlying
+    /// resources (task manager, networking, caches, etc.). This is what each
+    /// guest thread is given when it is spawned.
+    ///
+    /// This should be cheap, so runtimes should keep their components behind
+    /// [`Arc`]s and only clone the [`Arc`]s.
+    fn shallow_clone(&self) -> Arc<DynRuntime>;
+
     /// The list of journals which will be used to restore the state of the
     /// runtime at a particular point in time
     #[cfg(feature = "journal")]
@@ -136,6 +498,41 @@ where
 
 pub type DynRuntime = dyn Runtime + Send + Sync;
 
+/// A callback registered with [`Runtime::on_shutdown()`].
+pub type ShutdownCallback = Box<dyn FnOnce() + Send>;
+
+/// A queue of [`ShutdownCallback`]s which are run in LIFO order.
+#[derive(Default)]
+pub struct ShutdownCallbacks(Mutex<Vec<ShutdownCallback>>);
+
+impl ShutdownCallbacks {
+    pub fn push(&self, f: ShutdownCallback) {
+        self.0.lock().unwrap().push(f);
+    }
+
+    /// Run (and remove) every queued callback, most recently registered
+    /// first.
+    pub fn run(&self) {
+        // Note: callbacks are popped one at a time so they can register
+        // more callbacks without deadlocking.
+        loop {
+            let Some(f) = self.0.lock().unwrap().pop() else {
+                break;
+            };
+            f();
+        }
+    }
+}
+
+impl fmt::Debug for ShutdownCallbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.0.lock().map(|callbacks| callbacks.len()).unwrap_or(0);
+        f.debug_struct("ShutdownCallbacks")
+            .field("len", &len)
+            .finish()
+    }
+}
+
 #[cfg(feature = "journal")]
 static EMPTY_JOURNAL_LIST: Vec<Arc<DynJournal>> = Vec::new();
 
@@ -183,6 +580,7 @@ pub async fn load_module(
 #[derive(Debug, Default)]
 pub struct DefaultTty {
     state: Mutex<WasiTtyState>,
+    bracketed_paste: AtomicBool,
 }
 
 impl TtyBridge for DefaultTty {
@@ -202,8 +600,37 @@ impl TtyBridge for DefaultTty {
         let mut state = self.state.lock().unwrap();
         *state = tty_state;
     }
+
+    fn set_raw_mode(&self, enabled: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.echo = !enabled;
+        state.line_buffered = !enabled;
+    }
+
+    /// Nothing translates the output of a [`DefaultTty`], so `line_feeds`
+    /// is emulated by writing `\r\n`s.
+    fn line_ending(&self) -> LineEnding {
+        if self.state.lock().unwrap().line_feeds {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Passthrough
+        }
+    }
+
+    fn set_bracketed_paste(&self, enabled: bool) {
+        self.bracketed_paste.store(enabled, Ordering::SeqCst);
+    }
+
+    fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste.load(Ordering::SeqCst)
+    }
 }
 
+/// A [`Runtime`] assembled from individually configurable components.
+///
+/// The components are reference-counted, so cloning a [`PluggableRuntime`] is
+/// cheap and the clone shares its task manager, networking, caches, etc. with
+/// the original.
 #[derive(Debug, Clone)]
 pub struct PluggableRuntime {
     pub rt: Arc<dyn VirtualTaskManager>,
@@ -214,8 +641,48 @@ pub struct PluggableRuntime {
     pub engine: Option<wasmer::Engine>,
     pub module_cache: Arc<dyn ModuleCache + Send + Sync>,
     pub tty: Option<Arc<dyn TtyBridge + Send + Sync>>,
+    pub trace_context: Arc<RwLock<Option<TraceContext>>>,
+    /// HTTP clients used for specific hosts, checked in order before falling
+    /// back to [`PluggableRuntime::http_client`].
+    ///
+    /// A host of the form `*.example.com` matches all subdomains of
+    /// `example.com`.
+    pub http_client_routes: Vec<(String, DynHttpClient)>,
+    pub deadline: Option<Instant>,
+    /// Assigned with [`PluggableRuntime::set_instance_id()`]. Otherwise each
+    /// instance generates its own.
+    pub instance_id: Option<Uuid>,
+    pub hostname: Option<String>,
+    pub domainname: Option<String>,
+    pub log_sink: Option<Arc<dyn LogSink>>,
+    pub env_filter: Option<Arc<dyn EnvFilter>>,
+    pub secrets: Option<Arc<dyn SecretStore>>,
+    pub fs_access_policy: Option<Arc<dyn FsAccessPolicy>>,
+    pub fs_watcher: Option<Arc<dyn FsWatcher>>,
+    pub rng: Option<Arc<dyn VirtualRng>>,
+    /// The seed [`PluggableRuntime::rng`] was derived from, if it was set
+    /// with [`PluggableRuntime::set_rng_seed()`].
+    pub rng_seed: Option<u64>,
+    pub entropy_fallback: EntropyFallback,
+    pub shared_regions: HashMap<String, Arc<SharedRegion>>,
+    pub clock: Option<Arc<dyn VirtualClock>>,
+    pub pid_allocator: Option<Arc<dyn PidAllocator>>,
+    pub memory_limits: Option<MemoryLimits>,
+    pub max_open_fds: Option<usize>,
+    pub syscall_rate_limit: Option<RateLimit>,
+    pub store_creation_timeout: Option<Duration>,
+    pub default_args: Vec<String>,
+    pub program_name: Option<String>,
+    pub default_cwd: Option<PathBuf>,
+    pub shutdown_callbacks: Arc<ShutdownCallbacks>,
     #[cfg(feature = "journal")]
     pub journals: Vec<Arc<DynJournal>>,
+    /// Whether [`PluggableRuntime::source`] is still the default registry
+    /// source, which is rebuilt when the HTTP clients change.
+    default_source: bool,
+    /// Whether [`PluggableRuntime::http_client`] is still the default client,
+    /// which [`PluggableRuntime::set_user_agent()`] may replace.
+    default_http_client: bool,
 }
 
 impl PluggableRuntime {
@@ -228,31 +695,93 @@ impl PluggableRuntime {
                 let networking = Arc::new(virtual_net::UnsupportedVirtualNetworking::default());
             }
         }
-        let http_client =
-            crate::http::default_http_client().map(|client| Arc::new(client) as DynHttpClient);
+        let trace_context = Arc::new(RwLock::new(None));
+        let http_client = crate::http::default_http_client().map(|client| {
+            Arc::new(TraceContextHttpClient::new(
+                Arc::new(client),
+                trace_context.clone(),
+            )) as DynHttpClient
+        });
 
         let loader = UnsupportedPackageLoader;
 
-        let mut source = MultiSource::default();
-        if let Some(client) = &http_client {
-            source.add_source(BackendSource::new(
-                BackendSource::WASMER_PROD_ENDPOINT.parse().unwrap(),
-                client.clone(),
-            ));
-        }
-
-        Self {
+        let mut runtime = Self {
             rt,
             networking,
             http_client,
             engine: None,
             tty: None,
-            source: Arc::new(source),
+            trace_context,
+            http_client_routes: Vec::new(),
+            deadline: None,
+            instance_id: None,
+            hostname: None,
+            domainname: None,
+            log_sink: None,
+            env_filter: None,
+            secrets: None,
+            fs_access_policy: None,
+            fs_watcher: None,
+            rng: None,
+            rng_seed: None,
+            entropy_fallback: EntropyFallback::default(),
+            shared_regions: HashMap::new(),
+            clock: None,
+            pid_allocator: None,
+            memory_limits: None,
+            max_open_fds: None,
+            syscall_rate_limit: None,
+            store_creation_timeout: None,
+            default_args: Vec::new(),
+            program_name: None,
+            default_cwd: None,
+            shutdown_callbacks: Arc::default(),
+            source: Arc::new(MultiSource::default()),
             package_loader: Arc::new(loader),
             module_cache: Arc::new(module_cache::in_memory()),
             #[cfg(feature = "journal")]
             journals: Vec::new(),
+            default_source: true,
+            default_http_client: true,
+        };
+        runtime.refresh_default_source();
+
+        runtime
+    }
+
+    /// Point the default registry source at the current HTTP clients.
+    fn refresh_default_source(&mut self) {
+        if !self.default_source {
+            return;
+        }
+
+        let mut source = MultiSource::default();
+        if let Some(client) = self.routed_http_client() {
+            source.add_source(BackendSource::new(
+                BackendSource::WASMER_PROD_ENDPOINT.parse().unwrap(),
+                client,
+            ));
         }
+        self.source = Arc::new(source);
+    }
+
+    /// A client which sends each request with the client
+    /// [`Runtime::http_client_for()`] picks for its URL, for components which
+    /// make requests to arbitrary URLs (e.g. a
+    /// [`BuiltinPackageLoader`][crate::runtime::package_loader::BuiltinPackageLoader]).
+    ///
+    /// The client is a snapshot, so it doesn't see clients and routes set
+    /// after it was created.
+    pub fn routed_http_client(&self) -> Option<DynHttpClient> {
+        if self.http_client.is_none() && self.http_client_routes.is_empty() {
+            return None;
+        }
+
+        let mut client = RoutedHttpClient::new(self.http_client.clone());
+        for (pattern, route) in &self.http_client_routes {
+            client.add_route(pattern.clone(), route.clone());
+        }
+        Some(Arc::new(client))
     }
 
     pub fn set_networking_implementation<I>(&mut self, net: I) -> &mut Self
@@ -283,6 +812,7 @@ impl PluggableRuntime {
 
     pub fn set_source(&mut self, source: impl Source + Send + 'static) -> &mut Self {
         self.source = Arc::new(source);
+        self.default_source = false;
         self
     }
 
@@ -298,7 +828,286 @@ impl PluggableRuntime {
         &mut self,
         client: impl HttpClient + Send + Sync + 'static,
     ) -> &mut Self {
-        self.http_client = Some(Arc::new(client));
+        self.replace_http_client(client);
+        self.default_http_client = false;
+        self
+    }
+
+    fn replace_http_client(&mut self, client: impl HttpClient + Send + Sync + 'static) {
+        self.http_client = Some(Arc::new(TraceContextHttpClient::new(
+            Arc::new(client),
+            self.trace_context.clone(),
+        )));
+        self.refresh_default_source();
+    }
+
+    /// Use a specific instance ID (e.g. one assigned by an orchestrator)
+    /// instead of letting each instance generate its own.
+    ///
+    /// Every instance created with this runtime shares the ID, so this is
+    /// meant for runtimes which only run one guest.
+    pub fn set_instance_id(&mut self, instance_id: Uuid) -> &mut Self {
+        self.instance_id = Some(instance_id);
+        self
+    }
+
+    pub fn set_hostname(&mut self, hostname: impl Into<String>) -> &mut Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    pub fn set_domainname(&mut self, domainname: impl Into<String>) -> &mut Self {
+        self.domainname = Some(domainname.into());
+        self
+    }
+
+    /// Route structured guest logs to `sink` (e.g. a [`TracingLogSink`]).
+    pub fn set_log_sink(&mut self, sink: Arc<dyn LogSink>) -> &mut Self {
+        self.log_sink = Some(sink);
+        self
+    }
+
+    /// Rewrite or hide environment variables before guests can see them.
+    pub fn set_env_filter(&mut self, filter: Arc<dyn EnvFilter>) -> &mut Self {
+        self.env_filter = Some(filter);
+        self
+    }
+
+    /// Make the secrets in `store` available to guests.
+    pub fn set_secrets(&mut self, store: Arc<dyn SecretStore>) -> &mut Self {
+        self.secrets = Some(store);
+        self
+    }
+
+    /// Check every path a guest opens against `policy`.
+    pub fn set_fs_access_policy(&mut self, policy: Arc<dyn FsAccessPolicy>) -> &mut Self {
+        self.fs_access_policy = Some(policy);
+        self
+    }
+
+    /// Report filesystem changes to `watcher`, and let guests watch for
+    /// changes through it.
+    pub fn set_fs_watcher(&mut self, watcher: Arc<dyn FsWatcher>) -> &mut Self {
+        self.fs_watcher = Some(watcher);
+        self
+    }
+
+    /// Give guests random bytes from `rng` instead of the host's RNG.
+    pub fn set_rng(&mut self, rng: Arc<dyn VirtualRng>) -> &mut Self {
+        self.rng = Some(rng);
+        self.rng_seed = None;
+        self
+    }
+
+    /// Give each guest deterministic random bytes derived from `seed` and
+    /// its instance ID (see [`SeededRng::for_instance()`]).
+    ///
+    /// Instances sharing a seed are each reproducible on their own without
+    /// all producing the same bytes. The runtime's own [`Runtime::rng()`]
+    /// is seeded with `seed` directly.
+    pub fn set_rng_seed(&mut self, seed: u64) -> &mut Self {
+        self.rng = Some(Arc::new(SeededRng::new(seed)));
+        self.rng_seed = Some(seed);
+        self
+    }
+
+    /// Choose what happens when the host's entropy source fails. This is
+    /// ignored if an RNG has been set.
+    pub fn set_entropy_fallback(&mut self, fallback: EntropyFallback) -> &mut Self {
+        self.entropy_fallback = fallback;
+        self
+    }
+
+    /// Describe how this runtime has been configured.
+    pub fn describe(&self) -> RuntimeDescription {
+        let features = self.feature_flags().to_string();
+        let hooks = [
+            ("log-sink", self.log_sink.is_some()),
+            ("env-filter", self.env_filter.is_some()),
+            ("secrets", self.secrets.is_some()),
+            ("fs-access-policy", self.fs_access_policy.is_some()),
+            ("fs-watcher", self.fs_watcher.is_some()),
+            ("clock", self.clock.is_some()),
+            ("pid-allocator", self.pid_allocator.is_some()),
+        ];
+        let rng = match (&self.rng, self.rng_seed) {
+            (None, _) => "host",
+            (Some(_), Some(_)) => "seeded",
+            (Some(_), None) => "custom",
+        };
+        let mut shared_regions: Vec<_> = self.shared_regions.keys().cloned().collect();
+        shared_regions.sort();
+
+        RuntimeDescription {
+            networking: self.networking_kind().to_string(),
+            features: features
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect(),
+            engine: self
+                .engine
+                .as_ref()
+                .map(|engine| engine.deterministic_id().to_string()),
+            http_client_routes: self
+                .http_client_routes
+                .iter()
+                .map(|(host, _)| host.clone())
+                .collect(),
+            instance_id: self.instance_id.map(|id| id.to_string()),
+            hostname: self.hostname.clone(),
+            domainname: self.domainname.clone(),
+            hooks: hooks
+                .into_iter()
+                .filter(|(_, installed)| *installed)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            rng: rng.to_string(),
+            entropy_fallback: self.entropy_fallback,
+            shared_regions,
+            max_open_fds: self.max_open_fds,
+            store_creation_timeout_ms: self
+                .store_creation_timeout
+                .map(|timeout| timeout.as_millis() as u64),
+            default_args: self.default_args.clone(),
+            program_name: self.program_name.clone(),
+            default_cwd: self.default_cwd.clone(),
+            memory_limits: self.memory_limits,
+            syscall_rate_limit: self.syscall_rate_limit,
+        }
+    }
+
+    /// [`PluggableRuntime::describe()`] this runtime, serialized as JSON,
+    /// YAML or TOML.
+    pub fn describe_as(&self, format: ConfigFormat) -> Result<String, DescribeError> {
+        self.describe().to_string_as(format)
+    }
+
+    /// Make `region` available to guests which map it by `name`.
+    pub fn add_shared_region(
+        &mut self,
+        name: impl Into<String>,
+        region: Arc<SharedRegion>,
+    ) -> &mut Self {
+        self.shared_regions.insert(name.into(), region);
+        self
+    }
+
+    /// Let guests read the time from `clock` instead of the host's clocks.
+    pub fn set_clock(&mut self, clock: Arc<dyn VirtualClock>) -> &mut Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Give guests process IDs from `allocator` (e.g. a
+    /// [`MonotonicPidAllocator`]), so they are the same however the
+    /// processes were created.
+    ///
+    /// Clones of this runtime share the allocator, so use a separate
+    /// runtime for each sandbox whose process IDs should be independent.
+    pub fn set_pid_allocator(&mut self, allocator: Arc<dyn PidAllocator>) -> &mut Self {
+        self.pid_allocator = Some(allocator);
+        self
+    }
+
+    /// Tell guests how much memory they may use.
+    ///
+    /// Guests are never told they have more than the maximum size of their
+    /// memory, so a cap enforced by the engine's tunables is picked up
+    /// automatically. This only needs to be set to report a lower limit.
+    pub fn set_memory_limits(&mut self, limits: Option<MemoryLimits>) -> &mut Self {
+        self.memory_limits = limits;
+        self
+    }
+
+    /// Limit how many file descriptors each guest may have open at once.
+    pub fn set_max_open_fds(&mut self, max_open_fds: Option<usize>) -> &mut Self {
+        self.max_open_fds = max_open_fds;
+        self
+    }
+
+    /// Limit how quickly each guest may make syscalls.
+    pub fn set_syscall_rate_limit(&mut self, limit: Option<RateLimit>) -> &mut Self {
+        self.syscall_rate_limit = limit;
+        self
+    }
+
+    /// Give up on [`Runtime::new_store_async()`] if creating a store takes
+    /// longer than `timeout`.
+    pub fn set_store_creation_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.store_creation_timeout = timeout;
+        self
+    }
+
+    /// Pass `args` to every guest, before its own arguments.
+    pub fn set_default_args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.default_args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Override the `argv[0]` every guest is started with.
+    pub fn set_program_name(&mut self, program_name: impl Into<String>) -> &mut Self {
+        self.program_name = Some(program_name.into());
+        self
+    }
+
+    /// Start guests in `dir` unless they are given a working directory
+    /// explicitly.
+    pub fn set_default_cwd(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.default_cwd = Some(dir.into());
+        self
+    }
+
+    /// Abort guests which are still running once `deadline` has passed.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) -> &mut Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Send `user_agent` as the `User-Agent` header of requests made with
+    /// the default HTTP client.
+    ///
+    /// A client set with [`PluggableRuntime::set_http_client()`] is never
+    /// replaced, so its user agent needs to be configured on the client
+    /// itself.
+    #[cfg(feature = "host-reqwest")]
+    pub fn set_user_agent(&mut self, user_agent: impl Into<String>) -> &mut Self {
+        if self.default_http_client {
+            let client =
+                crate::http::reqwest::ReqwestHttpClient::default().with_user_agent(user_agent);
+            self.replace_http_client(client);
+        }
+        self
+    }
+
+    /// Use a different HTTP client for requests sent to `host`.
+    ///
+    /// Routes are checked in the order they were added.
+    pub fn add_http_client_route(
+        &mut self,
+        host: impl Into<String>,
+        client: impl HttpClient + Send + Sync + 'static,
+    ) -> &mut Self {
+        let client = Arc::new(TraceContextHttpClient::new(
+            Arc::new(client),
+            self.trace_context.clone(),
+        ));
+        self.http_client_routes.push((host.into(), client));
+        self.refresh_default_source();
+        self
+    }
+
+    /// Set the trace context that outgoing HTTP requests will be stitched
+    /// into.
+    ///
+    /// This takes `&self` so embedders can update the context per-operation
+    /// on a runtime that has already been shared.
+    pub fn set_trace_context(&self, context: Option<TraceContext>) -> &Self {
+        *self.trace_context.write().unwrap() = context;
         self
     }
 
@@ -318,6 +1127,10 @@ impl Runtime for PluggableRuntime {
         self.http_client.as_ref()
     }
 
+    fn http_client_for(&self, url: &Url) -> Option<&DynHttpClient> {
+        crate::http::find_route(&self.http_client_routes, url).or(self.http_client())
+    }
+
     fn package_loader(&self) -> Arc<dyn PackageLoader + Send + Sync> {
         Arc::clone(&self.package_loader)
     }
@@ -337,6 +1150,38 @@ impl Runtime for PluggableRuntime {
             .unwrap_or_default()
     }
 
+    fn new_store_async(&self) -> BoxFuture<'_, Result<wasmer::Store, StoreCreationError>> {
+        let Some(timeout) = self.store_creation_timeout else {
+            let store = self.new_store();
+            return Box::pin(async move { Ok(store) });
+        };
+
+        // The store is created on the thread pool so a stalled engine only
+        // ties up a background thread rather than the caller.
+        let engine = self.engine.clone();
+        let store = Arc::new(Mutex::new(None));
+        let created = Arc::clone(&store);
+        let work = self.rt.run_blocking_with_timeout(
+            Box::new(move || {
+                let store = engine.map(wasmer::Store::new).unwrap_or_default();
+                *created.lock().unwrap() = Some(store);
+            }),
+            timeout,
+        );
+
+        Box::pin(async move {
+            match work.await {
+                Ok(()) => Ok(store
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("the store is always created before the task completes")),
+                Err(WasiThreadError::TimedOut) => Err(StoreCreationError::TimedOut { timeout }),
+                Err(e) => Err(StoreCreationError::Thread(e)),
+            }
+        })
+    }
+
     fn task_manager(&self) -> &Arc<dyn VirtualTaskManager> {
         &self.rt
     }
@@ -345,6 +1190,94 @@ impl Runtime for PluggableRuntime {
         self.tty.as_deref()
     }
 
+    fn trace_context(&self) -> Option<TraceContext> {
+        *self.trace_context.read().unwrap()
+    }
+
+    fn log_sink(&self) -> Option<&dyn LogSink> {
+        self.log_sink.as_deref()
+    }
+
+    fn env_filter(&self) -> Option<&dyn EnvFilter> {
+        self.env_filter.as_deref()
+    }
+
+    fn secrets(&self) -> Option<&dyn SecretStore> {
+        self.secrets.as_deref()
+    }
+
+    fn fs_access_policy(&self) -> Option<&dyn FsAccessPolicy> {
+        self.fs_access_policy.as_deref()
+    }
+
+    fn fs_watcher(&self) -> Option<&dyn FsWatcher> {
+        self.fs_watcher.as_deref()
+    }
+
+    fn rng(&self) -> Option<Arc<dyn VirtualRng>> {
+        self.rng.clone()
+    }
+
+    fn rng_seed(&self) -> Option<u64> {
+        self.rng_seed
+    }
+
+    fn entropy_fallback(&self) -> EntropyFallback {
+        self.entropy_fallback
+    }
+
+    fn shared_region(&self, name: &str) -> Option<Arc<SharedRegion>> {
+        self.shared_regions.get(name).cloned()
+    }
+
+    fn clock(&self) -> Option<&dyn VirtualClock> {
+        self.clock.as_deref()
+    }
+
+    fn pid_allocator(&self) -> Option<&dyn PidAllocator> {
+        self.pid_allocator.as_deref()
+    }
+
+    fn memory_limits(&self) -> Option<MemoryLimits> {
+        self.memory_limits
+    }
+
+    fn max_open_fds(&self) -> Option<usize> {
+        self.max_open_fds
+    }
+
+    fn syscall_rate_limit(&self) -> Option<RateLimit> {
+        self.syscall_rate_limit
+    }
+
+    fn default_args(&self) -> Vec<String> {
+        self.default_args.clone()
+    }
+
+    fn program_name(&self) -> Option<String> {
+        self.program_name.clone()
+    }
+
+    fn default_cwd(&self) -> Option<PathBuf> {
+        self.default_cwd.clone()
+    }
+
+    fn instance_id(&self) -> Option<Uuid> {
+        self.instance_id
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.hostname.clone()
+    }
+
+    fn domainname(&self) -> Option<String> {
+        self.domainname.clone()
+    }
+
+    fn execution_deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
     fn module_cache(&self) -> Arc<dyn ModuleCache + Send + Sync> {
         self.module_cache.clone()
     }
@@ -354,12 +1287,44 @@ impl Runtime for PluggableRuntime {
         &self.journals
     }
 
+    fn on_shutdown(&self, f: ShutdownCallback) {
+        self.shutdown_callbacks.push(f);
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move { self.shutdown_callbacks.run() })
+    }
+
+    fn shallow_clone(&self) -> Arc<DynRuntime> {
+        Arc::new(self.clone())
+    }
+
     #[cfg(feature = "journal")]
     fn active_journal(&self) -> Option<&DynJournal> {
         self.journals.iter().last().map(|a| a.as_ref())
     }
 }
 
+/// Does `host` match `pattern`, which is either a host name or a wildcard
+/// like `*.example.com` which matches all subdomains? Host names are
+/// compared case-insensitively.
+pub(crate) fn matches_host(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => {
+            let Some(split) = host.len().checked_sub(domain.len()) else {
+                return false;
+            };
+            match (host.get(..split), host.get(split..)) {
+                (Some(prefix), Some(suffix)) => {
+                    prefix.ends_with('.') && suffix.eq_ignore_ascii_case(domain)
+                }
+                _ => false,
+            }
+        }
+        None => pattern.eq_ignore_ascii_case(host),
+    }
+}
+
 /// Runtime that allows for certain things to be overridden
 /// such as the active journals
 #[derive(Clone, Debug)]
@@ -373,6 +1338,7 @@ pub struct OverriddenRuntime {
     engine: Option<wasmer::Engine>,
     module_cache: Option<Arc<dyn ModuleCache + Send + Sync>>,
     tty: Option<Arc<dyn TtyBridge + Send + Sync>>,
+    instance_id: Option<Uuid>,
     #[cfg(feature = "journal")]
     journals: Option<Vec<Arc<DynJournal>>>,
 }
@@ -389,6 +1355,7 @@ impl OverriddenRuntime {
             engine: None,
             module_cache: None,
             tty: None,
+            instance_id: None,
             #[cfg(feature = "journal")]
             journals: None,
         }
@@ -438,6 +1405,13 @@ impl OverriddenRuntime {
         self
     }
 
+    /// Give this runtime its own instance ID, rather than sharing the inner
+    /// runtime's.
+    pub fn with_instance_id(mut self, instance_id: Uuid) -> Self {
+        self.instance_id.replace(instance_id);
+        self
+    }
+
     #[cfg(feature = "journal")]
     pub fn with_journals(mut self, journals: Vec<Arc<DynJournal>>) -> Self {
         self.journals.replace(journals);
@@ -502,6 +1476,14 @@ impl Runtime for OverriddenRuntime {
         }
     }
 
+    fn new_store_async(&self) -> BoxFuture<'_, Result<wasmer::Store, StoreCreationError>> {
+        if let Some(engine) = self.engine.clone() {
+            Box::pin(async move { Ok(wasmer::Store::new(engine)) })
+        } else {
+            self.inner.new_store_async()
+        }
+    }
+
     fn http_client(&self) -> Option<&DynHttpClient> {
         if let Some(client) = self.http_client.as_ref() {
             Some(client)
@@ -510,6 +1492,14 @@ impl Runtime for OverriddenRuntime {
         }
     }
 
+    fn http_client_for(&self, url: &Url) -> Option<&DynHttpClient> {
+        if let Some(client) = self.http_client.as_ref() {
+            Some(client)
+        } else {
+            self.inner.http_client_for(url)
+        }
+    }
+
     fn tty(&self) -> Option<&(dyn TtyBridge + Send + Sync)> {
         if let Some(tty) = self.tty.as_ref() {
             Some(tty.deref())
@@ -518,6 +1508,106 @@ impl Runtime for OverriddenRuntime {
         }
     }
 
+    fn trace_context(&self) -> Option<TraceContext> {
+        self.inner.trace_context()
+    }
+
+    fn log_sink(&self) -> Option<&dyn LogSink> {
+        self.inner.log_sink()
+    }
+
+    fn env_filter(&self) -> Option<&dyn EnvFilter> {
+        self.inner.env_filter()
+    }
+
+    fn secrets(&self) -> Option<&dyn SecretStore> {
+        self.inner.secrets()
+    }
+
+    fn fs_access_policy(&self) -> Option<&dyn FsAccessPolicy> {
+        self.inner.fs_access_policy()
+    }
+
+    fn fs_watcher(&self) -> Option<&dyn FsWatcher> {
+        self.inner.fs_watcher()
+    }
+
+    fn rng(&self) -> Option<Arc<dyn VirtualRng>> {
+        self.inner.rng()
+    }
+
+    fn rng_seed(&self) -> Option<u64> {
+        self.inner.rng_seed()
+    }
+
+    fn entropy_fallback(&self) -> EntropyFallback {
+        self.inner.entropy_fallback()
+    }
+
+    fn shared_region(&self, name: &str) -> Option<Arc<SharedRegion>> {
+        self.inner.shared_region(name)
+    }
+
+    fn clock(&self) -> Option<&dyn VirtualClock> {
+        self.inner.clock()
+    }
+
+    fn pid_allocator(&self) -> Option<&dyn PidAllocator> {
+        self.inner.pid_allocator()
+    }
+
+    fn memory_limits(&self) -> Option<MemoryLimits> {
+        self.inner.memory_limits()
+    }
+
+    fn max_open_fds(&self) -> Option<usize> {
+        self.inner.max_open_fds()
+    }
+
+    fn syscall_rate_limit(&self) -> Option<RateLimit> {
+        self.inner.syscall_rate_limit()
+    }
+
+    fn default_args(&self) -> Vec<String> {
+        self.inner.default_args()
+    }
+
+    fn program_name(&self) -> Option<String> {
+        self.inner.program_name()
+    }
+
+    fn default_cwd(&self) -> Option<PathBuf> {
+        self.inner.default_cwd()
+    }
+
+    fn instance_id(&self) -> Option<Uuid> {
+        self.instance_id.or_else(|| self.inner.instance_id())
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.inner.hostname()
+    }
+
+    fn domainname(&self) -> Option<String> {
+        self.inner.domainname()
+    }
+
+    fn execution_deadline(&self) -> Option<Instant> {
+        self.inner.execution_deadline()
+    }
+
+    fn on_shutdown(&self, f: ShutdownCallback) {
+        self.inner.on_shutdown(f)
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, ()> {
+        self.inner.shutdown()
+    }
+
+    fn shallow_clone(&self) -> Arc<DynRuntime> {
+        Arc::new(self.clone())
+    }
+
     #[cfg(feature = "journal")]
     fn journals(&self) -> &'_ Vec<Arc<DynJournal>> {
         if let Some(journals) = self.journals.as_ref() {
@@ -557,3 +1647,196 @@ impl Runtime for OverriddenRuntime {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_callbacks_run_in_lifo_order() {
+        let callbacks = ShutdownCallbacks::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        for i in 0..3 {
+            let order = order.clone();
+            callbacks.push(Box::new(move || order.lock().unwrap().push(i)));
+        }
+        callbacks.run();
+        callbacks.run();
+
+        assert_eq!(*order.lock().unwrap(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn memory_limits_are_capped_by_the_memory() {
+        let configured = Some(MemoryLimits::new(1024));
+
+        assert_eq!(
+            MemoryLimits::for_memory(configured, Some(4096), 256),
+            Some(MemoryLimits {
+                total_bytes: 1024,
+                available_bytes: 768,
+            })
+        );
+        assert_eq!(
+            MemoryLimits::for_memory(configured, Some(512), 768),
+            Some(MemoryLimits {
+                total_bytes: 512,
+                available_bytes: 0,
+            })
+        );
+        assert_eq!(
+            MemoryLimits::for_memory(None, Some(4096), 1024),
+            Some(MemoryLimits {
+                total_bytes: 4096,
+                available_bytes: 3072,
+            })
+        );
+        assert_eq!(MemoryLimits::for_memory(None, None, 1024), None);
+    }
+
+    #[tokio::test]
+    async fn networking_kind_comes_from_the_backend() {
+        let mut runtime =
+            PluggableRuntime::new(Arc::new(task_manager::tokio::TokioTaskManager::default()));
+
+        runtime.set_networking_implementation(virtual_net::UnsupportedVirtualNetworking::default());
+        assert_eq!(runtime.networking_kind(), "unsupported");
+
+        let overridden = OverriddenRuntime::new(Arc::new(runtime))
+            .with_networking(Arc::new(virtual_net::LoopbackNetworking::new()));
+        assert_eq!(overridden.networking_kind(), "loopback");
+    }
+
+    #[derive(Debug)]
+    struct UnreachableHttpClient;
+
+    impl HttpClient for UnreachableHttpClient {
+        fn request(
+            &self,
+            _request: crate::http::HttpRequest,
+        ) -> BoxFuture<'_, Result<crate::http::HttpResponse, anyhow::Error>> {
+            Box::pin(async { anyhow::bail!("unreachable") })
+        }
+    }
+
+    #[tokio::test]
+    async fn http_client_routes() {
+        let mut runtime =
+            PluggableRuntime::new(Arc::new(task_manager::tokio::TokioTaskManager::default()));
+        runtime
+            .set_http_client(UnreachableHttpClient)
+            .add_http_client_route("*.Example.com", UnreachableHttpClient);
+
+        let default = runtime.http_client().unwrap();
+        let route = &runtime.http_client_routes[0].1;
+        let client = |url: &str| runtime.http_client_for(&url.parse().unwrap()).unwrap();
+
+        assert!(Arc::ptr_eq(client("https://api.example.com/"), route));
+        assert!(Arc::ptr_eq(client("https://example.com/"), default));
+        assert!(Arc::ptr_eq(client("https://evil-example.com/"), default));
+
+        assert!(matches_host("*.example.com", "API.Example.COM"));
+        assert!(matches_host("Registry.Wasmer.io", "registry.wasmer.io"));
+        assert!(!matches_host("*.example.com", "example.com"));
+    }
+
+    #[cfg(feature = "host-reqwest")]
+    #[tokio::test]
+    async fn user_agents_dont_replace_custom_http_clients() {
+        let mut runtime =
+            PluggableRuntime::new(Arc::new(task_manager::tokio::TokioTaskManager::default()));
+        let default = runtime.http_client().unwrap().clone();
+        runtime.set_user_agent("my-embedder/1.0");
+        assert!(!Arc::ptr_eq(runtime.http_client().unwrap(), &default));
+
+        runtime.set_http_client(UnreachableHttpClient);
+        let custom = runtime.http_client().unwrap().clone();
+        runtime.set_user_agent("my-embedder/2.0");
+        assert!(Arc::ptr_eq(runtime.http_client().unwrap(), &custom));
+    }
+
+    #[tokio::test]
+    async fn describe_in_every_format() {
+        let mut runtime =
+            PluggableRuntime::new(Arc::new(task_manager::tokio::TokioTaskManager::default()));
+        runtime
+            .set_networking_implementation(virtual_net::UnsupportedVirtualNetworking::default())
+            .set_hostname("guest")
+            .set_memory_limits(Some(MemoryLimits::new(1024)))
+            .set_rng_seed(42);
+
+        let description = runtime.describe();
+        assert_eq!(description.networking, "unsupported");
+        assert_eq!(description.hostname.as_deref(), Some("guest"));
+        assert_eq!(description.rng, "seeded");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&runtime.describe_as(ConfigFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["hostname"], "guest");
+        assert_eq!(json["memory_limits"]["total_bytes"], 1024);
+
+        let yaml: serde_yaml::Value =
+            serde_yaml::from_str(&runtime.describe_as(ConfigFormat::Yaml).unwrap()).unwrap();
+        assert_eq!(yaml["hostname"].as_str(), Some("guest"));
+
+        let toml: toml::Table = runtime
+            .describe_as(ConfigFormat::Toml)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(toml["hostname"].as_str(), Some("guest"));
+        assert_eq!(
+            toml["memory_limits"]["total_bytes"].as_integer(),
+            Some(1024)
+        );
+
+        assert_eq!("YML".parse::<ConfigFormat>(), Ok(ConfigFormat::Yaml));
+        assert!("xml".parse::<ConfigFormat>().is_err());
+    }
+
+    #[tokio::test]
+    async fn store_creation_with_a_timeout() {
+        let mut runtime =
+            PluggableRuntime::new(Arc::new(task_manager::tokio::TokioTaskManager::default()));
+        runtime.set_store_creation_timeout(Some(Duration::from_secs(30)));
+
+        runtime.new_store_async().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn instance_ids() {
+        let mut runtime =
+            PluggableRuntime::new(Arc::new(task_manager::tokio::TokioTaskManager::default()));
+        assert_eq!(runtime.instance_id(), None);
+
+        let assigned = Uuid::new_v4();
+        runtime.set_instance_id(assigned);
+        assert_eq!(runtime.instance_id(), Some(assigned));
+
+        let runtime = Arc::new(runtime);
+        let own = Uuid::new_v4();
+        let overridden = OverriddenRuntime::new(runtime.clone()).with_instance_id(own);
+        assert_eq!(overridden.instance_id(), Some(own));
+        assert_eq!(
+            OverriddenRuntime::new(runtime).instance_id(),
+            Some(assigned)
+        );
+    }
+
+    #[tokio::test]
+    async fn feature_flags_follow_the_runtime() {
+        let mut runtime =
+            PluggableRuntime::new(Arc::new(task_manager::tokio::TokioTaskManager::default()));
+        runtime.set_networking_implementation(virtual_net::UnsupportedVirtualNetworking::default());
+        assert!(!runtime.feature_flags().contains(FeatureFlags::NETWORKING));
+        assert!(!runtime.feature_flags().contains(FeatureFlags::SECRETS));
+
+        runtime.set_secrets(Arc::new(InMemorySecretStore::default()));
+        let flags = runtime.feature_flags();
+        assert!(flags.contains(FeatureFlags::SECRETS));
+        assert!(flags.contains(FeatureFlags::THREADS));
+        assert!(flags.to_string().contains("threads"));
+        assert!(!flags.to_string().contains("networking"));
+    }
+}
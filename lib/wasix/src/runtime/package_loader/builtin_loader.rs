@@ -4,11 +4,13 @@ use std::{
     io::{ErrorKind, Write as _},
     path::PathBuf,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use anyhow::{Context, Error};
 use bytes::Bytes;
 use http::{HeaderMap, Method};
+use rand::{rngs::StdRng, SeedableRng};
 use tempfile::NamedTempFile;
 use url::Url;
 use wasmer_package::{
@@ -20,10 +22,11 @@ use webc::{Container, ContainerError};
 
 use crate::{
     bin_factory::BinaryPackage,
-    http::{HttpClient, HttpRequest, USER_AGENT},
+    http::{HttpClient, HttpRequest, HttpResponse, RetryPolicy, USER_AGENT},
     runtime::{
         package_loader::PackageLoader,
         resolver::{DistributionInfo, PackageSummary, Resolution, WebcHash},
+        VirtualRng, VirtualTaskManager,
     },
 };
 
@@ -38,6 +41,9 @@ pub struct BuiltinPackageLoader {
     tokens: HashMap<String, String>,
 
     hash_validation: HashIntegrityValidationMode,
+    retry_policy: RetryPolicy,
+    tasks: Option<Arc<dyn VirtualTaskManager>>,
+    rng: Option<Arc<dyn VirtualRng>>,
 }
 
 /// Defines how to validate package hash integrity.
@@ -60,6 +66,9 @@ impl BuiltinPackageLoader {
             cache: None,
             hash_validation: HashIntegrityValidationMode::NoValidate,
             tokens: HashMap::new(),
+            retry_policy: RetryPolicy::default(),
+            tasks: None,
+            rng: None,
         }
     }
 
@@ -71,6 +80,30 @@ impl BuiltinPackageLoader {
         self
     }
 
+    /// Set how failed package downloads are retried.
+    ///
+    /// Downloads are only retried when the loader has a task manager to wait
+    /// between attempts with (see
+    /// [`BuiltinPackageLoader::with_task_manager()`]).
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Use `tasks` to wait between retries of failed downloads.
+    pub fn with_task_manager(mut self, tasks: Arc<dyn VirtualTaskManager>) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// Use `rng` for the retry policy's jitter (e.g. the runtime's
+    /// [`Runtime::rng()`][crate::Runtime::rng], to make the delays
+    /// reproducible).
+    pub fn with_rng(mut self, rng: Arc<dyn VirtualRng>) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+
     pub fn with_cache_dir(self, cache_dir: impl Into<PathBuf>) -> Self {
         BuiltinPackageLoader {
             cache: Some(FileSystemCache {
@@ -248,18 +281,7 @@ impl BuiltinPackageLoader {
             }
         }
 
-        let request = HttpRequest {
-            headers: self.headers(&dist.webc),
-            url: dist.webc.clone(),
-            method: Method::GET,
-            body: None,
-            options: Default::default(),
-        };
-
-        tracing::debug!(%request.url, %request.method, "webc_package_download_start");
-        tracing::trace!(?request.headers);
-
-        let response = self.client.request(request).await?;
+        let response = self.send_with_retries(&dist.webc).await?;
 
         tracing::trace!(
             %response.status,
@@ -289,6 +311,59 @@ impl BuiltinPackageLoader {
         Ok(body)
     }
 
+    /// Send a `GET` request, retrying transient failures according to the
+    /// [`RetryPolicy`].
+    async fn send_with_retries(&self, url: &Url) -> Result<HttpResponse, Error> {
+        let mut retry = 0;
+
+        loop {
+            let request = HttpRequest {
+                headers: self.headers(url),
+                url: url.clone(),
+                method: Method::GET,
+                body: None,
+                options: Default::default(),
+            };
+
+            tracing::debug!(%request.url, %request.method, "webc_package_download_start");
+            tracing::trace!(?request.headers);
+
+            let result = self.client.request(request).await;
+            let transient = match &result {
+                Ok(response) => {
+                    response.status.is_server_error()
+                        || response.status == http::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(_) => true,
+            };
+
+            if !transient || retry >= self.retry_policy.max_retries {
+                return result;
+            }
+            let Some(tasks) = &self.tasks else {
+                tracing::debug!(%url, "package download failed, not retrying without a task manager");
+                return result;
+            };
+
+            let delay = self.retry_delay(retry);
+            tracing::debug!(%url, retry, ?delay, "package download failed, retrying");
+            retry += 1;
+            tasks.sleep_now(delay).await;
+        }
+    }
+
+    /// How long to wait before retry number `retry`.
+    fn retry_delay(&self, retry: u32) -> Duration {
+        match &self.rng {
+            Some(rng) => {
+                let mut seed = <StdRng as SeedableRng>::Seed::default();
+                rng.fill_bytes(&mut seed);
+                self.retry_policy.delay(retry, &mut StdRng::from_seed(seed))
+            }
+            None => self.retry_policy.delay(retry, &mut rand::thread_rng()),
+        }
+    }
+
     fn headers(&self, url: &Url) -> HeaderMap {
         let mut headers = HeaderMap::new();
         headers.insert("Accept", "application/webc".parse().unwrap());
@@ -594,8 +669,8 @@ mod tests {
     use wasmer_config::package::PackageId;
 
     use crate::{
-        http::{HttpRequest, HttpResponse},
-        runtime::resolver::PackageInfo,
+        http::{HttpRequest, HttpResponse, Jitter},
+        runtime::{resolver::PackageInfo, task_manager::tokio::TokioTaskManager, SeededRng},
     };
 
     use super::*;
@@ -633,6 +708,7 @@ mod tests {
         let client = Arc::new(DummyClient::with_responses([HttpResponse {
             body: Some(PYTHON.to_vec()),
             redirected: false,
+            version: None,
             status: StatusCode::OK,
             headers: HeaderMap::new(),
         }]));
@@ -690,6 +766,67 @@ mod tests {
     async fn cache_misses_will_trigger_a_download() {
         cache_misses_will_trigger_a_download_internal().await
     }
+
+    async fn transient_failures_are_retried_internal() {
+        let client = Arc::new(DummyClient::with_responses([
+            HttpResponse {
+                body: None,
+                redirected: false,
+                version: None,
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                headers: HeaderMap::new(),
+            },
+            HttpResponse {
+                body: Some(PYTHON.to_vec()),
+                redirected: false,
+                version: None,
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+            },
+        ]));
+        let loader = BuiltinPackageLoader::new()
+            .with_shared_http_client(client.clone())
+            .with_task_manager(Arc::new(TokioTaskManager::default()))
+            .with_rng(Arc::new(SeededRng::new(42)))
+            .with_retry_policy(RetryPolicy {
+                initial_delay: std::time::Duration::from_millis(1),
+                ..RetryPolicy::default().with_jitter(Jitter::Equal)
+            });
+        let dist = DistributionInfo {
+            webc: "https://wasmer.io/python/python".parse().unwrap(),
+            webc_sha256: [0xaa; 32].into(),
+        };
+
+        let body = loader.download(&dist).await.unwrap();
+
+        assert_eq!(body, PYTHON);
+        assert_eq!(client.requests.lock().unwrap().len(), 2);
+
+        // Errors which aren't transient are returned immediately
+        let client = Arc::new(DummyClient::with_responses([HttpResponse {
+            body: None,
+            redirected: false,
+            version: None,
+            status: StatusCode::NOT_FOUND,
+            headers: HeaderMap::new(),
+        }]));
+        let loader = BuiltinPackageLoader::new().with_shared_http_client(client.clone());
+
+        assert!(loader.download(&dist).await.is_err());
+        assert_eq!(client.requests.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn transient_failures_are_retried() {
+        transient_failures_are_retried_internal().await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[tokio::test()]
+    async fn transient_failures_are_retried() {
+        transient_failures_are_retried_internal().await
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::WasiProcessId;
+
+/// Assigns the process IDs guests see from `getpid()`, `getppid()` and
+/// `proc_spawn()`.
+///
+/// Without one, each
+/// [`WasiControlPlane`][crate::os::task::control_plane::WasiControlPlane]
+/// numbers its processes itself.
+pub trait PidAllocator: std::fmt::Debug + Send + Sync {
+    /// Allocate the ID of a new process, or return `None` if there are none
+    /// left.
+    ///
+    /// `parent` is the process which forked or spawned it, if it has one.
+    fn allocate(&self, parent: Option<WasiProcessId>) -> Option<WasiProcessId>;
+}
+
+/// A [`PidAllocator`] which counts up from a starting ID without ever
+/// reusing one.
+#[derive(Debug)]
+pub struct MonotonicPidAllocator {
+    next: AtomicU32,
+}
+
+impl MonotonicPidAllocator {
+    pub fn new(first: u32) -> Self {
+        MonotonicPidAllocator {
+            next: AtomicU32::new(first),
+        }
+    }
+}
+
+impl Default for MonotonicPidAllocator {
+    /// Start at 1, like an init process.
+    fn default() -> Self {
+        MonotonicPidAllocator::new(1)
+    }
+}
+
+impl PidAllocator for MonotonicPidAllocator {
+    fn allocate(&self, _parent: Option<WasiProcessId>) -> Option<WasiProcessId> {
+        self.next
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pid| pid.checked_add(1))
+            .ok()
+            .map(WasiProcessId::from)
+    }
+}
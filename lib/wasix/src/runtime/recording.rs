@@ -0,0 +1,992 @@
+//! Record the values a [`Runtime`] hands to guests so a misbehaving run can
+//! be replayed deterministically.
+//!
+//! Only the hooks on the [`Runtime`] trait are captured: HTTP requests, clock
+//! readings, random bytes and the values presented to guests (hostname,
+//! arguments, memory limits, etc.). Everything else (networking, the
+//! filesystem) is passed through to the wrapped runtime untouched. Secrets
+//! are never recorded.
+
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use anyhow::Context;
+use futures::future::BoxFuture;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+use rand::RngCore;
+use url::Url;
+use uuid::Uuid;
+use virtual_net::DynVirtualNetworking;
+use wasmer::Module;
+use wasmer_wasix_types::wasi::{Errno, Snapshot0Clockid, Timestamp};
+
+#[cfg(feature = "journal")]
+use crate::journal::DynJournal;
+use crate::{
+    http::{DynHttpClient, HttpClient, HttpRequest, HttpResponse, TraceContext},
+    os::TtyBridge,
+    runtime::{
+        module_cache::ModuleCache, package_loader::PackageLoader, resolver::Source, DynRuntime,
+        EntropyFallback, EnvFilter, FsAccessPolicy, FsWatcher, LogSink, MemoryLimits, PidAllocator,
+        RateLimit, Runtime, SecretStore, SharedRegion, ShutdownCallback, StoreCreationError,
+        TaintReason, VirtualClock, VirtualRng, VirtualTaskManager,
+    },
+    syscalls::platform_clock_time_get,
+    SpawnError,
+};
+
+/// Everything a [`RecordingRuntime`] observed, in the order it happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RuntimeTrace {
+    pub events: Vec<TraceEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TraceEvent {
+    Hostname {
+        value: Option<String>,
+    },
+    Domainname {
+        value: Option<String>,
+    },
+    ProgramName {
+        value: Option<String>,
+    },
+    DefaultArgs {
+        value: Vec<String>,
+    },
+    DefaultCwd {
+        value: Option<PathBuf>,
+    },
+    MemoryLimits {
+        value: Option<MemoryLimits>,
+    },
+    MaxOpenFds {
+        value: Option<usize>,
+    },
+    Http {
+        request: RecordedRequest,
+        /// The response, or the error message if the request failed.
+        response: Result<RecordedResponse, String>,
+    },
+    /// Random bytes handed out by [`Runtime::rng()`].
+    Random {
+        bytes: Vec<u8>,
+    },
+    /// A reading of one of the [`Runtime::clock()`]'s clocks.
+    ClockTime {
+        clock_id: u32,
+        /// The time in nanoseconds, or the errno if the clock couldn't be
+        /// read.
+        value: Result<i64, u16>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl RecordedRequest {
+    fn new(request: &HttpRequest) -> Self {
+        RecordedRequest {
+            method: request.method.to_string(),
+            url: request.url.to_string(),
+            headers: record_headers(&request.headers),
+            body: request.body.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub redirected: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl RecordedResponse {
+    fn new(response: &HttpResponse) -> Self {
+        RecordedResponse {
+            status: response.status.as_u16(),
+            redirected: response.redirected,
+            version: response.version.map(|v| format!("{v:?}")),
+            headers: record_headers(&response.headers),
+            body: response.body.clone(),
+        }
+    }
+
+    fn to_response(&self) -> Result<HttpResponse, anyhow::Error> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_bytes(value)?,
+            );
+        }
+
+        Ok(HttpResponse {
+            body: self.body.clone(),
+            redirected: self.redirected,
+            version: self.version.as_deref().map(parse_version).transpose()?,
+            status: StatusCode::from_u16(self.status)?,
+            headers,
+        })
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, anyhow::Error> {
+    match version {
+        "HTTP/0.9" => Ok(Version::HTTP_09),
+        "HTTP/1.0" => Ok(Version::HTTP_10),
+        "HTTP/1.1" => Ok(Version::HTTP_11),
+        "HTTP/2.0" => Ok(Version::HTTP_2),
+        "HTTP/3.0" => Ok(Version::HTTP_3),
+        other => anyhow::bail!("unknown HTTP version \"{other}\""),
+    }
+}
+
+fn record_headers(headers: &HeaderMap) -> Vec<(String, Vec<u8>)> {
+    headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+        .collect()
+}
+
+/// A [`Runtime`] which records everything it hands to guests into a
+/// [`RuntimeTrace`], so the run can later be reproduced with a
+/// [`ReplayRuntime`].
+#[derive(Debug)]
+pub struct RecordingRuntime<R> {
+    inner: Arc<R>,
+    trace: Arc<Mutex<RuntimeTrace>>,
+    http_client: DynHttpClient,
+    rng: Arc<dyn VirtualRng>,
+    clock: Arc<dyn VirtualClock>,
+}
+
+impl<R> RecordingRuntime<R>
+where
+    R: Runtime + Send + Sync + 'static,
+{
+    pub fn new(inner: R) -> Self {
+        let inner = Arc::new(inner);
+        let trace = Arc::new(Mutex::new(RuntimeTrace::default()));
+        let http_client = Arc::new(RecordingHttpClient {
+            runtime: inner.clone(),
+            trace: trace.clone(),
+        });
+        let rng = Arc::new(RecordingRng {
+            runtime: inner.clone(),
+            trace: trace.clone(),
+        });
+        let clock = Arc::new(RecordingClock {
+            runtime: inner.clone(),
+            trace: trace.clone(),
+        });
+
+        RecordingRuntime {
+            inner,
+            trace,
+            http_client,
+            rng,
+            clock,
+        }
+    }
+
+    /// A snapshot of everything recorded so far.
+    pub fn trace(&self) -> RuntimeTrace {
+        self.trace.lock().unwrap().clone()
+    }
+
+    fn record(&self, event: TraceEvent) {
+        self.trace.lock().unwrap().events.push(event);
+    }
+}
+
+impl<R> Clone for RecordingRuntime<R> {
+    fn clone(&self) -> Self {
+        RecordingRuntime {
+            inner: self.inner.clone(),
+            trace: self.trace.clone(),
+            http_client: self.http_client.clone(),
+            rng: self.rng.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+impl<R> Runtime for RecordingRuntime<R>
+where
+    R: Runtime + Send + Sync + 'static,
+{
+    fn networking(&self) -> &DynVirtualNetworking {
+        self.inner.networking()
+    }
+
+    fn task_manager(&self) -> &Arc<dyn VirtualTaskManager> {
+        self.inner.task_manager()
+    }
+
+    fn package_loader(&self) -> Arc<dyn PackageLoader + Send + Sync> {
+        self.inner.package_loader()
+    }
+
+    fn module_cache(&self) -> Arc<dyn ModuleCache + Send + Sync> {
+        self.inner.module_cache()
+    }
+
+    fn source(&self) -> Arc<dyn Source + Send + Sync> {
+        self.inner.source()
+    }
+
+    fn engine(&self) -> wasmer::Engine {
+        self.inner.engine()
+    }
+
+    fn new_store(&self) -> wasmer::Store {
+        self.inner.new_store()
+    }
+
+    fn new_store_async(&self) -> BoxFuture<'_, Result<wasmer::Store, StoreCreationError>> {
+        self.inner.new_store_async()
+    }
+
+    fn http_client(&self) -> Option<&DynHttpClient> {
+        self.inner.http_client().map(|_| &self.http_client)
+    }
+
+    fn http_client_for(&self, url: &Url) -> Option<&DynHttpClient> {
+        self.inner.http_client_for(url).map(|_| &self.http_client)
+    }
+
+    fn tty(&self) -> Option<&(dyn TtyBridge + Send + Sync)> {
+        self.inner.tty()
+    }
+
+    fn trace_context(&self) -> Option<TraceContext> {
+        self.inner.trace_context()
+    }
+
+    fn log_sink(&self) -> Option<&dyn LogSink> {
+        self.inner.log_sink()
+    }
+
+    fn env_filter(&self) -> Option<&dyn EnvFilter> {
+        self.inner.env_filter()
+    }
+
+    fn secrets(&self) -> Option<&dyn SecretStore> {
+        self.inner.secrets()
+    }
+
+    fn fs_access_policy(&self) -> Option<&dyn FsAccessPolicy> {
+        self.inner.fs_access_policy()
+    }
+
+    fn fs_watcher(&self) -> Option<&dyn FsWatcher> {
+        self.inner.fs_watcher()
+    }
+
+    fn rng(&self) -> Option<Arc<dyn VirtualRng>> {
+        Some(self.rng.clone())
+    }
+
+    fn rng_seed(&self) -> Option<u64> {
+        // Instances deriving their own RNG would bypass Runtime::rng()
+        None
+    }
+
+    fn entropy_fallback(&self) -> EntropyFallback {
+        self.inner.entropy_fallback()
+    }
+
+    fn shared_region(&self, name: &str) -> Option<Arc<SharedRegion>> {
+        self.inner.shared_region(name)
+    }
+
+    fn instance_id(&self) -> Option<Uuid> {
+        self.inner.instance_id()
+    }
+
+    fn clock(&self) -> Option<&dyn VirtualClock> {
+        Some(&*self.clock)
+    }
+
+    fn pid_allocator(&self) -> Option<&dyn PidAllocator> {
+        self.inner.pid_allocator()
+    }
+
+    fn hostname(&self) -> Option<String> {
+        let value = self.inner.hostname();
+        self.record(TraceEvent::Hostname {
+            value: value.clone(),
+        });
+        value
+    }
+
+    fn domainname(&self) -> Option<String> {
+        let value = self.inner.domainname();
+        self.record(TraceEvent::Domainname {
+            value: value.clone(),
+        });
+        value
+    }
+
+    fn memory_limits(&self) -> Option<MemoryLimits> {
+        let value = self.inner.memory_limits();
+        self.record(TraceEvent::MemoryLimits { value });
+        value
+    }
+
+    fn max_open_fds(&self) -> Option<usize> {
+        let value = self.inner.max_open_fds();
+        self.record(TraceEvent::MaxOpenFds { value });
+        value
+    }
+
+    fn default_args(&self) -> Vec<String> {
+        let value = self.inner.default_args();
+        self.record(TraceEvent::DefaultArgs {
+            value: value.clone(),
+        });
+        value
+    }
+
+    fn program_name(&self) -> Option<String> {
+        let value = self.inner.program_name();
+        self.record(TraceEvent::ProgramName {
+            value: value.clone(),
+        });
+        value
+    }
+
+    fn default_cwd(&self) -> Option<PathBuf> {
+        let value = self.inner.default_cwd();
+        self.record(TraceEvent::DefaultCwd {
+            value: value.clone(),
+        });
+        value
+    }
+
+    fn syscall_rate_limit(&self) -> Option<RateLimit> {
+        self.inner.syscall_rate_limit()
+    }
+
+    fn execution_deadline(&self) -> Option<Instant> {
+        self.inner.execution_deadline()
+    }
+
+    fn load_module<'a>(&'a self, wasm: &'a [u8]) -> BoxFuture<'a, Result<Module, SpawnError>> {
+        self.inner.load_module(wasm)
+    }
+
+    fn load_module_sync(&self, wasm: &[u8]) -> Result<Module, SpawnError> {
+        self.inner.load_module_sync(wasm)
+    }
+
+    fn on_taint(&self, reason: TaintReason) {
+        self.inner.on_taint(reason)
+    }
+
+    fn on_shutdown(&self, f: ShutdownCallback) {
+        self.inner.on_shutdown(f)
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, ()> {
+        self.inner.shutdown()
+    }
+
+    fn shallow_clone(&self) -> Arc<DynRuntime> {
+        Arc::new(self.clone())
+    }
+
+    #[cfg(feature = "journal")]
+    fn journals(&self) -> &'_ Vec<Arc<DynJournal>> {
+        self.inner.journals()
+    }
+
+    #[cfg(feature = "journal")]
+    fn active_journal(&self) -> Option<&'_ DynJournal> {
+        self.inner.active_journal()
+    }
+}
+
+/// Sends requests using the wrapped runtime's HTTP clients, recording each
+/// exchange.
+#[derive(Debug)]
+struct RecordingHttpClient<R> {
+    runtime: Arc<R>,
+    trace: Arc<Mutex<RuntimeTrace>>,
+}
+
+impl<R> HttpClient for RecordingHttpClient<R>
+where
+    R: Runtime + Send + Sync,
+{
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        Box::pin(async move {
+            let recorded_request = RecordedRequest::new(&request);
+            let client = self
+                .runtime
+                .http_client_for(&request.url)
+                .context("no http client available")?;
+
+            let result = client.request(request).await;
+
+            let response = match &result {
+                Ok(response) => Ok(RecordedResponse::new(response)),
+                Err(e) => Err(e.to_string()),
+            };
+            self.trace.lock().unwrap().events.push(TraceEvent::Http {
+                request: recorded_request,
+                response,
+            });
+
+            result
+        })
+    }
+}
+
+/// Hands out random bytes from the wrapped runtime's RNG (or the host's),
+/// recording them.
+#[derive(Debug)]
+struct RecordingRng<R> {
+    runtime: Arc<R>,
+    trace: Arc<Mutex<RuntimeTrace>>,
+}
+
+impl<R> VirtualRng for RecordingRng<R>
+where
+    R: Runtime + Send + Sync,
+{
+    fn fill_bytes(&self, buf: &mut [u8]) {
+        match self.runtime.rng() {
+            Some(rng) => rng.fill_bytes(buf),
+            None => rand::thread_rng().fill_bytes(buf),
+        }
+
+        self.trace.lock().unwrap().events.push(TraceEvent::Random {
+            bytes: buf.to_vec(),
+        });
+    }
+}
+
+/// Reads the wrapped runtime's clocks (or the host's), recording each
+/// reading.
+#[derive(Debug)]
+struct RecordingClock<R> {
+    runtime: Arc<R>,
+    trace: Arc<Mutex<RuntimeTrace>>,
+}
+
+impl<R> VirtualClock for RecordingClock<R>
+where
+    R: Runtime + Send + Sync,
+{
+    fn time(&self, clock_id: Snapshot0Clockid, precision: Timestamp) -> Result<i64, Errno> {
+        let value = match self.runtime.clock() {
+            Some(clock) => clock.time(clock_id, precision),
+            None => platform_clock_time_get(clock_id, precision),
+        };
+
+        let event = TraceEvent::ClockTime {
+            clock_id: clock_id as u32,
+            value: value.map_err(|errno| errno as u16),
+        };
+        self.trace.lock().unwrap().events.push(event);
+
+        value
+    }
+}
+
+/// A [`Runtime`] which serves the values captured by a [`RecordingRuntime`]
+/// back to guests.
+///
+/// HTTP requests never reach the network. Each one is answered with the
+/// first unused recorded exchange with the same method and URL, and fails if
+/// there is none. Random bytes and clock readings are served in the order
+/// they were recorded. Hooks that weren't used while recording (or have run
+/// out of recorded values) fall back to the wrapped runtime.
+#[derive(Debug)]
+pub struct ReplayRuntime<R> {
+    inner: Arc<R>,
+    trace: Arc<RuntimeTrace>,
+    http_client: DynHttpClient,
+    rng: Arc<dyn VirtualRng>,
+    clock: Arc<dyn VirtualClock>,
+}
+
+impl<R> ReplayRuntime<R>
+where
+    R: Runtime + Send + Sync + 'static,
+{
+    pub fn new(inner: R, trace: RuntimeTrace) -> Self {
+        let exchanges = trace
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Http { request, response } => Some((request.clone(), response.clone())),
+                _ => None,
+            })
+            .collect();
+        let bytes = trace
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::Random { bytes } => Some(bytes.iter().copied()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        let readings = trace
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                TraceEvent::ClockTime { clock_id, value } => Some((*clock_id, *value)),
+                _ => None,
+            })
+            .collect();
+
+        let inner = Arc::new(inner);
+
+        ReplayRuntime {
+            trace: Arc::new(trace),
+            http_client: Arc::new(ReplayHttpClient {
+                exchanges: Mutex::new(exchanges),
+            }),
+            rng: Arc::new(ReplayRng {
+                runtime: inner.clone(),
+                bytes: Mutex::new(bytes),
+            }),
+            clock: Arc::new(ReplayClock {
+                runtime: inner.clone(),
+                readings: Mutex::new(readings),
+            }),
+            inner,
+        }
+    }
+
+    /// The most recently recorded value for a hook.
+    fn recorded<T>(&self, value: impl Fn(&TraceEvent) -> Option<T>) -> Option<T> {
+        self.trace.events.iter().rev().find_map(value)
+    }
+}
+
+impl<R> Clone for ReplayRuntime<R> {
+    fn clone(&self) -> Self {
+        ReplayRuntime {
+            inner: self.inner.clone(),
+            trace: self.trace.clone(),
+            http_client: self.http_client.clone(),
+            rng: self.rng.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+impl<R> Runtime for ReplayRuntime<R>
+where
+    R: Runtime + Send + Sync + 'static,
+{
+    fn networking(&self) -> &DynVirtualNetworking {
+        self.inner.networking()
+    }
+
+    fn task_manager(&self) -> &Arc<dyn VirtualTaskManager> {
+        self.inner.task_manager()
+    }
+
+    fn package_loader(&self) -> Arc<dyn PackageLoader + Send + Sync> {
+        self.inner.package_loader()
+    }
+
+    fn module_cache(&self) -> Arc<dyn ModuleCache + Send + Sync> {
+        self.inner.module_cache()
+    }
+
+    fn source(&self) -> Arc<dyn Source + Send + Sync> {
+        self.inner.source()
+    }
+
+    fn engine(&self) -> wasmer::Engine {
+        self.inner.engine()
+    }
+
+    fn new_store(&self) -> wasmer::Store {
+        self.inner.new_store()
+    }
+
+    fn new_store_async(&self) -> BoxFuture<'_, Result<wasmer::Store, StoreCreationError>> {
+        self.inner.new_store_async()
+    }
+
+    fn http_client(&self) -> Option<&DynHttpClient> {
+        Some(&self.http_client)
+    }
+
+    fn http_client_for(&self, _url: &Url) -> Option<&DynHttpClient> {
+        Some(&self.http_client)
+    }
+
+    fn tty(&self) -> Option<&(dyn TtyBridge + Send + Sync)> {
+        self.inner.tty()
+    }
+
+    fn trace_context(&self) -> Option<TraceContext> {
+        self.inner.trace_context()
+    }
+
+    fn log_sink(&self) -> Option<&dyn LogSink> {
+        self.inner.log_sink()
+    }
+
+    fn env_filter(&self) -> Option<&dyn EnvFilter> {
+        self.inner.env_filter()
+    }
+
+    fn secrets(&self) -> Option<&dyn SecretStore> {
+        self.inner.secrets()
+    }
+
+    fn fs_access_policy(&self) -> Option<&dyn FsAccessPolicy> {
+        self.inner.fs_access_policy()
+    }
+
+    fn fs_watcher(&self) -> Option<&dyn FsWatcher> {
+        self.inner.fs_watcher()
+    }
+
+    fn rng(&self) -> Option<Arc<dyn VirtualRng>> {
+        Some(self.rng.clone())
+    }
+
+    fn rng_seed(&self) -> Option<u64> {
+        // Instances deriving their own RNG would bypass Runtime::rng()
+        None
+    }
+
+    fn entropy_fallback(&self) -> EntropyFallback {
+        self.inner.entropy_fallback()
+    }
+
+    fn shared_region(&self, name: &str) -> Option<Arc<SharedRegion>> {
+        self.inner.shared_region(name)
+    }
+
+    fn instance_id(&self) -> Option<Uuid> {
+        self.inner.instance_id()
+    }
+
+    fn clock(&self) -> Option<&dyn VirtualClock> {
+        Some(&*self.clock)
+    }
+
+    fn pid_allocator(&self) -> Option<&dyn PidAllocator> {
+        self.inner.pid_allocator()
+    }
+
+    fn hostname(&self) -> Option<String> {
+        self.recorded(|event| match event {
+            TraceEvent::Hostname { value } => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.inner.hostname())
+    }
+
+    fn domainname(&self) -> Option<String> {
+        self.recorded(|event| match event {
+            TraceEvent::Domainname { value } => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.inner.domainname())
+    }
+
+    fn memory_limits(&self) -> Option<MemoryLimits> {
+        self.recorded(|event| match event {
+            TraceEvent::MemoryLimits { value } => Some(*value),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.inner.memory_limits())
+    }
+
+    fn max_open_fds(&self) -> Option<usize> {
+        self.recorded(|event| match event {
+            TraceEvent::MaxOpenFds { value } => Some(*value),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.inner.max_open_fds())
+    }
+
+    fn default_args(&self) -> Vec<String> {
+        self.recorded(|event| match event {
+            TraceEvent::DefaultArgs { value } => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.inner.default_args())
+    }
+
+    fn program_name(&self) -> Option<String> {
+        self.recorded(|event| match event {
+            TraceEvent::ProgramName { value } => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.inner.program_name())
+    }
+
+    fn default_cwd(&self) -> Option<PathBuf> {
+        self.recorded(|event| match event {
+            TraceEvent::DefaultCwd { value } => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.inner.default_cwd())
+    }
+
+    fn syscall_rate_limit(&self) -> Option<RateLimit> {
+        self.inner.syscall_rate_limit()
+    }
+
+    fn execution_deadline(&self) -> Option<Instant> {
+        self.inner.execution_deadline()
+    }
+
+    fn load_module<'a>(&'a self, wasm: &'a [u8]) -> BoxFuture<'a, Result<Module, SpawnError>> {
+        self.inner.load_module(wasm)
+    }
+
+    fn load_module_sync(&self, wasm: &[u8]) -> Result<Module, SpawnError> {
+        self.inner.load_module_sync(wasm)
+    }
+
+    fn on_taint(&self, reason: TaintReason) {
+        self.inner.on_taint(reason)
+    }
+
+    fn on_shutdown(&self, f: ShutdownCallback) {
+        self.inner.on_shutdown(f)
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, ()> {
+        self.inner.shutdown()
+    }
+
+    fn shallow_clone(&self) -> Arc<DynRuntime> {
+        Arc::new(self.clone())
+    }
+
+    #[cfg(feature = "journal")]
+    fn journals(&self) -> &'_ Vec<Arc<DynJournal>> {
+        self.inner.journals()
+    }
+
+    #[cfg(feature = "journal")]
+    fn active_journal(&self) -> Option<&'_ DynJournal> {
+        self.inner.active_journal()
+    }
+}
+
+/// Answers requests from the exchanges in a [`RuntimeTrace`].
+#[derive(Debug)]
+struct ReplayHttpClient {
+    exchanges: Mutex<Vec<(RecordedRequest, Result<RecordedResponse, String>)>>,
+}
+
+impl ReplayHttpClient {
+    fn replay(&self, request: &HttpRequest) -> Result<HttpResponse, anyhow::Error> {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        let index = exchanges
+            .iter()
+            .position(|(recorded, _)| {
+                recorded.method == request.method.as_str() && recorded.url == request.url.as_str()
+            })
+            .with_context(|| {
+                format!(
+                    "no recorded response for {} {}",
+                    request.method, request.url
+                )
+            })?;
+
+        match exchanges.remove(index).1 {
+            Ok(response) => response.to_response(),
+            Err(message) => Err(anyhow::Error::msg(message)),
+        }
+    }
+}
+
+impl HttpClient for ReplayHttpClient {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+        let result = self.replay(&request);
+        Box::pin(async move { result })
+    }
+}
+
+/// Hands out the random bytes in a [`RuntimeTrace`].
+#[derive(Debug)]
+struct ReplayRng<R> {
+    runtime: Arc<R>,
+    bytes: Mutex<VecDeque<u8>>,
+}
+
+impl<R> VirtualRng for ReplayRng<R>
+where
+    R: Runtime + Send + Sync,
+{
+    fn fill_bytes(&self, buf: &mut [u8]) {
+        let mut bytes = self.bytes.lock().unwrap();
+        let replayed = buf.len().min(bytes.len());
+        for (dest, byte) in buf.iter_mut().zip(bytes.drain(..replayed)) {
+            *dest = byte;
+        }
+
+        let rest = &mut buf[replayed..];
+        if !rest.is_empty() {
+            match self.runtime.rng() {
+                Some(rng) => rng.fill_bytes(rest),
+                None => rand::thread_rng().fill_bytes(rest),
+            }
+        }
+    }
+}
+
+/// Answers clock reads with the readings in a [`RuntimeTrace`].
+#[derive(Debug)]
+struct ReplayClock<R> {
+    runtime: Arc<R>,
+    readings: Mutex<Vec<(u32, Result<i64, u16>)>>,
+}
+
+impl<R> VirtualClock for ReplayClock<R>
+where
+    R: Runtime + Send + Sync,
+{
+    fn time(&self, clock_id: Snapshot0Clockid, precision: Timestamp) -> Result<i64, Errno> {
+        let mut readings = self.readings.lock().unwrap();
+        let recorded = readings
+            .iter()
+            .position(|(id, _)| *id == clock_id as u32)
+            .map(|index| readings.remove(index).1);
+
+        match recorded {
+            Some(value) => value.map_err(|errno| Errno::try_from(errno).unwrap_or(Errno::Io)),
+            None => match self.runtime.clock() {
+                Some(clock) => clock.time(clock_id, precision),
+                None => platform_clock_time_get(clock_id, precision),
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sys-thread"))]
+mod tests {
+    use crate::{
+        http::HttpRequestOptions,
+        runtime::{task_manager::tokio::TokioTaskManager, PluggableRuntime},
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct EchoClient;
+
+    impl HttpClient for EchoClient {
+        fn request(
+            &self,
+            request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, anyhow::Error>> {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-path", request.url.path().parse().unwrap());
+            Box::pin(async move {
+                Ok(HttpResponse {
+                    body: Some(request.url.path().as_bytes().to_vec()),
+                    redirected: false,
+                    version: None,
+                    status: StatusCode::OK,
+                    headers,
+                })
+            })
+        }
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            url: url.parse().unwrap(),
+            method: http::Method::GET,
+            headers: HeaderMap::new(),
+            body: None,
+            options: HttpRequestOptions::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_serves_recorded_values() {
+        let mut rt = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        rt.set_http_client(EchoClient).set_hostname("recorded");
+        let recording = RecordingRuntime::new(rt);
+
+        assert_eq!(recording.hostname().as_deref(), Some("recorded"));
+        let client = recording.http_client().unwrap();
+        client.request(get("https://example.com/a")).await.unwrap();
+
+        // Round-trip the trace to make sure it is serializable
+        let json = serde_json::to_string(&recording.trace()).unwrap();
+        let trace: RuntimeTrace = serde_json::from_str(&json).unwrap();
+        assert_eq!(trace, recording.trace());
+        assert_eq!(trace.events.len(), 2);
+
+        let rt = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        let replay = ReplayRuntime::new(rt, trace);
+
+        assert_eq!(replay.hostname().as_deref(), Some("recorded"));
+        let client = replay.http_client().unwrap();
+        let response = client.request(get("https://example.com/a")).await.unwrap();
+        assert_eq!(response.body.unwrap(), b"/a");
+        assert_eq!(response.headers["x-path"], "/a");
+        // Each recorded exchange is only replayed once
+        assert!(client.request(get("https://example.com/a")).await.is_err());
+        assert!(client.request(get("https://example.com/b")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn replay_serves_recorded_randomness_and_time() {
+        let rt = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        let recording = RecordingRuntime::new(rt);
+
+        let mut random = [0_u8; 16];
+        recording.rng().unwrap().fill_bytes(&mut random);
+        let clock = recording.clock().unwrap();
+        let realtime = clock.time(Snapshot0Clockid::Realtime, 1).unwrap();
+        let monotonic = clock.time(Snapshot0Clockid::Monotonic, 1).unwrap();
+
+        let json = serde_json::to_string(&recording.trace()).unwrap();
+        let trace: RuntimeTrace = serde_json::from_str(&json).unwrap();
+        assert_eq!(trace.events.len(), 3);
+
+        let rt = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+        let replay = ReplayRuntime::new(rt, trace);
+
+        // Reads may be split differently from when they were recorded
+        let mut replayed = [0_u8; 16];
+        let rng = replay.rng().unwrap();
+        rng.fill_bytes(&mut replayed[..4]);
+        rng.fill_bytes(&mut replayed[4..]);
+        assert_eq!(replayed, random);
+
+        // Each clock is replayed in order, independently of the others
+        let clock = replay.clock().unwrap();
+        assert_eq!(clock.time(Snapshot0Clockid::Monotonic, 1), Ok(monotonic));
+        assert_eq!(clock.time(Snapshot0Clockid::Realtime, 1), Ok(realtime));
+        // Once the recorded readings run out, the real clock is used
+        assert!(clock.time(Snapshot0Clockid::Monotonic, 1).unwrap() >= monotonic);
+    }
+}
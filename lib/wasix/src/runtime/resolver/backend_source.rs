@@ -922,6 +922,7 @@ mod tests {
         let response = HttpResponse {
             body: Some(WASMER_PACK_CLI_RESPONSE.to_vec()),
             redirected: false,
+            version: None,
             status: StatusCode::OK,
             headers: HeaderMap::new(),
         };
@@ -1044,6 +1045,7 @@ mod tests {
         let response = HttpResponse {
             body: Some(serde_json::to_vec(&body).unwrap()),
             redirected: false,
+            version: None,
             status: StatusCode::OK,
             headers: HeaderMap::new(),
         };
@@ -1123,6 +1125,7 @@ mod tests {
         let response = HttpResponse {
             body: Some(serde_json::to_vec(&body).unwrap()),
             redirected: false,
+            version: None,
             status: StatusCode::OK,
             headers: HeaderMap::new(),
         };
@@ -1214,6 +1217,7 @@ mod tests {
         let response = HttpResponse {
             body: Some(serde_json::to_vec(&body).unwrap()),
             redirected: false,
+            version: None,
             status: StatusCode::OK,
             headers: HeaderMap::new(),
         };
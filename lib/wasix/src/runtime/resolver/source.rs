@@ -46,6 +46,21 @@ pub trait Source: Sync + Debug {
                 .ok_or_else(|| QueryError::NotFound { query: pkg.clone() }),
         }
     }
+
+    /// Warm up any caches this source has for the provided packages, so
+    /// later queries don't pay the cost of a cold lookup.
+    ///
+    /// Embedders typically call this at startup. The default implementation
+    /// just runs [`Source::query()`] for each package and discards the
+    /// results, but sources backed by a service with batch APIs may want to
+    /// override it.
+    async fn prefetch(&self, packages: &[PackageSource]) {
+        for package in packages {
+            if let Err(e) = self.query(package).await {
+                tracing::debug!(error = &e as &dyn std::error::Error, "Unable to prefetch");
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -57,6 +72,10 @@ where
     async fn query(&self, package: &PackageSource) -> Result<Vec<PackageSummary>, QueryError> {
         (**self).query(package).await
     }
+
+    async fn prefetch(&self, packages: &[PackageSource]) {
+        (**self).prefetch(packages).await
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -431,6 +431,7 @@ mod tests {
             ResponseBuilder(HttpResponse {
                 body: None,
                 redirected: false,
+                version: None,
                 status: StatusCode::OK,
                 headers: HeaderMap::new(),
             })
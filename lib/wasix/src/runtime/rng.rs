@@ -0,0 +1,309 @@
+use std::{
+    fmt, io,
+    pin::Pin,
+    sync::{Arc, Mutex, OnceLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use uuid::Uuid;
+use virtual_fs::VirtualFile;
+use wasmer_wasix_types::wasi::Snapshot0Clockid;
+
+use crate::syscalls::platform_clock_time_get;
+
+/// A source of random bytes for guests.
+///
+/// When installed, it is used for the `random_get` syscall and for reads
+/// from `/dev/random` and `/dev/urandom`, so a guest can be given
+/// reproducible randomness regardless of how it asks for it.
+pub trait VirtualRng: fmt::Debug + Send + Sync {
+    /// Fill `buf` with random bytes.
+    fn fill_bytes(&self, buf: &mut [u8]);
+}
+
+/// A deterministic [`VirtualRng`] which always produces the same bytes for
+/// the same seed.
+///
+/// This is not cryptographically secure.
+#[derive(Debug)]
+pub struct SeededRng(Mutex<StdRng>);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng(Mutex::new(StdRng::seed_from_u64(seed)))
+    }
+
+    /// Derive an RNG for one instance from a seed shared by a whole fleet.
+    ///
+    /// Each instance gets a different stream of bytes, but the same seed and
+    /// instance ID always produce the same stream.
+    pub fn for_instance(seed: u64, instance_id: Uuid) -> Self {
+        let mut full_seed = <StdRng as SeedableRng>::Seed::default();
+        full_seed[..8].copy_from_slice(&seed.to_le_bytes());
+        full_seed[8..24].copy_from_slice(instance_id.as_bytes());
+        SeededRng(Mutex::new(StdRng::from_seed(full_seed)))
+    }
+}
+
+impl VirtualRng for SeededRng {
+    fn fill_bytes(&self, buf: &mut [u8]) {
+        self.0.lock().unwrap().fill_bytes(buf);
+    }
+}
+
+/// What happens when a guest asks for random bytes and the host's entropy
+/// source fails.
+///
+/// This only applies to runtimes without a [`VirtualRng`]. When one is
+/// installed, the host's entropy source is never used.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum EntropyFallback {
+    /// Keep retrying until the host's entropy source succeeds, unless the
+    /// host doesn't have one at all.
+    Block,
+    /// Fail the guest's request with `EIO`.
+    #[default]
+    FailFast,
+    /// Fall back to a PRNG seeded from the host's clock.
+    ///
+    /// This is not cryptographically secure.
+    UseWeakPrng,
+}
+
+impl EntropyFallback {
+    /// Fill `buf` from the host's entropy source, using `sleep` to wait
+    /// between attempts when blocking.
+    pub(crate) fn fill_bytes(
+        self,
+        buf: &mut [u8],
+        sleep: impl FnMut(Duration),
+    ) -> Result<(), getrandom::Error> {
+        self.fill_bytes_from(buf, getrandom::getrandom, sleep)
+    }
+
+    fn fill_bytes_from(
+        self,
+        buf: &mut [u8],
+        mut source: impl FnMut(&mut [u8]) -> Result<(), getrandom::Error>,
+        mut sleep: impl FnMut(Duration),
+    ) -> Result<(), getrandom::Error> {
+        const MAX_DELAY: Duration = Duration::from_millis(100);
+        let mut delay = Duration::from_millis(1);
+
+        loop {
+            let error = match source(buf) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            match self {
+                EntropyFallback::FailFast => return Err(error),
+                EntropyFallback::UseWeakPrng => {
+                    weak_rng().lock().unwrap().fill_bytes(buf);
+                    return Ok(());
+                }
+                EntropyFallback::Block if error == getrandom::Error::UNSUPPORTED => {
+                    return Err(error);
+                }
+                EntropyFallback::Block => {
+                    tracing::debug!(%error, ?delay, "Waiting for the host's entropy source");
+                    sleep(delay);
+                    delay = (delay * 2).min(MAX_DELAY);
+                }
+            }
+        }
+    }
+}
+
+/// The PRNG used by [`EntropyFallback::UseWeakPrng`].
+fn weak_rng() -> &'static Mutex<StdRng> {
+    static WEAK_RNG: OnceLock<Mutex<StdRng>> = OnceLock::new();
+
+    WEAK_RNG.get_or_init(|| {
+        tracing::warn!("The host's entropy source failed, falling back to a weak PRNG");
+        let now = platform_clock_time_get(Snapshot0Clockid::Realtime, 1).unwrap_or_default();
+        // Mix in an address so processes started at the same time differ
+        let address = &now as *const i64 as u64;
+        Mutex::new(StdRng::seed_from_u64(now as u64 ^ address.rotate_left(32)))
+    })
+}
+
+/// A device file (e.g. `/dev/urandom`) which reads from a [`VirtualRng`].
+///
+/// Writes are accepted and discarded.
+#[derive(Debug, Clone)]
+pub struct RandomDeviceFile {
+    rng: Arc<dyn VirtualRng>,
+}
+
+impl RandomDeviceFile {
+    pub fn new(rng: Arc<dyn VirtualRng>) -> Self {
+        RandomDeviceFile { rng }
+    }
+}
+
+impl AsyncSeek for RandomDeviceFile {
+    fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl AsyncWrite for RandomDeviceFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for RandomDeviceFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.rng.fill_bytes(buf.initialize_unfilled());
+        let remaining = buf.remaining();
+        buf.advance(remaining);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl VirtualFile for RandomDeviceFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn device_file_reads_from_the_rng() {
+        let mut expected = [0_u8; 32];
+        SeededRng::new(42).fill_bytes(&mut expected);
+        let mut file = RandomDeviceFile::new(Arc::new(SeededRng::new(42)));
+
+        let mut buf = [0_u8; 32];
+        file.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn entropy_fallbacks() {
+        let failing = |_: &mut [u8]| Err(getrandom::Error::UNEXPECTED);
+        let mut buf = [0_u8; 32];
+
+        assert_eq!(
+            EntropyFallback::FailFast.fill_bytes_from(&mut buf, failing, |_| unreachable!()),
+            Err(getrandom::Error::UNEXPECTED)
+        );
+
+        EntropyFallback::UseWeakPrng
+            .fill_bytes_from(&mut buf, failing, |_| unreachable!())
+            .unwrap();
+        assert_ne!(buf, [0_u8; 32]);
+
+        let mut attempts = 0;
+        let mut delays = Vec::new();
+        EntropyFallback::Block
+            .fill_bytes_from(
+                &mut buf,
+                |buf| {
+                    attempts += 1;
+                    if attempts < 4 {
+                        Err(getrandom::Error::UNEXPECTED)
+                    } else {
+                        buf.fill(1);
+                        Ok(())
+                    }
+                },
+                |delay| delays.push(delay.as_millis()),
+            )
+            .unwrap();
+        assert_eq!(buf, [1_u8; 32]);
+        assert_eq!(delays, [1, 2, 4]);
+
+        assert_eq!(
+            EntropyFallback::Block.fill_bytes_from(
+                &mut buf,
+                |_| Err(getrandom::Error::UNSUPPORTED),
+                |_| unreachable!()
+            ),
+            Err(getrandom::Error::UNSUPPORTED)
+        );
+    }
+
+    #[test]
+    fn instances_get_their_own_streams() {
+        let bytes = |rng: SeededRng| {
+            let mut buf = [0_u8; 32];
+            rng.fill_bytes(&mut buf);
+            buf
+        };
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+
+        assert_eq!(
+            bytes(SeededRng::for_instance(42, first)),
+            bytes(SeededRng::for_instance(42, first))
+        );
+        assert_ne!(
+            bytes(SeededRng::for_instance(42, first)),
+            bytes(SeededRng::for_instance(42, second))
+        );
+        assert_ne!(
+            bytes(SeededRng::for_instance(42, first)),
+            bytes(SeededRng::for_instance(43, first))
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::{collections::HashMap, fmt};
+
+use zeroize::Zeroizing;
+
+/// A store of named secrets (API keys, tokens, etc.) that guests can be
+/// given access to without putting them in their environment variables.
+pub trait SecretStore: fmt::Debug + Send + Sync {
+    /// Look up a secret by name.
+    fn get(&self, name: &str) -> Option<SecretBytes>;
+}
+
+/// The contents of a secret, which are wiped from memory when dropped.
+///
+/// The [`fmt::Debug`] implementation never prints the contents, so secrets
+/// don't accidentally end up in logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretBytes(Zeroizing<Vec<u8>>);
+
+impl SecretBytes {
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        SecretBytes(Zeroizing::new(bytes.into()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SecretBytes").field(&"<redacted>").finish()
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SecretBytes::new(bytes)
+    }
+}
+
+impl From<String> for SecretBytes {
+    fn from(value: String) -> Self {
+        SecretBytes::new(value)
+    }
+}
+
+impl From<&str> for SecretBytes {
+    fn from(value: &str) -> Self {
+        SecretBytes::new(value)
+    }
+}
+
+/// A [`SecretStore`] which keeps its secrets in memory.
+#[derive(Debug, Default, Clone)]
+pub struct InMemorySecretStore {
+    secrets: HashMap<String, SecretBytes>,
+}
+
+impl InMemorySecretStore {
+    pub fn new() -> Self {
+        InMemorySecretStore::default()
+    }
+
+    pub fn with_secret(mut self, name: impl Into<String>, value: impl Into<SecretBytes>) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<SecretBytes>) {
+        self.secrets.insert(name.into(), value.into());
+    }
+}
+
+impl SecretStore for InMemorySecretStore {
+    fn get(&self, name: &str) -> Option<SecretBytes> {
+        self.secrets.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_never_printed() {
+        let store = InMemorySecretStore::new().with_secret("token", "hunter2");
+
+        assert_eq!(store.get("token").unwrap().as_bytes(), b"hunter2");
+        assert!(store.get("missing").is_none());
+        assert!(!format!("{store:?}").contains("hunter2"));
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use virtual_fs::{FsError, VirtualFile};
+
+/// A fixed-size block of host memory which the host and guests can both
+/// read and write, for passing large payloads without copying them through
+/// stdio or sockets.
+///
+/// Guests see the region as a file under `/dev/shm` (see
+/// [`WasiEnvBuilder::map_shared_region()`][map]), which they can read and
+/// write at any offset.
+///
+/// [map]: crate::WasiEnvBuilder::map_shared_region
+#[derive(Debug)]
+pub struct SharedRegion {
+    bytes: RwLock<Box<[u8]>>,
+}
+
+impl SharedRegion {
+    /// Allocate a zeroed region of `len` bytes.
+    pub fn new(len: usize) -> Self {
+        SharedRegion {
+            bytes: RwLock::new(vec![0; len].into_boxed_slice()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy bytes starting at `offset` into `buf`, returning how many were
+    /// copied (fewer than `buf.len()` at the end of the region).
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.with_bytes(|bytes| {
+            let available = bytes.get(offset..).unwrap_or_default();
+            let len = available.len().min(buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            len
+        })
+    }
+
+    /// Copy `data` into the region starting at `offset`, returning how many
+    /// bytes fit.
+    pub fn write_at(&self, offset: usize, data: &[u8]) -> usize {
+        self.with_bytes_mut(|bytes| {
+            let available = bytes.get_mut(offset..).unwrap_or_default();
+            let len = available.len().min(data.len());
+            available[..len].copy_from_slice(&data[..len]);
+            len
+        })
+    }
+
+    /// Access the whole region without copying it.
+    pub fn with_bytes<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        f(&self.bytes.read().unwrap())
+    }
+
+    /// Modify the whole region in place.
+    pub fn with_bytes_mut<R>(&self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        f(&mut self.bytes.write().unwrap())
+    }
+}
+
+/// A file which reads and writes a [`SharedRegion`].
+///
+/// The file is always the same size as the region, so writing past the end
+/// writes nothing.
+#[derive(Debug, Clone)]
+pub struct SharedRegionFile {
+    region: Arc<SharedRegion>,
+    position: usize,
+}
+
+impl SharedRegionFile {
+    pub fn new(region: Arc<SharedRegion>) -> Self {
+        SharedRegionFile {
+            region,
+            position: 0,
+        }
+    }
+}
+
+impl AsyncSeek for SharedRegionFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        let (base, offset) = match position {
+            io::SeekFrom::Start(offset) => {
+                self.position = usize::try_from(offset).unwrap_or(usize::MAX);
+                return Ok(());
+            }
+            io::SeekFrom::End(offset) => (self.region.len(), offset),
+            io::SeekFrom::Current(offset) => (self.position, offset),
+        };
+
+        self.position = i64::try_from(base)
+            .ok()
+            .and_then(|base| base.checked_add(offset))
+            .and_then(|position| usize::try_from(position).ok())
+            .ok_or(io::ErrorKind::InvalidInput)?;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position as u64))
+    }
+}
+
+impl AsyncWrite for SharedRegionFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let written = self.region.write_at(self.position, buf);
+        self.position += written;
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for SharedRegionFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = self
+            .region
+            .read_at(self.position, buf.initialize_unfilled());
+        buf.advance(read);
+        self.position += read;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl VirtualFile for SharedRegionFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        self.region.len() as u64
+    }
+
+    fn set_len(&mut self, new_size: u64) -> virtual_fs::Result<()> {
+        // The region is allocated by the host, so it can't be resized
+        if new_size == self.size() {
+            Ok(())
+        } else {
+            Err(FsError::PermissionDenied)
+        }
+    }
+
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let remaining = self.region.len().saturating_sub(self.position);
+        Poll::Ready(Ok(remaining))
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let remaining = self.region.len().saturating_sub(self.position);
+        Poll::Ready(Ok(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn file_reads_and_writes_the_region() {
+        let region = Arc::new(SharedRegion::new(8));
+        region.write_at(0, b"host");
+        let mut file = SharedRegionFile::new(region.clone());
+
+        let mut buf = [0_u8; 4];
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"host");
+
+        file.seek(io::SeekFrom::End(-4)).await.unwrap();
+        assert_eq!(file.write(b"guest").await.unwrap(), 4);
+        region.with_bytes(|bytes| assert_eq!(bytes, b"hostgues"));
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use futures::{future::BoxFuture, Future};
+use wasmer::{Memory, Module, StoreMut};
+
+use crate::os::task::thread::WasiThreadError;
+
+use super::{AffinityKey, SpawnMemoryType, TaskId, TaskPriority, TaskWasm, VirtualTaskManager};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A [`VirtualTaskManager`] which runs tasks spawned with
+/// [`VirtualTaskManager::task_dedicated_with_affinity()`] on an OS thread
+/// dedicated to their [`AffinityKey`].
+///
+/// Each key gets its own thread the first time it is used, and tasks with
+/// the same key are run on that thread one after another, in the order they
+/// were spawned. This lets guests use host resources which are bound to a
+/// particular thread (e.g. GPU contexts or thread-local state).
+///
+/// Everything else goes straight to the inner task manager.
+#[derive(Debug, Clone)]
+pub struct ThreadAffinityTaskManager {
+    inner: Arc<dyn VirtualTaskManager>,
+    threads: Arc<Mutex<HashMap<AffinityKey, mpsc::Sender<Job>>>>,
+}
+
+impl ThreadAffinityTaskManager {
+    pub fn new(inner: Arc<dyn VirtualTaskManager>) -> Self {
+        ThreadAffinityTaskManager {
+            inner,
+            threads: Arc::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &Arc<dyn VirtualTaskManager> {
+        &self.inner
+    }
+
+    /// The number of keys which currently have a thread.
+    pub fn thread_count(&self) -> usize {
+        self.threads.lock().unwrap().len()
+    }
+
+    /// Let the thread for `key` exit once it has run every task which was
+    /// already spawned on it.
+    ///
+    /// Spawning another task with the same key starts a new thread.
+    pub fn release(&self, key: AffinityKey) {
+        self.threads.lock().unwrap().remove(&key);
+    }
+
+    fn spawn_thread(key: AffinityKey) -> Result<mpsc::Sender<Job>, WasiThreadError> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+
+        std::thread::Builder::new()
+            .name(format!("wasmer-affinity-{key}"))
+            .spawn(move || {
+                while let Ok(job) = receiver.recv() {
+                    job();
+                }
+            })
+            .map_err(|e| WasiThreadError::InitFailed(Arc::new(e.into())))?;
+
+        Ok(sender)
+    }
+}
+
+impl VirtualTaskManager for ThreadAffinityTaskManager {
+    fn build_memory(
+        &self,
+        store: &mut StoreMut,
+        spawn_type: SpawnMemoryType,
+    ) -> Result<Option<Memory>, WasiThreadError> {
+        self.inner.build_memory(store, spawn_type)
+    }
+
+    fn sleep_now(
+        &self,
+        time: Duration,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        self.inner.sleep_now(time)
+    }
+
+    fn yield_now(&self) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        self.inner.yield_now()
+    }
+
+    fn task_shared(
+        &self,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.inner.task_shared(task)
+    }
+
+    fn task_shared_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.inner.task_shared_with_priority(priority, task)
+    }
+
+    fn task_wasm(&self, task: TaskWasm) -> Result<(), WasiThreadError> {
+        self.inner.task_wasm(task)
+    }
+
+    fn task_dedicated(
+        &self,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.inner.task_dedicated(task)
+    }
+
+    fn task_dedicated_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.inner.task_dedicated_with_priority(priority, task)
+    }
+
+    fn task_dedicated_with_affinity(
+        &self,
+        key: AffinityKey,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        let mut threads = self.threads.lock().unwrap();
+
+        let task = match threads.get(&key) {
+            Some(sender) => match sender.send(task) {
+                Ok(()) => return Ok(()),
+                // The thread died (i.e. a previous task panicked), so start
+                // a new one
+                Err(mpsc::SendError(task)) => task,
+            },
+            None => task,
+        };
+
+        let sender = Self::spawn_thread(key)?;
+        sender.send(task).expect("the thread was only just started");
+        threads.insert(key, sender);
+
+        Ok(())
+    }
+
+    fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
+        self.inner.thread_parallelism()
+    }
+
+    fn enqueued_count(&self) -> usize {
+        self.inner.enqueued_count()
+    }
+
+    fn list_tasks(&self) -> Vec<TaskId> {
+        self.inner.list_tasks()
+    }
+
+    fn abort_task(&self, id: TaskId) {
+        self.inner.abort_task(id)
+    }
+
+    fn observe_scheduling_latency(&self) -> Option<Duration> {
+        self.inner.observe_scheduling_latency()
+    }
+
+    fn pause(&self) {
+        self.inner.pause()
+    }
+
+    fn resume(&self) {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+
+    fn spawn_with_module(
+        &self,
+        module: Module,
+        task: Box<dyn FnOnce(Module) + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.inner.spawn_with_module(module, task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::ThreadId;
+
+    use super::super::test_utils::ManualTaskManager;
+    use super::*;
+
+    fn thread_of(
+        manager: &ThreadAffinityTaskManager,
+        inner: &ManualTaskManager,
+        key: Option<AffinityKey>,
+    ) -> ThreadId {
+        let (sender, receiver) = mpsc::channel();
+        let task = Box::new(move || sender.send(std::thread::current().id()).unwrap());
+        match key {
+            Some(key) => manager.task_dedicated_with_affinity(key, task).unwrap(),
+            None => {
+                manager.task_dedicated(task).unwrap();
+                assert!(inner.run_next());
+            }
+        }
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn tasks_with_the_same_key_share_a_thread() {
+        let inner = Arc::new(ManualTaskManager::default());
+        let manager = ThreadAffinityTaskManager::new(inner.clone());
+        let (a, b) = (AffinityKey(1), AffinityKey(2));
+
+        let first = thread_of(&manager, &inner, Some(a));
+        assert_ne!(first, std::thread::current().id());
+        assert_eq!(thread_of(&manager, &inner, Some(a)), first);
+        assert_ne!(thread_of(&manager, &inner, Some(b)), first);
+        assert_eq!(manager.thread_count(), 2);
+
+        // Tasks without a key go to the inner task manager
+        assert_eq!(
+            thread_of(&manager, &inner, None),
+            std::thread::current().id()
+        );
+
+        manager.release(a);
+        assert_eq!(manager.thread_count(), 1);
+        assert_ne!(thread_of(&manager, &inner, Some(a)), first);
+    }
+}
@@ -2,12 +2,21 @@
 #[cfg(feature = "sys-thread")]
 pub mod tokio;
 
+#[cfg(feature = "rayon-thread")]
+pub mod rayon;
+
+mod affinity;
+mod priority;
+
+pub use self::{affinity::ThreadAffinityTaskManager, priority::PriorityTaskManager};
+
 use std::ops::Deref;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::{pin::Pin, time::Duration};
 
 use bytes::Bytes;
-use futures::future::BoxFuture;
+use futures::future::{BoxFuture, Either};
 use futures::{Future, TryFutureExt};
 use wasmer::{AsStoreMut, AsStoreRef, Memory, MemoryType, Module, Store, StoreMut, StoreRef};
 use wasmer_wasix_types::wasi::{Errno, ExitCode};
@@ -18,6 +27,56 @@ use crate::{capture_store_snapshot, StoreSnapshot, WasiEnv, WasiFunctionEnv, Was
 
 pub use virtual_mio::waker::*;
 
+/// Identifies a task that was spawned by a [`VirtualTaskManager`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(pub u64);
+
+impl std::fmt::Display for TaskId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Identifies the OS thread a task spawned with
+/// [`VirtualTaskManager::task_dedicated_with_affinity()`] should run on.
+///
+/// Most task managers ignore this, but see [`ThreadAffinityTaskManager`] for
+/// one which doesn't.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AffinityKey(pub u64);
+
+impl std::fmt::Display for AffinityKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// How urgently a task should be run, relative to other tasks.
+///
+/// Most task managers ignore this, but see [`PriorityTaskManager`] for one
+/// which doesn't.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TaskPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl TaskPriority {
+    pub(crate) const COUNT: usize = 3;
+
+    /// The position of this priority in a list sorted from highest to
+    /// lowest priority.
+    pub(crate) fn index(self) -> usize {
+        match self {
+            TaskPriority::High => 0,
+            TaskPriority::Normal => 1,
+            TaskPriority::Low => 2,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SpawnMemoryType<'a> {
     CreateMemory,
@@ -204,6 +263,15 @@ pub trait VirtualTaskManager: std::fmt::Debug + Send + Sync + 'static {
         time: Duration,
     ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>>;
 
+    /// Give other tasks a chance to run before carrying on.
+    ///
+    /// This is what the `sched_yield` syscall uses, so guests which spin in
+    /// tight loops can avoid starving everything else. The default resolves
+    /// immediately.
+    fn yield_now(&self) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        Box::pin(std::future::ready(()))
+    }
+
     /// Run an asynchronous operation on the thread pool.
     ///
     /// This task must not block execution or it could cause deadlocks.
@@ -215,6 +283,19 @@ pub trait VirtualTaskManager: std::fmt::Debug + Send + Sync + 'static {
         task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
     ) -> Result<(), WasiThreadError>;
 
+    /// Run an asynchronous operation on the thread pool with a given
+    /// [`TaskPriority`].
+    ///
+    /// Task managers which don't support priorities treat this the same as
+    /// [`VirtualTaskManager::task_shared()`].
+    fn task_shared_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.task_shared(task)
+    }
+
     /// Run a blocking WebAssembly operation on the thread pool.
     ///
     /// This is primarily used inside the context of a syscall and allows
@@ -230,9 +311,109 @@ pub trait VirtualTaskManager: std::fmt::Debug + Send + Sync + 'static {
         task: Box<dyn FnOnce() + Send + 'static>,
     ) -> Result<(), WasiThreadError>;
 
+    /// Run a blocking operation on the thread pool with a given
+    /// [`TaskPriority`].
+    ///
+    /// Task managers which don't support priorities treat this the same as
+    /// [`VirtualTaskManager::task_dedicated()`].
+    fn task_dedicated_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.task_dedicated(task)
+    }
+
+    /// Run a blocking operation on the OS thread associated with `key`, so
+    /// every task with the same key runs on the same thread.
+    ///
+    /// Task managers which don't support thread affinity treat this the same
+    /// as [`VirtualTaskManager::task_dedicated()`].
+    fn task_dedicated_with_affinity(
+        &self,
+        key: AffinityKey,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.task_dedicated(task)
+    }
+
+    /// Run a blocking operation on the thread pool, giving up on it if it
+    /// does not complete within `timeout`.
+    ///
+    /// Blocking threads can not be cancelled, so when the deadline passes the
+    /// `work` keeps running in the background and only the caller regains
+    /// control, receiving a [`WasiThreadError::TimedOut`] error.
+    fn run_blocking_with_timeout(
+        &self,
+        work: Box<dyn FnOnce() + Send + 'static>,
+        timeout: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<(), WasiThreadError>> + Send + 'static>> {
+        let (tx, rx) = ::tokio::sync::oneshot::channel();
+        let spawned = self.task_dedicated(Box::new(move || {
+            work();
+            let _ = tx.send(());
+        }));
+        if let Err(err) = spawned {
+            return Box::pin(async move { Err(err) });
+        }
+
+        let deadline = self.sleep_now(timeout);
+        Box::pin(async move {
+            match futures::future::select(rx, deadline).await {
+                Either::Left((Ok(()), _)) => Ok(()),
+                Either::Left((Err(_), _)) => Err(WasiThreadError::InitFailed(Arc::new(
+                    anyhow::anyhow!("blocking task terminated before completing"),
+                ))),
+                Either::Right(_) => Err(WasiThreadError::TimedOut),
+            }
+        })
+    }
+
     /// Returns the amount of parallelism that is possible on this platform.
     fn thread_parallelism(&self) -> Result<usize, WasiThreadError>;
 
+    /// The number of tasks which have been scheduled but haven't started
+    /// running yet.
+    fn enqueued_count(&self) -> usize {
+        0
+    }
+
+    /// List the tasks which are currently running and can be aborted with
+    /// [`VirtualTaskManager::abort_task()`].
+    fn list_tasks(&self) -> Vec<TaskId> {
+        Vec::new()
+    }
+
+    /// Abort a running task.
+    ///
+    /// This does nothing if the task has already finished or if the task
+    /// manager doesn't support cancellation.
+    fn abort_task(&self, id: TaskId) {}
+
+    /// How long the most recently started task had to wait between being
+    /// scheduled and starting to run, if the task manager keeps track of it.
+    fn observe_scheduling_latency(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Stop starting new tasks until [`VirtualTaskManager::resume()`] is
+    /// called.
+    ///
+    /// Tasks which are already running carry on until they next yield to the
+    /// task manager (e.g. in [`VirtualTaskManager::sleep_now()`]) and wait
+    /// there. Tasks scheduled while paused are queued rather than rejected.
+    ///
+    /// This does nothing if the task manager doesn't support pausing.
+    fn pause(&self) {}
+
+    /// Let tasks run again after a [`VirtualTaskManager::pause()`].
+    fn resume(&self) {}
+
+    /// Whether the task manager is currently paused.
+    fn is_paused(&self) -> bool {
+        false
+    }
+
     /// Schedule a blocking task to run on the threadpool, explicitly
     /// transferring a [`Module`] to the task.
     ///
@@ -279,6 +460,10 @@ where
         (**self).sleep_now(time)
     }
 
+    fn yield_now(&self) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        (**self).yield_now()
+    }
+
     fn task_shared(
         &self,
         task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
@@ -286,6 +471,14 @@ where
         (**self).task_shared(task)
     }
 
+    fn task_shared_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        (**self).task_shared_with_priority(priority, task)
+    }
+
     fn task_wasm(&self, task: TaskWasm) -> Result<(), WasiThreadError> {
         (**self).task_wasm(task)
     }
@@ -297,10 +490,62 @@ where
         (**self).task_dedicated(task)
     }
 
+    fn task_dedicated_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        (**self).task_dedicated_with_priority(priority, task)
+    }
+
+    fn task_dedicated_with_affinity(
+        &self,
+        key: AffinityKey,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        (**self).task_dedicated_with_affinity(key, task)
+    }
+
+    fn run_blocking_with_timeout(
+        &self,
+        work: Box<dyn FnOnce() + Send + 'static>,
+        timeout: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<(), WasiThreadError>> + Send + 'static>> {
+        (**self).run_blocking_with_timeout(work, timeout)
+    }
+
     fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
         (**self).thread_parallelism()
     }
 
+    fn enqueued_count(&self) -> usize {
+        (**self).enqueued_count()
+    }
+
+    fn observe_scheduling_latency(&self) -> Option<Duration> {
+        (**self).observe_scheduling_latency()
+    }
+
+    fn pause(&self) {
+        (**self).pause()
+    }
+
+    fn resume(&self) {
+        (**self).resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        (**self).is_paused()
+    }
+
+    fn list_tasks(&self) -> Vec<TaskId> {
+        (**self).list_tasks()
+    }
+
+    fn abort_task(&self, id: TaskId) {
+        (**self).abort_task(id)
+    }
+
     fn spawn_with_module(
         &self,
         module: Module,
@@ -459,3 +704,64 @@ where
         Box::new(receiver.map_err(|e| Box::new(e).into()))
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// A task manager for tests which queues tasks until they are run with
+    /// [`ManualTaskManager::run_next()`].
+    #[derive(Debug, Default)]
+    pub(crate) struct ManualTaskManager {
+        pending: Mutex<VecDeque<Box<dyn FnOnce() + Send + 'static>>>,
+    }
+
+    impl ManualTaskManager {
+        /// Run the oldest queued task, returning `false` if there was none.
+        pub(crate) fn run_next(&self) -> bool {
+            let task = self.pending.lock().unwrap().pop_front();
+            match task {
+                Some(task) => {
+                    task();
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    impl VirtualTaskManager for ManualTaskManager {
+        fn sleep_now(
+            &self,
+            _time: Duration,
+        ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+            Box::pin(std::future::ready(()))
+        }
+
+        fn task_shared(
+            &self,
+            task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+        ) -> Result<(), WasiThreadError> {
+            self.task_dedicated(Box::new(move || futures::executor::block_on(task())))
+        }
+
+        fn task_wasm(&self, _task: TaskWasm) -> Result<(), WasiThreadError> {
+            Err(WasiThreadError::Unsupported)
+        }
+
+        fn task_dedicated(
+            &self,
+            task: Box<dyn FnOnce() + Send + 'static>,
+        ) -> Result<(), WasiThreadError> {
+            self.pending.lock().unwrap().push_back(task);
+            Ok(())
+        }
+
+        fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
+            Ok(1)
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{future::BoxFuture, Future};
+use wasmer::{Memory, Module, StoreMut};
+
+use crate::os::task::thread::WasiThreadError;
+
+use super::{SpawnMemoryType, TaskId, TaskPriority, TaskWasm, VirtualTaskManager};
+
+/// A [`VirtualTaskManager`] which queues tasks by [`TaskPriority`] before
+/// handing them to another task manager.
+///
+/// At most `max_in_flight` tasks are passed to the inner task manager at a
+/// time. When a slot frees up, the oldest task from the highest priority
+/// queue is started, except that a lower priority tier which has been passed
+/// over `max_skips` times in a row gets the next slot so it is never starved.
+///
+/// Only [`VirtualTaskManager::task_shared()`] and
+/// [`VirtualTaskManager::task_dedicated()`] (and their `_with_priority`
+/// variants) are queued. Everything else, including
+/// [`VirtualTaskManager::task_wasm()`], goes straight to the inner task
+/// manager.
+///
+/// Tasks which block until another task completes count against
+/// `max_in_flight` while they wait, so the limit needs to be large enough
+/// for the guests being run.
+#[derive(Debug, Clone)]
+pub struct PriorityTaskManager {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    inner: Arc<dyn VirtualTaskManager>,
+    max_in_flight: usize,
+    max_skips: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    /// One queue per [`TaskPriority`], highest priority first.
+    queues: [VecDeque<Job>; TaskPriority::COUNT],
+    /// How many times each tier has been passed over while it had work
+    /// waiting.
+    skips: [usize; TaskPriority::COUNT],
+}
+
+enum Job {
+    Shared(Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>),
+    Dedicated(Box<dyn FnOnce() + Send + 'static>),
+}
+
+impl std::fmt::Debug for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Job::Shared(_) => f.write_str("Job::Shared"),
+            Job::Dedicated(_) => f.write_str("Job::Dedicated"),
+        }
+    }
+}
+
+impl PriorityTaskManager {
+    /// The default for [`PriorityTaskManager::with_max_skips()`].
+    pub const DEFAULT_MAX_SKIPS: usize = 8;
+
+    /// Wrap `inner`, letting it run as many tasks at once as it reports in
+    /// [`VirtualTaskManager::thread_parallelism()`].
+    pub fn new(inner: Arc<dyn VirtualTaskManager>) -> Self {
+        let max_in_flight = inner.thread_parallelism().unwrap_or(1);
+        PriorityTaskManager::with_max_in_flight(inner, max_in_flight)
+    }
+
+    /// Wrap `inner`, letting it run at most `max_in_flight` tasks at once.
+    pub fn with_max_in_flight(inner: Arc<dyn VirtualTaskManager>, max_in_flight: usize) -> Self {
+        PriorityTaskManager {
+            shared: Arc::new(Shared {
+                inner,
+                max_in_flight: max_in_flight.max(1),
+                max_skips: Self::DEFAULT_MAX_SKIPS,
+                state: Mutex::default(),
+            }),
+        }
+    }
+
+    /// How many times in a row a tier with queued tasks may be passed over
+    /// in favour of higher priority work before it gets the next slot.
+    ///
+    /// Must be called before any tasks are spawned.
+    pub fn with_max_skips(mut self, max_skips: usize) -> Self {
+        let shared = Arc::get_mut(&mut self.shared)
+            .expect("with_max_skips() must be called before the task manager is shared");
+        shared.max_skips = max_skips;
+        self
+    }
+
+    /// The number of tasks waiting for a slot, across all priorities.
+    pub fn queued_count(&self) -> usize {
+        let state = self.shared.state.lock().unwrap();
+        state.queues.iter().map(VecDeque::len).sum()
+    }
+
+    fn enqueue(&self, priority: TaskPriority, job: Job) {
+        self.shared.state.lock().unwrap().queues[priority.index()].push_back(job);
+        Shared::pump(&self.shared);
+    }
+}
+
+impl Shared {
+    /// Start queued tasks until either every slot is taken or the queues are
+    /// empty.
+    fn pump(shared: &Arc<Shared>) {
+        loop {
+            let job = {
+                let mut state = shared.state.lock().unwrap();
+                if state.in_flight >= shared.max_in_flight {
+                    return;
+                }
+                match state.next_job(shared.max_skips) {
+                    Some(job) => {
+                        state.in_flight += 1;
+                        job
+                    }
+                    None => return,
+                }
+            };
+
+            let slot = Slot(shared.clone());
+            let result = match job {
+                Job::Shared(task) => shared.inner.task_shared(Box::new(move || {
+                    let task = task();
+                    Box::pin(async move {
+                        task.await;
+                        drop(slot);
+                    })
+                })),
+                Job::Dedicated(task) => shared.inner.task_dedicated(Box::new(move || {
+                    task();
+                    drop(slot);
+                })),
+            };
+
+            if let Err(e) = result {
+                // The slot was dropped along with the task, so the loop
+                // carries on with the next one
+                tracing::error!(
+                    error = &e as &dyn std::error::Error,
+                    "unable to start a queued task"
+                );
+            }
+        }
+    }
+}
+
+impl State {
+    fn next_job(&mut self, max_skips: usize) -> Option<Job> {
+        let highest = self.queues.iter().position(|q| !q.is_empty())?;
+
+        // Give the lowest starved tier priority, so every tier eventually
+        // makes progress
+        let tier = (highest + 1..TaskPriority::COUNT)
+            .rev()
+            .find(|&tier| !self.queues[tier].is_empty() && self.skips[tier] >= max_skips)
+            .unwrap_or(highest);
+
+        for (i, skips) in self.skips.iter_mut().enumerate() {
+            if i == tier {
+                *skips = 0;
+            } else if i > tier && !self.queues[i].is_empty() {
+                *skips += 1;
+            }
+        }
+
+        self.queues[tier].pop_front()
+    }
+}
+
+/// A slot in the inner task manager, released when the task finishes.
+struct Slot(Arc<Shared>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().in_flight -= 1;
+        Shared::pump(&self.0);
+    }
+}
+
+impl VirtualTaskManager for PriorityTaskManager {
+    fn build_memory(
+        &self,
+        store: &mut StoreMut,
+        spawn_type: SpawnMemoryType,
+    ) -> Result<Option<Memory>, WasiThreadError> {
+        self.shared.inner.build_memory(store, spawn_type)
+    }
+
+    fn sleep_now(
+        &self,
+        time: Duration,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        self.shared.inner.sleep_now(time)
+    }
+
+    fn yield_now(&self) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        self.shared.inner.yield_now()
+    }
+
+    fn task_shared(
+        &self,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.task_shared_with_priority(TaskPriority::default(), task)
+    }
+
+    fn task_shared_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.enqueue(priority, Job::Shared(task));
+        Ok(())
+    }
+
+    fn task_wasm(&self, task: TaskWasm) -> Result<(), WasiThreadError> {
+        self.shared.inner.task_wasm(task)
+    }
+
+    fn task_dedicated(
+        &self,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.task_dedicated_with_priority(TaskPriority::default(), task)
+    }
+
+    fn task_dedicated_with_priority(
+        &self,
+        priority: TaskPriority,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.enqueue(priority, Job::Dedicated(task));
+        Ok(())
+    }
+
+    fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
+        self.shared.inner.thread_parallelism()
+    }
+
+    fn enqueued_count(&self) -> usize {
+        self.queued_count() + self.shared.inner.enqueued_count()
+    }
+
+    fn list_tasks(&self) -> Vec<TaskId> {
+        self.shared.inner.list_tasks()
+    }
+
+    fn abort_task(&self, id: TaskId) {
+        self.shared.inner.abort_task(id)
+    }
+
+    fn observe_scheduling_latency(&self) -> Option<Duration> {
+        self.shared.inner.observe_scheduling_latency()
+    }
+
+    fn pause(&self) {
+        self.shared.inner.pause()
+    }
+
+    fn resume(&self) {
+        self.shared.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.shared.inner.is_paused()
+    }
+
+    fn spawn_with_module(
+        &self,
+        module: Module,
+        task: Box<dyn FnOnce(Module) + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.shared.inner.spawn_with_module(module, task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::ManualTaskManager;
+    use super::*;
+
+    fn spawn(
+        manager: &PriorityTaskManager,
+        priority: TaskPriority,
+        log: &Arc<Mutex<Vec<&'static str>>>,
+        name: &'static str,
+    ) {
+        let log = log.clone();
+        manager
+            .task_dedicated_with_priority(
+                priority,
+                Box::new(move || log.lock().unwrap().push(name)),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn higher_priority_tasks_run_first() {
+        let inner = Arc::new(ManualTaskManager::default());
+        let manager = PriorityTaskManager::new(inner.clone());
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        spawn(&manager, TaskPriority::Low, &log, "first");
+        spawn(&manager, TaskPriority::Low, &log, "low");
+        spawn(&manager, TaskPriority::Normal, &log, "normal");
+        spawn(&manager, TaskPriority::High, &log, "high");
+        assert_eq!(manager.queued_count(), 3);
+
+        while inner.run_next() {}
+
+        assert_eq!(*log.lock().unwrap(), ["first", "high", "normal", "low"]);
+    }
+
+    #[test]
+    fn lower_priorities_are_not_starved() {
+        let inner = Arc::new(ManualTaskManager::default());
+        let manager = PriorityTaskManager::new(inner.clone()).with_max_skips(2);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        spawn(&manager, TaskPriority::High, &log, "high");
+        spawn(&manager, TaskPriority::Low, &log, "low");
+        for _ in 0..4 {
+            spawn(&manager, TaskPriority::High, &log, "high");
+        }
+
+        while inner.run_next() {}
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["high", "high", "high", "low", "high", "high"]
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
+use std::{pin::Pin, time::Duration};
+
+use futures::{future::BoxFuture, Future};
+
+use crate::{os::task::thread::WasiThreadError, WasiFunctionEnv};
+
+use super::{TaskWasm, TaskWasmRunProperties, VirtualTaskManager};
+
+/// A task manager that runs everything on a fixed-size rayon thread pool.
+///
+/// Rayon's work-stealing scheduler is tuned for CPU-bound work, which makes
+/// this a good fit for compute-heavy guests.
+///
+/// This task manager is unsuitable for guests that do a lot of async I/O.
+/// Each async task blocks a pool thread until it completes, and there is no
+/// tokio runtime available, so anything relying on tokio's reactor (e.g.
+/// host networking) will not work.
+#[derive(Clone, Debug)]
+pub struct RayonTaskManager {
+    pool: Arc<rayon::ThreadPool>,
+    timer: Arc<Timer>,
+}
+
+impl RayonTaskManager {
+    /// Create a task manager with its own pool of `num_threads` threads.
+    pub fn new(num_threads: usize) -> Result<Self, rayon::ThreadPoolBuildError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("RayonTaskManager Thread Pool {i}"))
+            .build()?;
+        Ok(Self::with_pool(Arc::new(pool)))
+    }
+
+    /// Create a task manager which runs tasks on an existing thread pool.
+    pub fn with_pool(pool: Arc<rayon::ThreadPool>) -> Self {
+        Self {
+            pool,
+            timer: Arc::new(Timer::new()),
+        }
+    }
+
+    pub fn pool_handle(&self) -> Arc<rayon::ThreadPool> {
+        self.pool.clone()
+    }
+}
+
+impl Default for RayonTaskManager {
+    /// Create a task manager with one thread per CPU.
+    fn default() -> Self {
+        Self::new(0).expect("unable to create the rayon thread pool")
+    }
+}
+
+impl VirtualTaskManager for RayonTaskManager {
+    /// See [`VirtualTaskManager::sleep_now`].
+    fn sleep_now(&self, time: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + Sync>> {
+        Box::pin(self.timer.sleep(time))
+    }
+
+    /// See [`VirtualTaskManager::task_shared`].
+    fn task_shared(
+        &self,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.pool.spawn(move || futures::executor::block_on(task()));
+        Ok(())
+    }
+
+    /// See [`VirtualTaskManager::task_wasm`].
+    fn task_wasm(&self, task: TaskWasm) -> Result<(), WasiThreadError> {
+        // Create the context on a new store
+        let run = task.run;
+        let recycle = task.recycle;
+        let (ctx, mut store) = WasiFunctionEnv::new_with_store(
+            task.module,
+            task.env,
+            task.globals,
+            task.spawn_type,
+            task.update_layout,
+        )?;
+
+        let Some(trigger) = task.trigger else {
+            tracing::trace!("spawning task_wasm on the rayon pool");
+            self.pool.spawn(move || {
+                run(TaskWasmRunProperties {
+                    ctx,
+                    store,
+                    trigger_result: None,
+                    recycle,
+                });
+            });
+            return Ok(());
+        };
+
+        tracing::trace!("spawning task_wasm trigger on the rayon pool");
+        let mut trigger = trigger();
+        self.pool.spawn(move || {
+            // We wait for either the trigger or for a snapshot to take place
+            let result = futures::executor::block_on(async {
+                loop {
+                    let env = ctx.data(&store);
+                    break tokio::select! {
+                        r = &mut trigger => r,
+                        _ = env.thread.wait_for_signal() => {
+                            tracing::debug!("wait-for-signal(triggered)");
+                            let mut ctx = ctx.env.clone().into_mut(&mut store);
+                            if let Err(err) = crate::WasiEnv::process_signals_and_exit(&mut ctx) {
+                                match err {
+                                    crate::WasiError::Exit(code) => Err(code),
+                                    // Sleeping threads can't trap, so they exit instead
+                                    crate::WasiError::DeadlineExceeded => {
+                                        Err(wasmer_wasix_types::wasi::Errno::Timedout.into())
+                                    }
+                                    err => {
+                                        tracing::error!("failed to process signals - {}", err);
+                                        continue;
+                                    }
+                                }
+                            } else {
+                                continue;
+                            }
+                        }
+                        _ = crate::wait_for_snapshot(env) => {
+                            tracing::debug!("wait-for-snapshot(triggered)");
+                            let mut ctx = ctx.env.clone().into_mut(&mut store);
+                            crate::os::task::WasiProcessInner::do_checkpoints_from_outside(&mut ctx);
+                            continue;
+                        }
+                    };
+                }
+            });
+
+            run(TaskWasmRunProperties {
+                ctx,
+                store,
+                trigger_result: Some(result),
+                recycle,
+            });
+        });
+        Ok(())
+    }
+
+    /// See [`VirtualTaskManager::task_dedicated`].
+    fn task_dedicated(
+        &self,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        self.pool.spawn(task);
+        Ok(())
+    }
+
+    /// See [`VirtualTaskManager::thread_parallelism`].
+    fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
+        Ok(self.pool.current_num_threads())
+    }
+}
+
+/// Wakes up [`Sleep`] futures from a single background thread.
+#[derive(Debug)]
+struct Timer {
+    shared: Arc<TimerShared>,
+}
+
+#[derive(Debug, Default)]
+struct TimerShared {
+    state: Mutex<TimerState>,
+    condvar: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct TimerState {
+    next_id: u64,
+    sleepers: BTreeMap<(Instant, u64), Arc<SleepState>>,
+    shutdown: bool,
+}
+
+impl Timer {
+    fn new() -> Self {
+        let shared = Arc::new(TimerShared::default());
+
+        let background = shared.clone();
+        std::thread::Builder::new()
+            .name("RayonTaskManager Timer".to_string())
+            .spawn(move || background.run())
+            .expect("unable to spawn the timer thread");
+
+        Timer { shared }
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let state = Arc::new(SleepState::default());
+        let now = Instant::now();
+        // Treat absurdly long sleeps as "sleep for a very long time" instead
+        // of overflowing.
+        let deadline = now
+            .checked_add(duration)
+            .unwrap_or_else(|| now + Duration::from_secs(60 * 60 * 24 * 365));
+
+        let mut timer = self.shared.state.lock().unwrap();
+        let key = (deadline, timer.next_id);
+        timer.next_id += 1;
+        timer.sleepers.insert(key, state.clone());
+        drop(timer);
+        self.shared.condvar.notify_one();
+
+        Sleep {
+            timer: self.shared.clone(),
+            key,
+            state,
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.condvar.notify_one();
+    }
+}
+
+impl TimerShared {
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        while !state.shutdown {
+            let now = Instant::now();
+            while let Some(entry) = state.sleepers.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                entry.remove().wake();
+            }
+
+            let next_deadline = state.sleepers.keys().next().map(|&(deadline, _)| deadline);
+            state = match next_deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(now);
+                    self.condvar.wait_timeout(state, timeout).unwrap().0
+                }
+                None => self.condvar.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SleepState {
+    done: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl SleepState {
+    fn wake(&self) {
+        self.done.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The future returned by [`Timer::sleep()`].
+struct Sleep {
+    timer: Arc<TimerShared>,
+    key: (Instant, u64),
+    state: Arc<SleepState>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.state.done.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+
+        *self.state.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        // The timer may have fired while we were registering the waker
+        if self.state.done.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if !self.state.done.load(Ordering::Acquire) {
+            self.timer.state.lock().unwrap().sleepers.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleep_waits_for_the_deadline() {
+        let manager = RayonTaskManager::new(2).unwrap();
+
+        let start = Instant::now();
+        futures::executor::block_on(manager.sleep_now(Duration::from_millis(50)));
+
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn tasks_run_on_the_pool() {
+        let manager = RayonTaskManager::new(2).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        let dedicated = sender.clone();
+        manager
+            .task_dedicated(Box::new(move || dedicated.send("dedicated").unwrap()))
+            .unwrap();
+        manager
+            .task_shared(Box::new(move || {
+                Box::pin(async move { sender.send("shared").unwrap() })
+            }))
+            .unwrap();
+
+        let mut received = vec![receiver.recv().unwrap(), receiver.recv().unwrap()];
+        received.sort();
+        assert_eq!(received, ["dedicated", "shared"]);
+        assert_eq!(manager.thread_parallelism().unwrap(), 2);
+    }
+}
@@ -1,12 +1,20 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Mutex;
+use std::time::Instant;
 use std::{num::NonZeroUsize, pin::Pin, sync::Arc, time::Duration};
 
-use futures::{future::BoxFuture, Future};
+use futures::{future::BoxFuture, Future, FutureExt};
 use tokio::runtime::{Handle, Runtime};
 
-use crate::{os::task::thread::WasiThreadError, WasiFunctionEnv};
+use crate::{
+    os::task::thread::{WasiThread, WasiThreadError},
+    WasiFunctionEnv, WasiRuntimeError,
+};
 
-use super::{TaskWasm, TaskWasmRunProperties, VirtualTaskManager};
+use super::{TaskId, TaskWasm, TaskWasmRunProperties, VirtualTaskManager};
 
 #[derive(Debug, Clone)]
 pub enum RuntimeOrHandle {
@@ -66,11 +74,212 @@ impl std::fmt::Debug for ThreadPool {
     }
 }
 
+/// Keeps track of how many tasks are waiting to be run and how long they
+/// had to wait.
+#[derive(Debug, Default)]
+struct SchedulingMetrics {
+    enqueued: AtomicUsize,
+    /// The latency of the last task to start, in nanoseconds plus one (zero
+    /// means nothing has been observed yet).
+    last_latency: AtomicU64,
+}
+
+impl SchedulingMetrics {
+    fn enqueue(self: &Arc<Self>) -> EnqueuedTask {
+        self.enqueued.fetch_add(1, Ordering::Relaxed);
+        EnqueuedTask {
+            metrics: self.clone(),
+            enqueued_at: Instant::now(),
+        }
+    }
+}
+
+/// A task which has been scheduled, but hasn't started yet.
+struct EnqueuedTask {
+    metrics: Arc<SchedulingMetrics>,
+    enqueued_at: Instant,
+}
+
+impl EnqueuedTask {
+    /// Record that the task has started running.
+    fn start(self) {
+        let nanos = u64::try_from(self.enqueued_at.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.metrics
+            .last_latency
+            .store(nanos.saturating_add(1), Ordering::Relaxed);
+    }
+}
+
+impl Drop for EnqueuedTask {
+    fn drop(&mut self) {
+        self.metrics.enqueued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The async tasks spawned by a [`TokioTaskManager`] which are still running.
+#[derive(Debug, Default)]
+struct TaskRegistry {
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<TaskId, tokio::task::AbortHandle>>,
+}
+
+impl TaskRegistry {
+    /// Spawn a future onto the runtime, keeping track of it until it either
+    /// completes or is aborted.
+    fn spawn<F>(self: &Arc<Self>, handle: &Handle, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let id = TaskId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let guard = RegisteredTask {
+            registry: self.clone(),
+            id,
+        };
+
+        // Note: the lock is held while spawning so the task can't deregister
+        // itself before it has been registered.
+        let mut tasks = self.tasks.lock().unwrap();
+        let join = handle.spawn(async move {
+            let _guard = guard;
+            task.await
+        });
+        tasks.insert(id, join.abort_handle());
+    }
+}
+
+/// Removes a task from its [`TaskRegistry`] when dropped.
+struct RegisteredTask {
+    registry: Arc<TaskRegistry>,
+    id: TaskId,
+}
+
+impl Drop for RegisteredTask {
+    fn drop(&mut self) {
+        self.registry.tasks.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Holds back tasks while a [`TokioTaskManager`] is paused.
+#[derive(Debug, Default)]
+struct PauseGate {
+    paused: AtomicBool,
+    notify: tokio::sync::Notify,
+}
+
+impl PauseGate {
+    fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the task manager isn't paused.
+    async fn wait(&self) {
+        loop {
+            // Note: the notification needs to be registered before checking
+            // the flag, otherwise a resume() in between would be missed.
+            let notified = self.notify.notified();
+            if !self.is_paused() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Block the current thread until the task manager isn't paused.
+    fn wait_blocking(&self) {
+        if self.is_paused() {
+            futures::executor::block_on(self.wait());
+        }
+    }
+}
+
+/// What a [`TokioTaskManager`] does when one of its tasks panics.
+#[derive(Clone, Default)]
+pub enum PanicPolicy {
+    /// Log the panic and abort the whole process.
+    Abort,
+    /// Log the panic and terminate just the task which panicked.
+    ///
+    /// A WebAssembly thread which panics finishes with a trap, so anything
+    /// joining it sees an error instead of waiting forever.
+    #[default]
+    IsolateTask,
+    /// Like [`PanicPolicy::IsolateTask`], except the panic message is passed
+    /// to the callback instead of being logged.
+    Callback(Arc<dyn Fn(&str) + Send + Sync>),
+}
+
+impl PanicPolicy {
+    /// Run a task, handling any panic according to the policy.
+    ///
+    /// Returns the panic message if the task panicked.
+    fn catch<T>(&self, task: impl FnOnce() -> T) -> Result<T, String> {
+        std::panic::catch_unwind(AssertUnwindSafe(task)).map_err(|payload| self.handle(payload))
+    }
+
+    /// Like [`PanicPolicy::catch()`], but for async tasks.
+    async fn catch_async<F: Future>(&self, task: F) -> Result<F::Output, String> {
+        AssertUnwindSafe(task)
+            .catch_unwind()
+            .await
+            .map_err(|payload| self.handle(payload))
+    }
+
+    fn handle(&self, payload: Box<dyn Any + Send>) -> String {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<dyn Any>".to_string()
+        };
+
+        match self {
+            PanicPolicy::Abort => {
+                tracing::error!(%message, "a task panicked, aborting");
+                std::process::abort();
+            }
+            PanicPolicy::IsolateTask => tracing::error!(%message, "a task panicked"),
+            PanicPolicy::Callback(callback) => callback(&message),
+        }
+
+        message
+    }
+}
+
+impl std::fmt::Debug for PanicPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PanicPolicy::Abort => f.write_str("Abort"),
+            PanicPolicy::IsolateTask => f.write_str("IsolateTask"),
+            PanicPolicy::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Finish a WebAssembly thread whose task panicked with a trap.
+fn trap_panicked_thread(thread: &WasiThread, message: &str) {
+    let err = wasmer::RuntimeError::new(format!("the thread panicked: {message}"));
+    thread.set_status_finished(Err(WasiRuntimeError::Runtime(err)));
+}
+
 /// A task manager that uses tokio to spawn tasks.
 #[derive(Clone, Debug)]
 pub struct TokioTaskManager {
     rt: RuntimeOrHandle,
     pool: Arc<ThreadPool>,
+    metrics: Arc<SchedulingMetrics>,
+    registry: Arc<TaskRegistry>,
+    gate: Arc<PauseGate>,
+    panic_policy: PanicPolicy,
 }
 
 impl TokioTaskManager {
@@ -92,9 +301,22 @@ impl TokioTaskManager {
                     .max_size(max_threads)
                     .build(),
             }),
+            metrics: Arc::new(SchedulingMetrics::default()),
+            registry: Arc::new(TaskRegistry::default()),
+            gate: Arc::new(PauseGate::default()),
+            panic_policy: PanicPolicy::default(),
         }
     }
 
+    /// Choose what happens when a task panics.
+    ///
+    /// By default, only the task which panicked is terminated (see
+    /// [`PanicPolicy::IsolateTask`]).
+    pub fn with_panic_policy(mut self, policy: PanicPolicy) -> Self {
+        self.panic_policy = policy;
+        self
+    }
+
     pub fn runtime_handle(&self) -> tokio::runtime::Handle {
         self.rt.handle().clone()
     }
@@ -123,12 +345,26 @@ impl VirtualTaskManager for TokioTaskManager {
     /// See [`VirtualTaskManager::sleep_now`].
     fn sleep_now(&self, time: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + Sync>> {
         let handle = self.runtime_handle();
+        let gate = self.gate.clone();
         Box::pin(async move {
             SleepNow::default()
                 .enter(handle, time)
                 .await
                 .ok()
-                .unwrap_or(())
+                .unwrap_or(());
+
+            // Sleeping is where running tasks yield, so this is where they
+            // get held while the task manager is paused
+            gate.wait().await;
+        })
+    }
+
+    /// See [`VirtualTaskManager::yield_now`].
+    fn yield_now(&self) -> Pin<Box<dyn Future<Output = ()> + Send + Sync>> {
+        let gate = self.gate.clone();
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            gate.wait().await;
         })
     }
 
@@ -137,9 +373,14 @@ impl VirtualTaskManager for TokioTaskManager {
         &self,
         task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
     ) -> Result<(), WasiThreadError> {
-        self.rt.handle().spawn(async move {
-            let fut = task();
-            fut.await
+        let enqueued = self.metrics.enqueue();
+        let gate = self.gate.clone();
+        let policy = self.panic_policy.clone();
+        self.registry.spawn(self.rt.handle(), async move {
+            gate.wait().await;
+            enqueued.start();
+            // The policy has already dealt with any panic
+            let _ = policy.catch_async(async move { task().await }).await;
         });
         Ok(())
     }
@@ -156,6 +397,7 @@ impl VirtualTaskManager for TokioTaskManager {
             task.spawn_type,
             task.update_layout,
         )?;
+        let thread = ctx.data(&store).thread.clone();
 
         // If we have a trigger then we first need to run
         // the poller to completion
@@ -164,61 +406,97 @@ impl VirtualTaskManager for TokioTaskManager {
 
             let mut trigger = trigger();
             let pool = self.pool.clone();
-            self.rt.handle().spawn(async move {
+            let metrics = self.metrics.clone();
+            let gate = self.gate.clone();
+            let policy = self.panic_policy.clone();
+            self.registry.spawn(self.rt.handle(), async move {
                 // We wait for either the trigger or for a snapshot to take place
-                let result = loop {
-                    let env = ctx.data(&store);
-                    break tokio::select! {
-                        r = &mut trigger => r,
-                        _ = env.thread.wait_for_signal() => {
-                            tracing::debug!("wait-for-signal(triggered)");
-                            let mut ctx = ctx.env.clone().into_mut(&mut store);
-                            if let Err(err) = crate::WasiEnv::process_signals_and_exit(&mut ctx) {
-                                match err {
-                                    crate::WasiError::Exit(code) => Err(code),
-                                    err => {
-                                        tracing::error!("failed to process signals - {}", err);
-                                        continue;
+                let waiting = async move {
+                    let result = loop {
+                        let env = ctx.data(&store);
+                        break tokio::select! {
+                            r = &mut trigger => r,
+                            _ = env.thread.wait_for_signal() => {
+                                tracing::debug!("wait-for-signal(triggered)");
+                                let mut ctx = ctx.env.clone().into_mut(&mut store);
+                                if let Err(err) = crate::WasiEnv::process_signals_and_exit(&mut ctx) {
+                                    match err {
+                                        crate::WasiError::Exit(code) => Err(code),
+                                        // Sleeping threads can't trap, so they exit instead
+                                        crate::WasiError::DeadlineExceeded => {
+                                            Err(wasmer_wasix_types::wasi::Errno::Timedout.into())
+                                        }
+                                        err => {
+                                            tracing::error!("failed to process signals - {}", err);
+                                            continue;
+                                        }
                                     }
+                                } else {
+                                    continue;
                                 }
-                            } else {
+                            }
+                            _ = crate::wait_for_snapshot(env) => {
+                                tracing::debug!("wait-for-snapshot(triggered)");
+                                let mut ctx = ctx.env.clone().into_mut(&mut store);
+                                crate::os::task::WasiProcessInner::do_checkpoints_from_outside(&mut ctx);
                                 continue;
                             }
-                        }
-                        _ = crate::wait_for_snapshot(env) => {
-                            tracing::debug!("wait-for-snapshot(triggered)");
-                            let mut ctx = ctx.env.clone().into_mut(&mut store);
-                            crate::os::task::WasiProcessInner::do_checkpoints_from_outside(&mut ctx);
-                            continue;
-                        }
+                        };
                     };
+                    (ctx, store, result)
+                };
+                let (ctx, store, result) = match policy.catch_async(waiting).await {
+                    Ok(waited) => waited,
+                    Err(message) => {
+                        trap_panicked_thread(&thread, &message);
+                        return;
+                    }
                 };
 
                 // Build the task that will go on the callback
+                let enqueued = metrics.enqueue();
                 pool.execute(move || {
+                    gate.wait_blocking();
+                    enqueued.start();
+
                     // Invoke the callback
-                    run(TaskWasmRunProperties {
-                        ctx,
-                        store,
-                        trigger_result: Some(result),
-                        recycle,
+                    let ran = policy.catch(|| {
+                        run(TaskWasmRunProperties {
+                            ctx,
+                            store,
+                            trigger_result: Some(result),
+                            recycle,
+                        })
                     });
+                    if let Err(message) = ran {
+                        trap_panicked_thread(&thread, &message);
+                    }
                 });
             });
         } else {
             tracing::trace!("spawning task_wasm in blocking thread");
 
             // Run the callback on a dedicated thread
+            let enqueued = self.metrics.enqueue();
+            let gate = self.gate.clone();
+            let policy = self.panic_policy.clone();
             self.pool.execute(move || {
+                gate.wait_blocking();
+                enqueued.start();
                 tracing::trace!("task_wasm started in blocking thread");
 
                 // Invoke the callback
-                run(TaskWasmRunProperties {
-                    ctx,
-                    store,
-                    trigger_result: None,
-                    recycle,
+                let ran = policy.catch(|| {
+                    run(TaskWasmRunProperties {
+                        ctx,
+                        store,
+                        trigger_result: None,
+                        recycle,
+                    })
                 });
+                if let Err(message) = ran {
+                    trap_panicked_thread(&thread, &message);
+                }
             });
         }
         Ok(())
@@ -229,18 +507,104 @@ impl VirtualTaskManager for TokioTaskManager {
         &self,
         task: Box<dyn FnOnce() + Send + 'static>,
     ) -> Result<(), WasiThreadError> {
+        let enqueued = self.metrics.enqueue();
+        let gate = self.gate.clone();
+        let policy = self.panic_policy.clone();
         self.pool.execute(move || {
-            task();
+            gate.wait_blocking();
+            enqueued.start();
+            // The policy has already dealt with any panic
+            let _ = policy.catch(task);
         });
         Ok(())
     }
 
+    /// See [`VirtualTaskManager::run_blocking_with_timeout`].
+    fn run_blocking_with_timeout(
+        &self,
+        work: Box<dyn FnOnce() + Send + 'static>,
+        timeout: Duration,
+    ) -> Pin<Box<dyn Future<Output = Result<(), WasiThreadError>> + Send + 'static>> {
+        let handle = self.runtime_handle();
+        Box::pin(async move {
+            // The timer needs to be created inside the runtime context, so the
+            // race itself is spawned onto the runtime rather than polled here.
+            let inner = handle.clone();
+            let result = handle
+                .spawn(
+                    async move { tokio::time::timeout(timeout, inner.spawn_blocking(work)).await },
+                )
+                .await;
+
+            match result {
+                Ok(Ok(Ok(()))) => Ok(()),
+                Ok(Err(_elapsed)) => Err(WasiThreadError::TimedOut),
+                Ok(Ok(Err(err))) | Err(err) => {
+                    Err(WasiThreadError::InitFailed(Arc::new(err.into())))
+                }
+            }
+        })
+    }
+
     /// See [`VirtualTaskManager::thread_parallelism`].
     fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
         Ok(std::thread::available_parallelism()
             .map(usize::from)
             .unwrap_or(8))
     }
+
+    /// See [`VirtualTaskManager::enqueued_count`].
+    fn enqueued_count(&self) -> usize {
+        self.metrics.enqueued.load(Ordering::Relaxed)
+    }
+
+    /// See [`VirtualTaskManager::list_tasks`].
+    fn list_tasks(&self) -> Vec<TaskId> {
+        let mut tasks: Vec<_> = self
+            .registry
+            .tasks
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect();
+        tasks.sort();
+        tasks
+    }
+
+    /// See [`VirtualTaskManager::abort_task`].
+    ///
+    /// Only async tasks can be aborted. Work running on the blocking thread
+    /// pool will always run to completion.
+    fn abort_task(&self, id: TaskId) {
+        let handle = self.registry.tasks.lock().unwrap().remove(&id);
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+    }
+
+    /// See [`VirtualTaskManager::observe_scheduling_latency`].
+    fn observe_scheduling_latency(&self) -> Option<Duration> {
+        match self.metrics.last_latency.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos - 1)),
+        }
+    }
+
+    /// See [`VirtualTaskManager::pause`].
+    fn pause(&self) {
+        self.gate.pause();
+    }
+
+    /// See [`VirtualTaskManager::resume`].
+    fn resume(&self) {
+        self.gate.resume();
+    }
+
+    /// See [`VirtualTaskManager::is_paused`].
+    fn is_paused(&self) -> bool {
+        self.gate.is_paused()
+    }
 }
 
 // Used by [`VirtualTaskManager::sleep_now`] to abort a sleep task when drop.
@@ -274,3 +638,71 @@ impl Drop for SleepNow {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn yielding_lets_other_tasks_run() {
+        let tasks = TokioTaskManager::default();
+        let ran = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn({
+            let ran = ran.clone();
+            async move { ran.store(true, Ordering::SeqCst) }
+        });
+        tasks.yield_now().await;
+
+        assert!(ran.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn paused_tasks_wait_for_resume() {
+        let tasks = TokioTaskManager::default();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        tasks.pause();
+        tasks
+            .task_shared(Box::new(move || {
+                Box::pin(async move {
+                    sender.send(()).unwrap();
+                })
+            }))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(tasks.enqueued_count(), 1);
+
+        tasks.resume();
+        receiver.recv().await.unwrap();
+        assert!(!tasks.is_paused());
+    }
+
+    #[tokio::test]
+    async fn panicking_tasks_are_isolated() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let tasks = TokioTaskManager::default().with_panic_policy(PanicPolicy::Callback(Arc::new(
+            move |message: &str| sender.send(message.to_string()).unwrap(),
+        )));
+
+        tasks
+            .task_dedicated(Box::new(|| panic!("dedicated task")))
+            .unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), "dedicated task");
+
+        tasks
+            .task_shared(Box::new(|| {
+                Box::pin(async { panic!("shared task {}", 42) })
+            }))
+            .unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), "shared task 42");
+
+        // The thread pool keeps working after a panic
+        let (done, finished) = tokio::sync::oneshot::channel();
+        tasks
+            .task_dedicated(Box::new(move || done.send(()).unwrap()))
+            .unwrap();
+        finished.await.unwrap();
+    }
+}
@@ -8,7 +8,10 @@ use std::{
 
 use rand::Rng;
 use thiserror::Error;
-use virtual_fs::{ArcFile, FileSystem, FsError, TmpFileSystem, VirtualFile};
+use uuid::Uuid;
+use virtual_fs::{
+    ArcFile, FileSystem, FsError, LineEnding, LineEndingFile, TmpFileSystem, VirtualFile,
+};
 use wasmer::{AsStoreMut, Extern, Imports, Instance, Module, Store};
 use wasmer_config::package::PackageId;
 
@@ -19,6 +22,7 @@ use crate::{
     capabilities::Capabilities,
     fs::{WasiFs, WasiFsRoot, WasiInodes},
     os::task::control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
+    runtime::{EnvFilter, SeededRng, VirtualRng},
     state::WasiState,
     syscalls::{
         rewind_ext2,
@@ -65,9 +69,15 @@ pub struct WasiEnvBuilder {
     pub(super) stdout: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stderr: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stdin: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
+    /// How newlines written to stdout and stderr are translated, if it
+    /// shouldn't be left up to the runtime's TTY.
+    pub(super) line_ending: Option<LineEnding>,
     pub(super) fs: Option<WasiFsRoot>,
     pub(super) runtime: Option<Arc<dyn crate::Runtime + Send + Sync + 'static>>,
     pub(super) current_dir: Option<PathBuf>,
+    /// The ID of the instance, if it shouldn't be taken from the runtime or
+    /// generated.
+    pub(super) instance_id: Option<Uuid>,
 
     /// List of webc dependencies to be injected.
     pub(super) uses: Vec<BinaryPackage>,
@@ -79,6 +89,9 @@ pub struct WasiEnvBuilder {
     /// List of host commands to map into the WASI instance.
     pub(super) map_commands: HashMap<String, PathBuf>,
 
+    /// Names of the runtime's shared regions to map into `/dev/shm`.
+    pub(super) shared_regions: Vec<String>,
+
     pub(super) capabilites: Capabilities,
     pub(super) additional_imports: Imports,
 
@@ -417,6 +430,19 @@ impl WasiEnvBuilder {
         }
     }
 
+    /// Give the guest access to one of the runtime's shared regions (see
+    /// [`Runtime::shared_region()`]) as the file `/dev/shm/<name>`.
+    pub fn map_shared_region(mut self, name: impl Into<String>) -> Self {
+        self.add_shared_region(name);
+        self
+    }
+
+    /// Give the guest access to one of the runtime's shared regions (see
+    /// [`Runtime::shared_region()`]) as the file `/dev/shm/<name>`.
+    pub fn add_shared_region(&mut self, name: impl Into<String>) {
+        self.shared_regions.push(name.into());
+    }
+
     /// Preopen a directory
     ///
     /// This opens the given directory at the virtual root, `/`, and allows
@@ -633,6 +659,22 @@ impl WasiEnvBuilder {
         self.stdin = Some(new_file);
     }
 
+    /// Translate the newlines the guest writes to `stdout` and `stderr`.
+    ///
+    /// By default this is decided by the runtime's [`TtyBridge`][tty], and
+    /// output is left alone if there is no TTY.
+    ///
+    /// [tty]: crate::os::TtyBridge::line_ending
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.set_line_ending(line_ending);
+        self
+    }
+
+    /// Translate the newlines the guest writes to `stdout` and `stderr`.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = Some(line_ending);
+    }
+
     /// Sets the FileSystem to be used with this WASI instance.
     ///
     /// This is usually used in case a custom `virtual_fs::FileSystem` is needed.
@@ -672,6 +714,23 @@ impl WasiEnvBuilder {
         self.runtime = Some(runtime);
     }
 
+    /// Sets the ID this instance is identified by in logs and traces.
+    ///
+    /// Defaults to the runtime's [`Runtime::instance_id()`], or a new random
+    /// ID if the runtime doesn't assign one.
+    pub fn instance_id(mut self, instance_id: Uuid) -> Self {
+        self.set_instance_id(instance_id);
+        self
+    }
+
+    /// Sets the ID this instance is identified by in logs and traces.
+    ///
+    /// Defaults to the runtime's [`Runtime::instance_id()`], or a new random
+    /// ID if the runtime doesn't assign one.
+    pub fn set_instance_id(&mut self, instance_id: Uuid) {
+        self.instance_id = Some(instance_id);
+    }
+
     pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
         self.set_capabilities(capabilities);
         self
@@ -807,6 +866,30 @@ impl WasiEnvBuilder {
         //     .clone()
         //     .unwrap_or_else(|| Arc::new(PluggableRuntimeImplementation::default()));
 
+        let runtime = self.runtime.unwrap_or_else(|| {
+            #[cfg(feature = "sys-thread")]
+            {
+                #[allow(unused_mut)]
+                let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(crate::runtime::task_manager::tokio::TokioTaskManager::default()));
+                #[cfg(feature = "journal")]
+                for journal in self.journals.clone() {
+                    runtime.add_journal(journal);
+                }
+                Arc::new(runtime)
+            }
+
+            #[cfg(not(feature = "sys-thread"))]
+            {
+                panic!("this build does not support a default runtime - specify one with WasiEnvBuilder::runtime()");
+            }
+        });
+
+        // Start in the runtime's default working directory, unless the caller
+        // explicitly configured one.
+        if self.current_dir.is_none() {
+            self.current_dir = runtime.default_cwd();
+        }
+
         // Determine the STDIN
         let stdin: Box<dyn VirtualFile + Send + Sync + 'static> = self
             .stdin
@@ -840,6 +923,28 @@ impl WasiEnvBuilder {
             }
         }
 
+        let instance_id = self
+            .instance_id
+            .or_else(|| runtime.instance_id())
+            .unwrap_or_else(Uuid::new_v4);
+
+        let rng = instance_rng(&*runtime, instance_id);
+
+        // Reads from the random device files should be as reproducible as
+        // random_get(), so back them with the same RNG
+        if let Some(rng) = &rng {
+            mount_random_devices(&fs_backing, rng.clone())?;
+        }
+
+        for name in &self.shared_regions {
+            let region = runtime.shared_region(name).ok_or_else(|| {
+                WasiStateCreationError::WasiFsSetupError(format!(
+                    "The runtime has no shared region called \"{name}\""
+                ))
+            })?;
+            mount_shared_region(&fs_backing, name, region)?;
+        }
+
         // self.preopens are checked in [`PreopenDirBuilder::build`]
         let inodes = crate::state::WasiInodes::new();
         let wasi_fs = {
@@ -865,9 +970,36 @@ impl WasiEnvBuilder {
                     .map_err(WasiStateCreationError::FileSystemError)?;
             }
 
+            let line_ending = self
+                .line_ending
+                .or_else(|| runtime.tty().map(|tty| tty.line_ending()))
+                .unwrap_or_default();
+            if line_ending != LineEnding::Passthrough {
+                for fd in [__WASI_STDOUT_FILENO, __WASI_STDERR_FILENO] {
+                    let placeholder = Box::<virtual_fs::NullFile>::default();
+                    if let Some(file) = wasi_fs
+                        .swap_file(fd, placeholder)
+                        .map_err(WasiStateCreationError::FileSystemError)?
+                    {
+                        let file = LineEndingFile::new(file, line_ending);
+                        wasi_fs
+                            .swap_file(fd, Box::new(file))
+                            .map_err(WasiStateCreationError::FileSystemError)?;
+                    }
+                }
+            }
+
             if let Some(f) = &self.setup_fs_fn {
                 f(&inodes, &mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
             }
+
+            // Note: stdio and the preopened directories count against the limit
+            wasi_fs
+                .fd_map
+                .write()
+                .unwrap()
+                .set_max_open(runtime.max_open_fds());
+
             wasi_fs
         };
 
@@ -885,35 +1017,46 @@ impl WasiEnvBuilder {
             wasi_fs.has_unioned.lock().unwrap().insert(id.clone());
         }
 
+        // Let guests see the hostname the runtime wants to present, unless
+        // the caller explicitly configured one.
+        if let Some(hostname) = runtime.hostname() {
+            if !self.envs.iter().any(|(key, _)| key == "HOSTNAME") {
+                self.envs
+                    .push(("HOSTNAME".to_string(), hostname.into_bytes()));
+            }
+        }
+
+        if let Some(filter) = runtime.env_filter() {
+            self.envs = filter_env_vars(filter, self.envs)?;
+        }
+
+        self.args = merge_runtime_args(&*runtime, self.args)?;
+
+        // The instance's RNG is authoritative, so only fall back to the
+        // host's entropy when there isn't one
+        let secret = match &rng {
+            Some(rng) => {
+                let mut secret = [0_u8; 32];
+                rng.fill_bytes(&mut secret);
+                secret
+            }
+            None => rand::thread_rng().gen::<[u8; 32]>(),
+        };
+
         let state = WasiState {
             fs: wasi_fs,
-            secret: rand::thread_rng().gen::<[u8; 32]>(),
+            secret,
             inodes,
             args: std::sync::Mutex::new(self.args.clone()),
             preopen: self.vfs_preopens.clone(),
             futexs: Default::default(),
             clock_offset: Default::default(),
             envs: std::sync::Mutex::new(conv_env_vars(self.envs)),
+            syscall_throttle: Default::default(),
+            instance_id,
+            rng,
         };
 
-        let runtime = self.runtime.unwrap_or_else(|| {
-            #[cfg(feature = "sys-thread")]
-            {
-                #[allow(unused_mut)]
-                let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(crate::runtime::task_manager::tokio::TokioTaskManager::default()));
-                #[cfg(feature = "journal")]
-                for journal in self.journals.clone() {
-                    runtime.add_journal(journal);
-                }
-                Arc::new(runtime)
-            }
-
-            #[cfg(not(feature = "sys-thread"))]
-            {
-                panic!("this build does not support a default runtime - specify one with WasiEnvBuilder::runtime()");
-            }
-        });
-
         let uses = self.uses;
         let map_commands = self.map_commands;
 
@@ -1115,6 +1258,147 @@ impl WasiEnvBuilder {
     }
 }
 
+/// Run every environment variable through an [`EnvFilter`], making sure the
+/// result is still valid.
+fn filter_env_vars(
+    filter: &dyn EnvFilter,
+    envs: Vec<(String, Vec<u8>)>,
+) -> Result<Vec<(String, Vec<u8>)>, WasiStateCreationError> {
+    let mut filtered = Vec::with_capacity(envs.len());
+
+    for (key, value) in envs {
+        let lossy_value = String::from_utf8_lossy(&value);
+        let Some((new_key, new_value)) = filter.transform(&key, &lossy_value) else {
+            continue;
+        };
+
+        if new_key.is_empty() || new_key.contains(['=', '\0']) || new_value.contains('\0') {
+            return Err(WasiStateCreationError::EnvironmentVariableFormatError(
+                format!("the environment filter produced an invalid env var \"{new_key}\""),
+            ));
+        }
+
+        // Avoid mangling non-UTF-8 values the filter left untouched
+        if new_value == lossy_value {
+            filtered.push((new_key, value));
+        } else {
+            filtered.push((new_key, new_value.into_bytes()));
+        }
+    }
+
+    Ok(filtered)
+}
+
+/// The RNG for the instance with the ID `instance_id`. A shared seed gives
+/// each instance its own reproducible RNG.
+pub(crate) fn instance_rng(
+    runtime: &(dyn Runtime + Send + Sync),
+    instance_id: Uuid,
+) -> Option<Arc<dyn VirtualRng>> {
+    match runtime.rng_seed() {
+        Some(seed) => Some(Arc::new(SeededRng::for_instance(seed, instance_id))),
+        None => runtime.rng(),
+    }
+}
+
+/// Replace `/dev/random` and `/dev/urandom` with device files backed by
+/// `rng`.
+fn mount_random_devices(
+    fs: &WasiFsRoot,
+    rng: Arc<dyn VirtualRng>,
+) -> Result<(), WasiStateCreationError> {
+    let WasiFsRoot::Sandbox(fs) = fs else {
+        tracing::debug!("not mounting the random devices on a custom root filesystem");
+        return Ok(());
+    };
+
+    if let Err(e) = fs.create_dir(Path::new("/dev")) {
+        if e != FsError::AlreadyExists {
+            return Err(WasiStateCreationError::WasiFsSetupError(format!(
+                "Could not create /dev: {e}"
+            )));
+        }
+    }
+
+    for path in ["/dev/random", "/dev/urandom"] {
+        fs.new_open_options_ext()
+            .insert_device_file(
+                PathBuf::from(path),
+                Box::new(crate::runtime::RandomDeviceFile::new(rng.clone())),
+            )
+            .map_err(|e| {
+                WasiStateCreationError::WasiFsSetupError(format!("Could not mount {path}: {e}"))
+            })?;
+    }
+
+    Ok(())
+}
+
+fn mount_shared_region(
+    fs: &WasiFsRoot,
+    name: &str,
+    region: Arc<crate::runtime::SharedRegion>,
+) -> Result<(), WasiStateCreationError> {
+    let WasiFsRoot::Sandbox(fs) = fs else {
+        return Err(WasiStateCreationError::WasiFsSetupError(format!(
+            "Could not map the \"{name}\" shared region onto a custom root filesystem"
+        )));
+    };
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+        return Err(WasiStateCreationError::WasiFsSetupError(format!(
+            "\"{name}\" is not a valid shared region name"
+        )));
+    }
+
+    for dir in ["/dev", "/dev/shm"] {
+        if let Err(e) = fs.create_dir(Path::new(dir)) {
+            if e != FsError::AlreadyExists {
+                return Err(WasiStateCreationError::WasiFsSetupError(format!(
+                    "Could not create {dir}: {e}"
+                )));
+            }
+        }
+    }
+
+    let path = Path::new("/dev/shm").join(name);
+    fs.new_open_options_ext()
+        .insert_device_file(
+            path.clone(),
+            Box::new(crate::runtime::SharedRegionFile::new(region)),
+        )
+        .map_err(|e| {
+            WasiStateCreationError::WasiFsSetupError(format!(
+                "Could not mount {}: {e}",
+                path.display()
+            ))
+        })
+}
+
+/// Apply the runtime's program name and default arguments to the arguments
+/// a guest was started with.
+fn merge_runtime_args(
+    runtime: &(dyn Runtime + Send + Sync),
+    args: Vec<String>,
+) -> Result<Vec<String>, WasiStateCreationError> {
+    let mut args = args.into_iter();
+    let program_name = runtime.program_name().or_else(|| args.next());
+    let default_args = runtime.default_args();
+
+    if let Some(arg) = program_name
+        .iter()
+        .chain(&default_args)
+        .find(|arg| arg.contains('\0'))
+    {
+        return Err(WasiStateCreationError::ArgumentContainsNulByte(arg.clone()));
+    }
+
+    Ok(program_name
+        .into_iter()
+        .chain(default_args)
+        .chain(args)
+        .collect())
+}
+
 pub(crate) fn conv_env_vars(envs: Vec<(String, Vec<u8>)>) -> Vec<Vec<u8>> {
     envs.into_iter()
         .map(|(key, value)| {
@@ -1287,6 +1571,466 @@ mod test {
         );
     }
 
+    #[derive(Debug)]
+    struct StripAws;
+
+    impl EnvFilter for StripAws {
+        fn transform(&self, key: &str, value: &str) -> Option<(String, String)> {
+            match key {
+                "AWS_SECRET" => None,
+                "OLD_NAME" => Some(("NEW_NAME".to_string(), value.to_string())),
+                _ => Some((key.to_string(), value.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn env_filter_rewrites_variables() {
+        let envs = vec![
+            ("AWS_SECRET".to_string(), b"hunter2".to_vec()),
+            ("OLD_NAME".to_string(), b"value".to_vec()),
+            ("BINARY".to_string(), vec![0xff, 0xfe]),
+        ];
+
+        let filtered = filter_env_vars(&StripAws, envs).unwrap();
+
+        assert_eq!(
+            filtered,
+            vec![
+                ("NEW_NAME".to_string(), b"value".to_vec()),
+                ("BINARY".to_string(), vec![0xff, 0xfe]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test]
+    async fn runtime_args_are_merged() {
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::default(),
+        ));
+        let args = vec!["original".to_string(), "--explicit".to_string()];
+
+        assert_eq!(merge_runtime_args(&runtime, args.clone()).unwrap(), args);
+
+        runtime
+            .set_program_name("renamed")
+            .set_default_args(["--verbose"]);
+        assert_eq!(
+            merge_runtime_args(&runtime, args).unwrap(),
+            ["renamed", "--verbose", "--explicit"]
+        );
+
+        runtime.set_default_args(["--bad\0"]);
+        assert!(matches!(
+            merge_runtime_args(&runtime, Vec::new()),
+            Err(WasiStateCreationError::ArgumentContainsNulByte(_))
+        ));
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test]
+    async fn instances_get_their_own_ids_and_rngs() {
+        let bytes = |init: &WasiEnvInit| {
+            let mut buf = [0_u8; 16];
+            init.state.rng.as_ref().unwrap().fill_bytes(&mut buf);
+            buf
+        };
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::default(),
+        ));
+        runtime.set_rng_seed(42);
+        let runtime: Arc<dyn Runtime + Send + Sync> = Arc::new(runtime);
+        let build = |instance_id: Option<Uuid>| {
+            let mut builder = WasiEnvBuilder::new("test_prog").runtime(runtime.clone());
+            if let Some(instance_id) = instance_id {
+                builder.set_instance_id(instance_id);
+            }
+            builder.build_init().unwrap()
+        };
+
+        let first = build(None);
+        let second = build(None);
+        assert_ne!(first.state.instance_id, second.state.instance_id);
+        assert_ne!(bytes(&first), bytes(&second));
+
+        // The same seed and ID always produce the same bytes
+        let id = Uuid::new_v4();
+        let first = build(Some(id));
+        let second = build(Some(id));
+        assert_eq!(first.state.instance_id, id);
+        assert_eq!(bytes(&first), bytes(&second));
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn guests_past_the_deadline_trap() {
+        const WAT: &str = r#"
+            (module
+                (import "wasi_snapshot_preview1" "fd_sync" (func $fd_sync (param i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "_start")
+                    (drop (call $fd_sync (i32.const 1)))))
+        "#;
+
+        let tokio_rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = tokio_rt.enter();
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::new(tokio_rt.handle().clone()),
+        ));
+        runtime.set_deadline(Some(std::time::Instant::now()));
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        let err = WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .run_with_store(module, &mut store)
+            .unwrap_err();
+
+        assert!(
+            matches!(err, WasiRuntimeError::Wasi(WasiError::DeadlineExceeded)),
+            "{err:?}"
+        );
+        assert_eq!(err.as_exit_code(), None);
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn guests_can_log_to_the_runtime() {
+        use crate::runtime::{LogLevel, LogSink};
+
+        const WAT: &str = r#"
+            (module
+                (import "wasix_32v1" "log_write" (func $log_write (param i32 i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "hello")
+                (data (i32.const 16) "user=alice\nattempt=2")
+                (func (export "_start")
+                    (if (call $log_write (i32.const 1) (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 20))
+                        (then unreachable))))
+        "#;
+
+        #[derive(Debug, Default)]
+        struct Captured(std::sync::Mutex<Vec<(LogLevel, String, Vec<(String, String)>)>>);
+
+        impl LogSink for Captured {
+            fn record(&self, level: LogLevel, msg: &str, fields: &[(&str, &str)]) {
+                let fields = fields
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((level, msg.to_string(), fields));
+            }
+        }
+
+        let tokio_rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = tokio_rt.enter();
+        let sink = Arc::new(Captured::default());
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::new(tokio_rt.handle().clone()),
+        ));
+        runtime.set_log_sink(sink.clone());
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .run_with_store(module, &mut store)
+            .unwrap();
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(
+            *records,
+            vec![(
+                LogLevel::Warn,
+                "hello".to_string(),
+                vec![
+                    ("user".to_string(), "alice".to_string()),
+                    ("attempt".to_string(), "2".to_string()),
+                ],
+            )]
+        );
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn guests_can_watch_the_filesystem() {
+        // Watches the root directory, creates "/notes", then reads the
+        // change from the watch handle and compares it with the expected
+        // "created /notes\n" line stored at offset 96.
+        const WAT: &str = r#"
+            (module
+                (import "wasix_32v1" "fs_watch" (func $fs_watch (param i32 i32 i32 i32) (result i32)))
+                (import "wasix_32v1" "path_create_directory" (func $path_create_directory (param i32 i32 i32) (result i32)))
+                (import "wasix_32v1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) ".")
+                (data (i32.const 8) "notes")
+                (data (i32.const 32) "\40\00\00\00\20\00\00\00")
+                (data (i32.const 96) "created /notes\n")
+                (func (export "_start")
+                    (if (call $fs_watch (i32.const 3) (i32.const 0) (i32.const 1) (i32.const 16))
+                        (then unreachable))
+                    (if (call $path_create_directory (i32.const 3) (i32.const 8) (i32.const 5))
+                        (then unreachable))
+                    (if (call $fd_read (i32.load (i32.const 16)) (i32.const 32) (i32.const 1) (i32.const 40))
+                        (then unreachable))
+                    (if (i32.ne (i32.load (i32.const 40)) (i32.const 15))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 64)) (i64.load (i32.const 96)))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 72)) (i64.load (i32.const 104)))
+                        (then unreachable))))
+        "#;
+
+        let tokio_rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = tokio_rt.enter();
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::new(tokio_rt.handle().clone()),
+        ));
+        runtime.set_fs_watcher(Arc::new(crate::runtime::InMemoryFsWatcher::new()));
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .run_with_store(module, &mut store)
+            .unwrap();
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn guests_can_query_their_memory_limits() {
+        // The memory can grow to two pages, and one of them is in use
+        const WAT: &str = r#"
+            (module
+                (import "wasix_32v1" "memory_limits" (func $memory_limits (param i32 i32) (result i32)))
+                (memory (export "memory") 1 2)
+                (func (export "_start")
+                    (if (call $memory_limits (i32.const 0) (i32.const 8))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 0)) (i64.const 131072))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 8)) (i64.const 65536))
+                        (then unreachable))))
+        "#;
+
+        let tokio_rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = tokio_rt.enter();
+        let runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::new(tokio_rt.handle().clone()),
+        ));
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .run_with_store(module, &mut store)
+            .unwrap();
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn guests_can_query_their_hostname_and_domainname() {
+        // Reads the hostname into offset 32 (its length ends up at offset
+        // 16) and checks it's "guest", then checks that a buffer that is too
+        // small fails with ERANGE and that the domain name is empty.
+        const WAT: &str = r#"
+            (module
+                (import "wasix_32v1" "gethostname" (func $gethostname (param i32 i32) (result i32)))
+                (import "wasix_32v1" "getdomainname" (func $getdomainname (param i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\40\00\00\00")
+                (data (i32.const 20) "\02\00\00\00")
+                (data (i32.const 24) "\40\00\00\00")
+                (func (export "_start")
+                    (if (call $gethostname (i32.const 32) (i32.const 16))
+                        (then unreachable))
+                    (if (i32.ne (i32.load (i32.const 16)) (i32.const 5))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 32)) (i64.const 0x0000007473657567))
+                        (then unreachable))
+                    (if (i32.ne (call $gethostname (i32.const 32) (i32.const 20)) (i32.const 68))
+                        (then unreachable))
+                    (if (call $getdomainname (i32.const 32) (i32.const 24))
+                        (then unreachable))
+                    (if (i32.ne (i32.load (i32.const 24)) (i32.const 0))
+                        (then unreachable))))
+        "#;
+
+        let tokio_rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = tokio_rt.enter();
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::new(tokio_rt.handle().clone()),
+        ));
+        runtime.set_hostname("guest");
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .run_with_store(module, &mut store)
+            .unwrap();
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn guests_can_read_secrets() {
+        // Reads the "token" secret into offset 32 (its length ends up at
+        // offset 16) and checks it's "hunter2", then checks that a missing
+        // secret fails with ENOENT.
+        const WAT: &str = r#"
+            (module
+                (import "wasix_32v1" "secret_get" (func $secret_get (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "token")
+                (data (i32.const 8) "nope")
+                (data (i32.const 16) "\40\00\00\00")
+                (func (export "_start")
+                    (if (call $secret_get (i32.const 0) (i32.const 5) (i32.const 32) (i32.const 16))
+                        (then unreachable))
+                    (if (i32.ne (i32.load (i32.const 16)) (i32.const 7))
+                        (then unreachable))
+                    (if (i64.ne (i64.load (i32.const 32)) (i64.const 0x00327265746e7568))
+                        (then unreachable))
+                    (if (i32.ne (call $secret_get (i32.const 8) (i32.const 4) (i32.const 32) (i32.const 16)) (i32.const 44))
+                        (then unreachable))))
+        "#;
+
+        let tokio_rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = tokio_rt.enter();
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::new(tokio_rt.handle().clone()),
+        ));
+        runtime.set_secrets(Arc::new(
+            crate::runtime::InMemorySecretStore::new().with_secret("token", "hunter2"),
+        ));
+
+        let mut store = Store::default();
+        let module = Module::new(&store, WAT).unwrap();
+        WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .run_with_store(module, &mut store)
+            .unwrap();
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[tokio::test]
+    async fn runtime_default_cwd_is_used() {
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::default(),
+        ));
+        runtime.set_default_cwd("/work");
+        let runtime: Arc<dyn Runtime + Send + Sync> = Arc::new(runtime);
+
+        let init = WasiEnvBuilder::new("test_prog")
+            .runtime(runtime.clone())
+            .build_init()
+            .unwrap();
+        assert_eq!(*init.state.fs.current_dir.lock().unwrap(), "/work");
+
+        // An explicit working directory takes precedence
+        let init = WasiEnvBuilder::new("test_prog")
+            .runtime(runtime)
+            .current_dir("/explicit")
+            .build_init()
+            .unwrap();
+        assert_eq!(*init.state.fs.current_dir.lock().unwrap(), "/explicit");
+    }
+
+    #[tokio::test]
+    async fn runtime_max_open_fds_is_enforced() {
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::default(),
+        ));
+        runtime.set_max_open_fds(Some(3));
+
+        let init = WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .build_init()
+            .unwrap();
+
+        let fs = &init.state.fs;
+        assert_eq!(fs.fd_map.read().unwrap().max_open(), Some(3));
+        assert_eq!(
+            fs.clone_fd(__WASI_STDIN_FILENO),
+            Err(wasmer_wasix_types::wasi::Errno::Mfile)
+        );
+    }
+
+    #[tokio::test]
+    async fn runtime_rng_backs_random_devices() {
+        use tokio::io::AsyncReadExt;
+
+        use crate::runtime::{SeededRng, VirtualRng};
+
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::default(),
+        ));
+        runtime.set_rng(Arc::new(SeededRng::new(7)));
+
+        let init = WasiEnvBuilder::new("test_prog")
+            .runtime(Arc::new(runtime))
+            .build_init()
+            .unwrap();
+
+        let mut expected = [0_u8; 16];
+        SeededRng::new(7).fill_bytes(&mut expected);
+        let mut buf = [0_u8; 16];
+        init.state
+            .fs
+            .root_fs
+            .new_open_options()
+            .read(true)
+            .open("/dev/urandom")
+            .unwrap()
+            .read_exact(&mut buf)
+            .await
+            .unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn shared_regions_are_mapped_into_dev_shm() {
+        use tokio::io::AsyncWriteExt;
+
+        use crate::runtime::SharedRegion;
+
+        let region = Arc::new(SharedRegion::new(4));
+        let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(
+            crate::runtime::task_manager::tokio::TokioTaskManager::default(),
+        ));
+        runtime.add_shared_region("ipc", region.clone());
+        let runtime = Arc::new(runtime);
+
+        let init = WasiEnvBuilder::new("test_prog")
+            .runtime(runtime.clone())
+            .map_shared_region("ipc")
+            .build_init()
+            .unwrap();
+        init.state
+            .fs
+            .root_fs
+            .new_open_options()
+            .write(true)
+            .open("/dev/shm/ipc")
+            .unwrap()
+            .write_all(b"ping")
+            .await
+            .unwrap();
+        region.with_bytes(|bytes| assert_eq!(bytes, b"ping"));
+
+        let err = WasiEnvBuilder::new("test_prog")
+            .runtime(runtime)
+            .map_shared_region("missing")
+            .build_init()
+            .unwrap_err();
+        assert!(matches!(err, WasiStateCreationError::WasiFsSetupError(_)));
+    }
+
     #[test]
     fn nul_character_in_args() {
         let output = WasiEnvBuilder::new("test_prog")
@@ -3,11 +3,12 @@ use std::{
     ops::Deref,
     path::{Path, PathBuf},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use futures::future::BoxFuture;
 use rand::Rng;
+use uuid::Uuid;
 use virtual_fs::{FileSystem, FsError, StaticFile, VirtualFile};
 use virtual_net::DynVirtualNetworking;
 use wasmer::{
@@ -17,7 +18,7 @@ use wasmer::{
 use wasmer_config::package::PackageSource;
 use wasmer_wasix_types::{
     types::Signal,
-    wasi::{Errno, ExitCode, Snapshot0Clockid},
+    wasi::{Errno, ExitCode, Snapshot0Clockid, Timestamp},
     wasix::ThreadStartType,
 };
 use webc::metadata::annotations::Wasi;
@@ -34,15 +35,15 @@ use crate::{
         process::{WasiProcess, WasiProcessId},
         thread::{WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
     },
-    runtime::{task_manager::InlineWaker, SpawnMemoryType},
-    syscalls::platform_clock_time_get,
+    runtime::{task_manager::InlineWaker, FsEvent, FsEventKind, SpawnMemoryType, VirtualRng},
+    syscalls::{__asyncify_light, platform_clock_time_get},
     Runtime, VirtualTaskManager, WasiControlPlane, WasiEnvBuilder, WasiError, WasiFunctionEnv,
     WasiResult, WasiRuntimeError, WasiStateCreationError, WasiVFork,
 };
 use wasmer_types::ModuleHash;
 
 pub(crate) use super::handles::*;
-use super::{conv_env_vars, WasiState};
+use super::{conv_env_vars, instance_rng, WasiState};
 
 /// Various [`TypedFunction`] and [`Global`] handles for an active WASI(X) instance.
 ///
@@ -260,6 +261,8 @@ impl WasiEnvInit {
             crate::fs::WasiFs::new_with_preopen(&inodes, &[], &[], self.state.fs.root_fs.clone())
                 .unwrap();
 
+        let instance_id = self.runtime.instance_id().unwrap_or_else(Uuid::new_v4);
+
         Self {
             state: WasiState {
                 secret: rand::thread_rng().gen::<[u8; 32]>(),
@@ -271,6 +274,9 @@ impl WasiEnvInit {
                 ),
                 args: std::sync::Mutex::new(self.state.args.lock().unwrap().clone()),
                 envs: std::sync::Mutex::new(self.state.envs.lock().unwrap().deref().clone()),
+                syscall_throttle: Default::default(),
+                instance_id,
+                rng: instance_rng(&*self.runtime, instance_id),
                 preopen: self.state.preopen.clone(),
             },
             runtime: self.runtime.clone(),
@@ -382,7 +388,11 @@ impl WasiEnv {
 
     /// Forking the WasiState is used when either fork or vfork is called
     pub fn fork(&self) -> Result<(Self, WasiThreadHandle), ControlPlaneError> {
-        let process = self.control_plane.new_process(self.process.module_hash)?;
+        let process = self.control_plane.new_process_with_pid_allocator(
+            self.process.module_hash,
+            self.runtime.pid_allocator(),
+            Some(self.process.pid()),
+        )?;
         let handle = process.new_thread(self.layout.clone(), ThreadStartType::MainThread)?;
 
         let thread = handle.as_thread();
@@ -505,7 +515,11 @@ impl WasiEnv {
         let process = if let Some(p) = init.process {
             p
         } else {
-            init.control_plane.new_process(module_hash)?
+            init.control_plane.new_process_with_pid_allocator(
+                module_hash,
+                init.runtime.pid_allocator(),
+                None,
+            )?
         };
 
         #[cfg(feature = "journal")]
@@ -677,6 +691,89 @@ impl WasiEnv {
         self.runtime.task_manager()
     }
 
+    /// The ID this instance is identified by in logs and traces.
+    pub fn instance_id(&self) -> Uuid {
+        self.state.instance_id
+    }
+
+    /// The source of randomness for this instance, if it doesn't use the
+    /// host's (see [`Runtime::rng()`] and [`Runtime::rng_seed()`]).
+    pub fn rng(&self) -> Option<&Arc<dyn VirtualRng>> {
+        self.state.rng.as_ref()
+    }
+
+    /// Read one of the guest's clocks, using the runtime's clock if it has
+    /// one.
+    pub(crate) fn clock_time(
+        &self,
+        clock_id: Snapshot0Clockid,
+        precision: Timestamp,
+    ) -> Result<i64, Errno> {
+        match self.runtime.clock() {
+            Some(clock) => clock.time(clock_id, precision),
+            None => platform_clock_time_get(clock_id, precision),
+        }
+    }
+
+    /// Delay the guest if it has gone over the runtime's
+    /// [`Runtime::syscall_rate_limit()`].
+    ///
+    /// The delay is asyncified like any other blocking syscall, so the guest
+    /// can still be signalled or told to exit while it waits.
+    pub(crate) fn throttle_syscall(&self) -> Result<(), WasiError> {
+        let Some(limit) = self.runtime.syscall_rate_limit() else {
+            return Ok(());
+        };
+        let Ok(now) = self.clock_time(Snapshot0Clockid::Monotonic, 1) else {
+            return Ok(());
+        };
+        let Some(delay) = self.state.syscall_throttle.acquire(limit, now) else {
+            return Ok(());
+        };
+
+        tracing::trace!(pid=%self.pid(), ?delay, "Syscall rate limit exceeded");
+        let sleep = self.tasks().sleep_now(delay);
+        // A signal cuts the delay short, which is fine because the syscall
+        // itself will notice it
+        let _ = __asyncify_light(self, None, async move {
+            sleep.await;
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Tell the runtime's [`crate::runtime::FsWatcher`] that the guest
+    /// changed `path`, relative to the directory `dirfd`.
+    pub(crate) fn notify_fs_change(
+        &self,
+        dirfd: crate::syscalls::WasiFd,
+        path: &str,
+        kind: FsEventKind,
+    ) {
+        let Some(watcher) = self.runtime.fs_watcher() else {
+            return;
+        };
+
+        watcher.notify(FsEvent::new(self.watched_path(dirfd, path), kind));
+    }
+
+    /// The absolute, lexically normalized path [`crate::runtime::FsWatcher`]
+    /// uses for `path`, relative to the directory `dirfd`.
+    pub(crate) fn watched_path(&self, dirfd: crate::syscalls::WasiFd, path: &str) -> PathBuf {
+        let dir_path = self
+            .state
+            .fs
+            .get_fd(dirfd)
+            .ok()
+            .and_then(|fd| match fd.inode.read().deref() {
+                crate::fs::Kind::Dir { path, .. } => Some(path.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| PathBuf::from("/"));
+        crate::runtime::fs_access::normalize_path(&dir_path, path)
+    }
+
     pub fn fs_root(&self) -> &WasiFsRoot {
         &self.state.fs.root_fs
     }
@@ -726,6 +823,14 @@ impl WasiEnv {
             return Err(WasiError::Exit(forced_exit));
         }
 
+        // Check if the guest has run out of time
+        if let Some(deadline) = env.runtime.execution_deadline() {
+            if Instant::now() >= deadline {
+                tracing::debug!(pid=%env.pid(), "Execution deadline exceeded");
+                return Err(WasiError::DeadlineExceeded);
+            }
+        }
+
         Self::process_signals(ctx)
     }
 
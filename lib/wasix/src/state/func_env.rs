@@ -12,7 +12,7 @@ use crate::os::task::thread::RewindResultType;
 use crate::syscalls::restore_snapshot;
 use crate::{
     import_object_for_all_wasi_versions,
-    runtime::SpawnMemoryType,
+    runtime::{task_manager::InlineWaker, SpawnMemoryType},
     state::WasiInstanceHandles,
     utils::{get_wasi_version, get_wasi_versions, store::restore_store_snapshot},
     RewindStateOption, StoreSnapshot, WasiEnv, WasiError, WasiRuntimeError, WasiThreadError,
@@ -47,7 +47,7 @@ impl WasiFunctionEnv {
     ) -> Result<(Self, Store), WasiThreadError> {
         // Create a new store and put the memory object in it
         // (but only if it has imported memory)
-        let mut store = env.runtime.new_store();
+        let mut store = InlineWaker::block_on(env.runtime.new_store_async())?;
         let memory = env
             .tasks()
             .build_memory(&mut store.as_store_mut(), spawn_type)?;
@@ -20,12 +20,13 @@ mod env;
 mod func_env;
 mod handles;
 mod run;
+mod throttle;
 mod types;
 
 use std::{
     collections::{BTreeMap, HashMap},
     path::Path,
-    sync::Mutex,
+    sync::{Arc, Mutex},
     task::Waker,
     time::Duration,
 };
@@ -33,6 +34,7 @@ use std::{
 use run::*;
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 use virtual_fs::{FileOpener, FileSystem, FsError, OpenOptions, VirtualFile};
 use wasmer_wasix_types::wasi::{Errno, Fd as WasiFd, Rights, Snapshot0Clockid};
 
@@ -45,10 +47,12 @@ pub use self::{
 pub use crate::fs::{InodeGuard, InodeWeakGuard};
 use crate::{
     fs::{fs_error_into_wasi_err, WasiFs, WasiFsRoot, WasiInodes, WasiStateFileGuard},
+    runtime::VirtualRng,
     syscalls::types::*,
     utils::WasiParkingLot,
 };
 pub(crate) use handles::*;
+pub(crate) use throttle::SyscallThrottle;
 
 /// all the rights enabled
 pub const ALL_RIGHTS: Rights = Rights::all();
@@ -135,6 +139,14 @@ pub(crate) struct WasiState {
     pub clock_offset: Mutex<HashMap<Snapshot0Clockid, i64>>,
     pub args: Mutex<Vec<String>>,
     pub envs: Mutex<Vec<Vec<u8>>>,
+    /// Enforces the runtime's [`crate::runtime::RateLimit`] on syscalls.
+    pub syscall_throttle: SyscallThrottle,
+    /// Identifies this instance in logs and traces.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub instance_id: Uuid,
+    /// Used instead of the host's entropy, when set.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub rng: Option<Arc<dyn VirtualRng>>,
 
     // TODO: should not be here, since this requires active work to resolve.
     // State should only hold active runtime state that can be reproducibly re-created.
@@ -257,6 +269,9 @@ impl WasiState {
             clock_offset: Mutex::new(self.clock_offset.lock().unwrap().clone()),
             args: Mutex::new(self.args.lock().unwrap().clone()),
             envs: Mutex::new(self.envs.lock().unwrap().clone()),
+            syscall_throttle: Default::default(),
+            instance_id: self.instance_id,
+            rng: self.rng.clone(),
             preopen: self.preopen.clone(),
         }
     }
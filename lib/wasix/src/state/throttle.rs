@@ -0,0 +1,115 @@
+use std::{sync::Mutex, time::Duration};
+
+use crate::runtime::RateLimit;
+
+/// A token bucket used to enforce a [`RateLimit`] on a guest's syscalls.
+///
+/// Tokens are allowed to go negative, so guests which keep making syscalls
+/// while they are being throttled have to wait longer and longer.
+#[derive(Debug, Default)]
+pub(crate) struct SyscallThrottle {
+    bucket: Mutex<Option<Bucket>>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Bucket {
+    tokens: f64,
+    /// When the bucket was last refilled, in nanoseconds.
+    refilled_at: i64,
+}
+
+impl SyscallThrottle {
+    /// Take a token for a syscall made at `now` (in nanoseconds), returning
+    /// how long the guest should be delayed for, if at all.
+    pub fn acquire(&self, limit: RateLimit, now: i64) -> Option<Duration> {
+        if limit.per_second == 0 {
+            return None;
+        }
+        let per_second = limit.per_second as f64;
+        let burst = limit.burst.max(1) as f64;
+
+        let mut bucket = self.bucket.lock().unwrap();
+        let bucket = bucket.get_or_insert(Bucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+
+        let elapsed = now.saturating_sub(bucket.refilled_at).max(0) as f64;
+        bucket.tokens = (bucket.tokens + elapsed * per_second / 1_000_000_000.0).min(burst);
+        bucket.refilled_at = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(-bucket.tokens / per_second))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: i64 = 1_000_000_000;
+
+    #[test]
+    fn syscalls_over_the_limit_are_delayed() {
+        let throttle = SyscallThrottle::default();
+        let limit = RateLimit::new(10).with_burst(2);
+
+        assert_eq!(throttle.acquire(limit, 0), None);
+        assert_eq!(throttle.acquire(limit, 0), None);
+        assert_eq!(throttle.acquire(limit, 0), Some(Duration::from_millis(100)));
+        assert_eq!(throttle.acquire(limit, 0), Some(Duration::from_millis(200)));
+
+        // Waiting refills the bucket, but never past the burst size
+        assert_eq!(throttle.acquire(limit, 10 * SECOND), None);
+        assert_eq!(throttle.acquire(limit, 10 * SECOND), None);
+        assert!(throttle.acquire(limit, 10 * SECOND).is_some());
+
+        // A zero limit isn't enforced at all
+        assert_eq!(throttle.acquire(RateLimit::new(0), 10 * SECOND), None);
+    }
+
+    #[cfg(feature = "sys-thread")]
+    #[test]
+    fn every_syscall_is_throttled() {
+        use std::{sync::Arc, time::Instant};
+
+        use crate::{
+            runtime::task_manager::tokio::TokioTaskManager, PluggableRuntime, WasiEnvBuilder,
+        };
+
+        // None of these calls go through asyncify
+        const WAT: &str = r#"
+            (module
+                (import "wasi_snapshot_preview1" "args_sizes_get"
+                    (func $args_sizes_get (param i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "_start")
+                    (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+                    (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+                    (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+                    (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))))
+        "#;
+
+        let tokio_rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = tokio_rt.enter();
+        let tasks = TokioTaskManager::new(tokio_rt.handle().clone());
+        let mut runtime = PluggableRuntime::new(Arc::new(tasks));
+        runtime.set_syscall_rate_limit(Some(RateLimit::new(20).with_burst(1)));
+
+        let mut store = wasmer::Store::default();
+        let module = wasmer::Module::new(&store, WAT).unwrap();
+
+        let start = Instant::now();
+        WasiEnvBuilder::new("throttled")
+            .runtime(Arc::new(runtime))
+            .run_with_store(module, &mut store)
+            .unwrap();
+
+        // The first call is free, and each of the others waits 50ms
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+}
@@ -38,7 +38,7 @@ pub fn clock_time_set_internal(
     let memory = unsafe { env.memory_view(&ctx) };
 
     let precision = 1 as Timestamp;
-    let t_now = wasi_try!(platform_clock_time_get(clock_id, precision));
+    let t_now = wasi_try!(env.clock_time(clock_id, precision));
 
     let t_target = time as i64;
     let t_offset = t_target - t_now;
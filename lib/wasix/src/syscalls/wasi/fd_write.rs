@@ -146,9 +146,13 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
             let (mut memory, _) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
             let mut guard = fd_entry.inode.write();
             match guard.deref_mut() {
-                Kind::File { handle, .. } => {
+                Kind::File { handle, path, .. } => {
                     if let Some(handle) = handle {
                         let handle = handle.clone();
+                        let watched_path = match env.runtime().fs_watcher() {
+                            Some(_) if !is_stdio => Some(path.clone()),
+                            _ => None,
+                        };
                         drop(guard);
 
                         let res = __asyncify_light(
@@ -216,6 +220,13 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                             Errno::Timedout => Errno::Again,
                             a => a,
                         }));
+                        if let Some(path) = watched_path {
+                            env.notify_fs_change(
+                                fd,
+                                &path.to_string_lossy(),
+                                FsEventKind::Modified,
+                            );
+                        }
 
                         (written, true, true)
                     } else {
@@ -34,6 +34,7 @@ pub fn path_create_directory<M: MemorySize>(
 
     wasi_try_ok!(path_create_directory_internal(&mut ctx, fd, &path_string));
     let env = ctx.data();
+    env.notify_fs_change(fd, &path_string, FsEventKind::Created);
 
     #[cfg(feature = "journal")]
     if env.enable_journal {
@@ -63,6 +63,27 @@ pub fn path_open<M: MemorySize>(
     let path_string = unsafe { get_input_str_ok!(&memory, path, path_len) };
     Span::current().record("path", path_string.as_str());
 
+    // Only look the path up again if someone is watching for the change
+    let change = match env.runtime().fs_watcher() {
+        Some(_) if o_flags.contains(Oflags::CREATE) || o_flags.contains(Oflags::TRUNC) => {
+            let exists = state
+                .fs
+                .get_inode_at_path(
+                    inodes,
+                    dirfd,
+                    &path_string,
+                    dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
+                )
+                .is_ok();
+            match exists {
+                false if o_flags.contains(Oflags::CREATE) => Some(FsEventKind::Created),
+                true if o_flags.contains(Oflags::TRUNC) => Some(FsEventKind::Modified),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
     let out_fd = wasi_try_ok!(path_open_internal(
         &mut ctx,
         dirfd,
@@ -75,6 +96,9 @@ pub fn path_open<M: MemorySize>(
         None,
     )?);
     let env = ctx.data();
+    if let Some(kind) = change {
+        env.notify_fs_change(dirfd, &path_string, kind);
+    }
 
     #[cfg(feature = "journal")]
     if env.enable_journal {
@@ -138,6 +162,30 @@ pub(crate) fn path_open_internal(
         return Ok(Err(Errno::Access));
     }
 
+    if let Some(policy) = env.runtime().fs_access_policy() {
+        let dir_path = match working_dir.inode.read().deref() {
+            Kind::Dir { path, .. } => path.clone(),
+            _ => std::path::PathBuf::from("/"),
+        };
+        let full_path = crate::runtime::fs_access::normalize_path(&dir_path, path);
+        let mode = crate::runtime::OpenMode {
+            read: fs_rights_base.contains(Rights::FD_READ),
+            write: fs_rights_base.contains(Rights::FD_WRITE),
+            create: o_flags.contains(Oflags::CREATE),
+            truncate: o_flags.contains(Oflags::TRUNC),
+            append: fs_flags.contains(Fdflags::APPEND),
+            directory: o_flags.contains(Oflags::DIRECTORY),
+        };
+        if policy.check(&full_path, mode) == crate::runtime::Decision::Deny {
+            tracing::debug!(
+                path = %full_path.display(),
+                ?mode,
+                "path_open denied by the filesystem access policy"
+            );
+            return Ok(Err(Errno::Access));
+        }
+    }
+
     let mut open_flags = 0;
     // TODO: traverse rights of dirs properly
     // COMMENTED OUT: WASI isn't giving appropriate rights here when opening
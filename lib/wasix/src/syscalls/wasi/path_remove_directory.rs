@@ -21,6 +21,7 @@ pub fn path_remove_directory<M: MemorySize>(
 
     wasi_try!(path_remove_directory_internal(&mut ctx, fd, &path_str));
     let env = ctx.data();
+    env.notify_fs_change(fd, &path_str, FsEventKind::Removed);
 
     #[cfg(feature = "journal")]
     if env.enable_journal {
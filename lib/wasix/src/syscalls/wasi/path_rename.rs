@@ -41,6 +41,9 @@ pub fn path_rename<M: MemorySize>(
     let env = ctx.data();
 
     if ret == Errno::Success {
+        env.notify_fs_change(old_fd, &source_str, FsEventKind::Removed);
+        env.notify_fs_change(new_fd, &target_str, FsEventKind::Created);
+
         #[cfg(feature = "journal")]
         if env.enable_journal {
             JournalEffector::save_path_rename(&mut ctx, old_fd, source_str, new_fd, target_str)
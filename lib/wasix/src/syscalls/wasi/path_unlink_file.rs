@@ -31,6 +31,8 @@ pub fn path_unlink_file<M: MemorySize>(
     let env = ctx.data();
 
     if ret == Errno::Success {
+        env.notify_fs_change(fd, &path_str, FsEventKind::Removed);
+
         #[cfg(feature = "journal")]
         if env.enable_journal {
             wasi_try_ok!(
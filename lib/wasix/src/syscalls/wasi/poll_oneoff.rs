@@ -310,10 +310,8 @@ where
                             .flags
                             .contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME)
                         {
-                            let now = wasi_try_ok!(platform_clock_time_get(
-                                Snapshot0Clockid::Monotonic,
-                                1
-                            )) as u64;
+                            let now =
+                                wasi_try_ok!(env.clock_time(Snapshot0Clockid::Monotonic, 1)) as u64;
 
                             Duration::from_nanos(clock_info.timeout)
                                 - Duration::from_nanos(now as u64)
@@ -18,7 +18,18 @@ pub fn random_get<M: MemorySize>(
     let memory = unsafe { env.memory_view(&ctx) };
     let buf_len64: u64 = buf_len.into();
     let mut u8_buffer = vec![0; buf_len64 as usize];
-    let res = getrandom::getrandom(&mut u8_buffer);
+    let res = match env.rng() {
+        Some(rng) => {
+            rng.fill_bytes(&mut u8_buffer);
+            Ok(())
+        }
+        None => env
+            .runtime()
+            .entropy_fallback()
+            .fill_bytes(&mut u8_buffer, |delay| {
+                InlineWaker::block_on(env.tasks().sleep_now(delay))
+            }),
+    };
     match res {
         Ok(()) => {
             let buf = wasi_try_mem!(buf.slice(&memory, buf_len));
@@ -0,0 +1,85 @@
+use futures::{future::BoxFuture, StreamExt};
+use virtual_fs::Pipe;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fs_watch()`
+/// Watch a path for changes to the filesystem, using the runtime's
+/// [`crate::runtime::FsWatcher`].
+///
+/// Each change is written to the returned file handle as a line of text,
+/// such as `created /home/notes.txt`, `modified /home/notes.txt` or
+/// `removed /home/notes.txt`. The handle can be polled for reads, and the
+/// subscription ends when it is closed.
+/// Inputs:
+/// - `Fd fd`
+///     The directory that the path is relative to
+/// - `const char *path`
+///     String containing the path to watch. If it is a directory,
+///     everything inside it is watched too.
+/// - `u32 path_len`
+///     The length of `path`
+/// Output:
+/// - `Fd ret_fd`
+///     The file handle the changes can be read from
+/// Errors:
+/// - `Errno::Notsup`
+///     The runtime doesn't have a filesystem watcher
+#[instrument(level = "trace", skip_all, fields(%fd, path = field::Empty, ret_fd = field::Empty), ret)]
+pub fn fs_watch<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    ret_fd: WasmPtr<WasiFd, M>,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    let path_string = unsafe { get_input_str_ok!(&memory, path, path_len) };
+    Span::current().record("path", path_string.as_str());
+
+    let Some(watcher) = env.runtime().fs_watcher() else {
+        return Ok(Errno::Notsup);
+    };
+    let mut changes = watcher.subscribe(&env.watched_path(fd, &path_string));
+
+    // The guest reads the changes from one end of a pipe, and a background
+    // task writes them into the other end. Once the guest closes its end,
+    // the task stops at the next change.
+    let (mut sender, receiver) = Pipe::channel();
+    let inode = state.fs.create_inode_with_default_stat(
+        inodes,
+        Kind::Pipe { pipe: receiver },
+        false,
+        "fs_watch".to_string().into(),
+    );
+    let rights = Rights::FD_READ
+        | Rights::POLL_FD_READWRITE
+        | Rights::FD_FDSTAT_SET_FLAGS
+        | Rights::FD_FILESTAT_GET;
+    let watch_fd = wasi_try_ok!(state
+        .fs
+        .create_fd(rights, rights, Fdflags::empty(), 0, inode));
+    Span::current().record("ret_fd", watch_fd);
+
+    let forward = Box::new(move || {
+        Box::pin(async move {
+            while let Some(change) = changes.next().await {
+                let line = format!("{change}\n");
+                if std::io::Write::write_all(&mut sender, line.as_bytes()).is_err() {
+                    break;
+                }
+            }
+        }) as BoxFuture<'static, ()>
+    });
+    if let Err(err) = env.tasks().task_shared(forward) {
+        tracing::warn!("failed to watch for filesystem changes - {}", err);
+        let _ = state.fs.close_fd(watch_fd);
+        return Ok(Errno::Io);
+    }
+
+    wasi_try_mem_ok!(ret_fd.write(&memory, watch_fd));
+    Ok(Errno::Success)
+}
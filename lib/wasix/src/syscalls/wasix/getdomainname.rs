@@ -0,0 +1,37 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `getdomainname()`
+/// Reads the domain name presented to the guest, which is the one returned
+/// by [`crate::Runtime::domainname()`] or empty when the runtime doesn't
+/// provide one.
+///
+/// Inputs:
+/// - `u8 *buf`
+///     The buffer the domain name is written to
+/// - `u32 *buf_len`
+///     The size of `buf`, which is updated with the size of the domain name
+/// Errors:
+/// - `Errno::Range`
+///     The domain name doesn't fit in `buf`
+#[instrument(level = "trace", skip_all, ret)]
+pub fn getdomainname<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    buf: WasmPtr<u8, M>,
+    buf_len: WasmPtr<M::Offset, M>,
+) -> Errno {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let domainname = env.runtime().domainname().unwrap_or_default();
+    let domainname = domainname.as_bytes();
+
+    let max_len: u64 = wasi_try_mem!(buf_len.read(&memory)).into();
+    wasi_try_mem!(buf_len.write(&memory, wasi_try!(to_offset::<M>(domainname.len()))));
+    if domainname.len() as u64 > max_len {
+        return Errno::Range;
+    }
+
+    let buf = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(domainname.len()))));
+    wasi_try_mem!(buf.write_slice(domainname));
+    Errno::Success
+}
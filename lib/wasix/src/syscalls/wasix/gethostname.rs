@@ -0,0 +1,40 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `gethostname()`
+/// Reads the hostname presented to the guest, which is the one returned by
+/// [`crate::Runtime::hostname()`] or `localhost` when the runtime doesn't
+/// provide one.
+///
+/// Inputs:
+/// - `u8 *buf`
+///     The buffer the hostname is written to
+/// - `u32 *buf_len`
+///     The size of `buf`, which is updated with the size of the hostname
+/// Errors:
+/// - `Errno::Range`
+///     The hostname doesn't fit in `buf`
+#[instrument(level = "trace", skip_all, ret)]
+pub fn gethostname<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    buf: WasmPtr<u8, M>,
+    buf_len: WasmPtr<M::Offset, M>,
+) -> Errno {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let hostname = env
+        .runtime()
+        .hostname()
+        .unwrap_or_else(|| "localhost".to_string());
+    let hostname = hostname.as_bytes();
+
+    let max_len: u64 = wasi_try_mem!(buf_len.read(&memory)).into();
+    wasi_try_mem!(buf_len.write(&memory, wasi_try!(to_offset::<M>(hostname.len()))));
+    if hostname.len() as u64 > max_len {
+        return Errno::Range;
+    }
+
+    let buf = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(hostname.len()))));
+    wasi_try_mem!(buf.write_slice(hostname));
+    Errno::Success
+}
@@ -0,0 +1,53 @@
+use super::*;
+use crate::{
+    runtime::{log_sink::parse_guest_fields, LogLevel},
+    syscalls::*,
+};
+
+/// ### `log_write()`
+/// Send a structured log record to the runtime's
+/// [`crate::runtime::LogSink`], keeping it separate from stdout and stderr
+/// so the host can filter and route it.
+/// Inputs:
+/// - `u32 level`
+///     The severity of the record, from `0` (error) to `4` (trace)
+/// - `const char *msg`
+///     The message
+/// - `u32 msg_len`
+///     The length of `msg`
+/// - `const char *fields`
+///     Extra `key=value` fields, one per line
+/// - `u32 fields_len`
+///     The length of `fields`
+/// Errors:
+/// - `Errno::Notsup`
+///     The runtime doesn't have a log sink
+/// - `Errno::Inval`
+///     The level is unknown or a field isn't a `key=value` pair
+#[instrument(level = "trace", skip_all, fields(%level), ret)]
+pub fn log_write<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    level: u32,
+    msg: WasmPtr<u8, M>,
+    msg_len: M::Offset,
+    fields: WasmPtr<u8, M>,
+    fields_len: M::Offset,
+) -> Errno {
+    let env = ctx.data();
+    let Some(sink) = env.runtime().log_sink() else {
+        return Errno::Notsup;
+    };
+    let Some(level) = LogLevel::from_guest(level) else {
+        return Errno::Inval;
+    };
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    let msg = unsafe { get_input_str!(&memory, msg, msg_len) };
+    let fields = unsafe { get_input_str!(&memory, fields, fields_len) };
+    let Some(fields) = parse_guest_fields(&fields) else {
+        return Errno::Inval;
+    };
+
+    sink.record(level, &msg, &fields);
+    Errno::Success
+}
@@ -0,0 +1,48 @@
+use super::*;
+use crate::{runtime::MemoryLimits, syscalls::*};
+
+/// ### `memory_limits()`
+/// Returns how much memory the guest may use in total and how much of
+/// that is still available, so it can size its heap without hitting the
+/// hard cap.
+///
+/// These are the limits reported by the runtime, narrowed down to the
+/// maximum size of the guest's memory (which is where any cap imposed by
+/// the engine's tunables shows up).
+/// Output:
+/// - `u64 total`
+///     The total number of bytes the guest may use
+/// - `u64 available`
+///     The number of bytes that are still available
+/// Errors:
+/// - `Errno::Notsup`
+///     Neither the runtime nor the memory have a limit
+#[instrument(level = "trace", skip_all, fields(total = field::Empty, available = field::Empty), ret)]
+pub fn memory_limits<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_total: WasmPtr<u64, M>,
+    ret_available: WasmPtr<u64, M>,
+) -> Errno {
+    let env = ctx.data();
+    // SAFETY: syscalls are only called on the guest's thread once its
+    // instance has been initialized, which is what `memory_view()` relies on
+    // too.
+    let maximum = unsafe { env.memory() }
+        .ty(&ctx)
+        .maximum
+        .map(|pages| wasmer_types::Bytes::from(pages).0 as u64);
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    let Some(limits) =
+        MemoryLimits::for_memory(env.runtime().memory_limits(), maximum, memory.data_size())
+    else {
+        return Errno::Notsup;
+    };
+    Span::current()
+        .record("total", limits.total_bytes)
+        .record("available", limits.available_bytes);
+
+    wasi_try_mem!(ret_total.write(&memory, limits.total_bytes));
+    wasi_try_mem!(ret_available.write(&memory, limits.available_bytes));
+    Errno::Success
+}
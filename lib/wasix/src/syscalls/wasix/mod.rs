@@ -4,10 +4,15 @@ mod epoll_create;
 mod epoll_ctl;
 mod epoll_wait;
 mod fd_pipe;
+mod fs_watch;
 mod futex_wait;
 mod futex_wake;
 mod futex_wake_all;
 mod getcwd;
+mod getdomainname;
+mod gethostname;
+mod log_write;
+mod memory_limits;
 mod port_addr_add;
 mod port_addr_clear;
 mod port_addr_list;
@@ -30,7 +35,9 @@ mod proc_parent;
 mod proc_signal;
 mod proc_spawn;
 mod resolve;
+mod runtime_features;
 mod sched_yield;
+mod secret_get;
 mod sock_accept;
 mod sock_addr_local;
 mod sock_addr_peer;
@@ -73,10 +80,15 @@ pub use epoll_create::*;
 pub use epoll_ctl::*;
 pub use epoll_wait::*;
 pub use fd_pipe::*;
+pub use fs_watch::*;
 pub use futex_wait::*;
 pub use futex_wake::*;
 pub use futex_wake_all::*;
 pub use getcwd::*;
+pub use getdomainname::*;
+pub use gethostname::*;
+pub use log_write::*;
+pub use memory_limits::*;
 pub use port_addr_add::*;
 pub use port_addr_clear::*;
 pub use port_addr_list::*;
@@ -99,7 +111,9 @@ pub use proc_parent::*;
 pub use proc_signal::*;
 pub use proc_spawn::*;
 pub use resolve::*;
+pub use runtime_features::*;
 pub use sched_yield::*;
+pub use secret_get::*;
 pub use sock_accept::*;
 pub use sock_addr_local::*;
 pub use sock_addr_peer::*;
@@ -100,7 +100,15 @@ pub fn proc_exec2<M: MemorySize>(
         }
     };
 
-    let new_store = ctx.data().runtime.new_store();
+    let new_store = match InlineWaker::block_on(ctx.data().runtime.new_store_async()) {
+        Ok(store) => store,
+        Err(err) => {
+            warn!("failed to create the store for the new process - {}", err);
+            return Err(WasiError::Exit(
+                Errno::from(crate::WasiThreadError::from(err)).into(),
+            ));
+        }
+    };
 
     // If we are in a vfork we need to first spawn a subprocess of this type
     // with the forked WasiEnv, then do a longjmp back to the vfork point.
@@ -104,7 +104,13 @@ pub fn proc_spawn_internal(
     let env = ctx.data();
 
     // Build a new store that will be passed to the thread
-    let new_store = ctx.data().runtime.new_store();
+    let new_store = match InlineWaker::block_on(ctx.data().runtime.new_store_async()) {
+        Ok(store) => store,
+        Err(err) => {
+            warn!("failed to create the store for the new process - {}", err);
+            return Ok(Err(crate::WasiThreadError::from(err).into()));
+        }
+    };
 
     // Fork the current environment and set the new arguments
     let (mut child_env, handle) = match ctx.data().fork() {
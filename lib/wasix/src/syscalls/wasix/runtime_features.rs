@@ -0,0 +1,22 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `runtime_features()`
+/// Returns the optional features the runtime provides (threads,
+/// networking, etc.) as a bitset, so guests can fall back gracefully
+/// instead of failing when they use something that isn't available.
+///
+/// See [`crate::runtime::FeatureFlags`] for the meaning of each bit.
+#[instrument(level = "trace", skip_all, fields(features = field::Empty), ret)]
+pub fn runtime_features<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    ret_features: WasmPtr<u64, M>,
+) -> Errno {
+    let env = ctx.data();
+    let features = env.runtime().feature_flags();
+    Span::current().record("features", field::display(features));
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_features.write(&memory, features.bits()));
+    Errno::Success
+}
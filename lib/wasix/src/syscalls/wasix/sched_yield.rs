@@ -2,7 +2,10 @@ use super::*;
 use crate::syscalls::*;
 
 /// ### `sched_yield()`
-/// Yields execution of the thread
+/// Yields execution of the thread, giving the task manager a chance to run
+/// other tasks (see [`VirtualTaskManager::yield_now()`][yield_now])
+///
+/// [yield_now]: crate::VirtualTaskManager::yield_now
 #[instrument(level = "trace", skip_all, ret)]
 pub fn sched_yield<M: MemorySize + 'static>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
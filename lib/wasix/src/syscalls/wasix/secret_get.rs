@@ -0,0 +1,56 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `secret_get()`
+/// Reads a named secret from the runtime's
+/// [`crate::runtime::SecretStore`], so credentials don't need to be passed
+/// to the guest through its environment variables.
+///
+/// The secret's contents are never logged.
+/// Inputs:
+/// - `const char *name`
+///     The name of the secret
+/// - `u32 name_len`
+///     The length of `name`
+/// - `u8 *buf`
+///     The buffer the secret is written to
+/// - `u32 *buf_len`
+///     The size of `buf`, which is updated with the size of the secret
+/// Errors:
+/// - `Errno::Notsup`
+///     The runtime doesn't have a secret store
+/// - `Errno::Noent`
+///     There is no secret with this name
+/// - `Errno::Range`
+///     The secret doesn't fit in `buf`
+#[instrument(level = "trace", skip_all, fields(name = field::Empty), ret)]
+pub fn secret_get<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+    buf: WasmPtr<u8, M>,
+    buf_len: WasmPtr<M::Offset, M>,
+) -> Errno {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let name = unsafe { get_input_str!(&memory, name, name_len) };
+    Span::current().record("name", name.as_str());
+
+    let Some(secrets) = env.runtime().secrets() else {
+        return Errno::Notsup;
+    };
+    let Some(secret) = secrets.get(&name) else {
+        return Errno::Noent;
+    };
+    let secret = secret.as_bytes();
+
+    let max_len: u64 = wasi_try_mem!(buf_len.read(&memory)).into();
+    wasi_try_mem!(buf_len.write(&memory, wasi_try!(to_offset::<M>(secret.len()))));
+    if secret.len() as u64 > max_len {
+        return Errno::Range;
+    }
+
+    let buf = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(secret.len()))));
+    wasi_try_mem!(buf.write_slice(secret));
+    Errno::Success
+}
@@ -32,9 +32,15 @@ pub(crate) fn thread_sleep_internal<M: MemorySize + 'static>(
 
     let env = ctx.data();
 
-    #[cfg(feature = "sys-thread")]
     if duration == 0 {
+        #[cfg(feature = "sys-thread")]
         std::thread::yield_now();
+
+        let tasks = env.tasks().clone();
+        let res = __asyncify_with_deep_sleep::<M, _, _>(ctx, async move {
+            tasks.yield_now().await;
+        })?;
+        return Ok(Errno::Success);
     }
 
     if duration > 0 {
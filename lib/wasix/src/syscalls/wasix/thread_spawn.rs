@@ -125,6 +125,7 @@ pub fn thread_spawn_internal_using_layout<M: MemorySize>(
     // We capture some local variables
     let state = env.state.clone();
     let mut thread_env = env.clone();
+    thread_env.runtime = env.runtime.shallow_clone();
     thread_env.thread = thread_handle.as_thread();
     thread_env.layout = layout;
 
@@ -229,6 +230,11 @@ fn call_module<M: MemorySize>(
                     trace!("entered a deep sleep");
                     return Err(deep);
                 }
+                Ok(WasiError::DeadlineExceeded) => {
+                    debug!("thread exceeded the execution deadline");
+                    ret = Errno::Timedout;
+                    exit_code = Some(ExitCode::from(ret));
+                }
                 Ok(WasiError::UnknownWasiVersion) => {
                     debug!("failed as wasi version is unknown",);
                     env.data(&store)